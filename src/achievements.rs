@@ -0,0 +1,147 @@
+use serde::{Deserialize, Serialize};
+use tiny_game_framework::{glam::{vec3, Vec3}, glfw::Key, imgui::{Condition, Ui}, EventLoop};
+
+use crate::{coords, enemies::EnemyKind, events::{EventBus, GameEvent}, save::SaveData};
+
+const LOCKED_COLOR: [f32; 4] = [0.5, 0.5, 0.5, 1.0];
+const UNLOCKED_COLOR: [f32; 4] = [1.0, 0.85, 0.3, 1.0];
+
+// lifetime totals for whoever plays on this machine
+#[derive(Serialize, Deserialize, Clone, Copy, Default, Debug)]
+#[serde(default)]
+pub struct Statistics {
+    // on the ground, in world units
+    pub distance_walked: f32,
+    pub enemies_killed: u32,
+    pub bosses_killed: u32,
+    pub doors_unlocked: u32,
+    pub floors_cleared: u32,
+    pub deaths: u32,
+}
+
+pub struct Achievement {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+    unlocked: fn(&Statistics) -> bool,
+}
+
+pub const ACHIEVEMENTS: &[Achievement] = &[
+    Achievement { id: "first_blood", name: "first blood", description: "kill an enemy", unlocked: |stats| stats.enemies_killed >= 1 },
+    Achievement { id: "exterminator", name: "exterminator", description: "kill 100 enemies", unlocked: |stats| stats.enemies_killed >= 100 },
+    Achievement { id: "giant_slayer", name: "giant slayer", description: "defeat a boss", unlocked: |stats| stats.bosses_killed >= 1 },
+    Achievement { id: "locksmith", name: "locksmith", description: "unlock a door with its key", unlocked: |stats| stats.doors_unlocked >= 1 },
+    Achievement { id: "delver", name: "delver", description: "clear a floor", unlocked: |stats| stats.floors_cleared >= 1 },
+    Achievement { id: "explorer", name: "explorer", description: "walk 1000 tiles", unlocked: |stats| stats.distance_walked >= 1000.0 * coords::tile_size() },
+    Achievement { id: "try_again", name: "try, try again", description: "die", unlocked: |stats| stats.deaths >= 1 },
+];
+
+// keeps the statistics up to date from the event bus and unlocks achievements as they're earned. tab opens a page
+// with all of it
+pub struct AchievementTracker {
+    pub stats: Statistics,
+    unlocked: Vec<String>,
+    last_position: Option<Vec3>,
+    page_open: bool,
+    toggle_held: bool,
+}
+
+impl AchievementTracker {
+    pub fn new(save: &SaveData) -> Self {
+        Self {
+            stats: save.statistics,
+            unlocked: save.achievements.clone(),
+            last_position: None,
+            page_open: false,
+            toggle_held: false,
+        }
+    }
+
+    // writes what's been tracked so far back into the save
+    pub fn store(&self, save: &mut SaveData) {
+        save.statistics = self.stats;
+        save.achievements = self.unlocked.clone();
+    }
+
+    pub fn is_unlocked(&self, achievement: &Achievement) -> bool {
+        self.unlocked.iter().any(|id| id == achievement.id)
+    }
+
+    pub fn handle_input(&mut self, el: &mut EventLoop) {
+        let toggle = el.is_key_down(Key::Tab);
+        if toggle && !self.toggle_held {
+            self.page_open = !self.page_open;
+        }
+        self.toggle_held = toggle;
+    }
+
+    // only counts what happened to `me`, the floor itself counts for everyone. returns the achievements that were
+    // unlocked this frame
+    pub fn update(&mut self, bus: &EventBus, me: usize, position: Vec3, grounded: bool) -> Vec<&'static Achievement> {
+        let flat = vec3(position.x, 0.0, position.z);
+        if let (Some(last), true) = (self.last_position, grounded) {
+            let step = flat.distance(last);
+            // anything longer than a tile in one frame was a teleport, not a walk
+            if step < coords::tile_size() {
+                self.stats.distance_walked += step;
+            }
+        }
+        self.last_position = Some(flat);
+
+        for event in bus.iter() {
+            match *event {
+                GameEvent::EnemyKilled { player, kind } if player == me => {
+                    self.stats.enemies_killed += 1;
+                    if kind == EnemyKind::Boss {
+                        self.stats.bosses_killed += 1;
+                    }
+                }
+                GameEvent::DoorOpened { player, .. } if player == me => self.stats.doors_unlocked += 1,
+                GameEvent::PlayerDied { player } if player == me => self.stats.deaths += 1,
+                GameEvent::FloorCleared => self.stats.floors_cleared += 1,
+                _ => {}
+            }
+        }
+
+        let earned: Vec<&'static Achievement> = ACHIEVEMENTS.iter()
+            .filter(|achievement| !self.is_unlocked(achievement) && (achievement.unlocked)(&self.stats))
+            .collect();
+        self.unlocked.extend(earned.iter().map(|achievement| achievement.id.to_string()));
+
+        earned
+    }
+
+    pub fn draw(&self, ui: &Ui) {
+        if !self.page_open {
+            return;
+        }
+
+        let [width, height] = ui.io().display_size;
+        ui.window("achievements")
+            .position([width / 2.0, height / 2.0], Condition::Always)
+            .position_pivot([0.5, 0.5])
+            .no_decoration()
+            .no_inputs()
+            .bg_alpha(0.8)
+            .always_auto_resize(true)
+            .build(|| {
+                ui.text("statistics");
+                ui.separator();
+                ui.text(format!("distance walked: {:.0} tiles", self.stats.distance_walked / coords::tile_size()));
+                ui.text(format!("enemies killed: {}", self.stats.enemies_killed));
+                ui.text(format!("bosses killed: {}", self.stats.bosses_killed));
+                ui.text(format!("doors unlocked: {}", self.stats.doors_unlocked));
+                ui.text(format!("floors cleared: {}", self.stats.floors_cleared));
+                ui.text(format!("deaths: {}", self.stats.deaths));
+
+                ui.spacing();
+                let unlocked = ACHIEVEMENTS.iter().filter(|achievement| self.is_unlocked(achievement)).count();
+                ui.text(format!("achievements {}/{}", unlocked, ACHIEVEMENTS.len()));
+                ui.separator();
+                for achievement in ACHIEVEMENTS {
+                    let color = if self.is_unlocked(achievement) { UNLOCKED_COLOR } else { LOCKED_COLOR };
+                    ui.text_colored(color, format!("{}: {}", achievement.name, achievement.description));
+                }
+            });
+    }
+}
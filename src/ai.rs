@@ -0,0 +1,207 @@
+use serde::Deserialize;
+use tiny_game_framework::glam::{vec3, Vec3};
+
+// how close counts as having reached a waypoint or a noise
+const ARRIVE_DISTANCE: f32 = 50.0;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Status {
+    Success,
+    Failure,
+    Running,
+}
+
+// a small behavior tree, re-evaluated from the root every tick so enemies react as soon as things change.
+// trees can be written in the config file, e.g. `{ selector = [{ action = "chase" }, { action = "idle" }] }`
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Node {
+    // runs children in order until one doesn't succeed
+    Sequence(Vec<Node>),
+    // runs children in order until one doesn't fail
+    Selector(Vec<Node>),
+    Condition(Condition),
+    Action(Action),
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Condition {
+    PlayerWithin(f32),
+    CanSeePlayer,
+    // fraction of max health, 0.25 is a quarter
+    HealthBelow(f32),
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Idle,
+    Patrol,
+    Investigate,
+    Chase,
+    Attack,
+    Flee,
+}
+
+// what the tree gets to look at, rebuilt every tick
+pub struct Senses {
+    pub position: Vec3,
+    pub player_pos: Vec3,
+    pub health_fraction: f32,
+    pub attack_range: f32,
+    pub can_see_player: bool,
+}
+
+// per enemy memory that survives between ticks
+#[derive(Default)]
+pub struct Blackboard {
+    pub patrol_route: Vec<Vec3>,
+    pub patrol_index: usize,
+    // last noise heard that hasn't been checked out yet
+    pub noise: Option<Vec3>,
+    // the way there around walls, walked before heading straight for the noise
+    pub route: Vec<Vec3>,
+    pub attack_cooldown: f32,
+}
+
+// what the tree decided to do this tick
+#[derive(Default)]
+pub struct Decision {
+    // normalized, on the xz plane
+    pub move_dir: Vec3,
+    pub attack: bool,
+}
+
+impl Node {
+    pub fn tick(&self, senses: &Senses, blackboard: &mut Blackboard, decision: &mut Decision) -> Status {
+        match self {
+            Node::Sequence(children) => {
+                for child in children {
+                    let status = child.tick(senses, blackboard, decision);
+                    if status != Status::Success {
+                        return status;
+                    }
+                }
+                Status::Success
+            }
+            Node::Selector(children) => {
+                for child in children {
+                    let status = child.tick(senses, blackboard, decision);
+                    if status != Status::Failure {
+                        return status;
+                    }
+                }
+                Status::Failure
+            }
+            Node::Condition(condition) => {
+                if condition.check(senses) { Status::Success } else { Status::Failure }
+            }
+            Node::Action(action) => action.run(senses, blackboard, decision),
+        }
+    }
+}
+
+impl Condition {
+    fn check(&self, senses: &Senses) -> bool {
+        match *self {
+            Condition::PlayerWithin(distance) => senses.position.distance(senses.player_pos) <= distance,
+            Condition::HealthBelow(fraction) => senses.health_fraction < fraction,
+            Condition::CanSeePlayer => senses.can_see_player,
+        }
+    }
+}
+
+impl Action {
+    fn run(&self, senses: &Senses, blackboard: &mut Blackboard, decision: &mut Decision) -> Status {
+        match self {
+            Action::Idle => {
+                decision.move_dir = Vec3::ZERO;
+                Status::Running
+            }
+            Action::Patrol => {
+                if blackboard.patrol_route.is_empty() {
+                    return Status::Failure;
+                }
+
+                let index = blackboard.patrol_index % blackboard.patrol_route.len();
+                let waypoint = blackboard.patrol_route[index];
+                if flat_distance(senses.position, waypoint) <= ARRIVE_DISTANCE {
+                    blackboard.patrol_index = (index + 1) % blackboard.patrol_route.len();
+                }
+
+                decision.move_dir = direction_to(senses.position, waypoint);
+                Status::Running
+            }
+            Action::Investigate => {
+                let Some(noise) = blackboard.noise else {
+                    return Status::Failure;
+                };
+
+                if flat_distance(senses.position, noise) <= ARRIVE_DISTANCE {
+                    blackboard.noise = None;
+                    blackboard.route.clear();
+                    return Status::Success;
+                }
+
+                if blackboard.route.first().is_some_and(|&waypoint| flat_distance(senses.position, waypoint) <= ARRIVE_DISTANCE) {
+                    blackboard.route.remove(0);
+                }
+                decision.move_dir = direction_to(senses.position, blackboard.route.first().copied().unwrap_or(noise));
+                Status::Running
+            }
+            Action::Chase => {
+                decision.move_dir = direction_to(senses.position, senses.player_pos);
+                Status::Running
+            }
+            Action::Attack => {
+                if senses.position.distance(senses.player_pos) > senses.attack_range {
+                    return Status::Failure;
+                }
+
+                // stand still and wait out the cooldown instead of falling through to the next branch
+                decision.move_dir = Vec3::ZERO;
+                if blackboard.attack_cooldown > 0.0 {
+                    return Status::Running;
+                }
+
+                decision.attack = true;
+                Status::Success
+            }
+            Action::Flee => {
+                decision.move_dir = -direction_to(senses.position, senses.player_pos);
+                Status::Running
+            }
+        }
+    }
+}
+
+fn flat_distance(a: Vec3, b: Vec3) -> f32 {
+    vec3(b.x - a.x, 0.0, b.z - a.z).length()
+}
+
+fn direction_to(from: Vec3, to: Vec3) -> Vec3 {
+    vec3(to.x - from.x, 0.0, to.z - from.z).normalize_or_zero()
+}
+
+// flee when nearly dead, hit the player when in reach, chase while they're in sight, otherwise check out noises or walk the patrol route
+pub fn default_tree() -> Node {
+    use Action::*;
+    use Condition::*;
+
+    Node::Selector(vec![
+        Node::Sequence(vec![
+            Node::Condition(HealthBelow(0.25)),
+            Node::Condition(CanSeePlayer),
+            Node::Action(Flee),
+        ]),
+        Node::Action(Attack),
+        Node::Sequence(vec![
+            Node::Condition(CanSeePlayer),
+            Node::Action(Chase),
+        ]),
+        Node::Action(Investigate),
+        Node::Action(Patrol),
+        Node::Action(Idle),
+    ])
+}
@@ -0,0 +1,177 @@
+use tiny_game_framework::{glam::{Quat, Vec3}, Mesh};
+
+// how far a walking body leans into its movement at full speed, in radians
+const MAX_WALK_TILT: f32 = 0.12;
+// bob height as a fraction of the body's height, scaled down when moving slower
+const WALK_BOB_AMOUNT: f32 = 0.04;
+// radians of the bob cycle per unit of distance walked
+const WALK_BOB_FREQUENCY: f32 = 0.03;
+const SQUASH_RECOVERY: f32 = 0.35;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Easing {
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+    // overshoots a little before settling, good for anything springy
+    OutBack,
+}
+
+impl Easing {
+    // `t` from 0 to 1, comes back from 0 to 1 except for `OutBack` going a little past it. also what camera
+    // sequences ease between keyframes with
+    pub fn apply(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t,
+            Easing::EaseOut => 1.0 - (1.0 - t) * (1.0 - t),
+            Easing::EaseInOut => {
+                if t < 0.5 {
+                    2.0 * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(2) / 2.0
+                }
+            }
+            Easing::OutBack => {
+                let c1 = 1.70158;
+                let c3 = c1 + 1.0;
+                1.0 + c3 * (t - 1.0).powi(3) + c1 * (t - 1.0).powi(2)
+            }
+        }
+    }
+}
+
+// what gets layered on top of wherever the entity actually is
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Pose {
+    pub offset: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+impl Pose {
+    pub const IDENTITY: Self = Self {
+        offset: Vec3::ZERO,
+        rotation: Quat::IDENTITY,
+        scale: Vec3::ONE,
+    };
+
+    // unclamped so overshooting easings actually overshoot
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        Self {
+            offset: self.offset.lerp(other.offset, t),
+            rotation: self.rotation.slerp(other.rotation, t),
+            scale: self.scale.lerp(other.scale, t),
+        }
+    }
+
+    fn combine(&self, other: &Self) -> Self {
+        Self {
+            offset: self.offset + other.offset,
+            rotation: self.rotation * other.rotation,
+            scale: self.scale * other.scale,
+        }
+    }
+}
+
+struct Tween {
+    from: Pose,
+    to: Pose,
+    duration: f32,
+    elapsed: f32,
+    easing: Easing,
+}
+
+// drives a mesh's transform procedurally, since everything is a primitive there are no skeletons to animate.
+// a tween plays on top of an optional walk cycle
+pub struct Animator {
+    // where the last tween left off, stays put once it finishes
+    pub pose: Pose,
+    walk: Pose,
+    walk_phase: f32,
+    tween: Option<Tween>,
+}
+
+impl Animator {
+    pub fn new() -> Self {
+        Self::with_pose(Pose::IDENTITY)
+    }
+
+    pub fn with_pose(pose: Pose) -> Self {
+        Self {
+            pose,
+            walk: Pose::IDENTITY,
+            walk_phase: 0.0,
+            tween: None,
+        }
+    }
+
+    // eases from the current pose to `to`, replacing whatever was playing
+    pub fn play(&mut self, to: Pose, duration: f32, easing: Easing) {
+        self.tween = Some(Tween {
+            from: self.pose,
+            to,
+            duration: duration.max(f32::EPSILON),
+            elapsed: 0.0,
+            easing,
+        });
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.tween.is_some()
+    }
+
+    // squashes flat (or stretches tall with a negative amount) and springs back to rest, keeps the volume about the same
+    pub fn squash(&mut self, amount: f32) {
+        let y = (1.0 - amount).max(0.1);
+        let xz = 1.0 / y.sqrt();
+        self.pose = Pose { scale: Vec3::new(xz, y, xz), ..Pose::IDENTITY };
+        self.play(Pose::IDENTITY, SQUASH_RECOVERY, Easing::OutBack);
+    }
+
+    // bob up and down and lean into the direction of travel, call every frame with the current velocity
+    pub fn walk(&mut self, velocity: Vec3, max_speed: f32, height: f32, dt: f32) {
+        let horizontal = Vec3::new(velocity.x, 0.0, velocity.z);
+        let speed = horizontal.length();
+        let ratio = (speed / max_speed.max(f32::EPSILON)).min(1.0);
+
+        let lean_axis = Vec3::Y.cross(horizontal).normalize_or_zero();
+        let rotation = if lean_axis == Vec3::ZERO {
+            Quat::IDENTITY
+        } else {
+            Quat::from_axis_angle(lean_axis, ratio * MAX_WALK_TILT)
+        };
+
+        self.walk = Pose {
+            offset: Vec3::Y * self.walk_phase.sin().abs() * height * WALK_BOB_AMOUNT * ratio,
+            rotation,
+            scale: Vec3::ONE,
+        };
+        self.walk_phase += speed * WALK_BOB_FREQUENCY * dt;
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        let Some(tween) = self.tween.as_mut() else {
+            return;
+        };
+
+        tween.elapsed += dt;
+        let t = tween.elapsed / tween.duration;
+        self.pose = tween.from.lerp(&tween.to, tween.easing.apply(t));
+
+        if t >= 1.0 {
+            self.pose = tween.to;
+            self.tween = None;
+        }
+    }
+
+    // `position` is where the entity really is, the mesh ends up there plus the animated pose
+    pub fn apply(&self, mesh: &mut Mesh, position: Vec3, rotation: Quat) {
+        let pose = self.walk.combine(&self.pose);
+        mesh.position = position + pose.offset;
+        mesh.rotation = pose.rotation * rotation;
+        mesh.scale = pose.scale;
+    }
+}
@@ -0,0 +1,187 @@
+use std::{collections::HashMap, hash::{Hash, Hasher}, marker::PhantomData, sync::mpsc::{self, Receiver, Sender}};
+
+use image::RgbaImage;
+use tiny_game_framework::gl::{self, types::{GLint, GLsizei, GLuint, GLvoid}};
+
+// a texture that's been uploaded to the gpu, put `id` into `Mesh::texture` to use it
+pub struct Texture {
+    pub id: GLuint,
+}
+
+// cheap to copy around, turns into the asset through `AssetManager::get` once it's loaded
+pub struct Handle<T> {
+    id: usize,
+    _marker: PhantomData<T>,
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Handle<T> {}
+
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> Hash for Handle<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub struct LoadProgress {
+    pub loaded: usize,
+    pub failed: usize,
+    pub total: usize,
+}
+
+impl LoadProgress {
+    // failures count as done, they're logged and the game goes on without them
+    pub fn is_done(&self) -> bool {
+        self.loaded + self.failed >= self.total
+    }
+}
+
+// what the loading threads hand back, anything touching gl still has to happen on the main thread
+enum Loaded {
+    Texture(RgbaImage),
+}
+
+pub trait Asset: Sized {
+    fn storage(assets: &AssetManager) -> &HashMap<usize, Self>;
+}
+
+impl Asset for Texture {
+    fn storage(assets: &AssetManager) -> &HashMap<usize, Self> {
+        &assets.textures
+    }
+}
+
+// reads and decodes files on tokio's blocking pool so the frame never waits on the disk. call `update` every
+// frame to pick up whatever finished
+pub struct AssetManager {
+    textures: HashMap<usize, Texture>,
+
+    // the same file asked for twice as the same kind of asset gets the same handle
+    paths: HashMap<(&'static str, String), usize>,
+    pending: HashMap<usize, String>,
+    sender: Sender<(usize, Result<Loaded, String>)>,
+    receiver: Receiver<(usize, Result<Loaded, String>)>,
+    next_id: usize,
+    progress: LoadProgress,
+}
+
+impl AssetManager {
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            textures: HashMap::new(),
+            paths: HashMap::new(),
+            pending: HashMap::new(),
+            sender,
+            receiver,
+            next_id: 0,
+            progress: LoadProgress::default(),
+        }
+    }
+
+    pub fn load_texture(&mut self, path: &str) -> Handle<Texture> {
+        self.load(path, |path| {
+            let image = image::open(path).map_err(|e| e.to_string())?;
+            // gl wants the bottom row first
+            Ok(Loaded::Texture(image.flipv().to_rgba8()))
+        })
+    }
+
+    fn load<T>(&mut self, path: &str, read: fn(&str) -> Result<Loaded, String>) -> Handle<T> {
+        let key = (std::any::type_name::<T>(), path.to_string());
+        if let Some(&id) = self.paths.get(&key) {
+            return Handle { id, _marker: PhantomData };
+        }
+
+        let id = self.next_id;
+        self.next_id += 1;
+        self.paths.insert(key, id);
+        self.pending.insert(id, path.to_string());
+        self.progress.total += 1;
+
+        let sender = self.sender.clone();
+        let path = path.to_string();
+        tokio::task::spawn_blocking(move || {
+            sender.send((id, read(&path))).ok();
+        });
+
+        Handle { id, _marker: PhantomData }
+    }
+
+    // `None` until it's loaded, and for good if loading it failed
+    pub fn get<T: Asset>(&self, handle: Handle<T>) -> Option<&T> {
+        T::storage(self).get(&handle.id)
+    }
+
+    pub fn progress(&self) -> LoadProgress {
+        self.progress
+    }
+
+    // the file the earliest unfinished load is reading, for showing on a loading screen
+    pub fn loading(&self) -> Option<&str> {
+        self.pending.iter().min_by_key(|(&id, _)| id).map(|(_, path)| path.as_str())
+    }
+
+    pub fn update(&mut self) {
+        while let Ok((id, result)) = self.receiver.try_recv() {
+            let path = self.pending.remove(&id).unwrap_or_default();
+            let loaded = match result {
+                Ok(loaded) => loaded,
+                Err(e) => {
+                    log::warn!("couldn't load {}: {}", path, e);
+                    self.progress.failed += 1;
+                    continue;
+                }
+            };
+
+            match loaded {
+                Loaded::Texture(image) => {
+                    let texture = unsafe { upload_texture(&image) };
+                    self.textures.insert(id, texture);
+                }
+            }
+            self.progress.loaded += 1;
+        }
+    }
+}
+
+// same settings the framework's own `add_texture` uses
+unsafe fn upload_texture(image: &RgbaImage) -> Texture {
+    let mut id: GLuint = 0;
+    gl::GenTextures(1, &mut id);
+    gl::BindTexture(gl::TEXTURE_2D, id);
+
+    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::REPEAT as GLint);
+    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::REPEAT as GLint);
+    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as GLint);
+    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as GLint);
+
+    gl::TexImage2D(
+        gl::TEXTURE_2D,
+        0,
+        gl::RGBA as GLint,
+        image.width() as GLsizei,
+        image.height() as GLsizei,
+        0,
+        gl::RGBA,
+        gl::UNSIGNED_BYTE,
+        image.as_raw().as_ptr() as *const GLvoid,
+    );
+    gl::GenerateMipmap(gl::TEXTURE_2D);
+
+    Texture { id }
+}
@@ -0,0 +1,271 @@
+use std::collections::{HashMap, HashSet};
+
+use tiny_game_framework::{gl::{self, types::{GLint, GLsizeiptr, GLuint}}, glam::{vec2, Vec2, Vec3, Vec4}, Camera, Mesh, Renderer};
+
+use crate::{lighting::Fog, shader::{location, program}};
+
+// texels along each side of one sprite in the atlas
+const CELL_SIZE: usize = 32;
+// position, uv and color
+const FLOATS_PER_VERTEX: usize = 9;
+
+// flat quads out of an atlas, tinted and fogged. `world_text` draws with it too
+pub const BILLBOARD_VS: &str = r#"
+#version 330 core
+layout (location = 0) in vec3 aPos;
+layout (location = 1) in vec2 aTexCoord;
+layout (location = 2) in vec4 aColor;
+
+uniform mat4 view;
+uniform mat4 proj;
+
+out vec2 TexCoord;
+out vec4 fColor;
+out float Distance;
+
+void main() {
+    vec4 viewPos = view * vec4(aPos, 1.0);
+    gl_Position = proj * viewPos;
+    TexCoord = aTexCoord;
+    fColor = aColor;
+    Distance = length(viewPos.xyz);
+}
+"#;
+
+pub const BILLBOARD_FS: &str = r#"
+#version 330 core
+in vec2 TexCoord;
+in vec4 fColor;
+in float Distance;
+out vec4 FragColor;
+
+uniform sampler2D atlas;
+uniform vec3 fog_color;
+uniform float fog_start;
+uniform float fog_end;
+
+void main() {
+    vec4 color = texture(atlas, TexCoord) * fColor;
+    // atlases are all or nothing, what's left of the alpha is a fade and needs blending on to show
+    if (color.a < 0.01) {
+        discard;
+    }
+    float fog = clamp((Distance - fog_start) / max(fog_end - fog_start, 0.0001), 0.0, 1.0);
+    FragColor = vec4(mix(color.rgb, fog_color, fog), color.a);
+}
+"#;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Sprite {
+    Enemy,
+    Key,
+}
+
+impl Sprite {
+    const ALL: [Sprite; 2] = [Sprite::Enemy, Sprite::Key];
+
+    // further than this the mesh is swapped for the sprite, unless the fog has hidden it before then
+    fn mesh_distance(&self) -> f32 {
+        match self {
+            Sprite::Enemy => 2500.0,
+            Sprite::Key => 1200.0,
+        }
+    }
+
+    // white where the sprite is, tinted by the entity's color when drawn. `u` and `v` go from 0 to 1 over the cell
+    fn covers(&self, u: f32, v: f32) -> bool {
+        match self {
+            // a head on top of a body
+            Sprite::Enemy => {
+                let head = Vec2::new(u - 0.5, v - 0.85).length() < 0.13;
+                let body = (u - 0.5).abs() < 0.3 - v * 0.1 && v < 0.72;
+                head || body
+            }
+            Sprite::Key => (u - 0.5).abs() + (v - 0.5).abs() < 0.45,
+        }
+    }
+}
+
+// something that has a mesh up close and can be a sprite far away
+pub struct Impostor<'a> {
+    pub mesh_name: &'a str,
+    // the middle of it
+    pub position: Vec3,
+    pub sprite: Sprite,
+    // world units across and up
+    pub size: Vec2,
+    pub color: Vec4,
+}
+
+// draws far away entities as camera facing quads out of one atlas, in a single draw call. their meshes are taken
+// out of the renderer while they're sprites so they cost nothing, and put back once they're close again
+pub struct Billboards {
+    // `None` when the shader didn't build, everything stays a mesh then
+    program: Option<GLuint>,
+    quads: QuadBatch,
+    atlas: GLuint,
+    // meshes the renderer isn't drawing while they're sprites
+    parked: HashMap<String, Mesh>,
+}
+
+impl Billboards {
+    // needs the gl context
+    pub fn new() -> Self {
+        let program = program(BILLBOARD_VS, BILLBOARD_FS).inspect_err(|e| log::warn!("no billboards: {}", e)).ok();
+        Self { program, quads: QuadBatch::new(), atlas: bake_atlas(), parked: HashMap::new() }
+    }
+
+    // swaps meshes and sprites over by how far each one is from `eye`, everything that can be a sprite has to be in
+    // `impostors` every frame. nothing stays a mesh past `fog`'s end, it couldn't be seen there anyway. parked meshes
+    // left out were destroyed by whoever owned them, so they go too
+    pub fn update<'a>(&mut self, r: &mut Renderer, eye: Vec3, fog: Fog, resolution: f32, impostors: impl Iterator<Item = Impostor<'a>>) {
+        // the view's rows are the camera's axes
+        let (right, up) = (r.camera.view.row(0).truncate(), r.camera.view.row(1).truncate());
+        let mut seen = HashSet::new();
+        self.quads.clear();
+
+        for impostor in impostors {
+            seen.insert(impostor.mesh_name);
+            if impostor.position.distance(eye) < impostor.sprite.mesh_distance().min(fog.end) || self.program.is_none() {
+                if let Some(mut mesh) = self.parked.remove(impostor.mesh_name) {
+                    // whoever owns it couldn't move it while it was parked
+                    mesh.position = impostor.position;
+                    r.meshes.insert(impostor.mesh_name.to_string(), mesh);
+                }
+                continue;
+            }
+
+            if let Some(mesh) = r.meshes.remove(impostor.mesh_name) {
+                self.parked.insert(impostor.mesh_name.to_string(), mesh);
+            }
+            self.push_quad(&impostor, right, up, resolution);
+        }
+
+        // dropping a mesh deletes its vertex array and buffers, nothing else has to free them
+        self.parked.retain(|name, _| seen.contains(name.as_str()));
+    }
+
+    fn push_quad(&mut self, impostor: &Impostor, right: Vec3, up: Vec3, resolution: f32) {
+        let cell = Sprite::ALL.iter().position(|&sprite| sprite == impostor.sprite).unwrap_or(0);
+        let (u0, u1) = (cell as f32 / Sprite::ALL.len() as f32, (cell + 1) as f32 / Sprite::ALL.len() as f32);
+        let (half_right, half_up) = (right * impostor.size.x / 2.0, up * impostor.size.y / 2.0);
+
+        let corner = |x: f32, y: f32| (impostor.position + half_right * x + half_up * y) / resolution;
+        let corners = [corner(-1.0, -1.0), corner(1.0, -1.0), corner(1.0, 1.0), corner(-1.0, 1.0)];
+        self.quads.push(corners, [vec2(u0, 0.0), vec2(u1, 1.0)], impostor.color);
+    }
+
+    // call after the scene, with the camera the frame was drawn with
+    pub fn draw(&self, camera: &Camera, fog: Fog, resolution: f32) {
+        if let Some(program) = self.program {
+            self.quads.draw(program, self.atlas, camera, fog, resolution);
+        }
+    }
+}
+
+// quads built on the cpu every frame and drawn in one go with `BILLBOARD_VS` and `BILLBOARD_FS`
+pub struct QuadBatch {
+    vertex_array: GLuint,
+    vertex_buffer: GLuint,
+    vertices: Vec<f32>,
+}
+
+impl QuadBatch {
+    // needs the gl context
+    pub fn new() -> Self {
+        let (mut vertex_array, mut vertex_buffer) = (0, 0);
+        unsafe {
+            gl::GenVertexArrays(1, &mut vertex_array);
+            gl::GenBuffers(1, &mut vertex_buffer);
+            gl::BindVertexArray(vertex_array);
+            gl::BindBuffer(gl::ARRAY_BUFFER, vertex_buffer);
+            let stride = (FLOATS_PER_VERTEX * std::mem::size_of::<f32>()) as i32;
+            for (index, (size, offset)) in [(3, 0), (2, 3), (4, 5)].into_iter().enumerate() {
+                gl::VertexAttribPointer(index as GLuint, size, gl::FLOAT, gl::FALSE, stride, (offset * std::mem::size_of::<f32>()) as *const _);
+                gl::EnableVertexAttribArray(index as GLuint);
+            }
+            gl::BindVertexArray(0);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+        }
+        Self { vertex_array, vertex_buffer, vertices: Vec::new() }
+    }
+
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+    }
+
+    // `corners` in the camera's units, going bottom left, bottom right, top right, top left. `uv` is the atlas
+    // rectangle's bottom left and top right
+    pub fn push(&mut self, corners: [Vec3; 4], uv: [Vec2; 2], color: Vec4) {
+        let [min, max] = uv;
+        let uvs = [min, vec2(max.x, min.y), max, vec2(min.x, max.y)];
+        for i in [0, 1, 2, 0, 2, 3] {
+            let (position, uv) = (corners[i], uvs[i]);
+            self.vertices.extend_from_slice(&[position.x, position.y, position.z, uv.x, uv.y]);
+            self.vertices.extend_from_slice(&color.to_array());
+        }
+    }
+
+    pub fn draw(&self, program: GLuint, atlas: GLuint, camera: &Camera, fog: Fog, resolution: f32) {
+        if self.vertices.is_empty() {
+            return;
+        }
+
+        unsafe {
+            gl::BindVertexArray(self.vertex_array);
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.vertex_buffer);
+            gl::BufferData(gl::ARRAY_BUFFER, (self.vertices.len() * std::mem::size_of::<f32>()) as GLsizeiptr, self.vertices.as_ptr().cast(), gl::STREAM_DRAW);
+
+            gl::UseProgram(program);
+            gl::UniformMatrix4fv(location(program, "view"), 1, gl::FALSE, camera.view.to_cols_array().as_ptr());
+            gl::UniformMatrix4fv(location(program, "proj"), 1, gl::FALSE, camera.proj.to_cols_array().as_ptr());
+            gl::Uniform1i(location(program, "atlas"), 0);
+            gl::Uniform3f(location(program, "fog_color"), fog.color.x, fog.color.y, fog.color.z);
+            gl::Uniform1f(location(program, "fog_start"), fog.start / resolution);
+            gl::Uniform1f(location(program, "fog_end"), fog.end / resolution);
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl::BindTexture(gl::TEXTURE_2D, atlas);
+            gl::DrawArrays(gl::TRIANGLES, 0, (self.vertices.len() / FLOATS_PER_VERTEX) as i32);
+
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+            gl::UseProgram(0);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+            gl::BindVertexArray(0);
+        }
+    }
+}
+
+// every sprite side by side in one row, drawn from `Sprite::covers`
+fn bake_atlas() -> GLuint {
+    let width = CELL_SIZE * Sprite::ALL.len();
+    let mut texels = vec![0u8; width * CELL_SIZE * 4];
+    for (cell, sprite) in Sprite::ALL.iter().enumerate() {
+        for y in 0..CELL_SIZE {
+            for x in 0..CELL_SIZE {
+                let (u, v) = ((x as f32 + 0.5) / CELL_SIZE as f32, (y as f32 + 0.5) / CELL_SIZE as f32);
+                if sprite.covers(u, v) {
+                    let index = (y * width + cell * CELL_SIZE + x) * 4;
+                    texels[index..index + 4].copy_from_slice(&[255; 4]);
+                }
+            }
+        }
+    }
+
+    upload_atlas(width, CELL_SIZE, &texels)
+}
+
+// rgba, rows going up from the bottom. sampled nearest so neighboring cells don't bleed into each other
+pub fn upload_atlas(width: usize, height: usize, texels: &[u8]) -> GLuint {
+    unsafe {
+        let mut texture = 0;
+        gl::GenTextures(1, &mut texture);
+        gl::BindTexture(gl::TEXTURE_2D, texture);
+        gl::TexImage2D(gl::TEXTURE_2D, 0, gl::RGBA8 as GLint, width as i32, height as i32, 0, gl::RGBA, gl::UNSIGNED_BYTE, texels.as_ptr().cast());
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as GLint);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as GLint);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as GLint);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as GLint);
+        gl::BindTexture(gl::TEXTURE_2D, 0);
+        texture
+    }
+}
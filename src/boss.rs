@@ -0,0 +1,220 @@
+use rapier3d::prelude::*;
+use tiny_game_framework::{glam::{vec3, vec4, Quat, Vec3, Vec4}, Cuboid, Renderer};
+
+use crate::{error::RendererExt, animation::{Animator, Easing, Pose}, coords, enemies::{EnemyKind, EnemySystem}, entity::{EntityId, EntityKind}, patrol::WalkableGrid, rapier_integration::{BodySpec, CollisionLayer, RapierPhysicsWorld}, world_text::WorldText};
+
+// the arena is the (2 * radius + 1) tiles wide square with the most floor in it
+const ARENA_RADIUS: i32 = 2;
+// adds summoned every time the boss drops into a new phase
+const MINIONS_PER_PHASE: usize = 2;
+// how long the doors take to slide out of or back into the floor
+const DOOR_MOVE_TIME: f32 = 0.6;
+const NAME: &str = "the warden";
+// the name hangs over the middle of the arena, this far above the top of the walls
+const NAME_HEIGHT: f32 = 60.0;
+const NAME_SIZE: f32 = 60.0;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BossPhase {
+    First,
+    // below two thirds health, starts calling for help
+    Second,
+    // below a third
+    Enraged,
+}
+
+impl BossPhase {
+    fn from_health(fraction: f32) -> Self {
+        if fraction > 2.0 / 3.0 {
+            BossPhase::First
+        } else if fraction > 1.0 / 3.0 {
+            BossPhase::Second
+        } else {
+            BossPhase::Enraged
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum BossState {
+    // waiting for the player to walk in
+    Dormant,
+    Fighting { boss: EntityId, phase: BossPhase },
+    Defeated,
+}
+
+struct Door {
+    position: Vec3,
+    body: Option<RigidBodyHandle>,
+    mesh_name: String,
+    animator: Animator,
+    // the mesh sticks around after unlocking until it has sunk back down
+    mesh_alive: bool,
+}
+
+impl Door {
+    fn sunk() -> Pose {
+        Pose { offset: Vec3::Y * -coords::wall_height(), ..Pose::IDENTITY }
+    }
+
+    fn animate(&mut self, r: &mut Renderer, dt: f32) {
+        if !self.mesh_alive {
+            return;
+        }
+
+        self.animator.update(dt);
+        if self.body.is_none() && !self.animator.is_playing() {
+            r.destroy_mesh(&self.mesh_name).ok();
+            self.mesh_alive = false;
+            return;
+        }
+
+        if let Some(mesh) = r.get_mesh_mut(&self.mesh_name) {
+            self.animator.apply(mesh, self.position - Vec3::Y * coords::floor_height(), Quat::IDENTITY);
+        }
+    }
+}
+
+// one per floor, picks the most open room in the layout, seals it when the player walks in and opens it back up once the boss is dead
+pub struct BossEncounter {
+    pub center: Vec3,
+    pub state: BossState,
+    sensor: ColliderHandle,
+    doors: Vec<Door>,
+}
+
+impl BossEncounter {
+    // call once the floor has finished generating
+    pub fn plan(rw: &mut RapierPhysicsWorld, walkable: &WalkableGrid) -> Option<Self> {
+        let tile_size = coords::tile_size();
+        let floor: Vec<Vec3> = walkable.positions(coords::floor_height()).collect();
+        let in_arena = |center: Vec3, tile: Vec3| {
+            (tile.x - center.x).abs() <= ARENA_RADIUS as f32 * tile_size + 1.0
+                && (tile.z - center.z).abs() <= ARENA_RADIUS as f32 * tile_size + 1.0
+        };
+
+        let center = floor.iter().copied().max_by_key(|&center| {
+            floor.iter().filter(|&&tile| in_arena(center, tile)).count()
+        })?;
+
+        // floor right outside the arena that leads into it, those get sealed off
+        let mut doors = Vec::new();
+        for &tile in floor.iter().filter(|&&tile| in_arena(center, tile)) {
+            for offset in [vec3(tile_size, 0.0, 0.0), vec3(-tile_size, 0.0, 0.0), vec3(0.0, 0.0, tile_size), vec3(0.0, 0.0, -tile_size)] {
+                let outside = tile + offset;
+                if walkable.is_walkable(outside) && !in_arena(center, outside) && !doors.iter().any(|door: &Door| door.position == outside) {
+                    doors.push(Door {
+                        position: outside,
+                        body: None,
+                        mesh_name: format!("BOSS_DOOR_MESH{}", doors.len()),
+                        animator: Animator::with_pose(Door::sunk()),
+                        mesh_alive: false,
+                    });
+                }
+            }
+        }
+
+        // a bit smaller than the arena so standing in a doorway doesn't count as walking in
+        let half = ARENA_RADIUS as f32 * tile_size;
+        let height = coords::wall_height() / 2.0;
+        let sensor = rw.add_sensor(center.x, center.y + height, center.z, vec3(half, height, half), CollisionLayer::Trigger, CollisionLayer::Trigger.default_mask());
+
+        Some(Self {
+            center,
+            state: BossState::Dormant,
+            sensor,
+            doors,
+        })
+    }
+
+    pub fn update(&mut self, rw: &mut RapierPhysicsWorld, r: &mut Renderer, enemies: &mut EnemySystem, players: &[RigidBodyHandle], dt: f32) {
+        for door in self.doors.iter_mut() {
+            door.animate(r, dt);
+        }
+
+        match self.state {
+            BossState::Dormant => {
+                if !players.iter().any(|&player| rw.is_body_in_sensor(self.sensor, player)) {
+                    return;
+                }
+
+                self.lock(rw, r);
+                let index = enemies.spawn(rw, r, self.center + Vec3::Y * coords::wall_height(), EnemyKind::Boss);
+                self.state = BossState::Fighting { boss: enemies.enemies[index].id, phase: BossPhase::First };
+            }
+            BossState::Fighting { boss, phase } => {
+                let Some(enemy) = enemies.find(boss).filter(|enemy| enemy.is_alive()) else {
+                    self.unlock(rw);
+                    self.state = BossState::Defeated;
+                    return;
+                };
+
+                let next_phase = BossPhase::from_health(enemy.health_fraction());
+                if next_phase != phase {
+                    for i in 0..MINIONS_PER_PHASE {
+                        let side = if i % 2 == 0 { 1.0 } else { -1.0 };
+                        enemies.spawn(rw, r, self.center + vec3(side * coords::tile_size(), coords::wall_height(), 0.0), EnemyKind::Grunt);
+                    }
+                    self.state = BossState::Fighting { boss, phase: next_phase };
+                }
+            }
+            BossState::Defeated => {}
+        }
+    }
+
+    // over the arena for as long as the boss is alive
+    pub fn label_name(&self, text: &mut WorldText) {
+        if self.state == BossState::Defeated {
+            return;
+        }
+        let position = self.center + Vec3::Y * (coords::wall_height() + NAME_HEIGHT);
+        text.label(NAME, position, NAME_SIZE, vec4(0.9, 0.2, 0.15, 1.0));
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.doors.iter().any(|door| door.body.is_some())
+    }
+
+    // for multiplayer clients, which follow the host's doors instead of running the fight themselves
+    pub fn set_locked(&mut self, rw: &mut RapierPhysicsWorld, r: &mut Renderer, locked: bool) {
+        if locked && !self.is_locked() {
+            self.lock(rw, r);
+        } else if !locked && self.is_locked() {
+            self.unlock(rw);
+        }
+    }
+
+    fn lock(&mut self, rw: &mut RapierPhysicsWorld, r: &mut Renderer) {
+        for door in self.doors.iter_mut() {
+            // walls are centered on the tile rather than sitting on the floor
+            let position = door.position - Vec3::Y * coords::floor_height();
+            let size = vec3(coords::tile_size(), coords::wall_height(), coords::tile_size());
+            let body = rw.add_body(position, &BodySpec::cuboid(size / 2.0)
+                .body_type(RigidBodyType::Fixed)
+                .layer(CollisionLayer::StaticWorld));
+            rw.tag_body(body, EntityId::new(EntityKind::Door));
+            door.body = Some(body);
+
+            // the collider is there right away, the mesh just catches up by rising out of the floor
+            let mut mesh = Cuboid::new(size, Vec4::new(0.3, 0.2, 0.1, 1.0)).mesh();
+            mesh.position = position + Door::sunk().offset;
+            mesh.setup_mesh();
+            // might still be sinking from the last time it opened
+            r.destroy_mesh(&door.mesh_name).ok();
+            if let Err(e) = r.try_add_mesh(&door.mesh_name, mesh) {
+                log::warn!("{}", e);
+            }
+            door.mesh_alive = true;
+            door.animator.play(Pose::IDENTITY, DOOR_MOVE_TIME, Easing::OutBack);
+        }
+    }
+
+    fn unlock(&mut self, rw: &mut RapierPhysicsWorld) {
+        for door in self.doors.iter_mut() {
+            if let Some(body) = door.body.take() {
+                rw.remove_rigidbody(body);
+            }
+            door.animator.play(Door::sunk(), DOOR_MOVE_TIME, Easing::EaseIn);
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use std::f32::consts::TAU;
+
+use tiny_game_framework::{glam::{vec2, Vec2, Vec3}, Camera};
+
+use crate::config::CameraSettings;
+
+// sits between the player body and the renderer camera, so the view doesn't just copy the physics position
+pub struct PlayerCamera {
+    pub settings: CameraSettings,
+    pub position: Vec3,
+
+    bob_phase: f32,
+    bob_offset: f32,
+    snapped: bool,
+}
+
+impl PlayerCamera {
+    pub fn new(settings: CameraSettings) -> Self {
+        Self {
+            settings,
+            position: Vec3::ZERO,
+            bob_phase: 0.0,
+            bob_offset: 0.0,
+            snapped: false,
+        }
+    }
+
+    // `speed_ratio` is the horizontal speed divided by the max speed, it scales the bob so standing still doesn't bob
+    pub fn update(&mut self, target: Vec3, speed_ratio: f32, dt: f32) -> Vec3 {
+        // the first frame has nothing to smooth from
+        if !self.settings.smoothing || !self.snapped {
+            self.position = target;
+            self.snapped = true;
+        } else {
+            let t = 1.0 - (-self.settings.smoothing_speed * dt).exp();
+            self.position = self.position.lerp(target, t);
+        }
+
+        let ratio = speed_ratio.clamp(0.0, 1.0);
+        let bob = if self.settings.head_bob && ratio > f32::EPSILON {
+            self.bob_phase = (self.bob_phase + self.settings.bob_frequency * ratio * dt * TAU) % TAU;
+            self.bob_phase.sin().abs() * self.settings.bob_amplitude * ratio
+        } else {
+            self.bob_phase = 0.0;
+            0.0
+        };
+
+        // eases back to rest when we stop instead of snapping out of the middle of a bob
+        let t = 1.0 - (-self.settings.smoothing_speed * dt).exp();
+        self.bob_offset += (bob - self.bob_offset) * t;
+
+        self.position + Vec3::Y * self.bob_offset
+    }
+
+    // jumps straight to the next target instead of gliding over from wherever it was
+    pub fn snap(&mut self) {
+        self.snapped = false;
+    }
+}
+
+// trauma goes from 0 to 1, things like explosions add to it and it wears off over time.
+// the shake is trauma squared so small bumps barely register and big hits really rattle
+pub struct CameraShake {
+    pub trauma: f32,
+    // 0 turns shaking off
+    pub intensity: f32,
+    pub max_offset: f32,
+    time: f32,
+}
+
+const TRAUMA_DECAY: f32 = 1.2;
+const SHAKE_FREQUENCY: f32 = 25.0;
+
+impl CameraShake {
+    pub fn new(settings: &CameraSettings) -> Self {
+        Self {
+            trauma: 0.0,
+            intensity: settings.shake_intensity,
+            max_offset: settings.shake_max_offset,
+            time: 0.0,
+        }
+    }
+
+    pub fn add_trauma(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount).clamp(0.0, 1.0);
+    }
+
+    // returns the offset to add to the camera position this frame
+    pub fn update(&mut self, dt: f32) -> Vec3 {
+        self.time += dt;
+        self.trauma = (self.trauma - TRAUMA_DECAY * dt).max(0.0);
+
+        let shake = self.trauma * self.trauma * self.intensity * self.max_offset;
+        if shake <= 0.0 {
+            return Vec3::ZERO;
+        }
+
+        Vec3::new(
+            Self::noise(self.time, 0.0),
+            Self::noise(self.time, 11.3),
+            Self::noise(self.time, 27.9),
+        ) * shake
+    }
+
+    // cheap smooth noise in -1..1, a few sines at unrelated frequencies so it doesn't look periodic
+    fn noise(time: f32, seed: f32) -> f32 {
+        let t = time * SHAKE_FREQUENCY + seed;
+        ((t).sin() * 0.5 + (t * 1.73 + 1.1).sin() * 0.3 + (t * 2.91 + 2.3).sin() * 0.2).clamp(-1.0, 1.0)
+    }
+}
+
+// where a world position ends up on screen in pixels, None if it's behind the camera.
+// `world_scale` is what positions get divided by before rendering (the window resolution)
+pub fn world_to_screen(camera: &Camera, position: Vec3, screen: Vec2, world_scale: f32) -> Option<Vec2> {
+    let clip = camera.proj * camera.view * (position / world_scale).extend(1.0);
+    if clip.w <= 0.0 {
+        return None;
+    }
+
+    let ndc = clip.truncate() / clip.w;
+    Some(vec2((ndc.x + 1.0) / 2.0 * screen.x, (1.0 - ndc.y) / 2.0 * screen.y))
+}
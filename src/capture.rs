@@ -0,0 +1,102 @@
+use std::{collections::VecDeque, fs::{self, File}, path::PathBuf, time::{SystemTime, UNIX_EPOCH}};
+
+use image::{codecs::gif::{GifEncoder, Repeat}, imageops::{self, FilterType}, Delay, Frame, RgbaImage};
+use tiny_game_framework::{gl, glfw::Key, EventLoop};
+
+use crate::error::CaptureError;
+
+pub const CAPTURE_DIR: &str = "captures";
+const SCREENSHOT_KEY: Key = Key::F12;
+const CLIP_KEY: Key = Key::F11;
+// the clip keeps this many seconds, grabbing a frame this many times a second
+const CLIP_SECONDS: f32 = 5.0;
+const CLIP_FPS: f32 = 10.0;
+// clip frames are this many times smaller than the window on each side
+const CLIP_DOWNSCALE: u32 = 4;
+
+// what the keys asked for this frame
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub struct CaptureRequest {
+    pub screenshot: bool,
+    pub clip: bool,
+}
+
+// f12 saves a png of the frame, f11 saves the last few seconds as a gif. the clip is always recording small frames
+// in the background so there's something there when a generation bug shows up
+pub struct Capture {
+    frames: VecDeque<RgbaImage>,
+    timer: f32,
+    screenshot_held: bool,
+    clip_held: bool,
+}
+
+impl Capture {
+    pub fn new() -> Self {
+        Self { frames: VecDeque::new(), timer: 0.0, screenshot_held: false, clip_held: false }
+    }
+
+    // only presses count, holding the key doesn't save every frame
+    pub fn update(&mut self, el: &mut EventLoop) -> CaptureRequest {
+        let screenshot = el.is_key_down(SCREENSHOT_KEY);
+        let clip = el.is_key_down(CLIP_KEY);
+        let request = CaptureRequest { screenshot: screenshot && !self.screenshot_held, clip: clip && !self.clip_held };
+        self.screenshot_held = screenshot;
+        self.clip_held = clip;
+        request
+    }
+
+    // call after everything is drawn but before the buffers swap, so the frame is still there to read back
+    pub fn record(&mut self, width: u32, height: u32, dt: f32) {
+        self.timer -= dt;
+        if self.timer > 0.0 {
+            return;
+        }
+        self.timer = 1.0 / CLIP_FPS;
+
+        let frame = read_framebuffer(width, height);
+        let small = imageops::resize(&frame, (width / CLIP_DOWNSCALE).max(1), (height / CLIP_DOWNSCALE).max(1), FilterType::Triangle);
+        self.frames.push_back(small);
+        while self.frames.len() > (CLIP_SECONDS * CLIP_FPS) as usize {
+            self.frames.pop_front();
+        }
+    }
+
+    // same timing as `record`, returns where the png went
+    pub fn screenshot(&self, width: u32, height: u32) -> Result<PathBuf, CaptureError> {
+        let path = capture_path("screenshot", "png")?;
+        read_framebuffer(width, height).save(&path)?;
+        Ok(path)
+    }
+
+    pub fn save_clip(&self) -> Result<PathBuf, CaptureError> {
+        if self.frames.is_empty() {
+            return Err(CaptureError::Empty);
+        }
+
+        let path = capture_path("clip", "gif")?;
+        let mut encoder = GifEncoder::new(File::create(&path)?);
+        encoder.set_repeat(Repeat::Infinite)?;
+        let delay = Delay::from_numer_denom_ms(1000, CLIP_FPS as u32);
+        encoder.encode_frames(self.frames.iter().map(|frame| Frame::from_parts(frame.clone(), 0, 0, delay)))?;
+        Ok(path)
+    }
+}
+
+fn read_framebuffer(width: u32, height: u32) -> RgbaImage {
+    let mut pixels = vec![0u8; (width * height * 4) as usize];
+    unsafe {
+        gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+        gl::ReadPixels(0, 0, width as i32, height as i32, gl::RGBA, gl::UNSIGNED_BYTE, pixels.as_mut_ptr().cast());
+    }
+
+    // gl reads from the bottom row up
+    let image = RgbaImage::from_raw(width, height, pixels).unwrap_or_else(|| RgbaImage::new(width, height));
+    imageops::flip_vertical(&image)
+}
+
+// named by the time so captures never overwrite each other
+fn capture_path(name: &str, extension: &str) -> Result<PathBuf, CaptureError> {
+    fs::create_dir_all(CAPTURE_DIR)?;
+    let millis = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |since| since.as_millis());
+    Ok(PathBuf::from(CAPTURE_DIR).join(format!("{}-{}.{}", name, millis, extension)))
+}
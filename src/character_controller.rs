@@ -1,41 +1,536 @@
 use std::collections::HashMap;
 
 use rapier3d::{dynamics::RigidBodyHandle, na::vector, parry::query::Ray};
-use tiny_game_framework::{glam::{quat, vec3, vec3a, vec4, Mat4, Quat, Vec3, Vec3A}, rand_betw, Cuboid as Goud, EventLoop, Light, Renderer, Sphere};
+use tiny_game_framework::{glam::{quat, vec3, vec3a, vec4, Mat4, Quat, Vec3, Vec3A}, glfw::Key, rand_betw, Cuboid as Goud, EventLoop, Light, Renderer, Sphere};
 use tokio::sync::MutexGuard;
 
-use crate::rapier_integration::RapierPhysicsWorld;
+use crate::{entity::{EntityId, EntityKind}, error::PhysicsError, platforms::MovingPlatform, progression::Stats, rapier_integration::{BodySpec, CollisionLayer, RapierPhysicsWorld}};
 
 use rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+const CAPSULE_RADIUS: f32 = 0.5;
+const CAPSULE_HALF_HEIGHT: f32 = 0.5;
+// distance from the capsule center to its feet, plus a bit of slack
+const GROUND_CHECK_DISTANCE: f32 = CAPSULE_HALF_HEIGHT + CAPSULE_RADIUS + 0.1;
+// how long after a knockback until input is fully in control again
+const KNOCKBACK_RECOVERY: f32 = 0.4;
+// shapecasts start this far above the capsule so the floor we're standing on doesn't count as a hit
+const SKIN: f32 = 0.05;
+// a dodge bursts to this many times the normal top speed and then slows down like any other movement
+const DODGE_SPEED: f32 = 3.0;
+// hits landing this soon after starting a dodge miss completely
+const DODGE_WINDOW: f32 = 0.25;
+const DODGE_COOLDOWN: f32 = 0.8;
+// how much of the usual acceleration input gets while swinging on the grappling hook
+const SWING_CONTROL: f32 = 0.2;
+
+// what's under the player's feet, footsteps pick their sound from this
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Surface {
+    // nothing below, we're jumping or falling
+    Air,
+    Stone,
+    Wood,
+    Rubble,
+    // swimming rather than standing
+    Water,
+}
+
+impl Surface {
+    fn from_entity(entity: Option<EntityId>) -> Self {
+        match entity.map(|entity| entity.kind) {
+            Some(EntityKind::Platform) => Surface::Wood,
+            Some(EntityKind::Debris) => Surface::Rubble,
+            // the ground and maze walls aren't tagged with anything special
+            _ => Surface::Stone,
+        }
+    }
+
+    pub fn speed_multiplier(&self) -> f32 {
+        match self {
+            Surface::Rubble => 0.8,
+            Surface::Water => 0.5,
+            _ => 1.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct MovementSettings {
+    pub max_speed: f32,
+    // units per second squared while there's input
+    pub acceleration: f32,
+    // units per second squared once the keys are let go
+    pub deceleration: f32,
+    // ledges up to this tall get walked over instead of blocking
+    pub max_step_height: f32,
+    // steepest surface (in degrees) that still counts as floor rather than wall
+    pub max_slope_angle: f32,
+    // how fast space/ctrl move up and down while swimming
+    pub swim_vertical_speed: f32,
+    // how fast w/s move up and down a ladder
+    pub climb_speed: f32,
+    // speed multiplier while holding shift
+    pub sprint_multiplier: f32,
+    // stamina per second used while sprinting, and regained while not
+    pub stamina_drain: f32,
+    pub stamina_regen: f32,
+}
+
+impl Default for MovementSettings {
+    fn default() -> Self {
+        Self {
+            max_speed: 300.0,
+            acceleration: 1800.0,
+            deceleration: 2400.0,
+            max_step_height: 0.3,
+            max_slope_angle: 45.0,
+            swim_vertical_speed: 150.0,
+            climb_speed: 120.0,
+            sprint_multiplier: 1.6,
+            stamina_drain: 25.0,
+            stamina_regen: 15.0,
+        }
+    }
+}
+
+// everything the player controls in a frame, kept apart from the keyboard so lockstep can send it to the
+// other peers and replay theirs
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default, Debug)]
+pub struct PlayerInput {
+    pub forward: bool,
+    pub back: bool,
+    pub left: bool,
+    pub right: bool,
+    pub sprint: bool,
+    // swim up / down
+    pub up: bool,
+    pub down: bool,
+    pub attack: bool,
+    pub dodge: bool,
+    pub interact: bool,
+    // held to aim with the trajectory preview showing, letting go throws
+    #[serde(default)]
+    pub throw: bool,
+    // shoots the grappling hook, or lets go of it
+    #[serde(default)]
+    pub grapple: bool,
+    #[serde(default)]
+    pub reel: bool,
+    // selected hotbar slot, whatever weapon is in it is what attack uses
+    #[serde(default)]
+    pub weapon: u8,
+    // where the camera is looking
+    pub look: [f32; 3],
+}
+
+impl PlayerInput {
+    pub fn read(el: &mut EventLoop, look: Vec3) -> Self {
+        Self {
+            forward: el.is_key_down(Key::W),
+            back: el.is_key_down(Key::S),
+            left: el.is_key_down(Key::A),
+            right: el.is_key_down(Key::D),
+            sprint: el.is_key_down(Key::LeftShift),
+            up: el.is_key_down(Key::Space),
+            down: el.is_key_down(Key::LeftControl),
+            attack: el.event_handler.lmb,
+            dodge: el.is_key_down(Key::Q),
+            interact: el.is_key_down(Key::E),
+            throw: el.event_handler.rmb,
+            grapple: el.is_key_down(Key::F),
+            reel: el.is_key_down(Key::R),
+            // the hud owns the hotbar, main fills this in
+            weapon: 0,
+            look: look.to_array(),
+        }
+    }
+
+    pub fn look(&self) -> Vec3 {
+        Vec3::from_array(self.look)
+    }
+}
 
 pub struct Player {
-    pub pos: Vec3A,
+    pub id: EntityId,
+    // read back from the body after every step, the body is what actually moves. see `position`
+    pos: Vec3A,
+    pub health: f32,
+    pub max_health: f32,
+    // from leveling up, scales `movement.max_speed`
+    pub speed_multiplier: f32,
+    pub stamina: f32,
+    pub max_stamina: f32,
+    pub movement: MovementSettings,
+    // hanging off the grappling hook, in the air this keeps momentum instead of walking
+    pub swinging: bool,
     collider_handle: RigidBodyHandle,
+
+    // horizontal velocity coming from input, before platforms and such get added in
+    move_velocity: Vec3,
+    surface: Surface,
+    climbing: bool,
+
+    // queued by `apply_knockback`, handed to the body on the next update
+    pending_impulse: Vec3,
+    knockback_timer: f32,
+
+    // how fast we were falling last frame we were airborne, and the speed we hit the ground with this frame
+    fall_speed: f32,
+    landing_speed: f32,
+
+    // time left where hits miss, and until the next dodge
+    dodge_timer: f32,
+    dodge_cooldown: f32,
+    // something missed us because of a dodge since the last `take_perfect_dodge`
+    perfect_dodge: bool,
+    // health ran out since the last `take_death`
+    died: bool,
 }
 
 impl Player {
-    pub fn setup(rw: &mut RapierPhysicsWorld, r: &mut Renderer) -> Self {
+    pub fn setup(rw: &mut RapierPhysicsWorld, r: &mut Renderer) -> Result<Self, PhysicsError> {
         let pos = vec3a(0.0, 0.0, 0.0);
-        let handle = rw.add_capsule_rigidbody(pos.x, pos.y, pos.z);
+        let handle = rw.add_body(pos.into(), &BodySpec::capsule(CAPSULE_RADIUS, CAPSULE_HALF_HEIGHT)
+            .restitution(0.5)
+            .friction(1.0)
+            .layer(CollisionLayer::Player));
+
+        rw.rigid_body_set.get_mut(handle).ok_or(PhysicsError::MissingBody(handle))?.lock_rotations(true, false); // so it doesnt fall
 
-        rw.rigid_body_set[handle].lock_rotations(false, false); // so it doesnt fall
+        let id = EntityId::new(EntityKind::Player);
+        rw.tag_body(handle, id);
  
-        Self {
+        Ok(Self {
+            id,
             pos,
+            health: 100.0,
+            max_health: 100.0,
+            speed_multiplier: 1.0,
+            stamina: 100.0,
+            max_stamina: 100.0,
+            movement: MovementSettings::default(),
+            swinging: false,
             collider_handle: handle,
-        }
+            move_velocity: Vec3::ZERO,
+            surface: Surface::Air,
+            climbing: false,
+            pending_impulse: Vec3::ZERO,
+            knockback_timer: 0.0,
+            fall_speed: 0.0,
+            landing_speed: 0.0,
+            dodge_timer: 0.0,
+            dodge_cooldown: 0.0,
+            perfect_dodge: false,
+            died: false,
+        })
     }
 
     pub fn update(
         &mut self, 
         rw: &mut RapierPhysicsWorld, 
-        el: &mut EventLoop, 
-        r: &mut Renderer,
+        input: &PlayerInput,
+        dt: f32,
+        platforms: &[MovingPlatform],
     ) {
+        // whatever moved the body since the last step (teleporters, the hook, being pushed) counts from here on
+        self.sync_from_body(rw);
+        let was_airborne = self.surface == Surface::Air;
+        self.surface = self.detect_surface(rw);
+        self.track_landing(rw, was_airborne);
+        self.set_climbing(rw, rw.is_body_on_ladder(self.collider_handle));
+
+        // on a ladder w/s climb instead of walking, so only strafing is left for the xz plane
+        let wish_dir = Self::wish_direction(input, !self.climbing);
+
+        let sprinting = wish_dir != Vec3::ZERO && self.stamina > 0.0 && input.sprint;
+        let sprint = if sprinting {
+            self.stamina = (self.stamina - self.movement.stamina_drain * dt).max(0.0);
+            self.movement.sprint_multiplier
+        } else {
+            self.stamina = (self.stamina + self.movement.stamina_regen * dt).min(self.max_stamina);
+            1.0
+        };
+
+        // ramp towards the target speed instead of snapping to it
+        let target = wish_dir * self.movement.max_speed * self.speed_multiplier * sprint * self.surface.speed_multiplier();
+        let rate = if wish_dir == Vec3::ZERO { self.movement.deceleration } else { self.movement.acceleration };
+        self.move_velocity = self.move_velocity.move_towards(target, rate * dt);
+
+        self.dodge_timer = (self.dodge_timer - dt).max(0.0);
+        self.dodge_cooldown = (self.dodge_cooldown - dt).max(0.0);
+        if input.dodge && self.dodge_cooldown <= 0.0 && wish_dir != Vec3::ZERO && self.surface != Surface::Air && !self.climbing {
+            self.move_velocity = wish_dir * self.movement.max_speed * self.speed_multiplier * DODGE_SPEED;
+            self.dodge_timer = DODGE_WINDOW;
+            self.dodge_cooldown = DODGE_COOLDOWN;
+        }
+
+        let mut velocity = if self.swinging && self.surface == Surface::Air {
+            // the rope is what's moving us, input only nudges the swing
+            let current = rw.rigid_body_set[self.collider_handle].linvel();
+            self.move_velocity = vec3(current.x, 0.0, current.z);
+            self.move_velocity + wish_dir * self.movement.acceleration * SWING_CONTROL * dt
+        } else {
+            self.resolve_motion(rw, self.move_velocity, dt)
+        };
+
+        // ride along with whatever platform we're standing on instead of sliding off it
+        if let Some(platform) = self.standing_on(rw, platforms) {
+            velocity += platform.velocity;
+        }
+
         let capsule = &mut rw.rigid_body_set[self.collider_handle];
-        
-        capsule.set_translation(vector![self.pos.x, self.pos.y, self.pos.z], true);
-    
-        // capsule.set_translation(vector![self.pos.x, self.pos.y, self.pos.z], true);
+        if self.pending_impulse != Vec3::ZERO {
+            let impulse = self.pending_impulse;
+            capsule.apply_impulse(vector![impulse.x, impulse.y, impulse.z], true);
+            self.pending_impulse = Vec3::ZERO;
+            self.knockback_timer = KNOCKBACK_RECOVERY;
+        }
+
+        // right after a knockback the body keeps most of its own momentum, input takes over again as the timer runs out
+        if self.knockback_timer > 0.0 {
+            let authority = 1.0 - self.knockback_timer / KNOCKBACK_RECOVERY;
+            let current = capsule.linvel();
+            velocity = vec3(current.x, 0.0, current.z).lerp(velocity, authority);
+            self.knockback_timer = (self.knockback_timer - dt).max(0.0);
+        }
+
+        // the solver owns the vertical axis (gravity, landing, buoyancy), we only steer horizontally unless swimming
+        let mut vertical = capsule.linvel().y;
+        if self.climbing {
+            vertical = Self::climb_direction(input) * self.movement.climb_speed;
+        } else if self.swimming() {
+            let swim = Self::swim_direction(input);
+            if swim != 0.0 {
+                vertical = swim * self.movement.swim_vertical_speed;
+            }
+        }
+        capsule.set_linvel(vector![velocity.x, vertical, velocity.z], true);
+    }
+
+    // slides along walls and climbs small ledges, returns the horizontal velocity that's actually possible
+    fn resolve_motion(&mut self, rw: &mut RapierPhysicsWorld, velocity: Vec3, dt: f32) -> Vec3 {
+        let speed = velocity.length();
+        let distance = speed * dt;
+        if distance <= f32::EPSILON {
+            return velocity;
+        }
+
+        let direction = velocity / speed;
+        let shape = Capsule::new_y(CAPSULE_HALF_HEIGHT, CAPSULE_RADIUS);
+        let origin = self.position() + Vec3::Y * SKIN;
+
+        let Some(hit) = rw.cast_shape(origin, direction, distance + SKIN, &shape, self.collider_handle) else {
+            return velocity;
+        };
+
+        // ramps are fine to walk up as is
+        if self.is_walkable(hit.normal) {
+            return velocity;
+        }
+
+        if let Some(step) = self.step_height(rw, &shape, origin, direction, distance) {
+            let body = &mut rw.rigid_body_set[self.collider_handle];
+            let t = *body.translation();
+            // the body goes up now and the step carries it forward, `pos` catches up when it's read back
+            body.set_translation(vector![t.x, t.y + step, t.z], true);
+            return velocity;
+        }
+
+        // take out whatever part of the motion points into the wall and keep the rest
+        let wall_normal = vec3(hit.normal.x, 0.0, hit.normal.z).normalize_or_zero();
+        velocity - wall_normal * velocity.dot(wall_normal).min(0.0)
+    }
+
+    // how high we'd have to go to get on top of the ledge in front of us, if it's low enough to step on
+    fn step_height(&self, rw: &RapierPhysicsWorld, shape: &Capsule, origin: Vec3, direction: Vec3, distance: f32) -> Option<f32> {
+        let max_step = self.movement.max_step_height;
+        let raised = origin + Vec3::Y * max_step;
+
+        // still blocked even when raised, so it's a wall and not a ledge
+        if rw.cast_shape(raised, direction, distance + SKIN, shape, self.collider_handle).is_some() {
+            return None;
+        }
+
+        let ahead = raised + direction * (distance + SKIN);
+        let ground = rw.cast_shape(ahead, -Vec3::Y, max_step, shape, self.collider_handle)?;
+        if !self.is_walkable(ground.normal) {
+            return None;
+        }
+
+        Some(max_step - ground.distance)
+    }
+
+    fn is_walkable(&self, normal: Vec3) -> bool {
+        normal.angle_between(Vec3::Y) <= self.movement.max_slope_angle.to_radians()
+    }
+
+    // how close to max speed we're moving horizontally, 0 when standing still
+    pub fn speed_ratio(&self) -> f32 {
+        vec3(self.move_velocity.x, 0.0, self.move_velocity.z).length() / self.movement.max_speed
+    }
+
+    // input direction on the xz plane, relative to where the camera looks and normalized so diagonals aren't faster
+    fn wish_direction(input: &PlayerInput, forward_input: bool) -> Vec3 {
+        let look = input.look();
+        let forward = vec3(look.x, 0.0, look.z).normalize_or_zero();
+        let right = forward.cross(Vec3::Y);
+
+        let mut direction = Vec3::ZERO;
+        if forward_input && input.forward {
+            direction += forward;
+        }
+        if forward_input && input.back {
+            direction -= forward;
+        }
+        if input.left {
+            direction -= right;
+        }
+        if input.right {
+            direction += right;
+        }
+
+        direction.normalize_or_zero()
+    }
+
+    fn climb_direction(input: &PlayerInput) -> f32 {
+        let mut direction = 0.0;
+        if input.forward {
+            direction += 1.0;
+        }
+        if input.back {
+            direction -= 1.0;
+        }
+
+        direction
+    }
+
+    // gravity is off while climbing so letting go of w/s leaves us hanging on the ladder
+    fn set_climbing(&mut self, rw: &mut RapierPhysicsWorld, climbing: bool) {
+        if self.climbing == climbing {
+            return;
+        }
+
+        self.climbing = climbing;
+        let gravity_scale = if climbing { 0.0 } else { 1.0 };
+        rw.rigid_body_set[self.collider_handle].set_gravity_scale(gravity_scale, true);
+    }
+
+    fn swim_direction(input: &PlayerInput) -> f32 {
+        let mut direction = 0.0;
+        if input.up {
+            direction += 1.0;
+        }
+        if input.down {
+            direction -= 1.0;
+        }
+
+        direction
+    }
+
+    pub fn swimming(&self) -> bool {
+        self.surface == Surface::Water
+    }
+
+    pub fn velocity(&self, rw: &RapierPhysicsWorld) -> Vec3 {
+        let v = rw.rigid_body_set[self.collider_handle].linvel();
+        vec3(v.x, v.y, v.z)
+    }
+
+    // 0 unless we touched down this frame
+    pub fn landing_speed(&self) -> f32 {
+        self.landing_speed
+    }
+
+    fn track_landing(&mut self, rw: &RapierPhysicsWorld, was_airborne: bool) {
+        self.landing_speed = 0.0;
+        if self.surface == Surface::Air {
+            self.fall_speed = (-rw.rigid_body_set[self.collider_handle].linvel().y).max(0.0);
+        } else {
+            if was_airborne {
+                self.landing_speed = self.fall_speed;
+            }
+            self.fall_speed = 0.0;
+        }
+    }
+
+    // where the body was after the last step, meshes, the camera and everything else read this and never move it
+    pub fn position(&self) -> Vec3 {
+        self.pos.into()
+    }
+
+    // call after stepping the physics world, the body is the one that knows where the player really is
+    pub fn sync_from_body(&mut self, rw: &RapierPhysicsWorld) {
+        let t = rw.rigid_body_set[self.collider_handle].translation();
+        self.pos = vec3a(t.x, t.y, t.z);
+    }
+
+    // after the body was moved somewhere else entirely, so getting put down there doesn't count as a fall
+    pub fn teleported(&mut self, rw: &RapierPhysicsWorld) {
+        self.sync_from_body(rw);
+        self.fall_speed = 0.0;
+    }
+
+    fn detect_surface(&self, rw: &RapierPhysicsWorld) -> Surface {
+        if rw.is_body_in_water(self.collider_handle) {
+            return Surface::Water;
+        }
+
+        match rw.cast_ray(self.pos.into(), -Vec3::Y, GROUND_CHECK_DISTANCE, self.collider_handle) {
+            Some((collider, _)) => Surface::from_entity(rw.entity_of(collider)),
+            None => Surface::Air,
+        }
+    }
+
+    // as of the last update
+    pub fn current_surface(&self) -> Surface {
+        self.surface
+    }
+
+    pub fn standing_on<'a>(&self, rw: &RapierPhysicsWorld, platforms: &'a [MovingPlatform]) -> Option<&'a MovingPlatform> {
+        let below = rw.body_below(self.pos.into(), GROUND_CHECK_DISTANCE, self.collider_handle)?;
+
+        platforms.iter().find(|platform| platform.handle == below)
+    }
+
+    pub fn body(&self) -> RigidBodyHandle {
+        self.collider_handle
+    }
+
+    pub fn damage(&mut self, amount: f32) {
+        if self.dodge_timer > 0.0 {
+            self.perfect_dodge = true;
+            return;
+        }
+        let was_alive = self.health > 0.0;
+        self.health = (self.health - amount).max(0.0);
+        self.died |= was_alive && self.health <= 0.0;
+    }
+
+    // true once after something missed us mid-dodge
+    pub fn take_perfect_dodge(&mut self) -> bool {
+        std::mem::take(&mut self.perfect_dodge)
+    }
+
+    // true once after the hit that took the last of our health
+    pub fn take_death(&mut self) -> bool {
+        std::mem::take(&mut self.died)
+    }
+
+    // the extra max health from a level up comes with the health to fill it, less max health just caps it
+    pub fn apply_stats(&mut self, stats: Stats) {
+        self.health = (self.health + (stats.max_health - self.max_health).max(0.0)).min(stats.max_health);
+        self.max_health = stats.max_health;
+        self.speed_multiplier = stats.speed;
+    }
+
+    // pushes the player around for a moment, stacking if several hits land in the same frame
+    pub fn apply_knockback(&mut self, impulse: Vec3) {
+        if self.dodge_timer > 0.0 {
+            self.perfect_dodge = true;
+            return;
+        }
+        self.pending_impulse += impulse;
     }
 }
@@ -0,0 +1,126 @@
+use std::collections::VecDeque;
+
+use tiny_game_framework::{glam::{vec2, Vec3}, glfw::Key, imgui::{Condition, Ui}, Camera, EventLoop};
+
+use crate::camera::world_to_screen;
+
+const MAX_MESSAGES: usize = 8;
+// messages fade out after this long unless the chat is open
+const MESSAGE_LIFETIME: f32 = 8.0;
+const MESSAGE_FADE: f32 = 1.0;
+const MAX_MESSAGE_LENGTH: usize = 200;
+const CHAT_WIDTH: f32 = 360.0;
+// name tags float this far above the middle of the player
+const NAME_TAG_HEIGHT: f32 = 90.0;
+
+struct ChatMessage {
+    name: String,
+    text: String,
+    age: f32,
+}
+
+// enter opens the input box, enter again sends whatever was typed and escape closes it without sending
+pub struct Chat {
+    pub open: bool,
+    draft: String,
+    messages: VecDeque<ChatMessage>,
+    enter_held: bool,
+    focus: bool,
+}
+
+impl Chat {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            draft: String::new(),
+            messages: VecDeque::new(),
+            enter_held: false,
+            focus: false,
+        }
+    }
+
+    pub fn receive(&mut self, name: &str, text: &str) {
+        self.messages.push_back(ChatMessage { name: name.to_string(), text: text.to_string(), age: 0.0 });
+        if self.messages.len() > MAX_MESSAGES {
+            self.messages.pop_front();
+        }
+    }
+
+    pub fn update(&mut self, el: &mut EventLoop, dt: f32) {
+        // only the press opens it, otherwise the enter that sent a message would open it right back up
+        let enter = el.is_key_down(Key::Enter);
+        if enter && !self.enter_held && !self.open {
+            self.open = true;
+            self.focus = true;
+        }
+        self.enter_held = enter;
+
+        if self.open && el.is_key_down(Key::Escape) {
+            self.open = false;
+            self.draft.clear();
+        }
+
+        for message in self.messages.iter_mut() {
+            message.age += dt;
+        }
+    }
+
+    // returns a message once the player hits enter on it
+    pub fn draw(&mut self, ui: &Ui) -> Option<String> {
+        let [_, height] = ui.io().display_size;
+        let visible = self.open || self.messages.iter().any(|message| message.age < MESSAGE_LIFETIME);
+        if !visible {
+            return None;
+        }
+
+        let mut sent = None;
+        ui.window("chat")
+            .position([10.0, height - 250.0], Condition::Always)
+            .size([CHAT_WIDTH, 0.0], Condition::Always)
+            .no_decoration()
+            .bg_alpha(if self.open { 0.4 } else { 0.0 })
+            .always_auto_resize(true)
+            .build(|| {
+                for message in self.messages.iter() {
+                    let alpha = if self.open { 1.0 } else { ((MESSAGE_LIFETIME - message.age) / MESSAGE_FADE).clamp(0.0, 1.0) };
+                    if alpha <= 0.0 {
+                        continue;
+                    }
+                    ui.text_colored([0.6, 0.8, 1.0, alpha], format!("{}:", message.name));
+                    ui.same_line();
+                    ui.text_colored([1.0, 1.0, 1.0, alpha], &message.text);
+                }
+
+                if !self.open {
+                    return;
+                }
+                if self.focus {
+                    ui.set_keyboard_focus_here();
+                    self.focus = false;
+                }
+                if ui.input_text("##chat", &mut self.draft).enter_returns_true(true).build() {
+                    let text: String = self.draft.trim().chars().take(MAX_MESSAGE_LENGTH).collect();
+                    if !text.is_empty() {
+                        sent = Some(text);
+                    }
+                    self.draft.clear();
+                    self.open = false;
+                }
+            });
+
+        sent
+    }
+}
+
+// names over the heads of the other players, skipped for anyone behind the camera
+pub fn draw_name_tags(ui: &Ui, camera: &Camera, world_scale: f32, players: &[(String, Vec3)]) {
+    let [width, height] = ui.io().display_size;
+    let screen = vec2(width, height);
+    let draw_list = ui.get_foreground_draw_list();
+
+    for (name, position) in players.iter() {
+        let Some(point) = world_to_screen(camera, *position + Vec3::Y * NAME_TAG_HEIGHT, screen, world_scale) else { continue };
+        let [text_width, _] = ui.calc_text_size(name);
+        draw_list.add_text([point.x - text_width / 2.0, point.y], [1.0, 1.0, 1.0, 0.9], name);
+    }
+}
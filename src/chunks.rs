@@ -0,0 +1,429 @@
+use std::{cmp::Reverse, collections::{BTreeMap, BinaryHeap, HashMap, HashSet}, sync::{atomic::{AtomicBool, Ordering}, Arc}};
+
+use rapier3d::prelude::*;
+use tiny_game_framework::{glam::Vec3, Mesh, Renderer, Vertex};
+use tokio::sync::{mpsc::{self, error::TryRecvError, Receiver}, oneshot};
+
+use crate::{
+    coords::{ChunkCoord, WorldPos},
+    dungeon::{DungeonConfig, DungeonLayout},
+    generator::{gen_maze_async, new_quadrant, BuildContext, ChunkBatches, MeshResult, UploadBudget},
+    rapier_integration::RapierPhysicsWorld,
+    shutdown::Shutdown,
+};
+
+// quadrants collapsing at the same time, each one keeps a core busy
+const MAX_ACTIVE: usize = 2;
+// quadrants this many chunks away from the player are dropped if they haven't started building yet
+const CANCEL_DISTANCE: i32 = 3;
+// chunks generated ahead of the player that nobody asked for yet, at most this many at once
+const SPECULATIVE_CHUNKS: usize = 2;
+// finished speculative chunks kept around in case the player turns back, the farthest goes first
+const MAX_CACHED: usize = 4;
+// the player is guessed to be where this many seconds of walking the way they're going takes them
+const LOOKAHEAD: f32 = 3.0;
+// slower than this and there's no telling where the player is headed
+const MIN_HEADING_SPEED: f32 = 50.0;
+// what rapier keeps per body or collider on top of its shape, a guess
+const COLLIDER_OVERHEAD: usize = 512;
+// the starting quadrant has the boss and the locks in it, it's never unloaded
+const HOME_CHUNK: ChunkCoord = ChunkCoord::new(0, 0);
+
+// everything a chunk's tiles put into the world, so it can all be taken out again, and roughly how much memory that
+// is. traps and teleporters aren't in it, they're linked to each other by index and stay for the whole run
+#[derive(Default)]
+pub struct ChunkFootprint {
+    meshes: Vec<String>,
+    bodies: Vec<RigidBodyHandle>,
+    colliders: Vec<ColliderHandle>,
+    pub bytes: usize,
+}
+
+impl ChunkFootprint {
+    // counted twice, the framework keeps the vertices around after uploading them
+    pub fn add_mesh(&mut self, name: &str, mesh: &Mesh) {
+        self.meshes.push(name.to_string());
+        self.bytes += 2 * (mesh.vertices.len() * std::mem::size_of::<Vertex>() + mesh.indices.len() * std::mem::size_of::<u32>());
+    }
+
+    // `shape_bytes` is whatever the collider's shape holds on to, a trimesh's triangles or a heightfield's heights
+    pub fn add_body(&mut self, body: RigidBodyHandle, shape_bytes: usize) {
+        self.bodies.push(body);
+        self.bytes += COLLIDER_OVERHEAD + shape_bytes;
+    }
+
+    // a collider on one of the footprint's bodies, it goes when the body does
+    pub fn add_attached_collider(&mut self, shape_bytes: usize) {
+        self.bytes += COLLIDER_OVERHEAD + shape_bytes;
+    }
+
+    pub fn add_collider(&mut self, collider: ColliderHandle, shape_bytes: usize) {
+        self.colliders.push(collider);
+        self.bytes += COLLIDER_OVERHEAD + shape_bytes;
+    }
+
+    pub fn unload(self, rw: &mut RapierPhysicsWorld, r: &mut Renderer) {
+        for name in self.meshes {
+            r.destroy_mesh(&name).ok();
+        }
+        for body in self.bodies {
+            rw.remove_rigidbody(body);
+        }
+        for collider in self.colliders {
+            rw.remove_collider(collider);
+        }
+    }
+}
+
+struct ActiveChunk {
+    receiver: Receiver<MeshResult>,
+    layout: oneshot::Receiver<DungeonLayout>,
+    cancel: Arc<AtomicBool>,
+    // once tiles start showing up the chunk is finished no matter what, so it's never left half built
+    tiles_built: usize,
+    footprint: ChunkFootprint,
+    batches: ChunkBatches,
+}
+
+struct LoadedChunk {
+    footprint: ChunkFootprint,
+    // `ChunkManager::clock` the last time a player was in or next to it
+    last_used: u64,
+}
+
+// a quadrant generated before anyone asked for it. the tiles are held here instead of built, so once it's asked
+// for it goes up all at once
+struct SpeculativeChunk {
+    receiver: Receiver<MeshResult>,
+    layout: oneshot::Receiver<DungeonLayout>,
+    cancel: Arc<AtomicBool>,
+    tiles: Vec<MeshResult>,
+    // the generator sent everything
+    complete: bool,
+    // asked for while it was still generating, it's built as soon as it completes
+    wanted: bool,
+}
+
+// decides which quadrants get generated and in what order, nearest to a player first
+pub struct ChunkManager {
+    seed: u64,
+    // every quadrant of the run is generated with this
+    config: DungeonConfig,
+    // (distance in chunks to the nearest player, chunk), re-sorted whenever a player moves to another chunk
+    queue: BinaryHeap<Reverse<(i32, ChunkCoord)>>,
+    // ordered so tiles from several chunks get built in the same order on every machine
+    active: BTreeMap<ChunkCoord, ActiveChunk>,
+    speculative: HashMap<ChunkCoord, SpeculativeChunk>,
+    done: HashSet<ChunkCoord>,
+    loaded: HashMap<ChunkCoord, LoadedChunk>,
+    // counts up every `evict`, for telling which chunk was visited least recently
+    clock: u64,
+    // built since the last `take_finished`
+    finished: Vec<ChunkCoord>,
+    layouts: HashMap<ChunkCoord, DungeonLayout>,
+    // the local player's, speculation only goes by where they're heading
+    player_chunk: ChunkCoord,
+    // off in lockstep games, where it's started depends on how fast this machine is and a guess that's asked for
+    // skips the queue
+    speculate: bool,
+    // every simulated player's, local one included. what's queued and what's cancelled goes by the nearest of them
+    player_chunks: Vec<ChunkCoord>,
+}
+
+impl ChunkManager {
+    pub fn new(seed: u64, config: DungeonConfig) -> Self {
+        Self {
+            seed,
+            config,
+            queue: BinaryHeap::new(),
+            active: BTreeMap::new(),
+            speculative: HashMap::new(),
+            done: HashSet::new(),
+            loaded: HashMap::new(),
+            clock: 0,
+            finished: Vec::new(),
+            layouts: HashMap::new(),
+            player_chunk: ChunkCoord::default(),
+            speculate: true,
+            player_chunks: vec![ChunkCoord::default()],
+        }
+    }
+
+    // whether to generate the chunks the local player seems to be heading into before anyone asks for them
+    pub fn with_speculation(mut self, speculate: bool) -> Self {
+        self.speculate = speculate;
+        self
+    }
+
+    pub fn request(&mut self, chunk: ChunkCoord) {
+        if let Some(speculative) = self.speculative.get_mut(&chunk) {
+            speculative.wanted = true;
+            return;
+        }
+        let queued = self.queue.iter().any(|Reverse((_, queued))| *queued == chunk);
+        if queued || self.active.contains_key(&chunk) || self.done.contains(&chunk) {
+            return;
+        }
+        self.queue.push(Reverse((self.nearest_player(chunk), chunk)));
+    }
+
+    pub fn is_done(&self, chunk: ChunkCoord) -> bool {
+        self.done.contains(&chunk)
+    }
+
+    // chunks that finished building since the last call, for passes that run once the whole chunk is in
+    pub fn take_finished(&mut self) -> Vec<ChunkCoord> {
+        std::mem::take(&mut self.finished)
+    }
+
+    // `None` until the chunk is done, and for good if generating it failed
+    pub fn layout(&self, chunk: ChunkCoord) -> Option<&DungeonLayout> {
+        self.layouts.get(&chunk)
+    }
+
+    // the chunk and the 8 around it, for when a player shows up somewhere without walking there
+    pub fn request_around(&mut self, center: ChunkCoord) {
+        for x in -1..=1 {
+            for z in -1..=1 {
+                self.request(ChunkCoord::new(center.x + x, center.z + z));
+            }
+        }
+    }
+
+    // chebyshev, so the 8 chunks around the player are all 1 away
+    fn distance(a: ChunkCoord, b: ChunkCoord) -> i32 {
+        (a.x - b.x).abs().max((a.z - b.z).abs())
+    }
+
+    // every chunk gets its own layout, the one at the origin keeps the plain seed
+    fn chunk_seed(&self, chunk: ChunkCoord) -> u64 {
+        let key = ((chunk.x as u32 as u64) << 32) | chunk.z as u32 as u64;
+        self.seed ^ key.wrapping_mul(0x9E37_79B9_7F4A_7C15)
+    }
+
+    // chunks away from the nearest player, the same on every client since it only goes by where players are
+    fn nearest_player(&self, chunk: ChunkCoord) -> i32 {
+        self.player_chunks.iter().map(|&player| Self::distance(chunk, player)).min().unwrap_or(0)
+    }
+
+    // `players` are every simulated player's positions, each one's chunk gets built so their bodies have something
+    // to stand on on every client. `local` and `velocity` are this client's player, only speculative generation
+    // goes by them
+    pub fn update(&mut self, players: &[Vec3], local: Vec3, velocity: Vec3, shutdown: &mut Shutdown) {
+        self.player_chunk = WorldPos(local).chunk();
+        let player_chunks: Vec<ChunkCoord> = players.iter().map(|&player| WorldPos(player).chunk()).collect();
+        if player_chunks != self.player_chunks {
+            self.player_chunks = player_chunks;
+            // walked in through a broken wall, or got here some other way nobody asked for it
+            for chunk in self.player_chunks.clone() {
+                self.request(chunk);
+            }
+
+            let queue = std::mem::take(&mut self.queue);
+            self.queue = queue.into_iter()
+                .map(|Reverse((_, chunk))| Reverse((self.nearest_player(chunk), chunk)))
+                .filter(|Reverse((distance, _))| *distance < CANCEL_DISTANCE)
+                .collect();
+
+            for (chunk, active) in self.active.iter() {
+                if active.tiles_built == 0 && self.nearest_player(*chunk) >= CANCEL_DISTANCE {
+                    active.cancel.store(true, Ordering::Relaxed);
+                }
+            }
+            // cancelled ones are forgotten, asking for them again starts over
+            self.active.retain(|_, active| !active.cancel.load(Ordering::Relaxed));
+            let player_chunks = &self.player_chunks;
+            self.speculative.retain(|chunk, speculative| {
+                let keep = speculative.wanted || player_chunks.iter().any(|&player| Self::distance(*chunk, player) < CANCEL_DISTANCE);
+                if !keep {
+                    speculative.cancel.store(true, Ordering::Relaxed);
+                }
+                keep
+            });
+        }
+
+        while self.active.len() < MAX_ACTIVE {
+            let Some(Reverse((_, chunk))) = self.queue.pop() else { break };
+
+            let cancel = Arc::new(AtomicBool::new(false));
+            let quadrant = new_quadrant(self.chunk_seed(chunk), chunk, self.config.clone(), cancel.clone(), shutdown.token());
+            shutdown.track(quadrant.task);
+            self.active.insert(chunk, ActiveChunk { receiver: quadrant.tiles, layout: quadrant.layout, cancel, tiles_built: 0, footprint: ChunkFootprint::default(), batches: ChunkBatches::default() });
+        }
+
+        if self.speculate {
+            self.update_speculative(local, velocity, shutdown);
+        }
+    }
+
+    // keeps the chunks the player seems to be heading into generating, and hands the finished ones that were asked
+    // for over to be built
+    fn update_speculative(&mut self, player: Vec3, velocity: Vec3, shutdown: &mut Shutdown) {
+        for speculative in self.speculative.values_mut() {
+            while !speculative.complete {
+                match speculative.receiver.try_recv() {
+                    Ok(tile) => speculative.tiles.push(tile),
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => speculative.complete = true,
+                }
+            }
+        }
+
+        let ready: Vec<ChunkCoord> = self.speculative.iter()
+            .filter(|(_, speculative)| speculative.complete && speculative.wanted)
+            .map(|(&chunk, _)| chunk)
+            .collect();
+        for chunk in ready {
+            let Some(speculative) = self.speculative.remove(&chunk) else { continue };
+            // everything's already in the channel and nothing else will be, so it builds in one go
+            let (sender, receiver) = mpsc::channel(speculative.tiles.len().max(1));
+            for tile in speculative.tiles {
+                sender.try_send(tile).ok();
+            }
+            self.active.insert(chunk, ActiveChunk { receiver, layout: speculative.layout, cancel: speculative.cancel, tiles_built: 0, footprint: ChunkFootprint::default(), batches: ChunkBatches::default() });
+        }
+
+        let predicted = self.predict(player, velocity);
+        // guesses the player already turned away from aren't worth finishing
+        self.speculative.retain(|chunk, speculative| {
+            let keep = speculative.complete || speculative.wanted || predicted.contains(chunk);
+            if !keep {
+                speculative.cancel.store(true, Ordering::Relaxed);
+            }
+            keep
+        });
+        while self.speculative.values().filter(|speculative| speculative.complete && !speculative.wanted).count() > MAX_CACHED {
+            let player_chunk = self.player_chunk;
+            let farthest = self.speculative.iter()
+                .filter(|(_, speculative)| speculative.complete && !speculative.wanted)
+                .max_by_key(|(&chunk, _)| Self::distance(chunk, player_chunk))
+                .map(|(&chunk, _)| chunk);
+            let Some(farthest) = farthest else { break };
+            self.speculative.remove(&farthest);
+        }
+
+        // only ever on cores the real queue isn't using
+        if !self.queue.is_empty() {
+            return;
+        }
+        let mut generating = self.active.len() + self.speculative.values().filter(|speculative| !speculative.complete).count();
+        for chunk in predicted {
+            if generating >= MAX_ACTIVE {
+                break;
+            }
+            if self.speculative.contains_key(&chunk) || self.active.contains_key(&chunk) || self.done.contains(&chunk) {
+                continue;
+            }
+
+            let cancel = Arc::new(AtomicBool::new(false));
+            let quadrant = new_quadrant(self.chunk_seed(chunk), chunk, self.config.clone(), cancel.clone(), shutdown.token());
+            shutdown.track(quadrant.task);
+            self.speculative.insert(chunk, SpeculativeChunk {
+                receiver: quadrant.tiles,
+                layout: quadrant.layout,
+                cancel,
+                tiles: Vec::new(),
+                complete: false,
+                wanted: false,
+            });
+            generating += 1;
+        }
+    }
+
+    // the chunk the player ends up in if they keep going the way they are, then the next one over along the way
+    // they're mostly going, most likely first
+    fn predict(&self, player: Vec3, velocity: Vec3) -> Vec<ChunkCoord> {
+        let heading = Vec3::new(velocity.x, 0.0, velocity.z);
+        if heading.length() < MIN_HEADING_SPEED {
+            return Vec::new();
+        }
+
+        let ahead = WorldPos(player + heading * LOOKAHEAD).chunk();
+        let step = if heading.x.abs() > heading.z.abs() {
+            ChunkCoord::new(heading.x.signum() as i32, 0)
+        } else {
+            ChunkCoord::new(0, heading.z.signum() as i32)
+        };
+        let next = ChunkCoord::new(self.player_chunk.x + step.x, self.player_chunk.z + step.z);
+
+        let mut predicted = Vec::new();
+        for chunk in [ahead, next] {
+            if chunk != self.player_chunk && !predicted.contains(&chunk) {
+                predicted.push(chunk);
+            }
+        }
+        predicted.truncate(SPECULATIVE_CHUNKS);
+        predicted
+    }
+
+    // builds whatever tiles arrived since last frame that `budget` has room for, returns how many
+    pub async fn build(&mut self, context: &mut BuildContext<'_>, budget: &mut UploadBudget) -> usize {
+        let mut built = 0;
+        let mut finished = Vec::new();
+
+        for (&chunk, active) in self.active.iter_mut() {
+            let before = active.tiles_built;
+            match gen_maze_async(&mut active.receiver, context, &mut active.tiles_built, &mut active.footprint, &mut active.batches, budget).await {
+                Ok(true) => finished.push(chunk),
+                Ok(false) => {}
+                // one broken tile shouldn't stop the rest of the chunk, it picks up again next frame
+                Err(e) => log::warn!("building chunk {:?}: {}", chunk, e),
+            }
+            built += active.tiles_built - before;
+        }
+
+        for chunk in finished {
+            if let Some(mut active) = self.active.remove(&chunk) {
+                if let Ok(layout) = active.layout.try_recv() {
+                    self.layouts.insert(chunk, layout);
+                }
+                self.loaded.insert(chunk, LoadedChunk { footprint: active.footprint, last_used: self.clock });
+            }
+            self.done.insert(chunk);
+            self.finished.push(chunk);
+        }
+
+        built
+    }
+
+    // roughly how much memory the built chunks take up, their meshes, colliders and layouts
+    pub fn memory_used(&self) -> usize {
+        self.loaded.values().map(|loaded| loaded.footprint.bytes).sum::<usize>()
+            + self.layouts.values().map(DungeonLayout::bytes).sum::<usize>()
+    }
+
+    // while the built chunks take up more than `budget` bytes, hands back the one players were near least recently
+    // to be unloaded. it's forgotten, so asking for it again builds it over from scratch. chunks with a player in or
+    // next to them are never picked, and it only depends on where `players` are so every client picks the same ones
+    pub fn evict(&mut self, budget: usize, players: &[Vec3]) -> Vec<(ChunkCoord, ChunkFootprint)> {
+        self.clock += 1;
+        let near: Vec<ChunkCoord> = players.iter().map(|&player| WorldPos(player).chunk()).collect();
+        let is_near = |chunk: ChunkCoord| near.iter().any(|&player| Self::distance(chunk, player) <= 1);
+        for (&chunk, loaded) in self.loaded.iter_mut() {
+            if is_near(chunk) {
+                loaded.last_used = self.clock;
+            }
+        }
+
+        let mut used = self.memory_used();
+        let mut evicted = Vec::new();
+        while used > budget {
+            // ties go to the lowest coordinate so the pick doesn't depend on the map's order
+            let oldest = self.loaded.iter()
+                .filter(|(&chunk, _)| chunk != HOME_CHUNK && !is_near(chunk))
+                .min_by_key(|(&chunk, loaded)| (loaded.last_used, chunk.x, chunk.z))
+                .map(|(&chunk, _)| chunk);
+            let Some(chunk) = oldest else { break };
+
+            let Some(loaded) = self.loaded.remove(&chunk) else { break };
+            used -= loaded.footprint.bytes;
+            if let Some(layout) = self.layouts.remove(&chunk) {
+                used -= layout.bytes();
+            }
+            self.done.remove(&chunk);
+            evicted.push((chunk, loaded.footprint));
+        }
+
+        evicted
+    }
+}
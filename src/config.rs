@@ -0,0 +1,501 @@
+use std::{collections::HashMap, fs};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{ai::Node, enemies::EnemyKind, versioning::CONFIG_FORMAT};
+
+// read from the working directory, anything missing from it falls back to the defaults below
+pub const CONFIG_PATH: &str = "config.toml";
+// what each kind of enemy is like, kinds left out of it are plain grunts
+pub const ENEMIES_PATH: &str = "enemies.toml";
+// the same file built in, for when `ENEMIES_PATH` is missing or broken
+const BUILT_IN_ENEMIES: &str = include_str!("../enemies.toml");
+
+#[derive(Deserialize, Clone, Default, Debug)]
+#[serde(default)]
+pub struct Config {
+    pub generation: GenerationSettings,
+    pub camera: CameraSettings,
+    pub lighting: LightingSettings,
+    pub post_processing: PostProcessSettings,
+    pub spawning: SpawnSettings,
+    pub physics: PhysicsSettings,
+    pub net: NetSettings,
+    pub difficulty: DifficultySettings,
+    // behavior tree overrides per enemy kind, see `ai::Node`
+    pub ai: HashMap<EnemyKind, Node>,
+}
+
+impl Config {
+    pub fn load() -> Self {
+        let Ok(source) = fs::read_to_string(CONFIG_PATH) else {
+            return Self::default();
+        };
+
+        // read loosely first so old layouts can be upgraded before they have to match this one
+        let config = toml::from_str::<serde_json::Value>(&source).map_err(|e| e.to_string())
+            .and_then(|value| CONFIG_FORMAT.upgrade(value).map_err(|e| e.to_string()))
+            .and_then(|value| serde_json::from_value(value).map_err(|e| e.to_string()));
+        match config {
+            Ok(config) => config,
+            Err(e) => {
+                log::warn!("couldn't parse {}, using the defaults: {}", CONFIG_PATH, e);
+                Self::default()
+            }
+        }
+    }
+}
+
+// the size of everything in the maze, see `coords`
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct GenerationSettings {
+    // world units per tile along x and z
+    pub tile_size: f32,
+    pub wall_height: f32,
+    // tiles along each side of a generated quadrant
+    pub quadrant_size: u32,
+
+    // a collapse with less open floor or more wall than this (from 0 to 1) is thrown away and redone with a
+    // new seed, up to `max_attempts` times before keeping whatever came out last
+    pub min_floor_ratio: f32,
+    pub max_wall_ratio: f32,
+    pub max_attempts: u32,
+
+    // corridors come out of the collapse one tile wide, past 1 they're widened to this many tiles. walls that end up
+    // thinner than `wall_thickness` get some of what was carved out of them back
+    pub corridor_width: u32,
+    pub wall_thickness: u32,
+
+    // odds of a quadrant being an open cavern with hills instead of a maze, the one everyone starts in never is.
+    // `terrain_amplitude` is the tallest the hills get in world units
+    pub terrain_chance: f32,
+    pub terrain_amplitude: f32,
+
+    // a maze quadrant is first split into cells this many tiles across, each one a room, a corridor between rooms
+    // or solid rock, and the collapse only fills in the detail inside them. `room_chance` is the odds of a cell being
+    // a room, 0 cells leaves the collapse to do everything on its own
+    pub macro_cell_size: u32,
+    pub room_chance: f32,
+
+    // megabytes the built chunks can take up, roughly, before the ones visited least recently are unloaded
+    pub memory_budget_mb: usize,
+
+    // streamed chunks are built a few meshes at a time so one landing doesn't stall the frame, at most this many
+    // uploads or this long each frame. lockstep games only go by the count, every machine has to build the same tiles
+    pub upload_meshes_per_frame: usize,
+    pub upload_ms_per_frame: f32,
+}
+
+impl Default for GenerationSettings {
+    fn default() -> Self {
+        Self {
+            tile_size: 200.0,
+            wall_height: 200.0,
+            quadrant_size: 12,
+            min_floor_ratio: 0.3,
+            max_wall_ratio: 0.6,
+            max_attempts: 5,
+            corridor_width: 1,
+            wall_thickness: 1,
+            terrain_chance: 0.2,
+            terrain_amplitude: 150.0,
+            macro_cell_size: 4,
+            room_chance: 0.5,
+            memory_budget_mb: 512,
+            upload_meshes_per_frame: 8,
+            upload_ms_per_frame: 4.0,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct CameraSettings {
+    pub head_bob: bool,
+    // how far the camera moves up and down at full speed
+    pub bob_amplitude: f32,
+    // steps per second at full speed
+    pub bob_frequency: f32,
+
+    pub smoothing: bool,
+    // higher is snappier, roughly the inverse of how long the camera takes to catch up
+    pub smoothing_speed: f32,
+
+    // scales all screen shake, 0 turns it off
+    pub shake_intensity: f32,
+    // how far the camera moves at full trauma
+    pub shake_max_offset: f32,
+}
+
+impl Default for CameraSettings {
+    fn default() -> Self {
+        Self {
+            head_bob: true,
+            bob_amplitude: 6.0,
+            bob_frequency: 2.0,
+            smoothing: true,
+            smoothing_speed: 20.0,
+            shake_intensity: 1.0,
+            shake_max_offset: 15.0,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct SpawnSettings {
+    // seconds of play it takes for the enemy count to double
+    pub difficulty_ramp: f32,
+    // extra difficulty per floor below the first, 0.25 means 25% more enemies each floor
+    pub floor_scaling: f32,
+    pub biomes: HashMap<String, BiomeSpawns>,
+}
+
+impl Default for SpawnSettings {
+    fn default() -> Self {
+        Self {
+            difficulty_ramp: 300.0,
+            floor_scaling: 0.25,
+            biomes: HashMap::from([("dungeon".to_string(), BiomeSpawns::default())]),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct BiomeSpawns {
+    // how many enemies a chunk should have before difficulty scaling
+    pub population_per_chunk: usize,
+    // how many enemies can be spawned per minute at most
+    pub budget_per_minute: f32,
+    // relative odds for each kind of enemy, kinds that are left out never spawn
+    pub weights: HashMap<EnemyKind, f32>,
+}
+
+impl Default for BiomeSpawns {
+    fn default() -> Self {
+        Self {
+            population_per_chunk: 4,
+            budget_per_minute: 6.0,
+            weights: HashMap::from([
+                (EnemyKind::Grunt, 3.0),
+                (EnemyKind::Brute, 1.0),
+                (EnemyKind::Archer, 1.0),
+                (EnemyKind::Tank, 0.5),
+                (EnemyKind::Swarmer, 1.0),
+            ]),
+        }
+    }
+}
+
+// everything about one kind of enemy, see `EnemyKind::archetype` for the built in ones. a kind in the file replaces
+// its built in archetype whole, fields missing from it are a grunt's
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct EnemyArchetype {
+    pub health: f32,
+    // world units across, up and deep
+    pub size: [f32; 3],
+    // heavier bodies are harder to shove around
+    pub density: f32,
+    pub speed: f32,
+    pub attack_damage: f32,
+    pub attack_knockback: f32,
+    // how close the player has to be to be attacked, and seconds between attacks
+    pub attack_range: f32,
+    pub attack_cooldown: f32,
+    pub color: [f32; 4],
+    // how many spawn together at once
+    pub group: usize,
+    // attacks fire one of these at the player instead of hitting them, `None` for melee
+    pub projectile: Option<ProjectileSettings>,
+    // `ai::default_tree` when not set, the config's `ai` overrides it
+    pub behavior: Option<Node>,
+}
+
+impl Default for EnemyArchetype {
+    fn default() -> Self {
+        Self {
+            health: 100.0,
+            size: [60.0, 180.0, 60.0],
+            density: 1.0,
+            speed: 200.0,
+            attack_damage: 10.0,
+            attack_knockback: 150.0,
+            attack_range: 120.0,
+            attack_cooldown: 1.0,
+            color: [0.6, 0.2, 0.2, 1.0],
+            group: 1,
+            projectile: None,
+            behavior: None,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(default)]
+pub struct ProjectileSettings {
+    // world units a second, it flies straight
+    pub speed: f32,
+    // seconds before it's gone if it doesn't hit anything
+    pub lifetime: f32,
+}
+
+impl Default for ProjectileSettings {
+    fn default() -> Self {
+        Self { speed: 700.0, lifetime: 3.0 }
+    }
+}
+
+// the kinds `ENEMIES_PATH` describes, the built in copy of it if it isn't there or doesn't parse
+pub fn load_archetypes() -> HashMap<EnemyKind, EnemyArchetype> {
+    let source = fs::read_to_string(ENEMIES_PATH).unwrap_or_else(|_| BUILT_IN_ENEMIES.to_string());
+
+    toml::from_str(&source).unwrap_or_else(|e| {
+        log::warn!("couldn't parse {}, using the built in enemies: {}", ENEMIES_PATH, e);
+        toml::from_str(BUILT_IN_ENEMIES).unwrap_or_default()
+    })
+}
+
+// see `lighting::Lighting`
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct LightingSettings {
+    // world units the player's torch lights up, fog starts where it gives out
+    pub torch_radius: f32,
+    // what far away things fade into, and the horizon behind everything
+    pub fog_color: [f32; 3],
+    // per biome, biomes left out get the default void
+    pub skies: HashMap<String, Sky>,
+}
+
+impl Default for LightingSettings {
+    fn default() -> Self {
+        Self { torch_radius: 1200.0, fog_color: [0.0, 0.0, 0.0], skies: HashMap::from([("dungeon".to_string(), Sky::default())]) }
+    }
+}
+
+// what's drawn behind the world, see `sky::SkyRenderer`. both fade into the fog color at the horizon so far walls
+// don't stand out against it
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum Sky {
+    // underground, darkness all round with mist drifting through it
+    Void {
+        mist: [f32; 3],
+        // world units a second, roughly
+        drift: f32,
+    },
+    // a gradient up to `zenith`, for open caverns
+    Open {
+        zenith: [f32; 3],
+    },
+}
+
+impl Default for Sky {
+    fn default() -> Self {
+        Sky::Void { mist: [0.08, 0.08, 0.1], drift: 0.05 }
+    }
+}
+
+// see `postprocess::PostProcess`, everything off draws straight to the window like before
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct PostProcessSettings {
+    pub enabled: bool,
+    // how dark the corners of the screen get, 0 is none
+    pub vignette: f32,
+    pub bloom: bool,
+    // brightness past which something glows. ordinary colors stop at 1 and emissive ones go past it, see
+    // `lighting::Emissive`
+    pub bloom_threshold: f32,
+    pub bloom_intensity: f32,
+    pub color_grading: bool,
+    // per biome, biomes left out aren't graded
+    pub grades: HashMap<String, ColorGrade>,
+}
+
+impl Default for PostProcessSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            vignette: 0.4,
+            bloom: true,
+            bloom_threshold: 1.0,
+            bloom_intensity: 0.8,
+            color_grading: true,
+            grades: HashMap::from([("dungeon".to_string(), ColorGrade { contrast: 1.1, saturation: 0.85, tint: [1.0, 0.95, 0.85], ..Default::default() })]),
+        }
+    }
+}
+
+// baked into a lut, applied in this order
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(default)]
+pub struct ColorGrade {
+    pub exposure: f32,
+    pub contrast: f32,
+    // 0 is grey, 1 leaves it alone
+    pub saturation: f32,
+    // multiplied in last
+    pub tint: [f32; 3],
+}
+
+impl Default for ColorGrade {
+    fn default() -> Self {
+        Self { exposure: 1.0, contrast: 1.0, saturation: 1.0, tint: [1.0, 1.0, 1.0] }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct PhysicsSettings {
+    // straight down, gravity volumes override it locally
+    pub gravity: f32,
+    // loose debris allowed at once, the oldest pieces go first past this
+    pub max_debris: usize,
+    // sleeping debris and props this far from every player stop being simulated until someone comes back
+    pub freeze_distance: f32,
+    // off skips continuous collision for every body, even ones that asked for it
+    pub ccd: bool,
+    // how many times a fast body can be rewound to its time of impact in one step, more catches glancing hits
+    // after the first one
+    pub max_ccd_substeps: usize,
+    // substeps shorter than this stop the rewinding, in seconds
+    pub min_ccd_dt: f32,
+    // steps on a thread of its own while the frame is drawn, in fixed steps. the frame still waits for the step at the
+    // end, it only overlaps the drawing, see `PhysicsThread`. ignored in lockstep, where every peer has to step at the
+    // same moment
+    pub threaded: bool,
+}
+
+impl Default for PhysicsSettings {
+    fn default() -> Self {
+        Self {
+            gravity: 9.81,
+            max_debris: 200,
+            freeze_distance: 3000.0,
+            ccd: true,
+            max_ccd_substeps: 1,
+            min_ccd_dt: 1.0 / 60.0 / 100.0,
+            threaded: false,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+    // whatever `DifficultySettings::custom` says
+    Custom,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 4] = [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard, Difficulty::Custom];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Normal => "normal",
+            Difficulty::Hard => "hard",
+            Difficulty::Custom => "custom",
+        }
+    }
+
+    pub fn scaling(&self, custom: &DifficultyScaling) -> DifficultyScaling {
+        match self {
+            Difficulty::Easy => DifficultyScaling {
+                enemy_density: 0.6,
+                trap_frequency: 0.5,
+                player_health: 1.5,
+                enemy_damage: 0.6,
+                dungeon_size: 0.75,
+            },
+            Difficulty::Normal => DifficultyScaling::default(),
+            Difficulty::Hard => DifficultyScaling {
+                enemy_density: 1.5,
+                trap_frequency: 1.5,
+                player_health: 0.75,
+                enemy_damage: 1.5,
+                dungeon_size: 1.25,
+            },
+            Difficulty::Custom => *custom,
+        }
+    }
+}
+
+// everything here multiplies the normal value, 1 everywhere is the normal difficulty
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(default)]
+pub struct DifficultyScaling {
+    // how many enemies a chunk holds and how fast they come back
+    pub enemy_density: f32,
+    // odds of a trap in the sample staying a trap, past 1 plain floor starts turning into traps too
+    pub trap_frequency: f32,
+    pub player_health: f32,
+    // what enemy attacks take off the player
+    pub enemy_damage: f32,
+    // tiles along each side of a quadrant
+    pub dungeon_size: f32,
+}
+
+impl Default for DifficultyScaling {
+    fn default() -> Self {
+        Self {
+            enemy_density: 1.0,
+            trap_frequency: 1.0,
+            player_health: 1.0,
+            enemy_damage: 1.0,
+            dungeon_size: 1.0,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Default, Debug)]
+#[serde(default)]
+pub struct DifficultySettings {
+    // picked in the main menu too, this is what it starts on and what networked games always use
+    pub preset: Difficulty,
+    pub custom: DifficultyScaling,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Default, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum NetMode {
+    #[default]
+    Offline,
+    // runs the game and lets others join
+    Host,
+    Client,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct NetSettings {
+    pub mode: NetMode,
+    // the port to listen on when hosting, the host to join as a client
+    pub address: String,
+    pub name: String,
+    // send inputs instead of state and have every machine run the whole simulation, everyone has to be in
+    // before it starts
+    pub lockstep: bool,
+    // how many players a lockstep game waits for, the host included
+    pub lockstep_players: u32,
+}
+
+impl Default for NetSettings {
+    fn default() -> Self {
+        Self {
+            mode: NetMode::Offline,
+            address: "127.0.0.1:7777".to_string(),
+            name: "player".to_string(),
+            lockstep: false,
+            lockstep_players: 2,
+        }
+    }
+}
@@ -0,0 +1,42 @@
+use tiny_game_framework::glam::Vec3;
+
+use crate::{profiler, rapier_integration::RapierPhysicsWorld, time::TimeScale};
+
+// runs a line typed into the chat after a `/`, the reply goes back into the chat. `networked` is whether there's
+// anyone else in the game, what only changes this machine's world is refused then. `lockstep` games step at a fixed
+// rate and ignore the time scale
+pub fn run(line: &str, time: &mut TimeScale, rw: &mut RapierPhysicsWorld, networked: bool, lockstep: bool) -> String {
+    let mut words = line.split_whitespace();
+    match words.next() {
+        Some("timescale") => match words.next().map(str::parse::<f32>) {
+            Some(Ok(scale)) if !scale.is_finite() => "the time scale has to be a number".to_string(),
+            Some(Ok(scale)) => {
+                time.set(scale);
+                if lockstep {
+                    format!("time scale set to {}, but lockstep games always run at normal speed", time.base())
+                } else {
+                    format!("time scale set to {}", time.base())
+                }
+            }
+            Some(Err(_)) => "usage: /timescale <scale>".to_string(),
+            None => format!("time scale is {}", time.base()),
+        },
+        Some("gravity") => match words.next().map(str::parse::<f32>) {
+            Some(Ok(_)) if networked => "gravity can't be changed in a multiplayer game".to_string(),
+            Some(Ok(gravity)) => {
+                rw.set_gravity(Vec3::NEG_Y * gravity);
+                format!("gravity set to {}", gravity)
+            }
+            Some(Err(_)) => "usage: /gravity <strength>".to_string(),
+            None => format!("gravity is {}", -rw.gravity().y),
+        },
+        Some("trace") => match profiler::toggle_trace() {
+            Ok(None) => "recording a trace, /trace again to stop".to_string(),
+            Ok(Some(count)) => format!("wrote {} scopes to {}", count, profiler::TRACE_PATH),
+            Err(e) => format!("couldn't write the trace: {}", e),
+        },
+        Some("help") => "commands: /timescale [scale], /gravity [strength], /trace, /help".to_string(),
+        Some(command) => format!("unknown command {}, try /help", command),
+        None => "try /help".to_string(),
+    }
+}
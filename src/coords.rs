@@ -0,0 +1,145 @@
+use std::{ops::Add, sync::atomic::{AtomicU32, Ordering}};
+
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use tiny_game_framework::glam::{vec3, Vec3};
+
+use crate::config::GenerationSettings;
+
+// set once at startup from the config, everything after that reads the same scale
+static SCALE: OnceCell<GenerationSettings> = OnceCell::new();
+// the difficulty resizes quadrants for a whole run, 0 keeps the configured size
+static QUADRANT_TILES: AtomicU32 = AtomicU32::new(0);
+
+// call before anything is generated, later calls are ignored
+pub fn configure(settings: &GenerationSettings) {
+    if SCALE.set(settings.clone()).is_err() {
+        log::warn!("the maze scale was already set, ignoring the new one");
+    }
+}
+
+pub fn settings() -> &'static GenerationSettings {
+    SCALE.get_or_init(GenerationSettings::default)
+}
+
+// world units per tile along x and z
+pub fn tile_size() -> f32 {
+    settings().tile_size
+}
+
+pub fn wall_height() -> f32 {
+    settings().wall_height
+}
+
+// tiles along each side of one generated quadrant
+pub fn quadrant_tiles() -> u32 {
+    match QUADRANT_TILES.load(Ordering::Relaxed) {
+        0 => settings().quadrant_size,
+        tiles => tiles,
+    }
+}
+
+// only before the first quadrant of a run is requested, every chunk coordinate means something else after it
+pub fn set_quadrant_tiles(tiles: u32) {
+    QUADRANT_TILES.store(tiles.max(1), Ordering::Relaxed);
+}
+
+// walls are centered on y = 0, the floor is where they stand
+pub fn floor_height() -> f32 {
+    -wall_height() / 2.0
+}
+
+// a tile of the maze grid. z is the canvas' second axis, y in pixel terms
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug, Default)]
+pub struct TileCoord {
+    pub x: i32,
+    pub z: i32,
+}
+
+// one generated quadrant, `quadrant_tiles` tiles on a side
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug, Default)]
+pub struct ChunkCoord {
+    pub x: i32,
+    pub z: i32,
+}
+
+// a position in world units, kept apart from plain vectors where mixing up tiles and units would be easy
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct WorldPos(pub Vec3);
+
+impl TileCoord {
+    pub const NEIGHBORS: [TileCoord; 4] = [
+        TileCoord { x: 1, z: 0 },
+        TileCoord { x: -1, z: 0 },
+        TileCoord { x: 0, z: 1 },
+        TileCoord { x: 0, z: -1 },
+    ];
+
+    pub const fn new(x: i32, z: i32) -> Self {
+        Self { x, z }
+    }
+
+    // the middle of the tile at `height`
+    pub fn world(&self, height: f32) -> WorldPos {
+        let size = tile_size();
+        WorldPos(vec3(self.x as f32 * size, height, self.z as f32 * size))
+    }
+
+    pub fn chunk(&self) -> ChunkCoord {
+        let size = quadrant_tiles() as i32;
+        ChunkCoord { x: self.x.div_euclid(size), z: self.z.div_euclid(size) }
+    }
+
+    pub fn neighbors(&self) -> impl Iterator<Item = TileCoord> {
+        let tile = *self;
+        Self::NEIGHBORS.into_iter().map(move |offset| tile + offset)
+    }
+}
+
+impl Add for TileCoord {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self { x: self.x + other.x, z: self.z + other.z }
+    }
+}
+
+impl ChunkCoord {
+    pub const fn new(x: i32, z: i32) -> Self {
+        Self { x, z }
+    }
+
+    // the tile in the corner closest to the origin
+    pub fn origin(&self) -> TileCoord {
+        let size = quadrant_tiles() as i32;
+        TileCoord { x: self.x * size, z: self.z * size }
+    }
+
+    pub fn contains(&self, tile: TileCoord) -> bool {
+        tile.chunk() == *self
+    }
+}
+
+impl WorldPos {
+    // whichever tile center is closest
+    pub fn tile(&self) -> TileCoord {
+        let size = tile_size();
+        TileCoord { x: (self.0.x / size).round() as i32, z: (self.0.z / size).round() as i32 }
+    }
+
+    pub fn chunk(&self) -> ChunkCoord {
+        self.tile().chunk()
+    }
+}
+
+impl From<Vec3> for WorldPos {
+    fn from(position: Vec3) -> Self {
+        Self(position)
+    }
+}
+
+impl From<WorldPos> for Vec3 {
+    fn from(position: WorldPos) -> Self {
+        position.0
+    }
+}
@@ -0,0 +1,137 @@
+use tiny_game_framework::glam::{vec3, Quat, Vec3};
+
+use crate::animation::Easing;
+
+// the boss intro circles this far out from the arena center and this high above the floor
+const INTRO_RADIUS: f32 = 900.0;
+const INTRO_HEIGHT: f32 = 500.0;
+// the run intro starts this high above the player and drops down into their eyes
+const ARRIVAL_HEIGHT: f32 = 2500.0;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Keyframe {
+    // seconds since the sequence started
+    pub time: f32,
+    pub position: Vec3,
+    // from looking down -z
+    pub rotation: Quat,
+    // how the move from the previous keyframe into this one speeds up and slows down
+    pub easing: Easing,
+}
+
+impl Keyframe {
+    pub fn looking_at(time: f32, position: Vec3, target: Vec3, easing: Easing) -> Self {
+        let direction = (target - position).normalize_or(Vec3::NEG_Z);
+        Self { time, position, rotation: Quat::from_rotation_arc(Vec3::NEG_Z, direction), easing }
+    }
+}
+
+// keyframes the camera goes through in order, positions along a catmull-rom spline so it curves through them
+// instead of zigzagging
+pub struct CameraSequence {
+    keyframes: Vec<Keyframe>,
+    elapsed: f32,
+}
+
+impl CameraSequence {
+    // `keyframes` sorted by time, the first one should be at 0
+    pub fn new(keyframes: Vec<Keyframe>) -> Self {
+        Self { keyframes, elapsed: 0.0 }
+    }
+
+    // swings around the arena once the doors shut, ending back where the player's camera was
+    pub fn boss_intro(eye: Vec3, front: Vec3, center: Vec3) -> Self {
+        let side = vec3(eye.x - center.x, 0.0, eye.z - center.z).normalize_or(Vec3::X);
+        let across = side.cross(Vec3::Y);
+        let above = center + Vec3::Y * INTRO_HEIGHT;
+        Self::new(vec![
+            Keyframe { time: 0.0, position: eye, rotation: Quat::from_rotation_arc(Vec3::NEG_Z, front.normalize_or(Vec3::NEG_Z)), easing: Easing::Linear },
+            Keyframe::looking_at(1.2, above + side * INTRO_RADIUS, center, Easing::EaseOut),
+            Keyframe::looking_at(2.6, above + across * INTRO_RADIUS, center, Easing::Linear),
+            Keyframe::looking_at(4.0, above - side * INTRO_RADIUS * 0.5, center, Easing::Linear),
+            Keyframe { time: 5.2, position: eye, rotation: Quat::from_rotation_arc(Vec3::NEG_Z, front.normalize_or(Vec3::NEG_Z)), easing: Easing::EaseInOut },
+        ])
+    }
+
+    // drops in from high above when a run starts, ending at the player's eyes
+    pub fn arrival(eye: Vec3, front: Vec3) -> Self {
+        let flat = vec3(front.x, 0.0, front.z).normalize_or(Vec3::NEG_Z);
+        Self::new(vec![
+            Keyframe::looking_at(0.0, eye + Vec3::Y * ARRIVAL_HEIGHT - flat * ARRIVAL_HEIGHT * 0.5, eye, Easing::Linear),
+            Keyframe::looking_at(1.5, eye + Vec3::Y * ARRIVAL_HEIGHT * 0.3 - flat * ARRIVAL_HEIGHT * 0.2, eye + flat * 200.0, Easing::EaseIn),
+            Keyframe { time: 2.5, position: eye, rotation: Quat::from_rotation_arc(Vec3::NEG_Z, front.normalize_or(Vec3::NEG_Z)), easing: Easing::EaseOut },
+        ])
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.keyframes.last().map_or(0.0, |keyframe| keyframe.time)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.elapsed >= self.duration()
+    }
+
+    // camera position and the direction it looks in right now
+    pub fn sample(&self) -> Option<(Vec3, Vec3)> {
+        let last = self.keyframes.len().checked_sub(1)?;
+        let next = self.keyframes.iter().position(|keyframe| keyframe.time > self.elapsed).unwrap_or(last).max(1).min(last);
+        let current = next.saturating_sub(1);
+        let (from, to) = (&self.keyframes[current], &self.keyframes[next]);
+
+        let span = to.time - from.time;
+        let t = if span > f32::EPSILON { (self.elapsed - from.time) / span } else { 1.0 };
+        let t = to.easing.apply(t);
+
+        // the keyframes on either side of the segment shape the curve, the ends just repeat
+        let before = self.keyframes[current.saturating_sub(1)].position;
+        let after = self.keyframes[(next + 1).min(last)].position;
+        let position = catmull_rom(before, from.position, to.position, after, t);
+        let rotation = from.rotation.slerp(to.rotation, t);
+
+        Some((position, rotation * Vec3::NEG_Z))
+    }
+}
+
+fn catmull_rom(p0: Vec3, p1: Vec3, p2: Vec3, p3: Vec3, t: f32) -> Vec3 {
+    let (t2, t3) = (t * t, t * t * t);
+    0.5 * (2.0 * p1 + (p2 - p0) * t + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t2 + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t3)
+}
+
+// takes the camera from the player while a sequence plays, player input is ignored until it's done
+#[derive(Default)]
+pub struct CutscenePlayer {
+    current: Option<CameraSequence>,
+    // yaw and pitch from before it started, so the view goes back to exactly where the mouse left it
+    restore: Option<(f32, f32)>,
+}
+
+impl CutscenePlayer {
+    // replaces whatever was playing, `yaw` and `pitch` are the renderer camera's right now
+    pub fn play(&mut self, sequence: CameraSequence, yaw: f32, pitch: f32) {
+        self.current = Some(sequence);
+        self.restore.get_or_insert((yaw, pitch));
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.current.is_some()
+    }
+
+    // where the camera is and where it looks this frame, `None` when nothing's playing
+    pub fn update(&mut self, dt: f32) -> Option<(Vec3, Vec3)> {
+        let sequence = self.current.as_mut()?;
+        sequence.elapsed += dt;
+        let view = sequence.sample();
+        if sequence.is_finished() {
+            self.current = None;
+        }
+        view
+    }
+
+    // the yaw and pitch to put back once the last frame of a sequence has been shown
+    pub fn take_restore(&mut self) -> Option<(f32, f32)> {
+        if self.is_playing() {
+            return None;
+        }
+        self.restore.take()
+    }
+}
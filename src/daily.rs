@@ -0,0 +1,109 @@
+use std::{fs, time::{SystemTime, UNIX_EPOCH}};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{config::Difficulty, error::SaveError, versioning::LEADERBOARD_FORMAT};
+
+const LEADERBOARD_PATH: &str = "leaderboard.json";
+pub const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+// everyone plays the daily run the same way, whatever they picked for their own runs
+pub const DAILY_DIFFICULTY: Difficulty = Difficulty::Normal;
+
+// days since 1970-01-01, in utc so everyone rolls over to the next dungeon at the same moment
+pub fn today() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |since| since.as_secs() / SECONDS_PER_DAY)
+}
+
+// splitmix64 of the day, so one day over is a completely different dungeon
+pub fn seed(day: u64) -> u64 {
+    let mut z = day.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+// yyyy-mm-dd, days to a civil date from howard hinnant's date algorithms
+pub fn date(day: u64) -> String {
+    let z = day as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let d = day_of_year - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = year_of_era + era * 400 + (m <= 2) as i64;
+
+    format!("{:04}-{:02}-{:02}", y, m, d)
+}
+
+// a run that's going on right now, the clock only runs while playing
+pub struct DailyRun {
+    pub day: u64,
+    pub elapsed: f32,
+    pub finished: bool,
+}
+
+impl DailyRun {
+    pub fn start() -> Self {
+        Self { day: today(), elapsed: 0.0, finished: false }
+    }
+
+    pub fn seed(&self) -> u64 {
+        seed(self.day)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LeaderboardEntry {
+    pub date: String,
+    pub name: String,
+    pub seconds: f32,
+}
+
+// finished daily runs on this machine, kept apart from the save so it can be shared or wiped on its own
+#[derive(Serialize, Deserialize, Default, Debug)]
+#[serde(default)]
+pub struct Leaderboard {
+    pub entries: Vec<LeaderboardEntry>,
+}
+
+impl Leaderboard {
+    // an empty one if there isn't one yet or it can't be read
+    pub fn load() -> Self {
+        match Self::read() {
+            Ok(leaderboard) => leaderboard,
+            Err(SaveError::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => Self::default(),
+            Err(e) => {
+                log::warn!("starting from an empty leaderboard, {}", e);
+                Self::default()
+            }
+        }
+    }
+
+    fn read() -> Result<Self, SaveError> {
+        let source = fs::read_to_string(LEADERBOARD_PATH)?;
+        let value = LEADERBOARD_FORMAT.upgrade(serde_json::from_str(&source)?)?;
+        Ok(serde_json::from_value(value)?)
+    }
+
+    pub fn write(&self) -> Result<(), SaveError> {
+        let mut value = serde_json::to_value(self)?;
+        LEADERBOARD_FORMAT.stamp(&mut value);
+        fs::write(LEADERBOARD_PATH, serde_json::to_string_pretty(&value)?)?;
+        Ok(())
+    }
+
+    pub fn record(&mut self, day: u64, name: &str, seconds: f32) {
+        self.entries.push(LeaderboardEntry { date: date(day), name: name.to_string(), seconds });
+    }
+
+    // fastest first
+    pub fn best(&self, day: u64, count: usize) -> Vec<&LeaderboardEntry> {
+        let date = date(day);
+        let mut entries: Vec<&LeaderboardEntry> = self.entries.iter().filter(|entry| entry.date == date).collect();
+        entries.sort_by(|a, b| a.seconds.total_cmp(&b.seconds));
+        entries.truncate(count);
+        entries
+    }
+}
@@ -0,0 +1,94 @@
+use std::cmp::Reverse;
+
+use tiny_game_framework::{glfw::Key, imgui::{Condition, Ui}, EventLoop};
+
+use crate::{profiler::FrameProfile, rapier_integration::PhysicsStats};
+
+const OVERLAY_MARGIN: f32 = 10.0;
+const FLAME_WIDTH: f32 = 300.0;
+const FLAME_ROW_HEIGHT: f32 = 16.0;
+// scope colors go around this list by name, so a scope keeps its color from frame to frame
+const FLAME_COLORS: [[f32; 4]; 5] = [
+    [0.9, 0.4, 0.3, 1.0],
+    [0.3, 0.7, 0.9, 1.0],
+    [0.5, 0.8, 0.3, 1.0],
+    [0.9, 0.7, 0.2, 1.0],
+    [0.7, 0.4, 0.9, 1.0],
+];
+
+// f3 toggles it, numbers for keeping an eye on what the streamed dungeon costs
+pub struct DebugOverlay {
+    pub visible: bool,
+    toggle_held: bool,
+}
+
+impl DebugOverlay {
+    pub fn new() -> Self {
+        Self { visible: false, toggle_held: false }
+    }
+
+    pub fn update(&mut self, el: &mut EventLoop) {
+        // only the press toggles it, holding the key would flicker it every frame
+        let toggle = el.is_key_down(Key::F3);
+        if toggle && !self.toggle_held {
+            self.visible = !self.visible;
+        }
+        self.toggle_held = toggle;
+    }
+
+    // `chunk_memory` is `ChunkManager::memory_used`
+    pub fn draw(&self, ui: &Ui, physics: &PhysicsStats, chunk_memory: usize, profile: &FrameProfile) {
+        if !self.visible {
+            return;
+        }
+
+        let [width, _] = ui.io().display_size;
+        ui.window("debug")
+            .position([width - OVERLAY_MARGIN, OVERLAY_MARGIN], Condition::Always)
+            .position_pivot([1.0, 0.0])
+            .no_decoration()
+            .no_inputs()
+            .bg_alpha(0.5)
+            .always_auto_resize(true)
+            .build(|| {
+                ui.text(format!("fps {:.0}", ui.io().framerate));
+                ui.separator();
+                ui.text(format!("physics step {:.2} ms", physics.last_step.as_secs_f64() * 1000.0));
+                ui.text(format!("bodies {} active, {} sleeping", physics.active_bodies, physics.sleeping_bodies));
+                ui.text(format!("islands {}", physics.islands));
+                ui.text(format!("colliders {}", physics.colliders));
+                ui.text(format!("debris {}, {} frozen", physics.debris, physics.frozen));
+                ui.text(format!("chunks {:.1} mb", chunk_memory as f64 / (1 << 20) as f64));
+                ui.separator();
+                Self::draw_flame(ui, profile);
+            });
+    }
+
+    // the last frame left to right, nested scopes stacked under the one they ran in, then the slowest outer scopes
+    fn draw_flame(ui: &Ui, profile: &FrameProfile) {
+        let total = profile.total.as_secs_f32().max(f32::EPSILON);
+        ui.text(format!("frame {:.2} ms", total * 1000.0));
+
+        let rows = profile.scopes.iter().map(|scope| scope.depth + 1).max().unwrap_or(0);
+        let [x, y] = ui.cursor_screen_pos();
+        let draw_list = ui.get_window_draw_list();
+        for scope in profile.scopes.iter() {
+            let left = x + scope.start.as_secs_f32() / total * FLAME_WIDTH;
+            let right = (left + scope.duration.as_secs_f32() / total * FLAME_WIDTH).max(left + 1.0);
+            let top = y + scope.depth as f32 * FLAME_ROW_HEIGHT;
+            let color = FLAME_COLORS[scope.name.bytes().map(usize::from).sum::<usize>() % FLAME_COLORS.len()];
+            draw_list.add_rect([left, top], [right, top + FLAME_ROW_HEIGHT - 1.0], color).filled(true).build();
+            // names only where they fit
+            if ui.calc_text_size(scope.name)[0] < right - left {
+                draw_list.add_text([left + 2.0, top], [0.0, 0.0, 0.0, 1.0], scope.name);
+            }
+        }
+        ui.dummy([FLAME_WIDTH, rows as f32 * FLAME_ROW_HEIGHT]);
+
+        let mut outer: Vec<_> = profile.scopes.iter().filter(|scope| scope.depth == 0).collect();
+        outer.sort_by_key(|scope| Reverse(scope.duration));
+        for scope in outer {
+            ui.text(format!("{} {:.2} ms", scope.name, scope.duration.as_secs_f64() * 1000.0));
+        }
+    }
+}
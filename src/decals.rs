@@ -0,0 +1,103 @@
+use std::collections::VecDeque;
+
+use tiny_game_framework::{glam::{Quat, Vec3, Vec4}, rand_betw, Circle, Renderer};
+
+use crate::{error::RendererExt, render_layers::RenderLayer};
+
+// once this many decals are around the oldest one gets reused
+const MAX_DECALS: usize = 64;
+// pushed off the surface a little so they don't z-fight with it
+const SURFACE_OFFSET: f32 = 1.0;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum DecalKind {
+    ImpactMark,
+    Scorch,
+    Blood,
+}
+
+impl DecalKind {
+    fn radius(&self) -> f32 {
+        match self {
+            DecalKind::ImpactMark => rand_betw(6.0, 10.0),
+            DecalKind::Scorch => rand_betw(60.0, 90.0),
+            DecalKind::Blood => rand_betw(20.0, 35.0),
+        }
+    }
+
+    // fewer sides for the small ones, nobody can tell up close anyway
+    fn sides(&self) -> i32 {
+        match self {
+            DecalKind::ImpactMark => 6,
+            DecalKind::Scorch => 16,
+            DecalKind::Blood => 10,
+        }
+    }
+
+    fn color(&self) -> Vec4 {
+        match self {
+            DecalKind::ImpactMark => Vec4::new(0.15, 0.15, 0.15, 1.0),
+            DecalKind::Scorch => Vec4::new(0.05, 0.04, 0.03, 1.0),
+            DecalKind::Blood => Vec4::new(0.4, 0.0, 0.0, 1.0),
+        }
+    }
+}
+
+pub struct Decal {
+    pub position: Vec3,
+    pub mesh_name: String,
+}
+
+pub struct DecalSystem {
+    pub decals: VecDeque<Decal>,
+    decal_counter: usize,
+}
+
+impl DecalSystem {
+    pub fn new() -> Self {
+        Self {
+            decals: VecDeque::new(),
+            decal_counter: 0,
+        }
+    }
+
+    // `normal` is the surface normal at `point`, the decal lies flat against it
+    pub fn place(&mut self, r: &mut Renderer, kind: DecalKind, point: Vec3, normal: Vec3) {
+        if self.decals.len() >= MAX_DECALS {
+            if let Some(oldest) = self.decals.pop_front() {
+                r.destroy_mesh(&oldest.mesh_name).ok();
+            }
+        }
+
+        let normal = normal.normalize_or_zero();
+        let position = point + normal * SURFACE_OFFSET;
+
+        // circles are built facing +z, and a random spin around the normal keeps repeats from looking stamped
+        let spin = Quat::from_axis_angle(Vec3::Z, rand_betw(0.0, std::f32::consts::TAU));
+        let facing = Quat::from_rotation_arc(Vec3::Z, if normal == Vec3::ZERO { Vec3::Y } else { normal });
+
+        let mesh_name = format!("DECAL_MESH{}", self.decal_counter);
+        self.decal_counter += 1;
+
+        let mut mesh = Circle::new(kind.sides(), kind.radius(), kind.color()).mesh();
+        mesh.position = position;
+        mesh.rotation = facing * spin;
+        mesh.setup_mesh();
+        if let Err(e) = r.try_add_mesh_on(&mesh_name, mesh, RenderLayer::Decals) {
+            log::warn!("{}", e);
+        }
+
+        self.decals.push_back(Decal { position, mesh_name });
+    }
+
+    // for when the surface the decals sit on goes away, like a wall breaking
+    pub fn clear_near(&mut self, r: &mut Renderer, center: Vec3, radius: f32) {
+        self.decals.retain(|decal| {
+            let keep = decal.position.distance(center) > radius;
+            if !keep {
+                r.destroy_mesh(&decal.mesh_name).ok();
+            }
+            keep
+        });
+    }
+}
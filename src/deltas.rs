@@ -0,0 +1,71 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+use tiny_game_framework::Renderer;
+
+use crate::{coords::{ChunkCoord, TileCoord}, destruction::DestructionSystem, rapier_integration::RapierPhysicsWorld, traps::TrapSystem};
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub enum Modification {
+    WallDestroyed,
+    // a pot or crate, so it isn't scattered again and can't be looted twice. see `PropSystem::scatter`
+    PropBroken,
+    // a pressure plate that already sprang
+    TrapTriggered,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub struct TileDelta {
+    pub tile: TileCoord,
+    pub modification: Modification,
+}
+
+// what the players changed in the world, by chunk. a chunk generated again from the seed comes out the way it
+// was first built, so this is put on top of it once it's done. saved with the run as a plain list
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+#[serde(from = "Vec<TileDelta>", into = "Vec<TileDelta>")]
+pub struct WorldDeltas {
+    chunks: BTreeMap<ChunkCoord, BTreeSet<TileDelta>>,
+}
+
+impl WorldDeltas {
+    pub fn record(&mut self, tile: TileCoord, modification: Modification) {
+        self.chunks.entry(tile.chunk()).or_default().insert(TileDelta { tile, modification });
+    }
+
+    pub fn has(&self, tile: TileCoord, modification: Modification) -> bool {
+        self.chunks.get(&tile.chunk()).is_some_and(|deltas| deltas.contains(&TileDelta { tile, modification }))
+    }
+
+    fn in_chunk(&self, chunk: ChunkCoord) -> impl Iterator<Item = &TileDelta> {
+        self.chunks.get(&chunk).into_iter().flatten()
+    }
+
+    // call once `chunk` has finished building
+    pub fn apply(&self, chunk: ChunkCoord, rw: &mut RapierPhysicsWorld, r: &mut Renderer, destruction: &mut DestructionSystem, traps: &mut TrapSystem) {
+        for delta in self.in_chunk(chunk) {
+            match delta.modification {
+                Modification::WallDestroyed => destruction.remove(rw, r, delta.tile),
+                Modification::TrapTriggered => traps.disarm(delta.tile),
+                // props are scattered after this, they look for it themselves
+                Modification::PropBroken => {}
+            }
+        }
+    }
+}
+
+impl From<Vec<TileDelta>> for WorldDeltas {
+    fn from(deltas: Vec<TileDelta>) -> Self {
+        let mut world = Self::default();
+        for delta in deltas {
+            world.record(delta.tile, delta.modification);
+        }
+        world
+    }
+}
+
+impl From<WorldDeltas> for Vec<TileDelta> {
+    fn from(world: WorldDeltas) -> Self {
+        world.chunks.into_values().flatten().collect()
+    }
+}
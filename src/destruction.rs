@@ -0,0 +1,154 @@
+use rapier3d::prelude::*;
+use tiny_game_framework::{glam::{vec3, Vec3, Vec4}, rand_betw, Cuboid, Renderer};
+
+use crate::{coords::{ChunkCoord, TileCoord, WorldPos}, error::RendererExt, entity::{EntityId, EntityKind}, physics_thread::Blend, rapier_integration::{BodySpec, CollisionLayer, RapierPhysicsWorld}, rng};
+
+// how many pieces along each axis a wall breaks into (so 2 gives 8 chunks of rubble)
+const RUBBLE_SPLITS: usize = 2;
+const RUBBLE_LIFETIME: f32 = 6.0;
+const RUBBLE_SCATTER_SPEED: f32 = 80.0;
+// broken walls are a pile of grey, each piece a bit lighter or darker than this
+const RUBBLE_COLOR: Vec4 = Vec4::new(0.55, 0.55, 0.55, 1.0);
+// what's left of a pot or crate doesn't stay around as long as a wall's rubble
+const SHARD_LIFETIME: f32 = 2.0;
+
+pub const DESTRUCTIBLE_WALL_HEALTH: f32 = 100.0;
+
+pub struct Destructible {
+    pub id: EntityId,
+    // on its chunk's body
+    pub collider: ColliderHandle,
+    pub mesh_name: String,
+    pub position: Vec3,
+    pub size: Vec3,
+    pub health: f32,
+}
+
+pub struct Rubble {
+    pub handle: RigidBodyHandle,
+    pub mesh_name: String,
+    lifetime: f32,
+}
+
+pub struct DestructionSystem {
+    pub walls: Vec<Destructible>,
+    pub rubble: Vec<Rubble>,
+    rubble_counter: usize,
+}
+
+impl DestructionSystem {
+    pub fn new() -> Self {
+        Self {
+            walls: Vec::new(),
+            rubble: Vec::new(),
+            rubble_counter: 0,
+        }
+    }
+
+    pub fn register(&mut self, id: EntityId, collider: ColliderHandle, mesh_name: String, position: Vec3, size: Vec3) {
+        self.walls.push(Destructible {
+            id,
+            collider,
+            mesh_name,
+            position,
+            size,
+            health: DESTRUCTIBLE_WALL_HEALTH,
+        });
+    }
+
+    // returns the tile the wall stood on if this hit broke it
+    pub fn damage(&mut self, rw: &mut RapierPhysicsWorld, r: &mut Renderer, id: EntityId, amount: f32) -> Option<TileCoord> {
+        let index = self.walls.iter().position(|wall| wall.id == id)?;
+
+        self.walls[index].health -= amount;
+        if self.walls[index].health > 0.0 {
+            return None;
+        }
+
+        let wall = self.walls.swap_remove(index);
+        rw.remove_collider(wall.collider);
+        r.destroy_mesh(&wall.mesh_name).ok();
+
+        self.spawn_rubble(rw, r, wall.position, wall.size, RUBBLE_COLOR, RUBBLE_LIFETIME);
+
+        Some(WorldPos(wall.position).tile())
+    }
+
+    // forgets the walls in `chunk`, their colliders and meshes went with the rest of it
+    pub fn unload(&mut self, chunk: ChunkCoord) {
+        self.walls.retain(|wall| WorldPos(wall.position).chunk() != chunk);
+    }
+
+    // takes out the wall on `tile` without any rubble, for walls that were broken before their chunk was built again
+    pub fn remove(&mut self, rw: &mut RapierPhysicsWorld, r: &mut Renderer, tile: TileCoord) {
+        let Some(index) = self.walls.iter().position(|wall| WorldPos(wall.position).tile() == tile) else { return };
+        let wall = self.walls.swap_remove(index);
+        rw.remove_collider(wall.collider);
+        r.destroy_mesh(&wall.mesh_name).ok();
+    }
+
+    // breaks something that isn't a wall into short lived pieces of `color`, like a pot or a crate
+    pub fn shatter(&mut self, rw: &mut RapierPhysicsWorld, r: &mut Renderer, center: Vec3, size: Vec3, color: Vec4) {
+        self.spawn_rubble(rw, r, center, size, color, SHARD_LIFETIME);
+    }
+
+    fn spawn_rubble(&mut self, rw: &mut RapierPhysicsWorld, r: &mut Renderer, center: Vec3, size: Vec3, color: Vec4, lifetime: f32) {
+        let piece = size / RUBBLE_SPLITS as f32;
+        let corner = center - (size / 2.0 - piece / 2.0);
+
+        for x in 0..RUBBLE_SPLITS {
+            for y in 0..RUBBLE_SPLITS {
+                for z in 0..RUBBLE_SPLITS {
+                    // a bit smaller than the slot so the pieces don't spawn overlapping each other
+                    let piece_size = piece * rng::range(0.5, 0.8);
+                    let position = corner + vec3(x as f32, y as f32, z as f32) * piece;
+
+                    let handle = rw.add_body(position, &BodySpec::cuboid(piece_size / 2.0)
+                        .restitution(0.2)
+                        .friction(0.8)
+                        .ccd(true)
+                        .layer(CollisionLayer::Debris)
+                        .impacts(true));
+                    let outward = (position - center).normalize_or_zero() * RUBBLE_SCATTER_SPEED;
+                    rw.rigid_body_set[handle].set_linvel(vector![outward.x, outward.y, outward.z], true);
+                    rw.tag_body(handle, EntityId::new(EntityKind::Debris));
+                    rw.track_debris(handle);
+
+                    let mesh_name = format!("RUBBLE_MESH{}", self.rubble_counter);
+                    self.rubble_counter += 1;
+
+                    let shade = rand_betw(0.65, 1.0);
+                    let mut mesh = Cuboid::new(piece_size, (color.truncate() * shade).extend(color.w)).mesh();
+                    mesh.position = position;
+                    mesh.setup_mesh();
+                    if let Err(e) = r.try_add_mesh(&mesh_name, mesh) {
+                        log::warn!("{}", e);
+                    }
+
+                    self.rubble.push(Rubble { handle, mesh_name, lifetime });
+                }
+            }
+        }
+    }
+
+    pub fn update(&mut self, rw: &mut RapierPhysicsWorld, r: &mut Renderer, blend: &Blend, dt: f32) {
+        let mut i = 0;
+        while i < self.rubble.len() {
+            let rubble = &mut self.rubble[i];
+            rubble.lifetime -= dt;
+
+            // the debris budget might have gotten to it first
+            if rubble.lifetime <= 0.0 || !rw.rigid_body_set.contains(rubble.handle) {
+                let rubble = self.rubble.swap_remove(i);
+                rw.remove_rigidbody(rubble.handle);
+                r.destroy_mesh(&rubble.mesh_name).ok();
+                continue;
+            }
+
+            if let (Some(transform), Some(mesh)) = (blend.transform(rw, rubble.handle), r.get_mesh_mut(&rubble.mesh_name)) {
+                (mesh.position, mesh.rotation) = (transform.position, transform.rotation);
+            }
+            i += 1;
+        }
+    }
+}
@@ -0,0 +1,237 @@
+use std::collections::BTreeMap;
+
+use tiny_game_framework::{glam::{vec3, Vec3}, Renderer};
+
+use crate::{config::{BiomeSpawns, SpawnSettings}, coords::{self, ChunkCoord, WorldPos}, enemies::{EnemyKind, EnemySystem}, entity::EntityKind, navmesh::{NavMesh, Navigator}, patrol::WalkableGrid, rapier_integration::RapierPhysicsWorld, rng};
+
+// enemies never pop in closer than this, or inside this half angle (degrees) of where the camera looks
+const MIN_SPAWN_DISTANCE: f32 = 600.0;
+const VIEW_HALF_ANGLE: f32 = 50.0;
+// floor tiles are at floor level, this keeps the capsule from starting inside the ground. enemies taller than a
+// grunt start higher, `SPAWN_GAP` above the floor
+const SPAWN_HEIGHT: f32 = 100.0;
+const SPAWN_GAP: f32 = 10.0;
+// random floor tiles tried per chunk before giving up until the next frame
+const SPAWN_ATTEMPTS: usize = 8;
+// nothing spawns with a player, enemy or anything else that moves around this close to where it'd stand
+const SPAWN_CLEARANCE: f32 = 80.0;
+// how far from the middle of the tile the members of a group spawn, less than half a tile so none end up in a wall
+const GROUP_SPREAD: f32 = 60.0;
+
+// decides when and where enemies show up, so the population keeps up with the player instead of being placed by hand
+pub struct SpawnDirector {
+    pub settings: SpawnSettings,
+    pub biome: String,
+    // 1 for the first floor, difficulty goes up with every floor below that
+    pub floor: u32,
+    pub elapsed: f32,
+    // from the difficulty, multiplies both how many enemies a chunk holds and how fast they're replaced
+    pub density: f32,
+
+    // enemies that can be spawned right now, refills over time
+    budget: f32,
+    // ordered so every lockstep peer walks the chunks the same way
+    floor_tiles: BTreeMap<ChunkCoord, Vec<Vec3>>,
+    // floor tiles the sample marked for enemies, tried before any of the others
+    anchors: BTreeMap<ChunkCoord, Vec<Vec3>>,
+    walkable: WalkableGrid,
+    // only has the ground that isn't a plain grid of tiles, like terrain
+    navmesh: NavMesh,
+    // centers and half widths of square areas nothing spawns in
+    safe_zones: Vec<(Vec3, f32)>,
+}
+
+impl SpawnDirector {
+    pub fn new(settings: SpawnSettings, biome: &str) -> Self {
+        Self {
+            settings,
+            biome: biome.to_string(),
+            floor: 1,
+            elapsed: 0.0,
+            density: 1.0,
+            budget: 0.0,
+            floor_tiles: BTreeMap::new(),
+            anchors: BTreeMap::new(),
+            walkable: WalkableGrid::default(),
+            navmesh: NavMesh::default(),
+            safe_zones: Vec::new(),
+        }
+    }
+
+    // `position` is the center of the tile at floor level
+    pub fn add_floor_tile(&mut self, position: Vec3) {
+        self.floor_tiles.entry(Self::chunk_of(position)).or_default().push(position);
+        self.walkable.add(position);
+    }
+
+    // has to be a floor tile as well, see `SpawnAnchor::Enemy`
+    pub fn add_anchor(&mut self, position: Vec3) {
+        self.anchors.entry(Self::chunk_of(position)).or_default().push(position);
+    }
+
+    // the chunk was unloaded, it's back to nothing being there until it's built again
+    pub fn unload_chunk(&mut self, chunk: ChunkCoord) {
+        self.anchors.remove(&chunk);
+        for position in self.floor_tiles.remove(&chunk).unwrap_or_default() {
+            self.walkable.remove(position);
+        }
+        self.navmesh.remove_chunk(chunk);
+    }
+
+    // something got put on the tile, nothing spawns or walks there anymore
+    pub fn block_tile(&mut self, position: Vec3) {
+        let tile = WorldPos(position).tile();
+        if let Some(tiles) = self.floor_tiles.get_mut(&Self::chunk_of(position)) {
+            tiles.retain(|&floor| WorldPos(floor).tile() != tile);
+        }
+        if let Some(anchors) = self.anchors.get_mut(&Self::chunk_of(position)) {
+            anchors.retain(|&anchor| WorldPos(anchor).tile() != tile);
+        }
+        self.walkable.remove(position);
+        self.navmesh.block_tile(tile);
+    }
+
+    // enemies don't spawn on tiles within `half_width` of `center` along x or z, they can still walk in
+    pub fn add_safe_zone(&mut self, center: Vec3, half_width: f32) {
+        self.safe_zones.push((center, half_width));
+    }
+
+    // world space triangles something can stand on, see `NavMesh::add_geometry`
+    pub fn add_walkable_geometry(&mut self, vertices: &[Vec3], indices: &[u32]) {
+        self.navmesh.add_geometry(vertices, indices);
+    }
+
+    fn chunk_of(position: Vec3) -> ChunkCoord {
+        WorldPos(position).chunk()
+    }
+
+    pub fn walkable(&self) -> &WalkableGrid {
+        &self.walkable
+    }
+
+    pub fn navigator(&self) -> Navigator<'_> {
+        Navigator { navmesh: &self.navmesh, grid: &self.walkable }
+    }
+
+    // 1 at the start of the first floor
+    pub fn difficulty(&self) -> f32 {
+        1.0 + self.elapsed / self.settings.difficulty_ramp + (self.floor - 1) as f32 * self.settings.floor_scaling
+    }
+
+    pub fn update(&mut self, rw: &mut RapierPhysicsWorld, r: &mut Renderer, enemies: &mut EnemySystem, player_pos: Vec3, view_dir: Vec3, dt: f32) {
+        self.elapsed += dt;
+
+        let Some(biome) = self.settings.biomes.get(&self.biome) else { return };
+        let difficulty = self.difficulty();
+        let target = (biome.population_per_chunk as f32 * difficulty * self.density).round() as usize;
+
+        // capped so a long stretch at full population doesn't bank a whole wave
+        let max_budget = (target as f32).max(1.0);
+        self.budget = (self.budget + biome.budget_per_minute / 60.0 * difficulty * self.density * dt).min(max_budget);
+
+        let mut spawns = Vec::new();
+        for (chunk, tiles) in self.floor_tiles.iter() {
+            if spawns.len() as f32 + 1.0 > self.budget {
+                break;
+            }
+            // only the chunks there's budget left to look at get counted
+            let (min, max) = Self::chunk_bounds(*chunk);
+            let population = rw.query_aabb(min, max).into_iter()
+                .filter(|&entity| entity.kind == EntityKind::Enemy && enemies.find(entity).is_some_and(|enemy| enemy.is_alive()))
+                .count();
+            if population >= target {
+                continue;
+            }
+
+            let anchors = self.anchors.get(chunk).map_or(&[][..], Vec::as_slice);
+            let Some(tile) = Self::pick_anchor(anchors, player_pos, view_dir).or_else(|| Self::pick_hidden_tile(tiles, player_pos, view_dir)) else { continue };
+            let crowded = rw.query_sphere(tile + Vec3::Y * SPAWN_HEIGHT, SPAWN_CLEARANCE).into_iter()
+                .any(|entity| matches!(entity.kind, EntityKind::Player | EntityKind::Enemy | EntityKind::Npc | EntityKind::Prop));
+            if crowded {
+                continue;
+            }
+            let safe = self.safe_zones.iter().any(|&(center, half_width)| {
+                (tile.x - center.x).abs() <= half_width + 1.0 && (tile.z - center.z).abs() <= half_width + 1.0
+            });
+            if safe {
+                continue;
+            }
+            let Some(kind) = Self::pick_kind(biome) else { return };
+            spawns.push((tile, kind));
+        }
+
+        // a group only costs one spawn, they're meant to be weak enough that it evens out
+        for (tile, kind) in spawns {
+            let archetype = enemies.archetype(kind);
+            let group = archetype.group.max(1);
+            let height = SPAWN_HEIGHT.max(archetype.size[1] / 2.0 + SPAWN_GAP);
+            let route = self.walkable.patrol_route(tile, tile.y + height);
+            for member in 0..group {
+                let angle = member as f32 / group as f32 * std::f32::consts::TAU;
+                let offset = if group > 1 { vec3(angle.cos(), 0.0, angle.sin()) * GROUP_SPREAD } else { Vec3::ZERO };
+                let index = enemies.spawn(rw, r, tile + offset + Vec3::Y * height, kind);
+                enemies.enemies[index].brain.patrol_route = route.clone();
+            }
+            self.budget -= 1.0;
+        }
+    }
+
+    // the whole quadrant from the floor up, with some room above and below for terrain hills
+    fn chunk_bounds(chunk: ChunkCoord) -> (Vec3, Vec3) {
+        let half_tile = coords::tile_size() / 2.0;
+        let side = coords::quadrant_tiles() as f32 * coords::tile_size();
+        let corner: Vec3 = chunk.origin().world(0.0).into();
+        let depth = coords::wall_height() * 4.0;
+        (corner - vec3(half_tile, depth, half_tile), corner + vec3(side - half_tile, depth, side - half_tile))
+    }
+
+    // the first one out of view, in the order they were built so it's the same on every machine
+    fn pick_anchor(anchors: &[Vec3], player_pos: Vec3, view_dir: Vec3) -> Option<Vec3> {
+        anchors.iter().copied().find(|&anchor| Self::is_out_of_view(anchor, player_pos, view_dir))
+    }
+
+    fn pick_hidden_tile(tiles: &[Vec3], player_pos: Vec3, view_dir: Vec3) -> Option<Vec3> {
+        if tiles.is_empty() {
+            return None;
+        }
+
+        (0..SPAWN_ATTEMPTS)
+            .map(|_| tiles[rng::usize(..tiles.len())])
+            .find(|&tile| Self::is_out_of_view(tile, player_pos, view_dir))
+    }
+
+    fn is_out_of_view(tile: Vec3, player_pos: Vec3, view_dir: Vec3) -> bool {
+        let to_tile = vec3(tile.x - player_pos.x, 0.0, tile.z - player_pos.z);
+        if to_tile.length() < MIN_SPAWN_DISTANCE {
+            return false;
+        }
+
+        let view = vec3(view_dir.x, 0.0, view_dir.z).normalize_or_zero();
+        to_tile.normalize_or_zero().dot(view) < VIEW_HALF_ANGLE.to_radians().cos()
+    }
+
+    fn pick_kind(biome: &BiomeSpawns) -> Option<EnemyKind> {
+        // sorted since hash map order differs between runs
+        let mut weights: Vec<(EnemyKind, f32)> = biome.weights.iter()
+            .filter(|(_, &weight)| weight > 0.0)
+            .map(|(&kind, &weight)| (kind, weight))
+            .collect();
+        weights.sort_by_key(|&(kind, _)| kind);
+        let total: f32 = weights.iter().map(|(_, weight)| weight).sum();
+        if total <= 0.0 {
+            return None;
+        }
+
+        let mut roll = rng::f32() * total;
+        let mut picked = None;
+        for (kind, weight) in weights {
+            picked = Some(kind);
+            if roll < weight {
+                break;
+            }
+            roll -= weight;
+        }
+
+        picked
+    }
+}
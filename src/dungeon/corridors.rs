@@ -0,0 +1,113 @@
+use crate::coords::TileCoord;
+
+use super::{DungeonLayout, Tile, TileMeta};
+
+const CONNECT_SEED: u64 = 0x6c1d_02f4_9e83_b75a;
+// a drunkard gives up and goes straight there after this many steps per tile between the ends
+const MAX_WANDER_STEPS: i32 = 8;
+
+// how a passage gets from one tile to another. anything that needs to open a way through walls goes through here,
+// so every carved passage looks like it came from the same dungeon
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Corridor {
+    // along x first and then along z, or the other way around
+    LShaped { x_first: bool },
+    // steps toward the end, except `wander` of the time it stumbles off in any direction instead
+    Drunkard { wander: f32 },
+}
+
+impl Corridor {
+    // every tile from `from` to `to`, both included, neighbors one after the other. `width` and `height` keep a
+    // drunkard inside the layout, the ends have to be inside it already. `rng` is only drawn from by a drunkard
+    pub fn path(&self, from: TileCoord, to: TileCoord, width: u32, height: u32, rng: &mut fastrand::Rng) -> Vec<TileCoord> {
+        match *self {
+            Corridor::LShaped { x_first } => l_shaped(from, to, x_first),
+            Corridor::Drunkard { wander } => {
+                let mut path = vec![from];
+                let mut current = from;
+                let max_steps = (manhattan(from, to) + 1) * MAX_WANDER_STEPS;
+                for _ in 0..max_steps {
+                    if current == to {
+                        break;
+                    }
+                    let step = if rng.f32() < wander {
+                        TileCoord::NEIGHBORS[rng.usize(..TileCoord::NEIGHBORS.len())]
+                    } else {
+                        toward(current, to, rng)
+                    };
+                    let next = current + step;
+                    if next.x < 0 || next.z < 0 || next.x >= width as i32 || next.z >= height as i32 {
+                        continue;
+                    }
+                    current = next;
+                    path.push(current);
+                }
+                // too drunk to make it, the rest of the way is straight
+                if current != to {
+                    path.extend(l_shaped(current, to, true).into_iter().skip(1));
+                }
+                path
+            }
+        }
+    }
+}
+
+impl DungeonLayout {
+    // turns the walls along `path` into floor and leaves every other tile alone, returns how many were opened.
+    // tiles outside the layout are skipped
+    pub fn carve(&mut self, path: impl IntoIterator<Item = TileCoord>) -> usize {
+        let mut carved = 0;
+        for tile in path {
+            if self.tile(tile) == Some(Tile::Wall) {
+                self.set(tile, TileMeta::new(Tile::Floor, None));
+                carved += 1;
+            }
+        }
+        carved
+    }
+
+    // opens a `corridor` between two tiles, see `Corridor::path`
+    pub fn carve_corridor(&mut self, corridor: Corridor, from: TileCoord, to: TileCoord, rng: &mut fastrand::Rng) -> usize {
+        let path = corridor.path(from, to, self.width, self.height, rng);
+        self.carve(path)
+    }
+
+    // every patch of walkable tiles that can't be reached from the biggest one gets a `corridor` to it. only
+    // depends on the layout's seed, so every client opens the same ones
+    pub(super) fn connect_regions(&mut self, corridor: Corridor) {
+        let starts = self.region_starts();
+        let Some(&(largest, _)) = starts.iter().max_by_key(|(_, size)| *size) else { return };
+
+        let mut rng = fastrand::Rng::with_seed(self.seed ^ CONNECT_SEED);
+        for &(start, _) in starts.iter().filter(|(start, _)| *start != largest) {
+            self.carve_corridor(corridor, start, largest, &mut rng);
+        }
+    }
+}
+
+fn l_shaped(from: TileCoord, to: TileCoord, x_first: bool) -> Vec<TileCoord> {
+    let corner = if x_first { TileCoord::new(to.x, from.z) } else { TileCoord::new(from.x, to.z) };
+    let mut path = straight(from, corner);
+    path.extend(straight(corner, to).into_iter().skip(1));
+    path
+}
+
+// along one axis only, `from` and `to` share the other
+fn straight(from: TileCoord, to: TileCoord) -> Vec<TileCoord> {
+    let step = TileCoord::new((to.x - from.x).signum(), (to.z - from.z).signum());
+    (0..=manhattan(from, to)).map(|i| TileCoord::new(from.x + step.x * i, from.z + step.z * i)).collect()
+}
+
+// one step closer to `to`, along whichever axis when both would do
+fn toward(from: TileCoord, to: TileCoord, rng: &mut fastrand::Rng) -> TileCoord {
+    let (dx, dz) = ((to.x - from.x).signum(), (to.z - from.z).signum());
+    if dx != 0 && (dz == 0 || rng.bool()) {
+        TileCoord::new(dx, 0)
+    } else {
+        TileCoord::new(0, dz)
+    }
+}
+
+fn manhattan(a: TileCoord, b: TileCoord) -> i32 {
+    (a.x - b.x).abs() + (a.z - b.z).abs()
+}
@@ -0,0 +1,357 @@
+use std::{collections::VecDeque, ops::BitOr};
+
+use image::{ImageFormat, ImageResult, Rgba, RgbaImage};
+
+use crate::{coords::TileCoord, traps::TrapKind};
+
+use super::{wfc::{print_pixel, Canvas}, ENEMY_ANCHOR_COLOR, FLOOR_COLOR, LADDER_COLOR, LAVA_COLOR, LOW_GRAVITY_COLOR, PROP_ANCHOR_COLOR, TELEPORTER_COLOR, WALL_COLOR, WATER_COLOR};
+
+// past a trap frequency of 1, the odds of a plain floor tile becoming a trap for every whole 1 over
+const EXTRA_TRAP_CHANCE: f32 = 0.05;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Tile {
+    Floor,
+    Wall,
+    // a wall that can be climbed
+    Ladder,
+    Water,
+    // burns whoever's in it
+    Lava,
+    // linked to another teleporter somewhere else in the dungeon
+    Teleporter,
+    // floor with weaker gravity over it, everything jumps higher and falls slower
+    LowGravity,
+    Trap(TrapKind),
+}
+
+impl Tile {
+    pub fn from_pixel(pixel: [u8; 4]) -> Self {
+        match pixel {
+            WALL_COLOR => Tile::Wall,
+            LADDER_COLOR => Tile::Ladder,
+            WATER_COLOR => Tile::Water,
+            LAVA_COLOR => Tile::Lava,
+            TELEPORTER_COLOR => Tile::Teleporter,
+            LOW_GRAVITY_COLOR => Tile::LowGravity,
+            ENEMY_ANCHOR_COLOR | PROP_ANCHOR_COLOR => Tile::Floor,
+            _ => TrapKind::from_pixel(pixel).map_or(Tile::Floor, Tile::Trap),
+        }
+    }
+
+    pub fn pixel(&self) -> [u8; 4] {
+        match self {
+            Tile::Floor => FLOOR_COLOR,
+            Tile::Wall => WALL_COLOR,
+            Tile::Ladder => LADDER_COLOR,
+            Tile::Water => WATER_COLOR,
+            Tile::Lava => LAVA_COLOR,
+            Tile::Teleporter => TELEPORTER_COLOR,
+            Tile::LowGravity => LOW_GRAVITY_COLOR,
+            Tile::Trap(trap) => trap.pixel(),
+        }
+    }
+
+    // water, lava and traps can be crossed, they just hurt or slow you down
+    pub fn is_walkable(&self) -> bool {
+        !matches!(self, Tile::Wall | Tile::Ladder)
+    }
+}
+
+// painted into the sample by hand to say where encounters go. it comes out of the collapse as plain floor with
+// this on it, nothing is built for it
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SpawnAnchor {
+    // enemies spawn here before anywhere else in the chunk
+    Enemy,
+    // always gets a prop
+    Prop,
+}
+
+impl SpawnAnchor {
+    pub fn from_pixel(pixel: [u8; 4]) -> Option<Self> {
+        match pixel {
+            ENEMY_ANCHOR_COLOR => Some(SpawnAnchor::Enemy),
+            PROP_ANCHOR_COLOR => Some(SpawnAnchor::Prop),
+            _ => None,
+        }
+    }
+
+    pub fn pixel(&self) -> [u8; 4] {
+        match self {
+            SpawnAnchor::Enemy => ENEMY_ANCHOR_COLOR,
+            SpawnAnchor::Prop => PROP_ANCHOR_COLOR,
+        }
+    }
+}
+
+// what can go wrong standing on a tile, several at once is fine
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct Hazards(u8);
+
+impl Hazards {
+    pub const NONE: Self = Self(0);
+    // spikes, arrows and lava
+    pub const DAMAGE: Self = Self(1);
+    // something else goes off when it's stepped on
+    pub const TRIGGER: Self = Self(1 << 1);
+    pub const SWIM: Self = Self(1 << 2);
+
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn is_empty(self) -> bool {
+        self == Self::NONE
+    }
+}
+
+impl BitOr for Hazards {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+// everything known about a tile once the collapse is decoded, so nothing past the canvas looks at colors again
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct TileMeta {
+    pub kind: Tile,
+    // in walls above the floor, water and lava sit one below it
+    pub height: i8,
+    pub hazards: Hazards,
+    // teleporter pads are numbered in the order they were decoded, nothing else is marked yet
+    pub marker: Option<u32>,
+    pub anchor: Option<SpawnAnchor>,
+}
+
+impl TileMeta {
+    pub fn new(kind: Tile, marker: Option<u32>) -> Self {
+        let (height, hazards) = match kind {
+            Tile::Wall | Tile::Ladder => (1, Hazards::NONE),
+            Tile::Water => (-1, Hazards::SWIM),
+            Tile::Lava => (-1, Hazards::DAMAGE),
+            Tile::Trap(TrapKind::PressurePlate) => (0, Hazards::TRIGGER),
+            // the shooter fires at whoever stands in front of it, the tile itself is just as bad to be on
+            Tile::Trap(TrapKind::Spikes | TrapKind::ArrowShooter) => (0, Hazards::DAMAGE),
+            Tile::Floor | Tile::Teleporter | Tile::LowGravity => (0, Hazards::NONE),
+        };
+        Self { kind, height, hazards, marker, anchor: None }
+    }
+
+    // the color it'd be in a sample, anchors included
+    pub fn pixel(&self) -> [u8; 4] {
+        self.anchor.map_or(self.kind.pixel(), |anchor| anchor.pixel())
+    }
+}
+
+// what a quadrant looks like, without anything built for it yet. tile coordinates are local, (0, 0) is the
+// corner the quadrant's `ChunkCoord::origin` maps to
+#[derive(Clone, Debug)]
+pub struct DungeonLayout {
+    // the seed of the collapse that was kept, not necessarily the one that was asked for
+    pub seed: u64,
+    pub width: u32,
+    pub height: u32,
+    // collapses thrown away for being too closed in, plus the one that was kept
+    pub attempts: u32,
+    // column by column, x * height + z
+    tiles: Vec<TileMeta>,
+}
+
+impl DungeonLayout {
+    pub(super) fn from_canvas(canvas: &Canvas, attempts: u32) -> Self {
+        let tiles = canvas.metadata.iter().flatten().copied().collect();
+        Self { seed: canvas.seed, width: canvas.width, height: canvas.height, attempts, tiles }
+    }
+
+    // nothing but floor, for quadrants that aren't mazes
+    pub(super) fn open(seed: u64, width: u32, height: u32) -> Self {
+        let tiles = vec![TileMeta::new(Tile::Floor, None); (width * height) as usize];
+        Self { seed, width, height, attempts: 1, tiles }
+    }
+
+    pub fn tile(&self, tile: TileCoord) -> Option<Tile> {
+        self.meta(tile).map(|meta| meta.kind)
+    }
+
+    pub fn meta(&self, tile: TileCoord) -> Option<TileMeta> {
+        if tile.x < 0 || tile.z < 0 || tile.x >= self.width as i32 || tile.z >= self.height as i32 {
+            return None;
+        }
+        Some(self.tiles[(tile.x * self.height as i32 + tile.z) as usize])
+    }
+
+    // tiles outside the layout are ignored
+    pub(super) fn set(&mut self, tile: TileCoord, meta: TileMeta) {
+        if self.meta(tile).is_some() {
+            self.tiles[(tile.x * self.height as i32 + tile.z) as usize] = meta;
+        }
+    }
+
+    // roughly what the canvas takes up in memory
+    pub fn bytes(&self) -> usize {
+        std::mem::size_of::<Self>() + self.tiles.len() * std::mem::size_of::<TileMeta>()
+    }
+
+    // column by column, the same order on every machine
+    pub fn tiles(&self) -> impl Iterator<Item = (TileCoord, Tile)> + '_ {
+        self.metadata().map(|(tile, meta)| (tile, meta.kind))
+    }
+
+    pub fn metadata(&self) -> impl Iterator<Item = (TileCoord, TileMeta)> + '_ {
+        let height = self.height as i32;
+        self.tiles.iter().enumerate().map(move |(i, &meta)| (TileCoord::new(i as i32 / height, i as i32 % height), meta))
+    }
+
+    // drops traps below a frequency of 1 and sprinkles new ones on plain floor above it. only depends on the
+    // layout's seed, so every client thins them out the same way
+    pub(super) fn scale_traps(&mut self, frequency: f32) {
+        if frequency == 1.0 {
+            return;
+        }
+
+        let mut rng = fastrand::Rng::with_seed(self.seed);
+        for meta in self.tiles.iter_mut() {
+            match meta.kind {
+                Tile::Trap(_) if rng.f32() >= frequency => *meta = TileMeta::new(Tile::Floor, None),
+                // an anchor is kept clear for whatever it's for
+                Tile::Floor if rng.f32() < (frequency - 1.0) * EXTRA_TRAP_CHANCE && meta.anchor.is_none() => {
+                    let trap = match rng.usize(..3) {
+                        0 => TrapKind::Spikes,
+                        1 => TrapKind::PressurePlate,
+                        _ => TrapKind::ArrowShooter,
+                    };
+                    *meta = TileMeta::new(Tile::Trap(trap), None);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // carves walls away so every walkable tile spreads into a `width` by `width` block, then puts carved tiles back
+    // next to the walls that are left until they're `thickness` thick again. nothing that was walkable before ever
+    // gets closed, so everything that was connected still is
+    pub(super) fn widen(&mut self, width: u32, thickness: u32) {
+        if width <= 1 {
+            return;
+        }
+
+        let original = self.tiles.clone();
+        let index = |tile: TileCoord| (tile.x * self.height as i32 + tile.z) as usize;
+        let mut carved = vec![false; self.tiles.len()];
+        for (tile, meta) in self.metadata().collect::<Vec<_>>() {
+            if !meta.kind.is_walkable() {
+                continue;
+            }
+            for x in 0..width as i32 {
+                for z in 0..width as i32 {
+                    let spread = tile + TileCoord::new(x, z);
+                    if self.tile(spread) == Some(Tile::Wall) {
+                        self.tiles[index(spread)] = TileMeta::new(Tile::Floor, None);
+                        carved[index(spread)] = true;
+                    }
+                }
+            }
+        }
+
+        // a ring of wall at a time onto walls that are still too thin across, only ever onto tiles that were wall
+        // to begin with
+        for _ in 1..thickness {
+            let walls: Vec<TileCoord> = self.tiles().filter(|&(_, kind)| kind == Tile::Wall).map(|(tile, _)| tile).collect();
+            for wall in walls {
+                for axis in [TileCoord::new(1, 0), TileCoord::new(0, 1)] {
+                    if self.wall_run(wall, axis) >= thickness {
+                        continue;
+                    }
+                    for neighbor in [wall + axis, wall + TileCoord::new(-axis.x, -axis.z)] {
+                        if self.tile(neighbor).is_some() && carved[index(neighbor)] {
+                            self.tiles[index(neighbor)] = original[index(neighbor)];
+                            carved[index(neighbor)] = false;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // how many walls in a row `tile` is part of along `axis`, itself included
+    fn wall_run(&self, tile: TileCoord, axis: TileCoord) -> u32 {
+        let mut run = 1;
+        for step in [axis, TileCoord::new(-axis.x, -axis.z)] {
+            let mut next = tile + step;
+            while self.tile(next) == Some(Tile::Wall) {
+                run += 1;
+                next = next + step;
+            }
+        }
+        run
+    }
+
+    pub fn count(&self, matches: impl Fn(Tile) -> bool) -> usize {
+        self.tiles.iter().filter(|meta| matches(meta.kind)).count()
+    }
+
+    // of all the tiles, how many are plain floor
+    pub fn floor_ratio(&self) -> f32 {
+        self.count(|tile| tile == Tile::Floor) as f32 / self.tiles.len().max(1) as f32
+    }
+
+    pub fn wall_ratio(&self) -> f32 {
+        self.count(|tile| tile == Tile::Wall) as f32 / self.tiles.len().max(1) as f32
+    }
+
+    // sizes of every 4-connected patch of walkable tiles, one entry means everything is reachable
+    pub fn regions(&self) -> Vec<usize> {
+        self.region_starts().into_iter().map(|(_, size)| size).collect()
+    }
+
+    // the first tile of every patch `regions` finds, column by column, with its size
+    pub(super) fn region_starts(&self) -> Vec<(TileCoord, usize)> {
+        let mut visited = vec![false; self.tiles.len()];
+        let mut starts = Vec::new();
+
+        for (start, tile) in self.tiles() {
+            let index = |tile: TileCoord| (tile.x * self.height as i32 + tile.z) as usize;
+            if visited[index(start)] || !tile.is_walkable() {
+                continue;
+            }
+
+            let mut size = 0;
+            let mut queue = VecDeque::from([start]);
+            visited[index(start)] = true;
+            while let Some(current) = queue.pop_front() {
+                size += 1;
+                for neighbor in current.neighbors() {
+                    if self.tile(neighbor).is_some_and(|tile| tile.is_walkable()) && !visited[index(neighbor)] {
+                        visited[index(neighbor)] = true;
+                        queue.push_back(neighbor);
+                    }
+                }
+            }
+            starts.push((start, size));
+        }
+
+        starts
+    }
+
+    pub fn save_png(&self, path: &str) -> ImageResult<()> {
+        let mut image = RgbaImage::new(self.width, self.height);
+        for (tile, meta) in self.metadata() {
+            image.put_pixel(tile.x as u32, tile.z as u32, Rgba(meta.pixel()));
+        }
+        image.save_with_format(path, ImageFormat::Png)
+    }
+
+    pub fn print(&self) {
+        for z in 0..self.height as i32 {
+            for x in 0..self.width as i32 {
+                if let Some(meta) = self.meta(TileCoord::new(x, z)) {
+                    print_pixel(&meta.pixel());
+                }
+            }
+            println!();
+        }
+    }
+}
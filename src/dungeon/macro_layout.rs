@@ -0,0 +1,86 @@
+use crate::coords::TileCoord;
+
+use super::{Corridor, WALL_COLOR};
+
+// mixed into the quadrant's seed for which cells are rooms
+const ROOM_SEED: u64 = 0x3ac5_17e0_55aa_91d3;
+// how much less likely wall is than anything else inside a room, it's still allowed so rooms get pillars and nooks
+const ROOM_WALL_WEIGHT: f32 = 0.2;
+// the same in a corridor, low enough that they nearly always come out open
+const CORRIDOR_WALL_WEIGHT: f32 = 0.05;
+
+// what a block of tiles is meant to be before the collapse fills in the detail
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MacroCell {
+    // mostly open, the collapse decides what's in it
+    Room,
+    // mostly open, it's what links the rooms
+    Corridor,
+    // nothing but wall
+    Solid,
+}
+
+impl MacroCell {
+    // how much a collapse pattern whose corner tile is `pixel` is favored in a tile inside this cell. 0 rules it out
+    pub fn weight(&self, pixel: [u8; 4]) -> f32 {
+        let wall = pixel == WALL_COLOR;
+        match self {
+            MacroCell::Room if wall => ROOM_WALL_WEIGHT,
+            MacroCell::Corridor if wall => CORRIDOR_WALL_WEIGHT,
+            MacroCell::Solid if !wall => 0.0,
+            _ => 1.0,
+        }
+    }
+}
+
+// the big picture of a quadrant, a coarse grid of rooms, the corridors between them and solid rock everywhere else.
+// the collapse is then only allowed patterns that fit the cell each tile is in, so the shape of the dungeon is
+// decided here and the collapse just gives it texture
+#[derive(Clone, Debug)]
+pub struct MacroLayout {
+    // tiles along each side of a cell
+    pub cell_size: u32,
+    pub columns: u32,
+    pub rows: u32,
+    // row by row, z * columns + x
+    pub cells: Vec<MacroCell>,
+}
+
+impl MacroLayout {
+    // `width` and `height` in tiles. every cell is a room with `room_chance` odds, there's always at least one, and
+    // each room has its corridor joined up to the one after it. the collapse can still wall a corridor off now and
+    // then, `DungeonLayout::connect_regions` opens whatever got cut off afterwards
+    pub fn generate(seed: u64, width: u32, height: u32, cell_size: u32, room_chance: f32) -> Self {
+        let cell_size = cell_size.max(1);
+        let columns = width.div_ceil(cell_size).max(1);
+        let rows = height.div_ceil(cell_size).max(1);
+        let mut rng = fastrand::Rng::with_seed(seed ^ ROOM_SEED);
+
+        let mut cells: Vec<MacroCell> = (0..columns * rows).map(|_| if rng.f32() < room_chance { MacroCell::Room } else { MacroCell::Solid }).collect();
+        let fallback = rng.usize(..cells.len());
+        if !cells.contains(&MacroCell::Room) {
+            cells[fallback] = MacroCell::Room;
+        }
+
+        // the same l shaped corridors `DungeonLayout::carve_corridor` opens, just a cell to a step
+        let rooms: Vec<TileCoord> = (0..cells.len() as i32).filter(|&i| cells[i as usize] == MacroCell::Room).map(|i| TileCoord::new(i % columns as i32, i / columns as i32)).collect();
+        let corridor = Corridor::LShaped { x_first: true };
+        for pair in rooms.windows(2) {
+            for step in corridor.path(pair[0], pair[1], columns, rows, &mut rng) {
+                let cell = &mut cells[(step.z as u32 * columns + step.x as u32) as usize];
+                if *cell == MacroCell::Solid {
+                    *cell = MacroCell::Corridor;
+                }
+            }
+        }
+
+        Self { cell_size, columns, rows, cells }
+    }
+
+    // the cell the tile at `x`, `z` is in
+    pub fn cell(&self, x: u32, z: u32) -> MacroCell {
+        let column = (x / self.cell_size).min(self.columns - 1);
+        let row = (z / self.cell_size).min(self.rows - 1);
+        self.cells[(row * self.columns + column) as usize]
+    }
+}
@@ -0,0 +1,181 @@
+use std::sync::{atomic::AtomicBool, Arc};
+
+use crate::{config::GenerationSettings, coords, error::GenerationError};
+
+mod corridors;
+mod layout;
+mod macro_layout;
+mod terrain;
+mod wfc;
+
+pub use corridors::Corridor;
+pub use layout::{DungeonLayout, Hazards, SpawnAnchor, Tile, TileMeta};
+pub use macro_layout::MacroLayout;
+pub use terrain::Heightmap;
+use wfc::Canvas;
+
+// what the colors in a sample image mean, traps have theirs in `traps`
+pub const FLOOR_COLOR: [u8; 4] = [255, 255, 255, 255];
+pub const WALL_COLOR: [u8; 4] = [0, 0, 0, 255];
+pub const WATER_COLOR: [u8; 4] = [0, 255, 255, 255];
+pub const LAVA_COLOR: [u8; 4] = [255, 128, 0, 255];
+pub const LADDER_COLOR: [u8; 4] = [255, 255, 0, 255];
+pub const TELEPORTER_COLOR: [u8; 4] = [255, 0, 255, 255];
+pub const LOW_GRAVITY_COLOR: [u8; 4] = [128, 128, 255, 255];
+// floor that's also where enemies or props should turn up, see `SpawnAnchor`
+pub const ENEMY_ANCHOR_COLOR: [u8; 4] = [128, 0, 0, 255];
+pub const PROP_ANCHOR_COLOR: [u8; 4] = [0, 128, 0, 255];
+// how often the passages that join up cut off parts of a layout stumble sideways, see `Corridor::Drunkard`
+const CONNECTOR_WANDER: f32 = 0.3;
+
+#[derive(Clone, Debug)]
+pub struct DungeonConfig {
+    pub width: u32,
+    pub height: u32,
+    // the built in rooms when not set
+    pub sample: Option<String>,
+    // a collapse with less floor or more wall than this is redone with another seed, see `GenerationSettings`
+    pub min_floor_ratio: f32,
+    pub max_wall_ratio: f32,
+    pub max_attempts: u32,
+    // how many of the sample's traps are kept, see `DifficultyScaling::trap_frequency`
+    pub trap_frequency: f32,
+    // in tiles, see `GenerationSettings`
+    pub corridor_width: u32,
+    pub wall_thickness: u32,
+    // odds of a quadrant being open terrain instead of a maze, and how tall its hills get
+    pub terrain_chance: f32,
+    pub terrain_amplitude: f32,
+    // tiles along each side of a macro cell, 0 leaves the whole quadrant to the collapse. see `MacroLayout`
+    pub macro_cell_size: u32,
+    pub room_chance: f32,
+}
+
+impl DungeonConfig {
+    // one square quadrant of the endless maze, as big as the run's difficulty made them
+    pub fn quadrant(settings: &GenerationSettings) -> Self {
+        Self {
+            width: coords::quadrant_tiles(),
+            height: coords::quadrant_tiles(),
+            sample: None,
+            min_floor_ratio: settings.min_floor_ratio,
+            max_wall_ratio: settings.max_wall_ratio,
+            max_attempts: settings.max_attempts,
+            trap_frequency: 1.0,
+            corridor_width: settings.corridor_width,
+            wall_thickness: settings.wall_thickness,
+            terrain_chance: settings.terrain_chance,
+            terrain_amplitude: settings.terrain_amplitude,
+            macro_cell_size: settings.macro_cell_size,
+            room_chance: settings.room_chance,
+        }
+    }
+}
+
+// turns a seed into a maze layout and nothing else. building meshes and colliders for it is `generator`'s job,
+// so the minimap, ai, saves and the headless generator can all work with layouts directly
+pub struct DungeonGenerator {
+    config: DungeonConfig,
+    cancel: Arc<AtomicBool>,
+}
+
+impl DungeonGenerator {
+    pub fn new(config: DungeonConfig) -> Self {
+        Self { config, cancel: Arc::new(AtomicBool::new(false)) }
+    }
+
+    // setting the flag from another thread makes `generate` give up with `GenerationError::Cancelled`
+    pub fn with_cancel(mut self, cancel: Arc<AtomicBool>) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    // collapses until the layout is open enough to play in, retrying with a seed derived from the last one. the
+    // retries depend only on `seed` so every client ends up with the same layout
+    pub fn generate(&self, seed: u64) -> Result<DungeonLayout, GenerationError> {
+        let mut canvas = Canvas::new(self.config.width, self.config.height);
+        canvas.seed = seed;
+        canvas.cancel = self.cancel.clone();
+        // from the first seed only, the retries just try another collapse inside the same rooms
+        if self.config.macro_cell_size > 0 {
+            canvas.macro_layout = Some(MacroLayout::generate(seed, self.config.width, self.config.height, self.config.macro_cell_size, self.config.room_chance));
+        }
+
+        let max_attempts = self.config.max_attempts.max(1);
+        for attempt in 1..=max_attempts {
+            let last = attempt == max_attempts;
+            let result = match self.config.sample.as_deref() {
+                Some(sample) => canvas.write_from_sample(sample),
+                None => canvas.write(),
+            };
+
+            match result {
+                Ok(()) => {
+                    let mut layout = DungeonLayout::from_canvas(&canvas, attempt);
+                    let (floor, walls) = (layout.floor_ratio(), layout.wall_ratio());
+                    let acceptable = floor >= self.config.min_floor_ratio && walls <= self.config.max_wall_ratio;
+                    if !acceptable && last {
+                        log::warn!("no acceptable layout after {} tries, keeping one with {:.0}% floor", attempt, floor * 100.0);
+                    }
+                    if acceptable || last {
+                        // after the checks, so neither the hall widths nor the difficulty change which collapse gets kept
+                        layout.widen(self.config.corridor_width, self.config.wall_thickness);
+                        layout.connect_regions(Corridor::Drunkard { wander: CONNECTOR_WANDER });
+                        layout.scale_traps(self.config.trap_frequency);
+                        return Ok(layout);
+                    }
+                }
+                // some seeds just don't work out with a given sample, another one usually does
+                Err(GenerationError::Contradiction(_)) if !last => {}
+                Err(e) => return Err(e),
+            }
+
+            canvas.seed = canvas.seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        }
+
+        unreachable!("the last attempt always returns")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::coords::TileCoord;
+
+    use super::*;
+
+    fn config(width: u32, height: u32) -> DungeonConfig {
+        DungeonConfig { width, height, ..DungeonConfig::quadrant(&GenerationSettings::default()) }
+    }
+
+    #[test]
+    fn fills_a_64_by_64_canvas() {
+        let layout = DungeonGenerator::new(config(64, 64)).generate(7).unwrap();
+        assert_eq!((layout.width, layout.height), (64, 64));
+        assert_eq!(layout.tiles().count(), 64 * 64);
+        assert!(layout.meta(TileCoord::new(63, 63)).is_some());
+        assert!(layout.count(|tile| tile.is_walkable()) > 0);
+    }
+
+    #[test]
+    fn same_seed_same_64_by_64_layout() {
+        let first = DungeonGenerator::new(config(64, 64)).generate(11).unwrap();
+        let second = DungeonGenerator::new(config(64, 64)).generate(11).unwrap();
+        assert_eq!(first.seed, second.seed);
+        assert!(first.metadata().zip(second.metadata()).all(|(a, b)| a == b));
+    }
+
+    #[test]
+    fn fills_a_canvas_that_isnt_square() {
+        let layout = DungeonGenerator::new(config(64, 24)).generate(3).unwrap();
+        assert_eq!((layout.width, layout.height), (64, 24));
+        assert_eq!(layout.tiles().count(), 64 * 24);
+        assert!(layout.meta(TileCoord::new(63, 23)).is_some());
+        assert!(layout.meta(TileCoord::new(23, 63)).is_none());
+    }
+
+    #[test]
+    fn too_small_a_canvas_is_an_error() {
+        let result = DungeonGenerator::new(config(2, 64)).generate(1);
+        assert!(matches!(result, Err(GenerationError::CanvasTooSmall { width: 2, height: 64 })));
+    }
+}
@@ -0,0 +1,96 @@
+use crate::coords::TileCoord;
+
+use super::DungeonLayout;
+
+// heights between tile corners, more makes smoother hills
+pub const SAMPLES_PER_TILE: u32 = 2;
+// layers of noise, each one twice as fine and half as tall as the one before
+const OCTAVES: u32 = 3;
+// the coarsest layer has a hill every this many tiles
+const HILL_SPACING: f32 = 6.0;
+// tiles at the edge that slope down to the floor, so terrain lines up with the mazes next to it
+const EDGE_FALLOFF: f32 = 2.0;
+
+// an open cavern quadrant instead of a maze, just heights above the floor. building the ground mesh and collider
+// for it is `generator`'s job, same as with layouts
+#[derive(Clone, Debug)]
+pub struct Heightmap {
+    // samples along x and z, neighboring tiles share the samples on their edge
+    pub columns: usize,
+    pub rows: usize,
+    // world units between samples
+    pub spacing: f32,
+    // row by row, z * columns + x
+    pub heights: Vec<f32>,
+}
+
+impl Heightmap {
+    // `width` and `height` in tiles, `amplitude` is the tallest a hill can get in world units
+    pub fn generate(seed: u64, width: u32, height: u32, tile_size: f32, amplitude: f32) -> Self {
+        let columns = (width * SAMPLES_PER_TILE + 1) as usize;
+        let rows = (height * SAMPLES_PER_TILE + 1) as usize;
+        let samples_per_tile = SAMPLES_PER_TILE as f32;
+
+        let mut heights = Vec::with_capacity(columns * rows);
+        for z in 0..rows {
+            for x in 0..columns {
+                let (tile_x, tile_z) = (x as f32 / samples_per_tile, z as f32 / samples_per_tile);
+
+                let mut noise = 0.0;
+                let mut weight = 1.0;
+                let mut total = 0.0;
+                for octave in 0..OCTAVES {
+                    let frequency = (1 << octave) as f32 / HILL_SPACING;
+                    noise += value_noise(seed.wrapping_add(octave as u64), tile_x * frequency, tile_z * frequency) * weight;
+                    total += weight;
+                    weight /= 2.0;
+                }
+
+                let edge = tile_x.min(tile_z).min(width as f32 - tile_x).min(height as f32 - tile_z);
+                let falloff = (edge / EDGE_FALLOFF).clamp(0.0, 1.0);
+                heights.push(noise / total * amplitude * falloff);
+            }
+        }
+
+        Self { columns, rows, spacing: tile_size / samples_per_tile, heights }
+    }
+
+    pub fn height(&self, column: usize, row: usize) -> f32 {
+        self.heights[row * self.columns + column]
+    }
+
+    // at the middle of a tile, local to the quadrant
+    pub fn tile_height(&self, tile: TileCoord) -> f32 {
+        let half = (SAMPLES_PER_TILE / 2) as usize;
+        let column = tile.x as usize * SAMPLES_PER_TILE as usize + half;
+        let row = tile.z as usize * SAMPLES_PER_TILE as usize + half;
+        self.height(column.min(self.columns - 1), row.min(self.rows - 1))
+    }
+
+    // every tile is open floor, so spawning, props and pathing work the same as in a maze
+    pub fn layout(&self, seed: u64) -> DungeonLayout {
+        let width = (self.columns - 1) as u32 / SAMPLES_PER_TILE;
+        let height = (self.rows - 1) as u32 / SAMPLES_PER_TILE;
+        DungeonLayout::open(seed, width, height)
+    }
+}
+
+// smoothly blended random values on a grid, from 0 to 1
+fn value_noise(seed: u64, x: f32, z: f32) -> f32 {
+    let (x0, z0) = (x.floor(), z.floor());
+    let smooth = |t: f32| t * t * (3.0 - 2.0 * t);
+    let (tx, tz) = (smooth(x - x0), smooth(z - z0));
+    let (x0, z0) = (x0 as i64, z0 as i64);
+
+    let top = lattice(seed, x0, z0) + (lattice(seed, x0 + 1, z0) - lattice(seed, x0, z0)) * tx;
+    let bottom = lattice(seed, x0, z0 + 1) + (lattice(seed, x0 + 1, z0 + 1) - lattice(seed, x0, z0 + 1)) * tx;
+    top + (bottom - top) * tz
+}
+
+// the same value for the same point and seed on every machine
+fn lattice(seed: u64, x: i64, z: i64) -> f32 {
+    let mut hash = seed ^ (x as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15) ^ (z as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F);
+    hash = (hash ^ (hash >> 31)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    hash ^= hash >> 29;
+    (hash >> 40) as f32 / (1u64 << 24) as f32
+}
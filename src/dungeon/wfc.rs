@@ -1,4 +1,4 @@
-use std::{collections::{HashMap, HashSet}, io::Write, sync::Arc};
+use std::{collections::{HashMap, HashSet}, io::Write, sync::{atomic::{AtomicBool, Ordering}, Arc}};
 use serde::{Serialize, Deserialize};
 use uuid::Uuid;
 use wave_function_collapse::wave_function::{WaveFunction, NodeStateCollection, Node, collapsable_wave_function::{collapsable_wave_function::{CollapsableWaveFunction, CollapsedWaveFunction, CollapsedNodeState}, entropic_collapsable_wave_function::EntropicCollapsableWaveFunction}};
@@ -6,7 +6,11 @@ use image::{io::Reader as ImageReader, GenericImageView, DynamicImage, ImageForm
 use colored::Colorize;
 use std::cmp;
 
-fn print_pixel(color: &[u8; 4]) {
+use crate::error::GenerationError;
+
+use super::{MacroLayout, SpawnAnchor, Tile, TileMeta};
+
+pub(super) fn print_pixel(color: &[u8; 4]) {
     let character = "\u{2588}";
     print!("{}{}", character.truecolor(color[0], color[1], color[2]), character.truecolor(color[0], color[1], color[2]));
 }
@@ -123,7 +127,15 @@ pub struct Canvas {
     pub width: u32,
     pub height: u32,
     pub pixels: Vec<Vec<[u8; 4]>>,
+    // what each pixel means, same layout as `pixels`. filled in right after the collapse is decoded
+    pub metadata: Vec<Vec<TileMeta>>,
+    // same seed, same dungeon. random unless something (like a multiplayer host) sets it
+    pub seed: u64,
     pub collapsed_wave_function: Arc<Option<CollapsedWaveFunction<ImageFragment>>>,
+    // set from another thread to stop `write` early, the canvas is left unbuilt
+    pub cancel: Arc<AtomicBool>,
+    // when set, each tile only collapses into patterns that fit the macro cell it's in
+    pub macro_layout: Option<MacroLayout>,
 }
 
 impl Canvas {
@@ -133,14 +145,31 @@ impl Canvas {
             height: height,
             been_built: false,
             pixels: Vec::new(),
+            metadata: Vec::new(),
+            seed: fastrand::u64(..),
             collapsed_wave_function: Arc::new(None),
+            cancel: Arc::new(AtomicBool::new(false)),
+            macro_layout: None,
         }
     }
-    pub fn get_wave_function(&self, source_image_file_path: &str, fragment_width: u32, fragment_height: u32, is_reflection_permitted: bool, is_rotation_permitted: bool, is_periodic: bool, contains_ground: bool) -> WaveFunction<ImageFragment> {
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+
+    // `Err(Cancelled)` if cancelled partway through
+    pub fn get_wave_function(&self, source_image_file_path: &str, fragment_width: u32, fragment_height: u32, is_reflection_permitted: bool, is_rotation_permitted: bool, is_periodic: bool, contains_ground: bool) -> Result<WaveFunction<ImageFragment>, GenerationError> {
+        if self.width < fragment_width || self.height < fragment_height {
+            return Err(GenerationError::CanvasTooSmall { width: self.width, height: self.height });
+        }
+
         // get all of the possible image fragments from the original image
-        let mut image_reader = ImageReader::open(source_image_file_path).expect("The source image file should exist at the provided file path.");
-        image_reader.set_format(ImageFormat::Bmp);
-        let image = image_reader.decode().unwrap();
+        let sample_error = |source| GenerationError::Sample { path: source_image_file_path.to_string(), source };
+        let mut image_reader = ImageReader::open(source_image_file_path).map_err(|e| sample_error(e.into()))?;
+        // the built in sample goes through a temp file with no extension
+        if image_reader.format().is_none() {
+            image_reader.set_format(ImageFormat::Bmp);
+        }
+        let image = image_reader.decode().map_err(sample_error)?;
         let image_width = image.width();
         let image_height = image.height();
 
@@ -213,6 +242,10 @@ impl Canvas {
 
         let mut permitted_node_states_per_height_offset_per_width_offset_per_node_state: HashMap<&ImageFragment, HashMap<i8, HashMap<i8, Vec<ImageFragment>>>> = HashMap::new();
         for root_image_fragment in image_fragments.iter() {
+            // comparing every fragment against every other is the slow part, so check in between
+            if self.is_cancelled() {
+                return Err(GenerationError::Cancelled);
+            }
             //println!("====================");
             //println!("Root:");
             //root_image_fragment.print();
@@ -258,11 +291,15 @@ impl Canvas {
         // construct nodes
         let mut nodes: Vec<Node<ImageFragment>> = Vec::new();
 
+        // indices and their neighbors' go in i32, the canvas is as big as whoever asked wants it
+        let node_columns = (self.width - (fragment_width - 1)) as i32;
+        let node_rows = (self.height - (fragment_height - 1)) as i32;
+
         // create grid of node IDs cooresponding to each image fragment's top-left corner
         let mut node_id_per_height_index_per_width_index: HashMap<usize, HashMap<usize, String>> = HashMap::new();
-        for node_width_index in 0..(self.width - (fragment_width - 1)) as usize {
+        for node_width_index in 0..node_columns as usize {
             let mut node_id_per_height_index: HashMap<usize, String> = HashMap::new();
-            for node_height_index in 0..(self.height - (fragment_height - 1)) as usize {
+            for node_height_index in 0..node_rows as usize {
                 let node_id: String = format!("node_{}_{}", node_width_index, node_height_index);
                 node_id_per_height_index.insert(node_height_index, node_id);
             }
@@ -270,34 +307,34 @@ impl Canvas {
         }
 
         // create each node such that its relative node state collections are specified
-        for node_width_index in 0..(self.width - (fragment_width - 1)) as i8 {
-            for node_height_index in 0..(self.height - (fragment_height - 1)) as i8 {
+        for node_width_index in 0..node_columns {
+            for node_height_index in 0..node_rows {
                 let node_id: &String = node_id_per_height_index_per_width_index.get(&(node_width_index as usize)).unwrap().get(&(node_height_index as usize)).unwrap();
                 let mut node_state_collection_ids_per_neighbor_node_id: HashMap<String, Vec<String>> = HashMap::new();
                 for neighbor_width_offset in -1..=1 as i8 {
                     for neighbor_height_offset in -1..=1 as i8 {
                         if !(neighbor_width_offset == 0 && neighbor_height_offset == 0 ||
                             neighbor_width_offset.abs() == 1 && neighbor_height_offset.abs() == 1) {
-                            let mut neighbor_width_index = node_width_index + neighbor_width_offset;
-                            let mut neighbor_height_index = node_height_index + neighbor_height_offset;
+                            let mut neighbor_width_index = node_width_index + neighbor_width_offset as i32;
+                            let mut neighbor_height_index = node_height_index + neighbor_height_offset as i32;
                             if is_periodic {
                                 if neighbor_width_index < 0 {
-                                    neighbor_width_index += (self.width - (fragment_width - 1)) as i8;
+                                    neighbor_width_index += node_columns;
                                 }
-                                else if neighbor_width_index >= (self.width - (fragment_width - 1)) as i8 {
-                                    neighbor_width_index -= (self.width - (fragment_width - 1)) as i8;
+                                else if neighbor_width_index >= node_columns {
+                                    neighbor_width_index -= node_columns;
                                 }
                                 if neighbor_height_index < 0 {
-                                    neighbor_height_index += (self.height - (fragment_height - 1)) as i8;
+                                    neighbor_height_index += node_rows;
                                 }
-                                else if neighbor_height_index >= (self.height - (fragment_height - 1)) as i8 {
-                                    neighbor_height_index -= (self.height - (fragment_height - 1)) as i8;
+                                else if neighbor_height_index >= node_rows {
+                                    neighbor_height_index -= node_rows;
                                 }
                             }
                             if neighbor_width_index >= 0 &&
-                                neighbor_width_index < (self.width - (fragment_width - 1)) as i8 &&
+                                neighbor_width_index < node_columns &&
                                 neighbor_height_index >= 0 &&
-                                neighbor_height_index < (self.height - (fragment_height - 1)) as i8 {
+                                neighbor_height_index < node_rows {
 
                                 let neighbor_node_id = node_id_per_height_index_per_width_index.get(&(neighbor_width_index as usize)).unwrap().get(&(neighbor_height_index as usize)).unwrap();
                                 let node_state_collection_ids = node_state_collection_ids_per_height_offset_per_width_offset.get(&neighbor_width_offset).unwrap().get(&neighbor_height_offset).unwrap();
@@ -309,7 +346,7 @@ impl Canvas {
 
                 let mut node_state_ratio_per_node_state_id: HashMap<ImageFragment, f32> = HashMap::new();
                 if contains_ground {
-                    if node_height_index + 1 == (self.height - (fragment_height - 1)) as i8 {
+                    if node_height_index + 1 == node_rows {
                         for (image_fragment, ratio) in image_fragment_duplicates_total_per_image_fragment.iter() {
                             if ground_image_fragments.contains(image_fragment) {
                                 node_state_ratio_per_node_state_id.insert(image_fragment.clone(), *ratio);
@@ -328,38 +365,63 @@ impl Canvas {
                     node_state_ratio_per_node_state_id = image_fragment_duplicates_total_per_image_fragment.clone();
                 }
 
+                // a node is the top left tile of its fragment, so that's the pixel the macro cell gets a say in. a cell
+                // nothing in the sample fits is left alone rather than made impossible
+                if let Some(macro_layout) = &self.macro_layout {
+                    let cell = macro_layout.cell(node_width_index as u32, node_height_index as u32);
+                    let constrained: HashMap<ImageFragment, f32> = node_state_ratio_per_node_state_id.iter()
+                        .map(|(image_fragment, ratio)| (image_fragment.clone(), ratio * cell.weight(image_fragment.pixels[0][0])))
+                        .filter(|(_, ratio)| *ratio > 0.0)
+                        .collect();
+                    if !constrained.is_empty() {
+                        node_state_ratio_per_node_state_id = constrained;
+                    }
+                }
+
                 let node: Node<ImageFragment> = Node::new(node_id.clone(), node_state_ratio_per_node_state_id, node_state_collection_ids_per_neighbor_node_id);
                 nodes.push(node);
             }
         }
 
-        WaveFunction::new(nodes, node_state_collections)
+        Ok(WaveFunction::new(nodes, node_state_collections))
+    }
+
+    pub fn write(&mut self) -> Result<(), GenerationError> {
+        let mut file = tempfile::NamedTempFile::new()?;
+        let bytes = include_bytes!("../../rooms.bmp");
+        file.write_all(bytes.as_slice())?;
+        let file_path = file.path().to_string_lossy().to_string();
+
+        self.write_from_sample(&file_path)?;
+
+        file.close()?;
+        Ok(())
     }
 
-    pub fn write(&mut self) {
+    // same as `write` but learns the patterns from any image instead of the built in rooms
+    pub fn write_from_sample(&mut self, sample_path: &str) -> Result<(), GenerationError> {
         let is_reflection_permitted = true;
         let is_rotation_permitted = true;
         let is_periodic = false;
         let contains_ground = false;
 
-        let mut file = tempfile::NamedTempFile::new().unwrap();
-        let bytes = include_bytes!("../rooms.bmp");
-        file.write(bytes.as_slice()).unwrap();
-        let file_path: &str = file.path().to_str().unwrap();
-
         let fragment_width: u32 = 3;
         let fragment_height: u32 = 3;
-        let wave_function = self.get_wave_function(file_path, fragment_width, fragment_height, is_reflection_permitted, is_rotation_permitted, is_periodic, contains_ground);
+        let wave_function = self.get_wave_function(sample_path, fragment_width, fragment_height, is_reflection_permitted, is_rotation_permitted, is_periodic, contains_ground)?;
     
-        file.close().unwrap();
+        wave_function.validate().map_err(GenerationError::InvalidWaveFunction)?;
     
-        wave_function.validate().unwrap();
-    
-        let mut random_instance = fastrand::Rng::new();
-        let random_seed = Some(random_instance.u64(..));
+        let random_seed = Some(self.seed);
     
         let mut collapsable_wave_function = wave_function.get_collapsable_wave_function::<EntropicCollapsableWaveFunction<ImageFragment>>(random_seed);
-        let collapsed_wave_function = collapsable_wave_function.collapse().unwrap();
+        // the collapse itself runs to the end once started, the library has no way to stop it
+        if self.is_cancelled() {
+            return Err(GenerationError::Cancelled);
+        }
+        let collapsed_wave_function = collapsable_wave_function.collapse().map_err(GenerationError::Contradiction)?;
+        if self.is_cancelled() {
+            return Err(GenerationError::Cancelled);
+        }
 
         let mut node_state_per_height_index_per_width_index: HashMap<usize, HashMap<usize, Option<ImageFragment>>> = HashMap::new();
         for width_index in 0..self.width as usize {
@@ -404,20 +466,25 @@ impl Canvas {
         }
 
         self.been_built = true;
+        self.metadata = Self::decode_metadata(&pixels);
         self.pixels = pixels;
+        Ok(())
     }
 
-    pub fn get_pixel(&self, w: usize, h: usize) -> [u8; 4] {
-        self.pixels[w][h]
+    // colors to tiles, column by column so markers are numbered the same on every machine
+    fn decode_metadata(pixels: &[Vec<[u8; 4]>]) -> Vec<Vec<TileMeta>> {
+        let mut next_marker = 0;
+        pixels.iter().map(|column| column.iter().map(|&pixel| {
+            let kind = Tile::from_pixel(pixel);
+            let marker = (kind == Tile::Teleporter).then(|| {
+                next_marker += 1;
+                next_marker - 1
+            });
+            TileMeta { anchor: SpawnAnchor::from_pixel(pixel), ..TileMeta::new(kind, marker) }
+        }).collect()).collect()
     }
 
-    pub fn print(&self) {
-        for height_index in 0..self.height as usize {
-            for width_index in 0..self.width as usize {
-                let color = self.pixels[width_index][height_index];
-                print_pixel(&color);
-            }
-            println!("");
-        }
+    pub fn get_pixel(&self, w: usize, h: usize) -> [u8; 4] {
+        self.pixels[w][h]
     }
 }
@@ -0,0 +1,513 @@
+use std::collections::HashMap;
+
+use rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
+use tiny_game_framework::{glam::{vec2, vec3, Quat, Vec3, Vec4}, Cuboid, Renderer, Sphere};
+
+use crate::{config::EnemyArchetype, coords::{ChunkCoord, WorldPos}, error::RendererExt, animation::Animator, billboards::{Impostor, Sprite}, ai::{self, Blackboard, Decision, Node, Senses}, navmesh::Navigator, perception::{NoiseBus, Perception}, entity::{EntityId, EntityKind}, physics_thread::Blend, rapier_integration::{BodySpec, CollisionLayer, Ragdoll, RapierPhysicsWorld, Skeleton}, shadows::ShadowCaster};
+
+// matches the humanoid skeleton (~1.8 * scale tall) up with the enemy mesh
+const RAGDOLL_SCALE: f32 = 100.0;
+// how long a corpse sticks around before it gets cleaned up
+const CORPSE_LIFETIME: f32 = 20.0;
+// how far up an enemy's body its shots come from, as a fraction of its height above the middle
+const SHOT_HEIGHT: f32 = 0.3;
+const BOLT_RADIUS: f32 = 8.0;
+const BOLT_COLOR: Vec4 = Vec4::new(0.9, 0.85, 0.6, 1.0);
+// enemies closer than this push apart so a crowd chasing the player doesn't end up inside itself
+const SEPARATION_RADIUS: f32 = 120.0;
+// how much the push counts next to where the enemy wants to go, 1 is as much as its own walking
+const SEPARATION_WEIGHT: f32 = 0.8;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum EnemyKind {
+    Grunt,
+    // slower to kill and heavier to push around
+    Brute,
+    // only spawned by boss encounters
+    Boss,
+    // keeps its distance and shoots
+    Archer,
+    // slow and so heavy it barely moves when hit
+    Tank,
+    // small and fast, never alone
+    Swarmer,
+}
+
+impl EnemyKind {
+    pub const ALL: [EnemyKind; 6] = [EnemyKind::Grunt, EnemyKind::Brute, EnemyKind::Boss, EnemyKind::Archer, EnemyKind::Tank, EnemyKind::Swarmer];
+}
+
+pub struct Enemy {
+    pub id: EntityId,
+    pub kind: EnemyKind,
+    pub body: RigidBodyHandle,
+    pub mesh_name: String,
+    pub health: f32,
+    pub max_health: f32,
+    // where the body was at the last update
+    pub position: Vec3,
+    // the same on every machine in a multiplayer game, which `id` isn't
+    pub net_id: u64,
+    // mirrored from the host instead of running its own ai
+    pub replica: bool,
+    pub brain: Blackboard,
+    pub perception: Perception,
+    // where the enemy is looking, follows the direction it last moved in
+    pub facing: Vec3,
+    pub animator: Animator,
+
+    pub ragdoll: Option<Ragdoll>,
+    ragdoll_mesh_names: Vec<String>,
+    corpse_timer: f32,
+}
+
+impl Enemy {
+    pub fn is_alive(&self) -> bool {
+        self.ragdoll.is_none()
+    }
+
+    pub fn health_fraction(&self) -> f32 {
+        self.health / self.max_health
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum EnemyEvent {
+    Damage(f32),
+    Knockback(Vec3),
+    // the boss hitting the ground with an attack, felt anywhere nearby
+    Stomp { position: Vec3 },
+}
+
+// something an enemy shot. it's moved by hand and checked along the way, so it can't skip through a wall or a player
+struct Bolt {
+    // left out of the check, so it doesn't hit whoever fired it on the way out
+    shooter: RigidBodyHandle,
+    position: Vec3,
+    velocity: Vec3,
+    damage: f32,
+    knockback: f32,
+    lifetime: f32,
+    mesh_name: String,
+}
+
+pub struct EnemySystem {
+    pub enemies: Vec<Enemy>,
+    pub skeleton: Skeleton,
+    // kinds without an entry here use `ai::default_tree`
+    pub behaviors: HashMap<EnemyKind, Node>,
+    default_behavior: Node,
+    // every kind has one
+    archetypes: HashMap<EnemyKind, EnemyArchetype>,
+    bolts: Vec<Bolt>,
+    enemy_counter: usize,
+    // from the difficulty, multiplies what every attack takes off the player
+    pub damage_scale: f32,
+}
+
+impl EnemySystem {
+    // `archetypes` come from `config::load_archetypes`, kinds it leaves out are plain grunts. `behaviors` win over
+    // the trees that come with them
+    pub fn new(mut behaviors: HashMap<EnemyKind, Node>, mut archetypes: HashMap<EnemyKind, EnemyArchetype>) -> Self {
+        for kind in EnemyKind::ALL {
+            let archetype = archetypes.entry(kind).or_default();
+            if let Some(behavior) = &archetype.behavior {
+                behaviors.entry(kind).or_insert_with(|| behavior.clone());
+            }
+        }
+
+        Self {
+            enemies: Vec::new(),
+            skeleton: Skeleton::humanoid(RAGDOLL_SCALE),
+            behaviors,
+            default_behavior: ai::default_tree(),
+            archetypes,
+            bolts: Vec::new(),
+            enemy_counter: 0,
+            damage_scale: 1.0,
+        }
+    }
+
+    pub fn archetype(&self, kind: EnemyKind) -> &EnemyArchetype {
+        &self.archetypes[&kind]
+    }
+
+    pub fn spawn(&mut self, rw: &mut RapierPhysicsWorld, r: &mut Renderer, position: Vec3, kind: EnemyKind) -> usize {
+        let archetype = &self.archetypes[&kind];
+        // as wide as the narrower side of the mesh and as tall as all of it, so hits land on what's drawn
+        let size = Vec3::from(archetype.size);
+        let radius = size.x.min(size.z) / 2.0;
+        let half_height = (size.y / 2.0 - radius).max(0.0);
+        let body = rw.add_body(position, &BodySpec::capsule(radius, half_height)
+            .restitution(0.5)
+            .friction(1.0)
+            .density(archetype.density)
+            .layer(CollisionLayer::Enemy));
+        rw.rigid_body_set[body].lock_rotations(true, false);
+
+        let id = EntityId::new(EntityKind::Enemy);
+        rw.tag_body(body, id);
+
+        let mesh_name = format!("ENEMY_MESH{}", self.enemy_counter);
+        self.enemy_counter += 1;
+
+        let mut mesh = Cuboid::new(archetype.size.into(), archetype.color.into()).mesh();
+        mesh.position = position;
+        mesh.setup_mesh();
+        if let Err(e) = r.try_add_mesh(&mesh_name, mesh) {
+            log::warn!("{}", e);
+        }
+
+        self.enemies.push(Enemy {
+            id,
+            kind,
+            body,
+            mesh_name,
+            health: archetype.health,
+            max_health: archetype.health,
+            position,
+            net_id: id.id,
+            replica: false,
+            brain: Blackboard::default(),
+            perception: Perception::new(),
+            facing: Vec3::Z,
+            animator: Animator::new(),
+            ragdoll: None,
+            ragdoll_mesh_names: Vec::new(),
+            corpse_timer: CORPSE_LIFETIME,
+        });
+
+        self.enemies.len() - 1
+    }
+
+    pub fn find(&self, id: EntityId) -> Option<&Enemy> {
+        self.enemies.iter().find(|enemy| enemy.id == id)
+    }
+
+    pub fn find_by_net_id(&self, net_id: u64) -> Option<&Enemy> {
+        self.enemies.iter().find(|enemy| enemy.net_id == net_id)
+    }
+
+    // the living ones, corpses are ragdolls made of several meshes and stay that way
+    pub fn impostors(&self) -> impl Iterator<Item = Impostor<'_>> {
+        self.enemies.iter().filter(|enemy| enemy.is_alive()).map(|enemy| {
+            let archetype = &self.archetypes[&enemy.kind];
+            let size = Vec3::from(archetype.size);
+            Impostor {
+                mesh_name: &enemy.mesh_name,
+                position: enemy.position,
+                sprite: Sprite::Enemy,
+                size: vec2(size.x.max(size.z), size.y),
+                color: archetype.color.into(),
+            }
+        })
+    }
+
+    pub fn shadow_casters(&self) -> impl Iterator<Item = ShadowCaster> + '_ {
+        self.enemies.iter().filter(|enemy| enemy.is_alive()).map(|enemy| ShadowCaster {
+            body: enemy.body,
+            position: enemy.position,
+            size: self.archetypes[&enemy.kind].size.into(),
+        })
+    }
+
+    // moves (or spawns, or kills) the local copy of an enemy the host told us about
+    pub fn sync_replica(&mut self, rw: &mut RapierPhysicsWorld, r: &mut Renderer, net_id: u64, kind: EnemyKind, position: Vec3, health: f32) {
+        let index = match self.enemies.iter().position(|enemy| enemy.net_id == net_id) {
+            Some(index) => index,
+            None if health > 0.0 => {
+                let index = self.spawn(rw, r, position, kind);
+                let enemy = &mut self.enemies[index];
+                enemy.net_id = net_id;
+                enemy.replica = true;
+                rw.rigid_body_set[enemy.body].set_body_type(RigidBodyType::KinematicPositionBased, true);
+                index
+            }
+            None => return,
+        };
+
+        let enemy = &mut self.enemies[index];
+        if !enemy.is_alive() {
+            return;
+        }
+        enemy.health = health;
+        if health <= 0.0 {
+            self.kill(rw, r, index);
+            return;
+        }
+        rw.rigid_body_set[enemy.body].set_next_kinematic_translation(vector![position.x, position.y, position.z]);
+    }
+
+    // returns true if this hit killed the enemy
+    pub fn damage(&mut self, rw: &mut RapierPhysicsWorld, r: &mut Renderer, id: EntityId, amount: f32) -> bool {
+        let Some(index) = self.enemies.iter().position(|enemy| enemy.id == id && enemy.is_alive()) else {
+            return false;
+        };
+
+        self.enemies[index].health -= amount;
+        if self.enemies[index].health > 0.0 {
+            return false;
+        }
+
+        self.kill(rw, r, index);
+        true
+    }
+
+    // swaps the capsule for a ragdoll that keeps the momentum the enemy had
+    fn kill(&mut self, rw: &mut RapierPhysicsWorld, r: &mut Renderer, index: usize) {
+        let enemy = &mut self.enemies[index];
+
+        let body = &rw.rigid_body_set[enemy.body];
+        let t = body.translation();
+        let v = body.linvel();
+        let (position, velocity) = (vec3(t.x, t.y, t.z), vec3(v.x, v.y, v.z));
+
+        rw.remove_rigidbody(enemy.body);
+        r.destroy_mesh(&enemy.mesh_name).ok();
+
+        let ragdoll = rw.build_ragdoll(&self.skeleton, position, velocity, enemy.id);
+        // a killing blow can fling the limbs hard enough to pass right through a wall. the bones count as debris, so
+        // corpses are frozen away from players and the oldest go once there are too many
+        for &bone in ragdoll.bodies.iter() {
+            rw.set_ccd(bone, true);
+            rw.track_debris(bone);
+        }
+
+        for (bone, handle) in self.skeleton.bones.iter().zip(ragdoll.bodies.iter()) {
+            let mesh_name = format!("{}_{}", enemy.mesh_name, bone.name);
+            let size = vec3(bone.radius * 2.0, (bone.half_height + bone.radius) * 2.0, bone.radius * 2.0);
+            let t = rw.rigid_body_set[*handle].translation();
+
+            let mut mesh = Cuboid::new(size, Vec4::new(0.4, 0.1, 0.1, 1.0)).mesh();
+            mesh.position = vec3(t.x, t.y, t.z);
+            mesh.setup_mesh();
+            if let Err(e) = r.try_add_mesh(&mesh_name, mesh) {
+                log::warn!("{}", e);
+            }
+
+            enemy.ragdoll_mesh_names.push(mesh_name);
+        }
+
+        enemy.ragdoll = Some(ragdoll);
+    }
+
+    // the chunk was unloaded, anything still in it would fall through where the floor used to be. corpses go too
+    pub fn unload(&mut self, rw: &mut RapierPhysicsWorld, r: &mut Renderer, chunk: ChunkCoord) {
+        let (gone, kept) = std::mem::take(&mut self.enemies).into_iter().partition(|enemy| WorldPos(enemy.position).chunk() == chunk);
+        self.enemies = kept;
+        for enemy in gone {
+            match enemy.ragdoll {
+                None => {
+                    rw.remove_rigidbody(enemy.body);
+                    r.destroy_mesh(&enemy.mesh_name).ok();
+                }
+                Some(ragdoll) => {
+                    for mesh_name in enemy.ragdoll_mesh_names.iter() {
+                        r.destroy_mesh(mesh_name).ok();
+                    }
+                    rw.remove_ragdoll(ragdoll);
+                }
+            }
+        }
+    }
+
+    // `players` is everyone enemies can go after, events come back with the index of the player they're for
+    pub fn update(&mut self, rw: &mut RapierPhysicsWorld, r: &mut Renderer, players: &[Vec3], noises: &NoiseBus, nav: &Navigator, blend: &Blend, dt: f32) -> Vec<(usize, EnemyEvent)> {
+        let mut events = Vec::new();
+        let mut shots = Vec::new();
+
+        let mut i = 0;
+        while i < self.enemies.len() {
+            let enemy = &mut self.enemies[i];
+
+            match &enemy.ragdoll {
+                None => {
+                    let archetype = &self.archetypes[&enemy.kind];
+                    let t = rw.rigid_body_set[enemy.body].translation();
+                    let position = vec3(t.x, t.y, t.z);
+                    enemy.position = position;
+
+                    if enemy.replica {
+                        enemy.animator.update(dt);
+                        if let Some(mesh) = r.get_mesh_mut(&enemy.mesh_name) {
+                            enemy.animator.apply(mesh, position, Quat::IDENTITY);
+                        }
+                        i += 1;
+                        continue;
+                    }
+
+                    let Some((target, player_pos)) = players.iter().copied().enumerate()
+                        .min_by(|(_, a), (_, b)| a.distance(position).total_cmp(&b.distance(position))) else {
+                        i += 1;
+                        continue;
+                    };
+
+                    // losing sight of the player is the same as hearing them where they were last seen
+                    let heard = enemy.brain.noise;
+                    if enemy.perception.update(rw, enemy.body, position, enemy.facing, player_pos, dt) {
+                        enemy.brain.noise = enemy.perception.last_seen;
+                    }
+                    if let Some(noise) = noises.loudest_heard(position) {
+                        enemy.brain.noise = Some(noise);
+                    }
+                    if enemy.brain.noise != heard {
+                        enemy.brain.route = enemy.brain.noise.and_then(|noise| nav.path(position, noise)).unwrap_or_default();
+                    }
+
+                    let senses = Senses {
+                        position,
+                        player_pos,
+                        health_fraction: enemy.health_fraction(),
+                        attack_range: archetype.attack_range,
+                        can_see_player: enemy.perception.can_see_player,
+                    };
+                    let mut decision = Decision::default();
+                    enemy.brain.attack_cooldown = (enemy.brain.attack_cooldown - dt).max(0.0);
+                    self.behaviors.get(&enemy.kind).unwrap_or(&self.default_behavior).tick(&senses, &mut enemy.brain, &mut decision);
+
+                    if decision.attack {
+                        enemy.brain.attack_cooldown = archetype.attack_cooldown;
+                        let damage = archetype.attack_damage * self.damage_scale;
+                        if let Some(projectile) = archetype.projectile {
+                            let origin = position + Vec3::Y * archetype.size[1] * SHOT_HEIGHT;
+                            shots.push(Bolt {
+                                shooter: enemy.body,
+                                position: origin,
+                                velocity: (player_pos - origin).normalize_or_zero() * projectile.speed,
+                                damage,
+                                knockback: archetype.attack_knockback,
+                                lifetime: projectile.lifetime,
+                                mesh_name: String::new(),
+                            });
+                        } else {
+                            let push = (player_pos - position).normalize_or_zero() * archetype.attack_knockback;
+                            events.push((target, EnemyEvent::Damage(damage)));
+                            events.push((target, EnemyEvent::Knockback(push)));
+                        }
+                        // winds up tall for the swing
+                        enemy.animator.squash(-0.2);
+                        if enemy.kind == EnemyKind::Boss {
+                            events.push((target, EnemyEvent::Stomp { position }));
+                        }
+                    }
+
+                    if decision.move_dir != Vec3::ZERO {
+                        enemy.facing = decision.move_dir;
+                    }
+
+                    // same deal as the player, we steer on the xz plane and leave falling to the solver
+                    let mut separation = Vec3::ZERO;
+                    for other in rw.bodies_near(position, SEPARATION_RADIUS, enemy.body) {
+                        if !matches!(rw.entity_of_body(other), Some(entity) if entity.kind == EntityKind::Enemy) {
+                            continue;
+                        }
+                        let t = rw.rigid_body_set[other].translation();
+                        let away = vec3(position.x - t.x, 0.0, position.z - t.z);
+                        let distance = away.length();
+                        if distance < SEPARATION_RADIUS {
+                            // right on top of each other there's no away, the two just go opposite ways along x
+                            let side = if enemy.body.into_raw_parts().0 < other.into_raw_parts().0 { 1.0 } else { -1.0 };
+                            let direction = if distance > f32::EPSILON { away / distance } else { vec3(side, 0.0, 0.0) };
+                            separation += direction * (1.0 - distance / SEPARATION_RADIUS);
+                        }
+                    }
+                    let steering = (decision.move_dir + separation * SEPARATION_WEIGHT).clamp_length_max(1.0);
+
+                    let body = &mut rw.rigid_body_set[enemy.body];
+                    let velocity = steering * archetype.speed;
+                    let vertical = body.linvel().y;
+                    body.set_linvel(vector![velocity.x, vertical, velocity.z], true);
+
+                    enemy.animator.walk(velocity, archetype.speed, archetype.size[1], dt);
+                    enemy.animator.update(dt);
+                    let drawn = blend.transform(rw, enemy.body).map_or(position, |transform| transform.position);
+                    if let Some(mesh) = r.get_mesh_mut(&enemy.mesh_name) {
+                        enemy.animator.apply(mesh, drawn, Quat::IDENTITY);
+                    }
+                }
+                Some(ragdoll) => {
+                    enemy.corpse_timer -= dt;
+
+                    if enemy.corpse_timer <= 0.0 {
+                        let enemy = self.enemies.swap_remove(i);
+                        for mesh_name in enemy.ragdoll_mesh_names.iter() {
+                            r.destroy_mesh(mesh_name).ok();
+                        }
+                        rw.remove_ragdoll(enemy.ragdoll.unwrap());
+                        continue;
+                    }
+
+                    for (handle, mesh_name) in ragdoll.bodies.iter().zip(enemy.ragdoll_mesh_names.iter()) {
+                        // the debris budget took the bone
+                        let Some(transform) = blend.transform(rw, *handle) else {
+                            r.destroy_mesh(mesh_name).ok();
+                            continue;
+                        };
+                        if let Some(mesh) = r.get_mesh_mut(mesh_name) {
+                            (mesh.position, mesh.rotation) = (transform.position, transform.rotation);
+                        }
+                    }
+                }
+            }
+
+            i += 1;
+        }
+
+        for bolt in shots {
+            self.fire(r, bolt);
+        }
+        self.update_bolts(rw, r, players, dt, &mut events);
+
+        events
+    }
+
+    // gives `bolt` its mesh and sends it on its way
+    fn fire(&mut self, r: &mut Renderer, mut bolt: Bolt) {
+        bolt.mesh_name = format!("BOLT_MESH{}", self.enemy_counter);
+        self.enemy_counter += 1;
+        let mut mesh = Sphere::new(8, BOLT_RADIUS, BOLT_COLOR).mesh();
+        mesh.position = bolt.position;
+        mesh.setup_mesh();
+        if let Err(e) = r.try_add_mesh(&bolt.mesh_name, mesh) {
+            log::warn!("{}", e);
+        }
+
+        self.bolts.push(bolt);
+    }
+
+    // moves every bolt along as far as it goes this frame, stopping at the first thing in the way. players hit are
+    // the closest one in `players` to where it landed
+    fn update_bolts(&mut self, rw: &RapierPhysicsWorld, r: &mut Renderer, players: &[Vec3], dt: f32, events: &mut Vec<(usize, EnemyEvent)>) {
+        let mut i = 0;
+        while i < self.bolts.len() {
+            let bolt = &mut self.bolts[i];
+            bolt.lifetime -= dt;
+
+            let step = bolt.velocity * dt;
+            let direction = step.normalize_or_zero();
+            let hit = rw.cast_ray(bolt.position, direction, step.length(), bolt.shooter);
+            if let Some((collider, distance)) = hit {
+                let point = bolt.position + direction * distance;
+                let target = players.iter().enumerate().min_by(|(_, a), (_, b)| a.distance(point).total_cmp(&b.distance(point))).map(|(index, _)| index);
+                if let (Some(target), Some(EntityKind::Player)) = (target, rw.entity_of(collider).map(|entity| entity.kind)) {
+                    events.push((target, EnemyEvent::Damage(bolt.damage)));
+                    events.push((target, EnemyEvent::Knockback(direction * bolt.knockback)));
+                }
+            }
+
+            if hit.is_some() || bolt.lifetime <= 0.0 {
+                let bolt = self.bolts.swap_remove(i);
+                r.destroy_mesh(&bolt.mesh_name).ok();
+                continue;
+            }
+
+            bolt.position += step;
+            if let Some(mesh) = r.get_mesh_mut(&bolt.mesh_name) {
+                mesh.position = bolt.position;
+            }
+            i += 1;
+        }
+    }
+}
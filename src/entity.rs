@@ -0,0 +1,93 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+// ids start at 1 so a zeroed `user_data` (rapier's default) never looks like an entity
+static NEXT_ENTITY_ID: AtomicU64 = AtomicU64::new(1);
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum EntityKind {
+    Player,
+    Enemy,
+    Wall,
+    Trap,
+    Projectile,
+    Debris,
+    Platform,
+    Water,
+    Ladder,
+    Door,
+    Prop,
+    Teleporter,
+    Terrain,
+    Npc,
+    Lava,
+}
+
+impl EntityKind {
+    fn to_bits(self) -> u64 {
+        match self {
+            EntityKind::Player => 1,
+            EntityKind::Enemy => 2,
+            EntityKind::Wall => 3,
+            EntityKind::Trap => 4,
+            EntityKind::Projectile => 5,
+            EntityKind::Debris => 6,
+            EntityKind::Platform => 7,
+            EntityKind::Water => 8,
+            EntityKind::Ladder => 9,
+            EntityKind::Door => 10,
+            EntityKind::Prop => 11,
+            EntityKind::Teleporter => 12,
+            EntityKind::Terrain => 13,
+            EntityKind::Npc => 14,
+            EntityKind::Lava => 15,
+        }
+    }
+
+    fn from_bits(bits: u64) -> Option<Self> {
+        match bits {
+            1 => Some(EntityKind::Player),
+            2 => Some(EntityKind::Enemy),
+            3 => Some(EntityKind::Wall),
+            4 => Some(EntityKind::Trap),
+            5 => Some(EntityKind::Projectile),
+            6 => Some(EntityKind::Debris),
+            7 => Some(EntityKind::Platform),
+            8 => Some(EntityKind::Water),
+            9 => Some(EntityKind::Ladder),
+            10 => Some(EntityKind::Door),
+            11 => Some(EntityKind::Prop),
+            12 => Some(EntityKind::Teleporter),
+            13 => Some(EntityKind::Terrain),
+            14 => Some(EntityKind::Npc),
+            15 => Some(EntityKind::Lava),
+            _ => None,
+        }
+    }
+}
+
+// stored in the `user_data` of rigid bodies and colliders: the kind in the upper 64 bits, the id in the lower ones
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct EntityId {
+    pub kind: EntityKind,
+    pub id: u64,
+}
+
+impl EntityId {
+    pub fn new(kind: EntityKind) -> Self {
+        Self {
+            kind,
+            id: NEXT_ENTITY_ID.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+
+    pub fn to_user_data(self) -> u128 {
+        ((self.kind.to_bits() as u128) << 64) | self.id as u128
+    }
+
+    pub fn from_user_data(user_data: u128) -> Option<Self> {
+        let kind = EntityKind::from_bits((user_data >> 64) as u64)?;
+        let id = user_data as u64;
+
+        Some(Self { kind, id })
+    }
+}
@@ -0,0 +1,165 @@
+use serde::{Deserialize, Serialize};
+
+use crate::progression::Stats;
+
+// what everyone starts a run with, weapons go on the hotbar and the rest is worn right away
+pub const STARTING_KIT: [Equipment; 3] = [Equipment::Sword, Equipment::Crossbow, Equipment::LeatherArmor];
+pub const STARTING_COINS: u32 = 20;
+
+// what the player hits with when the selected hotbar slot isn't a weapon
+pub const FISTS: WeaponStats = WeaponStats { damage: 12.5, range: 300.0, cooldown: 0.25, attack: AttackKind::Melee };
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AttackKind {
+    // a ray out to `range`, hits right away
+    Melee,
+    // a bolt that flies straight and hits whatever it touches first, gone after `range`
+    Projectile,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct WeaponStats {
+    // per hit, before the player's damage multiplier
+    pub damage: f32,
+    pub range: f32,
+    // seconds between hits while attack is held
+    pub cooldown: f32,
+    pub attack: AttackKind,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EquipSlot {
+    Weapon,
+    Armor,
+    Trinket,
+}
+
+// added on top of the player's derived stats, health is flat and the rest are fractions of the base
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub struct StatModifiers {
+    pub max_health: f32,
+    pub speed: f32,
+    pub damage: f32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Equipment {
+    Sword,
+    Spear,
+    Crossbow,
+    LeatherArmor,
+    PlateArmor,
+    SwiftCharm,
+    RageCharm,
+}
+
+impl Equipment {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Equipment::Sword => "sword",
+            Equipment::Spear => "spear",
+            Equipment::Crossbow => "crossbow",
+            Equipment::LeatherArmor => "leather armor",
+            Equipment::PlateArmor => "plate armor",
+            Equipment::SwiftCharm => "swift charm",
+            Equipment::RageCharm => "rage charm",
+        }
+    }
+
+    // in coins, merchants buy it back for less
+    pub fn price(&self) -> u32 {
+        match self {
+            Equipment::Sword => 30,
+            Equipment::Spear => 45,
+            Equipment::Crossbow => 60,
+            Equipment::LeatherArmor => 25,
+            Equipment::PlateArmor => 90,
+            Equipment::SwiftCharm => 50,
+            Equipment::RageCharm => 80,
+        }
+    }
+
+    pub fn slot(&self) -> EquipSlot {
+        match self {
+            Equipment::Sword | Equipment::Spear | Equipment::Crossbow => EquipSlot::Weapon,
+            Equipment::LeatherArmor | Equipment::PlateArmor => EquipSlot::Armor,
+            Equipment::SwiftCharm | Equipment::RageCharm => EquipSlot::Trinket,
+        }
+    }
+
+    // weapons only count while they're the one being held
+    pub fn modifiers(&self) -> StatModifiers {
+        match self {
+            Equipment::Sword | Equipment::Crossbow => StatModifiers::default(),
+            // long and heavy
+            Equipment::Spear => StatModifiers { speed: -0.05, ..Default::default() },
+            Equipment::LeatherArmor => StatModifiers { max_health: 20.0, ..Default::default() },
+            Equipment::PlateArmor => StatModifiers { max_health: 60.0, speed: -0.1, ..Default::default() },
+            Equipment::SwiftCharm => StatModifiers { speed: 0.15, ..Default::default() },
+            Equipment::RageCharm => StatModifiers { damage: 0.25, max_health: -10.0, ..Default::default() },
+        }
+    }
+
+    pub fn weapon(&self) -> Option<WeaponStats> {
+        match self {
+            Equipment::Sword => Some(WeaponStats { damage: 25.0, range: 250.0, cooldown: 0.4, attack: AttackKind::Melee }),
+            Equipment::Spear => Some(WeaponStats { damage: 35.0, range: 450.0, cooldown: 0.8, attack: AttackKind::Melee }),
+            Equipment::Crossbow => Some(WeaponStats { damage: 40.0, range: 3000.0, cooldown: 1.2, attack: AttackKind::Projectile }),
+            _ => None,
+        }
+    }
+}
+
+// what one player is wearing, and how long until they can attack again
+#[derive(Default, Debug)]
+pub struct Loadout {
+    pub armor: Option<Equipment>,
+    pub trinket: Option<Equipment>,
+    cooldown: f32,
+}
+
+impl Loadout {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // swaps out whatever was in the same slot and hands it back. weapons aren't worn, they're held from the hotbar
+    pub fn equip(&mut self, equipment: Equipment) -> Option<Equipment> {
+        match equipment.slot() {
+            EquipSlot::Weapon => None,
+            EquipSlot::Armor => self.armor.replace(equipment),
+            EquipSlot::Trinket => self.trinket.replace(equipment),
+        }
+    }
+
+    // takes it off if it's what's worn in its slot
+    pub fn unequip(&mut self, equipment: Equipment) {
+        for worn in [&mut self.armor, &mut self.trinket] {
+            if *worn == Some(equipment) {
+                *worn = None;
+            }
+        }
+    }
+
+    // `base` is what leveling gave, `held` is the weapon in the selected hotbar slot if there is one
+    pub fn stats(&self, base: Stats, held: Option<Equipment>) -> Stats {
+        [self.armor, self.trinket, held].into_iter().flatten().fold(base, |stats, equipment| {
+            let modifiers = equipment.modifiers();
+            Stats {
+                max_health: (stats.max_health + modifiers.max_health).max(1.0),
+                speed: stats.speed + modifiers.speed,
+                damage: stats.damage + modifiers.damage,
+            }
+        })
+    }
+
+    // true when an attack with `weapon` goes out this frame, holding attack keeps swinging every cooldown
+    pub fn attack(&mut self, attacking: bool, weapon: &WeaponStats, dt: f32) -> bool {
+        self.cooldown = (self.cooldown - dt).max(0.0);
+        if !attacking || self.cooldown > 0.0 {
+            return false;
+        }
+        self.cooldown = weapon.cooldown;
+        true
+    }
+}
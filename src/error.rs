@@ -0,0 +1,92 @@
+use rapier3d::prelude::RigidBodyHandle;
+use thiserror::Error;
+use tiny_game_framework::{Mesh, Renderer};
+
+use crate::render_layers::{self, RenderLayer};
+
+// anything that went wrong while turning a sample image into a maze
+#[derive(Error, Debug)]
+pub enum GenerationError {
+    #[error("couldn't read the sample image {path}: {source}")]
+    Sample {
+        path: String,
+        #[source]
+        source: image::ImageError,
+    },
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("the wave function built from the sample is invalid: {0}")]
+    InvalidWaveFunction(String),
+    #[error("a {width}x{height} canvas is smaller than the patterns it would be filled with")]
+    CanvasTooSmall { width: u32, height: u32 },
+    // the sample's patterns couldn't fill the canvas, a different seed usually works
+    #[error("the wave function couldn't be collapsed: {0}")]
+    Contradiction(String),
+    // not really a failure, whoever asked for the quadrant doesn't want it anymore
+    #[error("generation was cancelled")]
+    Cancelled,
+}
+
+#[derive(Error, Debug)]
+pub enum PhysicsError {
+    #[error("no rigid body with handle {0:?}")]
+    MissingBody(RigidBodyHandle),
+}
+
+#[derive(Error, Debug)]
+pub enum SaveError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("the save file is corrupted: {0}")]
+    Corrupted(#[from] serde_json::Error),
+    // written by a newer build, reading it here would lose whatever that build added
+    #[error("the {format} is version {found}, this build only reads up to {supported}")]
+    TooNew { format: &'static str, found: u32, supported: u32 },
+}
+
+#[derive(Error, Debug)]
+pub enum CaptureError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("couldn't encode the image: {0}")]
+    Encode(#[from] image::ImageError),
+    #[error("nothing has been recorded yet")]
+    Empty,
+}
+
+#[derive(Error, Debug)]
+pub enum ShaderError {
+    #[error("the {stage} shader didn't compile: {log}")]
+    Compile { stage: &'static str, log: String },
+    #[error("the shader program didn't link: {0}")]
+    Link(String),
+}
+
+#[derive(Error, Debug)]
+pub enum GameError {
+    #[error(transparent)]
+    Generation(#[from] GenerationError),
+    #[error(transparent)]
+    Physics(#[from] PhysicsError),
+    #[error("couldn't add mesh {name}: {reason}")]
+    Mesh { name: String, reason: String },
+}
+
+// the framework reports mesh errors as plain strings
+pub trait RendererExt {
+    fn try_add_mesh(&mut self, name: &str, mesh: Mesh) -> Result<(), GameError>;
+    // same, drawn on `layer` instead of with the opaque world
+    fn try_add_mesh_on(&mut self, name: &str, mesh: Mesh, layer: RenderLayer) -> Result<(), GameError>;
+}
+
+impl RendererExt for Renderer {
+    fn try_add_mesh(&mut self, name: &str, mesh: Mesh) -> Result<(), GameError> {
+        self.add_mesh(name, mesh).map_err(|reason| GameError::Mesh { name: name.to_string(), reason })
+    }
+
+    fn try_add_mesh_on(&mut self, name: &str, mesh: Mesh, layer: RenderLayer) -> Result<(), GameError> {
+        self.try_add_mesh(name, mesh)?;
+        render_layers::assign(name, layer);
+        Ok(())
+    }
+}
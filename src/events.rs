@@ -0,0 +1,39 @@
+use tiny_game_framework::glam::Vec3;
+
+use crate::{enemies::EnemyKind, props::PropKind};
+
+// things that happened this frame that more than one system might care about. `player` is an index into the
+// simulated players
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum GameEvent {
+    KeyPickedUp { player: usize, key: usize },
+    DoorOpened { player: usize, key: usize },
+    EnemyKilled { player: usize, kind: EnemyKind },
+    PropBroken { player: usize, kind: PropKind, position: Vec3 },
+    BossDefeated,
+    ExitReached { player: usize },
+    PlayerDied { player: usize },
+    // every objective on the floor is done
+    FloorCleared,
+}
+
+// same idea as the noise bus: anything can emit during the frame, listeners read them all, and it's cleared at the
+// end of the frame
+#[derive(Default)]
+pub struct EventBus {
+    events: Vec<GameEvent>,
+}
+
+impl EventBus {
+    pub fn emit(&mut self, event: GameEvent) {
+        self.events.push(event);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &GameEvent> + '_ {
+        self.events.iter()
+    }
+
+    pub fn clear(&mut self) {
+        self.events.clear();
+    }
+}
@@ -0,0 +1,118 @@
+use tiny_game_framework::{glam::{Vec2, Vec3, Vec4}, imgui::Ui};
+
+use crate::{entity::EntityId, world_text::WorldText};
+
+const NUMBER_LIFETIME: f32 = 0.8;
+// how far a number floats up over its lifetime, in world units
+const NUMBER_RISE: f32 = 80.0;
+// hits on the same target this soon after the last one add up into a single number
+const NUMBER_MERGE_WINDOW: f32 = 0.3;
+// how tall the digits are in world units
+const NUMBER_SIZE: f32 = 30.0;
+
+// damage that fills the vignette all the way up
+const VIGNETTE_FULL_DAMAGE: f32 = 40.0;
+const VIGNETTE_FADE_PER_SECOND: f32 = 2.0;
+// how far into the screen the vignette reaches, as a fraction of the smaller side
+const VIGNETTE_THICKNESS: f32 = 0.15;
+
+const FLASH_FADE_PER_SECOND: f32 = 2.5;
+const FLASH_COLOR: [f32; 3] = [0.8, 0.5, 1.0];
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FeedbackEvent {
+    Hit { target: EntityId, position: Vec3, amount: f32 },
+    PlayerDamaged { amount: f32 },
+    Teleported,
+}
+
+struct DamageNumber {
+    target: EntityId,
+    position: Vec3,
+    amount: f32,
+    age: f32,
+}
+
+// combat code emits events into this and it takes care of showing them
+pub struct Feedback {
+    numbers: Vec<DamageNumber>,
+    // 0 to 1, how strong the red screen edges are
+    vignette: f32,
+    // 0 to 1, covers the whole screen and hides the jump to somewhere else
+    flash: f32,
+}
+
+impl Feedback {
+    pub fn new() -> Self {
+        Self {
+            numbers: Vec::new(),
+            vignette: 0.0,
+            flash: 0.0,
+        }
+    }
+
+    pub fn emit(&mut self, event: FeedbackEvent) {
+        match event {
+            FeedbackEvent::Hit { target, position, amount } => {
+                let recent = self.numbers.iter_mut().find(|number| number.target == target && number.age < NUMBER_MERGE_WINDOW);
+                match recent {
+                    Some(number) => {
+                        number.amount += amount;
+                        number.age = 0.0;
+                    }
+                    None => self.numbers.push(DamageNumber { target, position, amount, age: 0.0 }),
+                }
+            }
+            FeedbackEvent::PlayerDamaged { amount } => {
+                self.vignette = (self.vignette + amount / VIGNETTE_FULL_DAMAGE).min(1.0);
+            }
+            FeedbackEvent::Teleported => self.flash = 1.0,
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        for number in self.numbers.iter_mut() {
+            number.age += dt;
+        }
+        self.numbers.retain(|number| number.age < NUMBER_LIFETIME);
+
+        self.vignette = (self.vignette - VIGNETTE_FADE_PER_SECOND * dt).max(0.0);
+        self.flash = (self.flash - FLASH_FADE_PER_SECOND * dt).max(0.0);
+    }
+
+    // damage numbers float up out of whatever got hit, in the world rather than on the screen
+    pub fn label_numbers(&self, text: &mut WorldText) {
+        for number in self.numbers.iter() {
+            let t = number.age / NUMBER_LIFETIME;
+            let position = number.position + Vec3::Y * NUMBER_RISE * t;
+            text.label(&format!("{:.0}", number.amount.max(1.0)), position, NUMBER_SIZE, Vec4::new(1.0, 0.9, 0.3, 1.0 - t));
+        }
+    }
+
+    pub fn draw(&self, ui: &Ui) {
+        let [width, height] = ui.io().display_size;
+        let screen = Vec2::new(width, height);
+
+        if self.vignette > 0.0 {
+            self.draw_vignette(ui, screen);
+        }
+
+        if self.flash > 0.0 {
+            let [r, g, b] = FLASH_COLOR;
+            ui.get_foreground_draw_list().add_rect([0.0, 0.0], [screen.x, screen.y], [r, g, b, self.flash]).filled(true).build();
+        }
+    }
+
+    fn draw_vignette(&self, ui: &Ui, screen: Vec2) {
+        let draw_list = ui.get_foreground_draw_list();
+        let edge = [0.8, 0.0, 0.0, self.vignette * 0.6];
+        let clear = [0.8, 0.0, 0.0, 0.0];
+        let thickness = screen.min_element() * VIGNETTE_THICKNESS;
+
+        // corners go top left, top right, bottom right, bottom left
+        draw_list.add_rect_filled_multicolor([0.0, 0.0], [screen.x, thickness], edge, edge, clear, clear);
+        draw_list.add_rect_filled_multicolor([0.0, screen.y - thickness], [screen.x, screen.y], clear, clear, edge, edge);
+        draw_list.add_rect_filled_multicolor([0.0, 0.0], [thickness, screen.y], edge, clear, clear, edge);
+        draw_list.add_rect_filled_multicolor([screen.x - thickness, 0.0], [screen.x, screen.y], clear, edge, edge, clear);
+    }
+}
@@ -1,64 +1,498 @@
-use std::sync::Arc;
+use std::{sync::{atomic::AtomicBool, Arc}, time::{Duration, Instant}};
 
 use once_cell::sync::Lazy;
-use tokio::sync::{mpsc::Receiver, Mutex};
+use tokio::{sync::{mpsc::{error::TryRecvError, Receiver}, Mutex}, task::JoinHandle};
+use tokio_util::sync::CancellationToken;
+use rapier3d::prelude::{Isometry, RigidBodyHandle, SharedShape};
 
-use tiny_game_framework::{glam::{vec3, Vec3, Vec4}, Cuboid, Renderer, Vertex};
-use tokio::sync::mpsc;
+use tiny_game_framework::{glam::{vec2, vec3, Vec3, Vec4}, Cuboid, Mesh, Renderer, Vertex};
+use tokio::sync::{mpsc, oneshot};
 
-use crate::{generation::Canvas, rapier_integration::RapierPhysicsWorld};
+use crate::{chunks::ChunkFootprint, coords::{self, ChunkCoord}, destruction::DestructionSystem, director::SpawnDirector, dungeon::{DungeonConfig, DungeonGenerator, DungeonLayout, Heightmap, SpawnAnchor, Tile}, entity::{EntityId, EntityKind}, error::{GameError, GenerationError, RendererExt}, geometry::{bake_corner_ao, MeshBatch, AROUND}, lighting::Emissive, rapier_integration::{compound_of_boxes, RapierPhysicsWorld}, render_layers::RenderLayer, teleporters::TeleporterSystem, traps::{TrapKind, TrapSystem}};
+
+// chance for any given wall block to be breakable
+const DESTRUCTIBLE_WALL_CHANCE: f32 = 0.1;
+
+const WATER_DEPTH: f32 = 150.0;
+const LAVA_DEPTH: f32 = 100.0;
+const LAVA_COLOR: Vec4 = Vec4::new(1.0, 0.4, 0.1, 1.0);
+// how much of the world's gravity is left over a low gravity tile, up to the ceiling
+const LOW_GRAVITY_SCALE: f32 = 0.3;
+const LOW_GRAVITY_COLOR: Vec4 = Vec4::new(0.55, 0.55, 1.0, 0.15);
+// how far from the wall the player can grab onto a ladder
+const LADDER_REACH: f32 = 40.0;
+// mixed into the quadrant's seed to decide whether it's terrain, so the maze rolls stay what they were
+const TERRAIN_SEED: u64 = 0x7E44_A1B5;
+const TERRAIN_LOW_COLOR: Vec4 = Vec4::new(0.35, 0.3, 0.25, 1.0);
+const TERRAIN_HIGH_COLOR: Vec4 = Vec4::new(0.4, 0.5, 0.3, 1.0);
 
 static GLOBAL_MESH_COUNTER: Lazy<Arc<Mutex<usize>>> = Lazy::new(|| {
     Arc::new(Mutex::new(0))
 });
 
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TileKind {
+    // walkable and empty, enemies can spawn here
+    Floor,
+    Wall { destructible: bool },
+    Ladder,
+    Water,
+    Lava,
+    Teleporter,
+    LowGravity,
+    Trap(TrapKind),
+    // the ground of a whole open quadrant, `MeshResult::heightmap` has its shape
+    Terrain,
+}
+
 pub struct MeshResult {
     pub shape: Cuboid,
     pub position: Vec3,
+    pub kind: TileKind,
+    // only for `TileKind::Terrain`
+    pub heightmap: Option<Heightmap>,
+    // which of the tiles around this one are walls, see `geometry::AROUND`
+    pub solid_around: u8,
+    // only for `TileKind::Floor`, and only what the sample marked
+    pub anchor: Option<SpawnAnchor>,
+}
+
+// how much of a chunk can go up to the gpu this frame, shared by every chunk being built
+pub struct UploadBudget {
+    meshes: usize,
+    // `None` goes by the count alone, and the generator and the welding are waited on instead of checked on again
+    // next frame, so every machine in a lockstep game builds the same things on the same frame
+    deadline: Option<Instant>,
+}
+
+impl UploadBudget {
+    // starts counting now, make one per frame
+    pub fn new(meshes: usize, milliseconds: Option<f32>) -> Self {
+        let deadline = milliseconds.map(|milliseconds| Instant::now() + Duration::from_secs_f32(milliseconds.max(0.0) / 1000.0));
+        Self { meshes, deadline }
+    }
+
+    fn spend(&mut self) {
+        self.meshes = self.meshes.saturating_sub(1);
+    }
+
+    fn exhausted(&self) -> bool {
+        self.meshes == 0 || self.deadline.is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    fn waits(&self) -> bool {
+        self.deadline.is_none()
+    }
+}
+
+// tiles that are only ever drawn together, held back until the chunk's last tile is in and then uploaded as one mesh
+// each instead of one per tile
+#[derive(Default)]
+pub struct ChunkBatches {
+    // every wall in the chunk collides through this one fixed body, made when the first one shows up. unloading the
+    // chunk is removing it
+    body: Option<RigidBodyHandle>,
+    // plain walls, drawn as one mesh
+    walls: MeshBatch,
+    // and collided with as one compound shape, `(center, half extents)` of each
+    wall_boxes: Vec<(Vec3, Vec3)>,
+    // the walls being welded and their compound built on another thread once the batch is complete
+    welding: Option<JoinHandle<WeldedWalls>>,
+    // the water's surface, the volumes stay one per tile
+    water: MeshBatch,
+    // the same for lava
+    lava: MeshBatch,
+    // the faint columns over low gravity tiles
+    low_gravity: MeshBatch,
+}
+
+impl ChunkBatches {
+    fn body(&mut self, rw: &mut RapierPhysicsWorld, footprint: &mut ChunkFootprint) -> RigidBodyHandle {
+        *self.body.get_or_insert_with(|| {
+            let body = rw.add_static_body();
+            footprint.add_body(body, 0);
+            body
+        })
+    }
+}
+
+struct WeldedWalls {
+    vertices: Vec<Vertex>,
+    indices: Vec<u32>,
+    shape: SharedShape,
+    // how many went into `shape`
+    boxes: usize,
 }
 
-pub fn new_quadrant() -> Receiver<MeshResult> {
+// what a quadrant being generated in the background hands back
+pub struct Quadrant {
+    // streamed one at a time so building them can be spread over several frames
+    pub tiles: Receiver<MeshResult>,
+    // the whole layout, sent before the first tile
+    pub layout: oneshot::Receiver<DungeonLayout>,
+    // lets shutdown wait for the task
+    pub task: JoinHandle<()>,
+}
+
+// generates one quadrant in the background and streams its tiles back. stops early once `token` is cancelled or
+// `cancel` is set
+pub fn new_quadrant(seed: u64, chunk: ChunkCoord, config: DungeonConfig, cancel: Arc<AtomicBool>, token: CancellationToken) -> Quadrant {
     let (sender, receiver) = mpsc::channel::<MeshResult>(1);
+    let (layout_sender, layout_receiver) = oneshot::channel();
     
-    tokio::spawn(async move {
-        let mut canvas = Canvas::new(12, 12);
+    let handle = tokio::spawn(async move {
+        // the quadrant everyone starts in is always a maze, the boss and the locks are planned in it
+        let terrain = chunk != ChunkCoord::default() && fastrand::Rng::with_seed(seed ^ TERRAIN_SEED).f32() < config.terrain_chance;
+        if terrain {
+            let heightmap = Heightmap::generate(seed, config.width, config.height, coords::tile_size(), config.terrain_amplitude);
+            let layout = heightmap.layout(seed);
+            layout_sender.send(layout.clone()).ok();
 
-        canvas.write();
-        canvas.print();
-        
-        let pixels = &canvas.pixels;
+            // the ground goes first so the floor tiles after it are standing on something
+            let tile_size = coords::tile_size();
+            let corner = chunk.origin().world(coords::floor_height() + tile_size / 2.0).0 - vec3(tile_size, 0.0, tile_size) / 2.0;
+            let size = vec3(config.width as f32 * tile_size, 0.0, config.height as f32 * tile_size);
+            let ground = MeshResult { shape: Cuboid::new(size, Vec4::ONE), position: corner, kind: TileKind::Terrain, heightmap: Some(heightmap.clone()), solid_around: 0, anchor: None };
+            if !send_tile(&sender, &token, ground).await {
+                return;
+            }
+            for (local, _) in layout.tiles() {
+                let position = (chunk.origin() + local).world(coords::floor_height() + heightmap.tile_height(local)).into();
+                let floor = MeshResult { shape: Cuboid::new(Vec3::splat(tile_size), Vec4::ONE), position, kind: TileKind::Floor, heightmap: None, solid_around: 0, anchor: None };
+                if !send_tile(&sender, &token, floor).await {
+                    return;
+                }
+            }
+            return;
+        }
+
+        let generator = DungeonGenerator::new(config).with_cancel(cancel);
+        // everything random about the layout comes from the seed so every client builds the same one
+        let mut rng = fastrand::Rng::with_seed(seed);
+
+        // the collapse is all cpu, keep it off the async workers
+        let Ok(result) = tokio::task::spawn_blocking(move || generator.generate(seed)).await else {
+            return;
+        };
+        let layout = match result {
+            Ok(layout) => layout,
+            Err(GenerationError::Cancelled) => return,
+            // the chunk is left empty, the rest of the maze still gets generated
+            Err(e) => {
+                log::error!("quadrant {:?} failed to generate: {}", chunk, e);
+                return;
+            }
+        };
+        if token.is_cancelled() {
+            return;
+        }
+        layout.print();
+        layout_sender.send(layout.clone()).ok();
 
-        for x in 0..pixels.len() {
-            for y in 0..pixels[1].len() {
-                if pixels[x][y] == [0, 0, 0, 255] {
-                    let mesh = Cuboid::new(vec3(200.0, 200.0, 200.0), Vec4::ONE);
-                    let position = vec3(x as f32, 0.0, y as f32) * 200.0;
+        for (local, meta) in layout.metadata() {
+            // raised tiles are centered on the tile, everything else sits on the floor
+            let tile_size = coords::tile_size();
+            let tile_coord = chunk.origin() + local;
+            let position = if meta.height > 0 { tile_coord.world(0.0) } else { tile_coord.world(coords::floor_height()) }.into();
 
-                    sender.send(MeshResult { shape: mesh, position, }).await.unwrap_or_else(|_| {
-                        
-                    });
+            let wall = vec3(tile_size, coords::wall_height(), tile_size);
+            let floor = Vec3::splat(tile_size);
+
+            let (shape, kind) = match meta.kind {
+                Tile::Wall => {
+                    let destructible = rng.f32() < DESTRUCTIBLE_WALL_CHANCE;
+                    (Cuboid::new(wall, Vec4::ONE), TileKind::Wall { destructible })
                 }
+                Tile::Trap(trap) => (Cuboid::new(floor, Vec4::ONE), TileKind::Trap(trap)),
+                Tile::Ladder => (Cuboid::new(wall, Vec4::new(0.55, 0.4, 0.25, 1.0)), TileKind::Ladder),
+                Tile::Water => (Cuboid::new(vec3(tile_size, WATER_DEPTH, tile_size), Vec4::new(0.2, 0.4, 0.8, 0.6)), TileKind::Water),
+                Tile::Lava => (Cuboid::new(vec3(tile_size, LAVA_DEPTH, tile_size), LAVA_COLOR), TileKind::Lava),
+                Tile::Teleporter => (Cuboid::new(floor, Vec4::ONE), TileKind::Teleporter),
+                Tile::LowGravity => (Cuboid::new(wall, LOW_GRAVITY_COLOR), TileKind::LowGravity),
+                Tile::Floor => (Cuboid::new(floor, Vec4::ONE), TileKind::Floor),
+            };
+
+            // tiles past the quadrant's edge aren't known yet, they count as open
+            let solid_around = AROUND.iter().enumerate()
+                .filter(|(_, &offset)| matches!(layout.tile(local + offset), Some(Tile::Wall | Tile::Ladder)))
+                .fold(0, |mask, (bit, _)| mask | 1 << bit);
+
+            if !send_tile(&sender, &token, MeshResult { shape, position, kind, heightmap: None, solid_around, anchor: meta.anchor }).await {
+                return;
             }
         }
         
     });
 
-    return receiver;
+    Quadrant { tiles: receiver, layout: layout_receiver, task: handle }
+}
+
+// false once nobody wants the rest of the quadrant, the receiver going away means that too
+async fn send_tile(sender: &mpsc::Sender<MeshResult>, token: &CancellationToken, tile: MeshResult) -> bool {
+    tokio::select! {
+        _ = token.cancelled() => false,
+        sent = sender.send(tile) => sent.is_ok(),
+    }
+}
+
+// the heightmap as triangles starting at the mesh's origin, colored from dirt in the valleys to moss at `peak`
+fn terrain_mesh(heightmap: &Heightmap, peak: f32) -> Mesh {
+    let (columns, rows) = (heightmap.columns, heightmap.rows);
+    let spacing = heightmap.spacing;
+    let height = |column: usize, row: usize| heightmap.height(column.min(columns - 1), row.min(rows - 1));
+
+    let mut vertices = Vec::with_capacity(columns * rows);
+    for row in 0..rows {
+        for column in 0..columns {
+            let y = height(column, row);
+            // slope from the samples on either side
+            let dx = height(column + 1, row) - height(column.saturating_sub(1), row);
+            let dz = height(column, row + 1) - height(column, row.saturating_sub(1));
+            let normal = vec3(-dx, 2.0 * spacing, -dz).normalize();
+            let color = TERRAIN_LOW_COLOR.lerp(TERRAIN_HIGH_COLOR, (y / peak.max(1.0)).clamp(0.0, 1.0));
+            let uv = vec2(column as f32 / (columns - 1) as f32, row as f32 / (rows - 1) as f32);
+            vertices.push(Vertex::new(vec3(column as f32 * spacing, y, row as f32 * spacing), color, uv, normal));
+        }
+    }
+
+    // two triangles per cell, wound to face up
+    let mut indices = Vec::with_capacity((columns - 1) * (rows - 1) * 6);
+    for row in 0..rows - 1 {
+        for column in 0..columns - 1 {
+            let i = (row * columns + column) as u32;
+            let (right, below) = (i + 1, i + columns as u32);
+            indices.extend_from_slice(&[i, below, right, right, below, below + 1]);
+        }
+    }
+
+    Mesh::new(&vertices, &indices)
+}
+
+// everything outside the chunk that building its tiles adds to
+pub struct BuildContext<'a> {
+    pub renderer: &'a mut Renderer,
+    pub rw: &'a mut RapierPhysicsWorld,
+    pub traps: &'a mut TrapSystem,
+    pub teleporters: &'a mut TeleporterSystem,
+    pub destruction: &'a mut DestructionSystem,
+    pub director: &'a mut SpawnDirector,
 }
 
-pub async fn gen_maze_async(receiver: &mut Receiver<MeshResult>, renderer: &mut Renderer, rw: &mut RapierPhysicsWorld) {
-    while let Ok(mesh_result) = receiver.try_recv() {
-        let MeshResult { shape, position } = mesh_result;
+pub async fn gen_maze_async(
+    receiver: &mut Receiver<MeshResult>,
+    context: &mut BuildContext<'_>,
+    // how many tiles have been built so far, counts up across calls
+    tiles_built: &mut usize,
+    // everything built goes in here so the chunk can be unloaded again
+    footprint: &mut ChunkFootprint,
+    batches: &mut ChunkBatches,
+    // stops early once it's spent, the rest of the tiles wait in the channel for next frame
+    budget: &mut UploadBudget,
+) -> Result<bool, GameError> {
+    let BuildContext { renderer, rw, traps, teleporters, destruction, director } = context;
+    loop {
+        if budget.exhausted() {
+            return Ok(false);
+        }
+        // a lockstep frame can't go by how far along the generator happens to be on this machine, so it waits for
+        // the next tile or for the generator to finish
+        let next = if budget.waits() { receiver.recv().await.ok_or(TryRecvError::Disconnected) } else { receiver.try_recv() };
+        let mesh_result = match next {
+            Ok(mesh_result) => mesh_result,
+            Err(TryRecvError::Empty) => return Ok(false),
+            // the generator task is done and everything it sent has been built
+            Err(TryRecvError::Disconnected) => {
+                if !batches.walls.is_empty() {
+                    let walls = std::mem::take(&mut batches.walls);
+                    let boxes = std::mem::take(&mut batches.wall_boxes);
+                    // the weld and the compound are all cpu and only data, the frame doesn't have to wait for them
+                    batches.welding = Some(tokio::task::spawn_blocking(move || {
+                        let (vertices, indices) = walls.welded();
+                        let shape = compound_of_boxes(&boxes);
+                        WeldedWalls { vertices, indices, shape, boxes: boxes.len() }
+                    }));
+                }
+                if let Some(welding) = batches.welding.take() {
+                    if !welding.is_finished() && !budget.waits() {
+                        batches.welding = Some(welding);
+                        return Ok(false);
+                    }
+                    match welding.await {
+                        Ok(walls) => {
+                            let body = batches.body(rw, footprint);
+                            build_walls(walls, body, renderer, rw, footprint).await?;
+                            budget.spend();
+                        }
+                        Err(e) => log::error!("welding a chunk's walls: {}", e),
+                    }
+                }
+                if !batches.water.is_empty() {
+                    build_pool(std::mem::take(&mut batches.water), "WATER_MESH", RenderLayer::Transparent, renderer, footprint).await?;
+                    budget.spend();
+                }
+                if !batches.lava.is_empty() {
+                    build_pool(std::mem::take(&mut batches.lava), "LAVA_MESH", RenderLayer::Opaque, renderer, footprint).await?;
+                    budget.spend();
+                }
+                if !batches.low_gravity.is_empty() {
+                    build_pool(std::mem::take(&mut batches.low_gravity), "LOW_GRAVITY_MESH", RenderLayer::Transparent, renderer, footprint).await?;
+                    budget.spend();
+                }
+                return Ok(true);
+            }
+        };
+
+        let MeshResult { shape, position, kind, heightmap, solid_around, anchor } = mesh_result;
+        *tiles_built += 1;
+        // floors are drawn by nothing and batched tiles go up with the rest of their batch
+        if !matches!(kind, TileKind::Floor | TileKind::Wall { destructible: false } | TileKind::Water | TileKind::Lava | TileKind::LowGravity) {
+            budget.spend();
+        }
+
+        match kind {
+            TileKind::Terrain => {
+                let Some(heightmap) = heightmap else { continue };
+                let peak = heightmap.heights.iter().copied().fold(0.0, f32::max);
+                let collider = rw.add_heightfield(position + shape.size / 2.0, heightmap.columns, heightmap.rows, &heightmap.heights, vec3(shape.size.x, 1.0, shape.size.z));
+                rw.tag_collider(collider, EntityId::new(EntityKind::Terrain));
+                footprint.add_collider(collider, heightmap.heights.len() * std::mem::size_of::<f32>());
+
+                let mut mesh = terrain_mesh(&heightmap, peak);
+                mesh.position = position;
+                let vertices: Vec<Vec3> = mesh.vertices.iter().map(|vertex| position + vertex.position).collect();
+                director.add_walkable_geometry(&vertices, &mesh.indices);
+                mesh.setup_mesh();
+                let name = format!("TERRAIN_MESH{:?}{:?}", position.x, position.z);
+                footprint.add_mesh(&name, &mesh);
+                renderer.try_add_mesh(&name, mesh)?;
+                continue;
+            }
+            TileKind::Floor => {
+                director.add_floor_tile(position);
+                if anchor == Some(SpawnAnchor::Enemy) {
+                    director.add_anchor(position);
+                }
+                continue;
+            }
+            TileKind::Trap(trap) => {
+                traps.spawn(rw, renderer, trap, position, shape.size.x);
+                continue;
+            }
+            TileKind::Teleporter => {
+                teleporters.spawn(rw, renderer, position, shape.size.x);
+                continue;
+            }
+            TileKind::Water => {
+                // `position` is the floor, the volume fills the tile up to the water depth
+                let center = position + vec3(0.0, shape.size.y / 2.0, 0.0);
+                let volume = rw.add_water_volume(center, shape.size / 2.0);
+                rw.tag_collider(volume, EntityId::new(EntityKind::Water));
+                footprint.add_collider(volume, 0);
+
+                let mut mesh = shape.mesh();
+                mesh.position = center;
+                batches.water.add(&mesh);
+                continue;
+            }
+            TileKind::Lava => {
+                // sunk into the floor like water, and bright enough to bloom. it doesn't light anything around it,
+                // a pool would use up every light the framework has
+                let center = position + vec3(0.0, shape.size.y / 2.0, 0.0);
+                let volume = rw.add_lava_volume(center, shape.size / 2.0);
+                rw.tag_collider(volume, EntityId::new(EntityKind::Lava));
+                footprint.add_collider(volume, 0);
+
+                let mut mesh = shape.mesh();
+                mesh.position = center;
+                Emissive::LAVA.apply(&mut mesh);
+                batches.lava.add(&mesh);
+                continue;
+            }
+            TileKind::LowGravity => {
+                // walked on like any floor, the volume goes from the floor up to where the walls end
+                director.add_floor_tile(position);
+                if anchor == Some(SpawnAnchor::Enemy) {
+                    director.add_anchor(position);
+                }
+                let center = position + vec3(0.0, shape.size.y / 2.0, 0.0);
+                let volume = rw.add_gravity_volume(center, shape.size / 2.0, rw.gravity() * LOW_GRAVITY_SCALE);
+                footprint.add_collider(volume, 0);
+
+                let mut mesh = shape.mesh();
+                mesh.position = center;
+                batches.low_gravity.add(&mesh);
+                continue;
+            }
+            TileKind::Wall { destructible: false } => {
+                let mut mesh = shape.mesh();
+                mesh.position = position;
+                bake_corner_ao(&mut mesh, solid_around);
+                batches.walls.add(&mesh);
+                batches.wall_boxes.push((position, shape.size / 2.0));
+                continue;
+            }
+            TileKind::Wall { destructible: true } | TileKind::Ladder => {}
+        }
+
         let mut mesh = shape.mesh();
         mesh.position = position;
-        rw.build_collider_from_mesh(mesh.vertices.clone(), mesh.indices.clone(), position.x, position.y, position.z);
-        
+        bake_corner_ao(&mut mesh, solid_around);
+        // on the chunk's body like the plain walls, but on its own so a broken one can be taken out alone
+        let body = batches.body(rw, footprint);
+        let half_extents = shape.size / 2.0;
+        let collider = rw.attach_static_shape(body, SharedShape::cuboid(half_extents.x, half_extents.y, half_extents.z), position);
+        let id = EntityId::new(EntityKind::Wall);
+        rw.tag_collider(collider, id);
+        footprint.add_attached_collider(0);
+
         mesh.setup_mesh();
     
         let mut global_mesh_counter = GLOBAL_MESH_COUNTER.lock().await;
-        renderer.add_mesh(&format!("MAZE_MESH{:?}{:?}{:?}", position.x, position.y, global_mesh_counter), mesh).unwrap();
+        let mesh_name = format!("MAZE_MESH{:?}{:?}{:?}", position.x, position.y, global_mesh_counter);
+        *global_mesh_counter += 1;
+        footprint.add_mesh(&mesh_name, &mesh);
+        renderer.try_add_mesh(&mesh_name, mesh)?;
 
+        if kind == (TileKind::Wall { destructible: true }) {
+            destruction.register(id, collider, mesh_name, position, shape.size);
+        }
 
-        *global_mesh_counter += 1;
+        if kind == TileKind::Ladder {
+            // a bit wider than the wall so standing next to it is enough, and a bit taller so we can climb over the top
+            let half_extents = shape.size / 2.0 + vec3(LADDER_REACH, LADDER_REACH, LADDER_REACH);
+            let sensor = rw.add_ladder(position, half_extents);
+            rw.tag_collider(sensor, EntityId::new(EntityKind::Ladder));
+            footprint.add_collider(sensor, 0);
+        }
     }
-}
\ No newline at end of file
+}
+
+// the chunk's plain walls as one mesh, welded so the faces between them and the corners they share aren't there
+// twice, and one compound collider on `body`
+async fn build_walls(walls: WeldedWalls, body: RigidBodyHandle, renderer: &mut Renderer, rw: &mut RapierPhysicsWorld, footprint: &mut ChunkFootprint) -> Result<(), GameError> {
+    let collider = rw.attach_static_shape(body, walls.shape, Vec3::ZERO);
+    rw.tag_collider(collider, EntityId::new(EntityKind::Wall));
+    // each box is a cuboid and a place in the compound
+    footprint.add_attached_collider(walls.boxes * (std::mem::size_of::<Vec3>() * 2 + std::mem::size_of::<Isometry<f32>>()));
+
+    let mut mesh = Mesh::new(&walls.vertices, &walls.indices);
+    mesh.setup_mesh();
+    let mut global_mesh_counter = GLOBAL_MESH_COUNTER.lock().await;
+    let mesh_name = format!("MAZE_WALLS{:?}", global_mesh_counter);
+    *global_mesh_counter += 1;
+    footprint.add_mesh(&mesh_name, &mesh);
+    renderer.try_add_mesh(&mesh_name, mesh)?;
+    Ok(())
+}
+
+// the chunk's water or lava as one mesh on `layer`, welded so the sides between two pools aren't drawn over each other
+async fn build_pool(pool: MeshBatch, prefix: &str, layer: RenderLayer, renderer: &mut Renderer, footprint: &mut ChunkFootprint) -> Result<(), GameError> {
+    let mut mesh = pool.build();
+    mesh.setup_mesh();
+    let mut global_mesh_counter = GLOBAL_MESH_COUNTER.lock().await;
+    let mesh_name = format!("{}{:?}", prefix, global_mesh_counter);
+    *global_mesh_counter += 1;
+    footprint.add_mesh(&mesh_name, &mesh);
+    renderer.try_add_mesh_on(&mesh_name, mesh, layer)?;
+    Ok(())
+}
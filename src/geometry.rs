@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+
+use tiny_game_framework::{glam::Vec3, Mesh, Vertex};
+
+use crate::coords::TileCoord;
+
+// corners closer than this are the same corner
+const WELD_DISTANCE: f32 = 0.01;
+// normals closer than this point the same way, so only flat neighbors share corners
+const NORMAL_TOLERANCE: f32 = 0.001;
+// how much darker a wall gets where it meets another one at an inside corner, and along the floor
+const CORNER_SHADE: f32 = 0.35;
+const FLOOR_SHADE: f32 = 0.2;
+
+// the 8 tiles around one going round from +x towards +z, bit `i` of a `solid_around` mask is `AROUND[i]`
+pub const AROUND: [TileCoord; 8] = [
+    TileCoord::new(1, 0),
+    TileCoord::new(1, 1),
+    TileCoord::new(0, 1),
+    TileCoord::new(-1, 1),
+    TileCoord::new(-1, 0),
+    TileCoord::new(-1, -1),
+    TileCoord::new(0, -1),
+    TileCoord::new(1, -1),
+];
+
+type Key = (i32, i32, i32);
+
+fn quantize(v: Vec3, step: f32) -> Key {
+    ((v.x / step).round() as i32, (v.y / step).round() as i32, (v.z / step).round() as i32)
+}
+
+// darkens the corners of a tile's cuboid (still centered on itself) by the walls around it, like minecraft's
+// smooth lighting. a side's corner gets darker when the tile diagonally in front of it is solid, which is an inside
+// corner, and its bottom gets darker where it stands on the floor. tops and bottoms are left alone
+pub fn bake_corner_ao(mesh: &mut Mesh, solid_around: u8) {
+    let solid = |offset: TileCoord| AROUND.iter().position(|&around| around == offset).is_some_and(|bit| solid_around & (1 << bit) != 0);
+
+    for vertex in mesh.vertices.iter_mut() {
+        if vertex.normal.y.abs() > 0.5 {
+            continue;
+        }
+        let front = TileCoord::new(vertex.normal.x.round() as i32, vertex.normal.z.round() as i32);
+        // along the face towards the corner the vertex is on
+        let side = if front.x != 0 {
+            TileCoord::new(0, vertex.position.z.signum() as i32)
+        } else {
+            TileCoord::new(vertex.position.x.signum() as i32, 0)
+        };
+
+        let mut light = 1.0;
+        if solid(front + side) {
+            light -= CORNER_SHADE;
+        }
+        if vertex.position.y < 0.0 {
+            light -= FLOOR_SHADE;
+        }
+        vertex.color = (vertex.color.truncate() * light).extend(vertex.color.w);
+    }
+}
+
+// meshes piled into one in world space, for geometry that never moves on its own like walls. only `position` is
+// taken from each mesh, tiles are never rotated or scaled
+#[derive(Default)]
+pub struct MeshBatch {
+    vertices: Vec<Vertex>,
+    indices: Vec<u32>,
+}
+
+impl MeshBatch {
+    pub fn add(&mut self, mesh: &Mesh) {
+        let offset = self.vertices.len() as u32;
+        self.vertices.extend(mesh.vertices.iter().map(|vertex| {
+            let mut vertex = *vertex;
+            vertex.position += mesh.position;
+            vertex
+        }));
+        self.indices.extend(mesh.indices.iter().map(|index| index + offset));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    // one mesh at the origin, welded, see `weld`
+    pub fn build(self) -> Mesh {
+        let (vertices, indices) = self.welded();
+        Mesh::new(&vertices, &indices)
+    }
+
+    // what `build` makes a mesh out of, doesn't need the gl context
+    pub fn welded(self) -> (Vec<Vertex>, Vec<u32>) {
+        weld(&self.vertices, &self.indices)
+    }
+}
+
+// shares corners between triangles that meet flat and have the same color, drops faces two cubes have pressed
+// against each other, and works the normals out again from what's left. texture coordinates are whatever the first
+// corner at a spot had, this is for untextured geometry
+pub fn weld(vertices: &[Vertex], indices: &[u32]) -> (Vec<Vertex>, Vec<u32>) {
+    let mut corners: HashMap<Key, u32> = HashMap::new();
+    let corner_of: Vec<u32> = vertices.iter().map(|vertex| {
+        let next = corners.len() as u32;
+        *corners.entry(quantize(vertex.position, WELD_DISTANCE)).or_insert(next)
+    }).collect();
+
+    // a face between two cubes comes in once from each side, wound opposite ways. the framework's cuboids split
+    // every face along the same diagonal, so both halves line up exactly
+    let face = |triangle: &[u32]| {
+        let mut face = [0, 1, 2].map(|i| corner_of[triangle[i] as usize]);
+        face.sort_unstable();
+        face
+    };
+    let mut faces: HashMap<[u32; 3], usize> = HashMap::new();
+    for triangle in indices.chunks_exact(3) {
+        *faces.entry(face(triangle)).or_default() += 1;
+    }
+
+    let mut welded: HashMap<(u32, Key, [u32; 4]), u32> = HashMap::new();
+    let mut out_vertices = Vec::new();
+    let mut out_indices = Vec::with_capacity(indices.len());
+    for triangle in indices.chunks_exact(3) {
+        if faces[&face(triangle)] > 1 {
+            continue;
+        }
+        for &index in triangle {
+            let vertex = vertices[index as usize];
+            let key = (corner_of[index as usize], quantize(vertex.normal, NORMAL_TOLERANCE), vertex.color.to_array().map(f32::to_bits));
+            let welded_index = *welded.entry(key).or_insert_with(|| {
+                out_vertices.push(vertex);
+                out_vertices.len() as u32 - 1
+            });
+            out_indices.push(welded_index);
+        }
+    }
+
+    recompute_normals(&mut out_vertices, &out_indices);
+    (out_vertices, out_indices)
+}
+
+// area weighted over the triangles around each corner. the framework's cuboids and our own meshes don't wind the
+// same way round, so the old normal only says which side is out
+pub fn recompute_normals(vertices: &mut [Vertex], indices: &[u32]) {
+    let mut sums = vec![Vec3::ZERO; vertices.len()];
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| vertices[triangle[i] as usize].position);
+        // twice the triangle's area long
+        let normal = (b - a).cross(c - a);
+        for &index in triangle {
+            let outward = if normal.dot(vertices[index as usize].normal) < 0.0 { -normal } else { normal };
+            sums[index as usize] += outward;
+        }
+    }
+
+    for (vertex, sum) in vertices.iter_mut().zip(sums) {
+        vertex.normal = sum.normalize_or(vertex.normal);
+    }
+}
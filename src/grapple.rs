@@ -0,0 +1,103 @@
+use rapier3d::prelude::*;
+use tiny_game_framework::{glam::{vec3, Quat, Vec3, Vec4}, Cuboid, Renderer};
+
+use crate::{character_controller::{Player, PlayerInput}, error::RendererExt, rapier_integration::RapierPhysicsWorld};
+
+const GRAPPLE_RANGE: f32 = 1500.0;
+const REEL_SPEED: f32 = 400.0;
+const MIN_ROPE_LENGTH: f32 = 60.0;
+// anything this far short of the anchor crossing the rope cuts it, so the wall it's stuck in doesn't count
+const OBSTRUCTION_MARGIN: f32 = 20.0;
+const ROPE_THICKNESS: f32 = 3.0;
+const ROPE_COLOR: Vec4 = Vec4::new(0.6, 0.5, 0.35, 1.0);
+
+struct Rope {
+    // fixed body at the hit point, the joint needs something to hold on to
+    anchor: RigidBodyHandle,
+    joint: ImpulseJointHandle,
+    point: Vec3,
+    length: f32,
+}
+
+// one per player. pressing grapple shoots it at whatever is being looked at and pressing again lets go
+pub struct GrapplingHook {
+    rope: Option<Rope>,
+    was_pressed: bool,
+    mesh_name: String,
+}
+
+impl GrapplingHook {
+    pub fn new(player: usize) -> Self {
+        Self { rope: None, was_pressed: false, mesh_name: format!("GRAPPLE_ROPE{}", player) }
+    }
+
+    pub fn is_attached(&self) -> bool {
+        self.rope.is_some()
+    }
+
+    // call before `Player::update`, which swings instead of walking while the hook is attached
+    pub fn update(&mut self, rw: &mut RapierPhysicsWorld, r: &mut Renderer, player: &mut Player, input: &PlayerInput, dt: f32) {
+        let pressed = input.grapple && !self.was_pressed;
+        self.was_pressed = input.grapple;
+        if pressed {
+            if self.is_attached() {
+                self.detach(rw, r);
+            } else {
+                self.fire(rw, r, player, input.look());
+            }
+        }
+
+        let origin = player.position();
+        let Some(rope) = self.rope.as_mut() else {
+            player.swinging = false;
+            return;
+        };
+
+        let to_anchor = rope.point - origin;
+        let distance = to_anchor.length();
+        let direction = to_anchor.normalize_or_zero();
+        if distance > OBSTRUCTION_MARGIN && rw.cast_ray(origin, direction, distance - OBSTRUCTION_MARGIN, player.body()).is_some() {
+            self.detach(rw, r);
+            player.swinging = false;
+            return;
+        }
+
+        if input.reel {
+            // reeling only ever shortens the rope, starting from however taut it is right now
+            rope.length = (rope.length.min(distance) - REEL_SPEED * dt).max(MIN_ROPE_LENGTH);
+            rw.set_rope_length(rope.joint, rope.length);
+        }
+        player.swinging = true;
+
+        if let Some(mesh) = r.get_mesh_mut(&self.mesh_name) {
+            mesh.position = origin + to_anchor / 2.0;
+            mesh.rotation = Quat::from_rotation_arc(Vec3::Y, direction);
+            mesh.scale = vec3(1.0, distance, 1.0);
+        }
+    }
+
+    fn fire(&mut self, rw: &mut RapierPhysicsWorld, r: &mut Renderer, player: &Player, look: Vec3) {
+        let origin = player.position();
+        let Some((_, distance)) = rw.cast_ray(origin, look, GRAPPLE_RANGE, player.body()) else { return };
+        let point = origin + look * distance;
+
+        let anchor = rw.add_anchor(point);
+        let joint = rw.add_rope_joint(player.body(), anchor, Vec3::ZERO, Vec3::ZERO, distance);
+        self.rope = Some(Rope { anchor, joint, point, length: distance });
+
+        // a unit long rope, stretched to the right length every update
+        let mut mesh = Cuboid::new(vec3(ROPE_THICKNESS, 1.0, ROPE_THICKNESS), ROPE_COLOR).mesh();
+        mesh.position = origin;
+        mesh.setup_mesh();
+        if let Err(e) = r.try_add_mesh(&self.mesh_name, mesh) {
+            log::warn!("{}", e);
+        }
+    }
+
+    pub fn detach(&mut self, rw: &mut RapierPhysicsWorld, r: &mut Renderer) {
+        let Some(rope) = self.rope.take() else { return };
+        // takes the joint with it
+        rw.remove_rigidbody(rope.anchor);
+        r.destroy_mesh(&self.mesh_name).ok();
+    }
+}
@@ -0,0 +1,94 @@
+use std::{error::Error, fs, path::Path, time::Instant};
+
+use serde::Serialize;
+
+use crate::{coords, dungeon::{DungeonConfig, DungeonGenerator, DungeonLayout, Hazards, Tile}};
+
+// what `--generate` needs, everything the window would normally decide
+pub struct GenerateOptions {
+    pub seed: u64,
+    // in tiles, doesn't have to be square
+    pub width: u32,
+    pub height: u32,
+    // the built in rooms when not set
+    pub sample: Option<String>,
+    pub output: String,
+}
+
+#[derive(Serialize, Default, Debug)]
+pub struct GenerationStats {
+    pub seed: u64,
+    pub width: u32,
+    pub height: u32,
+    pub sample: String,
+    pub elapsed_ms: u128,
+    // collapses thrown away for being too closed in, plus the one that was kept. `seed` is the kept one's
+    pub attempts: u32,
+    pub floor: usize,
+    pub walls: usize,
+    pub ladders: usize,
+    pub water: usize,
+    pub lava: usize,
+    pub teleporters: usize,
+    pub traps: usize,
+    // floor the sample marked for enemies or props
+    pub anchors: usize,
+    // tiles that hurt, set something off or have to be swum through, and the ones of those that hurt
+    pub hazards: usize,
+    pub damaging: usize,
+    // of all the tiles, how many can be walked on
+    pub floor_ratio: f32,
+    // walkable areas that can't reach each other, 1 means everything is connected
+    pub regions: usize,
+    pub largest_region: usize,
+}
+
+impl GenerationStats {
+    fn from_layout(layout: &DungeonLayout) -> Self {
+        let region_sizes = layout.regions();
+
+        Self {
+            seed: layout.seed,
+            width: layout.width,
+            height: layout.height,
+            attempts: layout.attempts,
+            floor: layout.count(|tile| tile == Tile::Floor),
+            walls: layout.count(|tile| tile == Tile::Wall),
+            ladders: layout.count(|tile| tile == Tile::Ladder),
+            water: layout.count(|tile| tile == Tile::Water),
+            lava: layout.count(|tile| tile == Tile::Lava),
+            teleporters: layout.count(|tile| tile == Tile::Teleporter),
+            traps: layout.count(|tile| matches!(tile, Tile::Trap(_))),
+            anchors: layout.metadata().filter(|(_, meta)| meta.anchor.is_some()).count(),
+            hazards: layout.metadata().filter(|(_, meta)| !meta.hazards.is_empty()).count(),
+            damaging: layout.metadata().filter(|(_, meta)| meta.hazards.contains(Hazards::DAMAGE)).count(),
+            floor_ratio: layout.floor_ratio(),
+            regions: region_sizes.len(),
+            largest_region: region_sizes.into_iter().max().unwrap_or(0),
+            ..Default::default()
+        }
+    }
+}
+
+// runs the wave function collapse without a window, writes the canvas as a png and a json report next to it
+pub fn generate(options: &GenerateOptions) -> Result<GenerationStats, Box<dyn Error>> {
+    let started = Instant::now();
+
+    let config = DungeonConfig {
+        width: options.width,
+        height: options.height,
+        sample: options.sample.clone(),
+        ..DungeonConfig::quadrant(coords::settings())
+    };
+    let layout = DungeonGenerator::new(config).generate(options.seed)?;
+
+    let mut stats = GenerationStats::from_layout(&layout);
+    stats.elapsed_ms = started.elapsed().as_millis();
+    stats.sample = options.sample.clone().unwrap_or_else(|| "rooms.bmp".to_string());
+
+    layout.save_png(&options.output)?;
+    let report = Path::new(&options.output).with_extension("json");
+    fs::write(&report, serde_json::to_string_pretty(&stats)?)?;
+
+    Ok(stats)
+}
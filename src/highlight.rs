@@ -0,0 +1,120 @@
+use rapier3d::prelude::*;
+use tiny_game_framework::{gl::{self, types::GLuint}, glam::{Mat4, Vec3}, Camera, Renderer};
+
+use crate::{entity::EntityId, rapier_integration::RapierPhysicsWorld, shader::{location, program}};
+
+// how far from the camera something can be looked at to interact with it, in world units
+const REACH: f32 = 400.0;
+const COLOR: Vec3 = Vec3::new(1.0, 0.85, 0.5);
+// how bright the whole thing gets, on top of the rim
+const BASE_GLOW: f32 = 0.12;
+const PULSE_SPEED: f32 = 4.0;
+
+// the looked at mesh drawn over itself a second time, added on top of what's there. edges facing away from the
+// camera glow the most
+const RIM_VS: &str = r#"
+#version 330 core
+layout (location = 0) in vec3 aPos;
+layout (location = 3) in vec3 aNormal;
+
+uniform mat4 model;
+uniform mat4 view;
+uniform mat4 proj;
+uniform vec3 eye;
+
+out vec3 Normal;
+out vec3 ToEye;
+
+void main() {
+    vec4 worldPos = model * vec4(aPos, 1.0);
+    gl_Position = proj * view * worldPos;
+    Normal = mat3(model) * aNormal;
+    ToEye = eye - worldPos.xyz;
+}
+"#;
+
+const RIM_FS: &str = r#"
+#version 330 core
+in vec3 Normal;
+in vec3 ToEye;
+out vec4 FragColor;
+
+uniform vec3 color;
+uniform float base_glow;
+uniform float pulse;
+
+void main() {
+    float facing = abs(dot(normalize(Normal), normalize(ToEye)));
+    float rim = pow(1.0 - facing, 2.0);
+    FragColor = vec4(color * (base_glow + rim) * pulse, 1.0);
+}
+"#;
+
+// something with a mesh that does something when it's interacted with
+pub struct Interactable<'a> {
+    pub entity: EntityId,
+    pub mesh_name: &'a str,
+}
+
+// finds what the crosshair is on and outlines it with a rim light, so it's clear what pressing interact is going
+// to do before it's pressed
+pub struct Highlight {
+    // `None` when the shader didn't build, nothing is highlighted then
+    program: Option<GLuint>,
+    // the mesh being looked at
+    target: Option<String>,
+    time: f32,
+}
+
+impl Highlight {
+    // needs the gl context
+    pub fn new() -> Self {
+        let program = program(RIM_VS, RIM_FS).inspect_err(|e| log::warn!("no interaction highlight: {}", e)).ok();
+        Self { program, target: None, time: 0.0 }
+    }
+
+    // casts from `eye` along `front`, both in world units, past the player's own `body`. only the first thing hit
+    // counts, an interactable behind a wall isn't looked at
+    pub fn update<'a>(&mut self, rw: &RapierPhysicsWorld, eye: Vec3, front: Vec3, body: RigidBodyHandle, mut interactables: impl Iterator<Item = Interactable<'a>>, dt: f32) {
+        self.time += dt;
+        let looked_at = rw.cast_ray(eye, front, REACH, body).and_then(|(collider, _)| rw.entity_of(collider));
+        self.target = looked_at.and_then(|entity| {
+            interactables.find(|interactable| interactable.entity == entity).map(|interactable| interactable.mesh_name.to_string())
+        });
+    }
+
+    // call after the scene, with the camera the frame was drawn with
+    pub fn draw(&self, r: &Renderer, camera: &Camera, resolution: f32) {
+        let (Some(program), Some(mesh)) = (self.program, self.target.as_ref().and_then(|name| r.get_mesh(name))) else { return };
+
+        // the same transform the framework draws it with
+        let model = Mat4::from_translation(mesh.position / resolution) * Mat4::from_quat(mesh.rotation) * Mat4::from_scale(mesh.scale / resolution);
+        let pulse = 0.75 + 0.25 * (self.time * PULSE_SPEED).sin();
+        unsafe {
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::ONE, gl::ONE);
+            // the mesh is drawn again right where it already is
+            let depth_test = gl::IsEnabled(gl::DEPTH_TEST) == gl::TRUE;
+            gl::Enable(gl::DEPTH_TEST);
+            gl::DepthFunc(gl::LEQUAL);
+
+            gl::UseProgram(program);
+            gl::UniformMatrix4fv(location(program, "model"), 1, gl::FALSE, model.to_cols_array().as_ptr());
+            gl::UniformMatrix4fv(location(program, "view"), 1, gl::FALSE, camera.view.to_cols_array().as_ptr());
+            gl::UniformMatrix4fv(location(program, "proj"), 1, gl::FALSE, camera.proj.to_cols_array().as_ptr());
+            gl::Uniform3f(location(program, "eye"), camera.pos.x, camera.pos.y, camera.pos.z);
+            gl::Uniform3f(location(program, "color"), COLOR.x, COLOR.y, COLOR.z);
+            gl::Uniform1f(location(program, "base_glow"), BASE_GLOW);
+            gl::Uniform1f(location(program, "pulse"), pulse);
+            gl::BindVertexArray(mesh.VAO);
+            gl::DrawElements(gl::TRIANGLES, mesh.indices.len() as i32, gl::UNSIGNED_INT, std::ptr::null());
+
+            gl::BindVertexArray(0);
+            gl::UseProgram(0);
+            if !depth_test {
+                gl::Disable(gl::DEPTH_TEST);
+            }
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+        }
+    }
+}
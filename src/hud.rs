@@ -0,0 +1,183 @@
+use std::f32::consts::FRAC_PI_2;
+
+use tiny_game_framework::{glam::{vec2, vec3, Vec2, Vec3}, glfw::Key, imgui::{Condition, ProgressBar, StyleColor, Ui}, EventLoop};
+
+use crate::{character_controller::Player, objectives::Objective, progression::Progression};
+
+pub const HOTBAR_SLOTS: usize = 5;
+const HOTBAR_KEYS: [Key; HOTBAR_SLOTS] = [Key::Num1, Key::Num2, Key::Num3, Key::Num4, Key::Num5];
+const SLOT_SIZE: f32 = 48.0;
+const SLOT_SPACING: f32 = 6.0;
+
+const BAR_WIDTH: f32 = 220.0;
+const CROSSHAIR_SIZE: f32 = 8.0;
+
+// how long a damage indicator stays on screen, it fades out over that time
+const INDICATOR_LIFETIME: f32 = 1.0;
+// distance from the center of the screen to the indicator
+const INDICATOR_DISTANCE: f32 = 90.0;
+const INDICATOR_SIZE: f32 = 16.0;
+
+const HEALTH_COLOR: [f32; 4] = [0.8, 0.15, 0.15, 1.0];
+const STAMINA_COLOR: [f32; 4] = [0.2, 0.7, 0.3, 1.0];
+const XP_COLOR: [f32; 4] = [0.3, 0.5, 0.9, 1.0];
+
+// the tracker hangs off the right edge, this far down the screen
+const TRACKER_TOP: f32 = 0.3;
+const OBJECTIVE_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const OBJECTIVE_DONE_COLOR: [f32; 4] = [0.5, 0.5, 0.5, 1.0];
+
+pub struct Hotbar {
+    // item names, the ui shows these until items get icons
+    pub items: [Option<String>; HOTBAR_SLOTS],
+    pub selected: usize,
+}
+
+struct DamageIndicator {
+    // world space direction the hit came from, flattened
+    direction: Vec3,
+    timer: f32,
+}
+
+pub struct Hud {
+    pub hotbar: Hotbar,
+    indicators: Vec<DamageIndicator>,
+}
+
+impl Hud {
+    pub fn new() -> Self {
+        Self {
+            hotbar: Hotbar { items: Default::default(), selected: 0 },
+            indicators: Vec::new(),
+        }
+    }
+
+    // `direction` points from the player towards whatever hurt them
+    pub fn damage_from(&mut self, direction: Vec3) {
+        let direction = vec3(direction.x, 0.0, direction.z).normalize_or_zero();
+        if direction != Vec3::ZERO {
+            self.indicators.push(DamageIndicator { direction, timer: INDICATOR_LIFETIME });
+        }
+    }
+
+    pub fn update(&mut self, el: &mut EventLoop, dt: f32) {
+        for (slot, key) in HOTBAR_KEYS.iter().enumerate() {
+            if el.is_key_down(*key) {
+                self.hotbar.selected = slot;
+            }
+        }
+
+        for indicator in self.indicators.iter_mut() {
+            indicator.timer -= dt;
+        }
+        self.indicators.retain(|indicator| indicator.timer > 0.0);
+    }
+
+    pub fn draw(&self, ui: &Ui, player: &Player, progression: &Progression, objectives: &[Objective], camera_front: Vec3) {
+        let [width, height] = ui.io().display_size;
+        let center = vec2(width, height) / 2.0;
+
+        self.draw_bars(ui, player, progression);
+        Self::draw_objectives(ui, objectives, width, height);
+        Self::draw_crosshair(ui, center);
+        self.draw_hotbar(ui, width, height);
+        self.draw_indicators(ui, center, camera_front);
+    }
+
+    fn draw_bars(&self, ui: &Ui, player: &Player, progression: &Progression) {
+        ui.window("hud")
+            .position([10.0, 10.0], Condition::Always)
+            .no_decoration()
+            .no_inputs()
+            .bg_alpha(0.0)
+            .always_auto_resize(true)
+            .build(|| {
+                Self::bar(ui, player.health / player.max_health, format!("{:.0}/{:.0}", player.health, player.max_health), HEALTH_COLOR);
+                Self::bar(ui, player.stamina / player.max_stamina, format!("{:.0}", player.stamina), STAMINA_COLOR);
+
+                ui.text(format!("level {}", progression.level));
+                Self::bar(ui, progression.progress(), format!("{}/{} xp", progression.xp, progression.xp_to_next_level()), XP_COLOR);
+            });
+    }
+
+    fn draw_objectives(ui: &Ui, objectives: &[Objective], width: f32, height: f32) {
+        if objectives.is_empty() {
+            return;
+        }
+
+        ui.window("objectives")
+            .position([width - 10.0, height * TRACKER_TOP], Condition::Always)
+            .position_pivot([1.0, 0.0])
+            .no_decoration()
+            .no_inputs()
+            .bg_alpha(0.3)
+            .always_auto_resize(true)
+            .build(|| {
+                ui.text("objectives");
+                ui.separator();
+                for objective in objectives {
+                    let (mark, color) = if objective.done { ("x", OBJECTIVE_DONE_COLOR) } else { (" ", OBJECTIVE_COLOR) };
+                    ui.text_colored(color, format!("[{}] {}", mark, objective.kind.description()));
+                }
+            });
+    }
+
+    fn bar(ui: &Ui, fraction: f32, overlay: String, color: [f32; 4]) {
+        let _color = ui.push_style_color(StyleColor::PlotHistogram, color);
+        ProgressBar::new(fraction.clamp(0.0, 1.0))
+            .overlay_text(overlay)
+            .size([BAR_WIDTH, 0.0])
+            .build(ui);
+    }
+
+    fn draw_crosshair(ui: &Ui, center: Vec2) {
+        let draw_list = ui.get_foreground_draw_list();
+        let color = [1.0, 1.0, 1.0, 0.8];
+
+        draw_list.add_line([center.x - CROSSHAIR_SIZE, center.y], [center.x + CROSSHAIR_SIZE, center.y], color).thickness(2.0).build();
+        draw_list.add_line([center.x, center.y - CROSSHAIR_SIZE], [center.x, center.y + CROSSHAIR_SIZE], color).thickness(2.0).build();
+    }
+
+    fn draw_hotbar(&self, ui: &Ui, width: f32, height: f32) {
+        let draw_list = ui.get_foreground_draw_list();
+
+        let total = HOTBAR_SLOTS as f32 * SLOT_SIZE + (HOTBAR_SLOTS - 1) as f32 * SLOT_SPACING;
+        let left = (width - total) / 2.0;
+        let top = height - SLOT_SIZE - 20.0;
+
+        for (slot, item) in self.hotbar.items.iter().enumerate() {
+            let min = vec2(left + slot as f32 * (SLOT_SIZE + SLOT_SPACING), top);
+            let max = min + Vec2::splat(SLOT_SIZE);
+
+            draw_list.add_rect([min.x, min.y], [max.x, max.y], [0.0, 0.0, 0.0, 0.5]).filled(true).build();
+            let outline = if slot == self.hotbar.selected { [1.0, 0.85, 0.3, 1.0] } else { [0.6, 0.6, 0.6, 1.0] };
+            draw_list.add_rect([min.x, min.y], [max.x, max.y], outline).thickness(2.0).build();
+
+            draw_list.add_text([min.x + 3.0, min.y + 2.0], [0.8, 0.8, 0.8, 1.0], format!("{}", slot + 1));
+            if let Some(item) = item {
+                draw_list.add_text([min.x + 4.0, min.y + SLOT_SIZE / 2.0], [1.0, 1.0, 1.0, 1.0], item);
+            }
+        }
+    }
+
+    fn draw_indicators(&self, ui: &Ui, center: Vec2, camera_front: Vec3) {
+        let draw_list = ui.get_foreground_draw_list();
+        let forward = vec3(camera_front.x, 0.0, camera_front.z).normalize_or_zero();
+        let right = forward.cross(Vec3::Y);
+
+        for indicator in self.indicators.iter() {
+            // angle on screen where straight up is in front of the player
+            let angle = indicator.direction.dot(right).atan2(indicator.direction.dot(forward)) - FRAC_PI_2;
+            let dir = vec2(angle.cos(), angle.sin());
+            let side = vec2(-dir.y, dir.x);
+
+            let tip = center + dir * (INDICATOR_DISTANCE + INDICATOR_SIZE);
+            let base = center + dir * INDICATOR_DISTANCE;
+            let a = base + side * INDICATOR_SIZE;
+            let b = base - side * INDICATOR_SIZE;
+
+            let alpha = indicator.timer / INDICATOR_LIFETIME;
+            draw_list.add_triangle([tip.x, tip.y], [a.x, a.y], [b.x, b.y], [0.9, 0.1, 0.1, alpha]).filled(true).build();
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use std::collections::HashMap;
+
+use rapier3d::prelude::*;
+use tiny_game_framework::glam::Vec3;
+
+use crate::{entity::EntityKind, particles::ParticlePreset, props::PropSystem, rapier_integration::RapierPhysicsWorld};
+
+// hitting this hard is as noisy and messy as it gets, in how much the hit slowed the body down
+const FULL_IMPACT_SPEED: f32 = 900.0;
+// the same body hitting again this soon is still the same hit, contacts last a few steps
+const IMPACT_COOLDOWN: f32 = 0.15;
+const MAX_IMPACT_PARTICLES: f32 = 16.0;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum ImpactMaterial {
+    Wood,
+    Stone,
+    Metal,
+}
+
+impl ImpactMaterial {
+    // what something tagged `kind` is made of, `None` for things that don't make a mess when they land. props
+    // each have their own, see `PropKind::material`
+    fn of(kind: EntityKind) -> Option<Self> {
+        match kind {
+            EntityKind::Debris => Some(ImpactMaterial::Stone),
+            EntityKind::Projectile => Some(ImpactMaterial::Metal),
+            _ => None,
+        }
+    }
+
+    pub fn particles(&self) -> ParticlePreset {
+        match self {
+            ImpactMaterial::Wood => ParticlePreset::Splinters,
+            ImpactMaterial::Stone => ParticlePreset::Dust,
+            ImpactMaterial::Metal => ParticlePreset::Sparks,
+        }
+    }
+}
+
+// what one hit should look like and how far it's heard
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ImpactEffect {
+    pub position: Vec3,
+    pub material: ImpactMaterial,
+    // 0 to 1, scales with how hard it hit
+    pub volume: f32,
+    pub particles: usize,
+}
+
+// turns the physics world's impacts into noise and particle bursts, once per hit
+#[derive(Default)]
+pub struct Impacts {
+    // seconds until each body that just hit something can make a mess again
+    cooldowns: HashMap<RigidBodyHandle, f32>,
+}
+
+impl Impacts {
+    // call after the step, takes everything it reported
+    pub fn update(&mut self, rw: &mut RapierPhysicsWorld, props: &PropSystem, dt: f32) -> Vec<ImpactEffect> {
+        for cooldown in self.cooldowns.values_mut() {
+            *cooldown -= dt;
+        }
+        self.cooldowns.retain(|_, cooldown| *cooldown > 0.0);
+
+        let mut effects = Vec::new();
+        for impact in rw.take_impacts() {
+            if self.cooldowns.contains_key(&impact.body) {
+                continue;
+            }
+            let Some(entity) = rw.entity_of_body(impact.body) else { continue };
+            let material = match entity.kind {
+                EntityKind::Prop => props.material(entity),
+                kind => ImpactMaterial::of(kind),
+            };
+            let Some(material) = material else { continue };
+            self.cooldowns.insert(impact.body, IMPACT_COOLDOWN);
+
+            let strength = (impact.speed / FULL_IMPACT_SPEED).min(1.0);
+            effects.push(ImpactEffect {
+                position: impact.position,
+                material,
+                volume: strength,
+                particles: (strength * MAX_IMPACT_PARTICLES).ceil() as usize,
+            });
+        }
+
+        effects
+    }
+}
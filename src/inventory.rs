@@ -0,0 +1,73 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{equipment::{EquipSlot, Equipment}, locks};
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Item {
+    // opens the locked door with the same index
+    Key(usize),
+    Equipment(Equipment),
+}
+
+impl Item {
+    pub fn name(&self) -> String {
+        match self {
+            Item::Key(key) => format!("{} key", locks::key_color_name(*key)),
+            Item::Equipment(equipment) => equipment.name().to_string(),
+        }
+    }
+}
+
+// what one player is carrying, in the order it was picked up
+#[derive(Default, Debug)]
+pub struct Inventory {
+    items: Vec<Item>,
+    // what merchants take, carried as a count instead of taking up slots
+    coins: u32,
+}
+
+impl Inventory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, item: Item) {
+        self.items.push(item);
+    }
+
+    pub fn add_coins(&mut self, amount: u32) {
+        self.coins += amount;
+    }
+
+    pub fn coins(&self) -> u32 {
+        self.coins
+    }
+
+    // false and nothing taken if there aren't enough
+    pub fn spend(&mut self, amount: u32) -> bool {
+        if self.coins < amount {
+            return false;
+        }
+        self.coins -= amount;
+        true
+    }
+
+    // false if there wasn't one to take
+    pub fn remove(&mut self, item: Item) -> bool {
+        let Some(index) = self.items.iter().position(|&carried| carried == item) else { return false };
+        self.items.remove(index);
+        true
+    }
+
+    pub fn items(&self) -> &[Item] {
+        &self.items
+    }
+
+    // the weapon in hotbar slot `slot`, the hotbar shows the first few items in order
+    pub fn weapon_in(&self, slot: usize) -> Option<Equipment> {
+        match self.items.get(slot) {
+            Some(&Item::Equipment(equipment)) if equipment.slot() == EquipSlot::Weapon => Some(equipment),
+            _ => None,
+        }
+    }
+}
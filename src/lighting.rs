@@ -0,0 +1,253 @@
+use std::collections::HashMap;
+
+use tiny_game_framework::{gl, glam::Vec3, Light, Mesh, Renderer, DEFAULT_SHADER};
+
+use crate::{config::{LightingSettings, Sky}, error::ShaderError, shader::{compile, link_result, location}};
+
+// fog is fully opaque this many torch radii out, chunks stream in further away than that
+const FOG_FALLOFF: f32 = 1.8;
+// the framework's full shader takes 5 lights and one of them is always "l1". the fog shader's arrays are this long too
+const MAX_POINT_LIGHTS: usize = 4;
+
+// the framework's default mesh shader, which is what the maze is drawn with, plus distance fog and the point lights
+// emissive decorations put out. the framework never sends its own lights to the default shader, they're set in
+// `Lighting::apply`
+const FOG_SHADER_VS: &str = r#"
+#version 330 core
+layout (location = 0) in vec3 aPos;
+layout (location = 1) in vec4 aColor;
+layout (location = 2) in vec2 aTexCoord;
+layout (location = 3) in vec3 aNormal;
+
+uniform mat4 model;
+uniform mat4 view;
+uniform mat4 proj;
+
+out vec4 fColor;
+out vec2 TexCoord;
+out float Distance;
+out vec3 FragPos;
+out vec3 Normal;
+
+void main() {
+    vec4 worldPos = model * vec4(aPos, 1.0);
+    vec4 viewPos = view * worldPos;
+    gl_Position = proj * viewPos;
+    fColor = aColor;
+    TexCoord = aTexCoord;
+    Distance = length(viewPos.xyz);
+    FragPos = worldPos.xyz;
+    Normal = mat3(transpose(inverse(model))) * aNormal;
+}
+"#;
+
+const FOG_SHADER_FS: &str = r#"
+#version 330 core
+out vec4 FragColor;
+
+in vec4 fColor;
+in vec2 TexCoord;
+in float Distance;
+in vec3 FragPos;
+in vec3 Normal;
+
+uniform sampler2D texture1;
+uniform vec3 fog_color;
+uniform float fog_start;
+uniform float fog_end;
+
+uniform int point_light_count;
+uniform vec3 point_light_position[4];
+uniform vec3 point_light_color[4];
+uniform float point_light_radius[4];
+
+void main()
+{
+    vec4 color = texture(texture1, TexCoord) * fColor;
+
+    // added on top, the maze is already drawn at full brightness. meshes without normals are lit from every side
+    vec3 lit = vec3(0.0);
+    for (int i = 0; i < point_light_count; ++i) {
+        vec3 toLight = point_light_position[i] - FragPos;
+        float reach = clamp(1.0 - length(toLight) / point_light_radius[i], 0.0, 1.0);
+        float facing = length(Normal) > 0.0 ? max(dot(normalize(Normal), normalize(toLight)), 0.0) : 1.0;
+        lit += point_light_color[i] * facing * reach;
+    }
+    color.rgb += color.rgb * lit;
+
+    float fog = clamp((Distance - fog_start) / max(fog_end - fog_start, 0.0001), 0.0, 1.0);
+    FragColor = vec4(mix(color.rgb, fog_color, fog), color.a);
+}
+"#;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Fog {
+    pub color: Vec3,
+    // world units from the camera, clear before `start` and nothing but fog past `end`
+    pub start: f32,
+    pub end: f32,
+}
+
+impl Fog {
+    pub fn around_torch(torch_radius: f32, color: Vec3) -> Self {
+        Self { color, start: torch_radius, end: torch_radius * FOG_FALLOFF }
+    }
+}
+
+// glows on its own. the mesh is painted brighter than white, which only emissive things ever are, so bloom picks
+// it up, and a point light goes where it is
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Emissive {
+    pub color: Vec3,
+    // how far past white it's painted, and how bright its light is
+    pub strength: f32,
+    // world units its light reaches
+    pub radius: f32,
+}
+
+impl Emissive {
+    pub const TORCH: Emissive = Emissive { color: Vec3::new(1.0, 0.6, 0.2), strength: 1.5, radius: 900.0 };
+    pub const CRYSTAL: Emissive = Emissive { color: Vec3::new(0.35, 0.6, 1.0), strength: 1.0, radius: 600.0 };
+    pub const LAVA: Emissive = Emissive { color: Vec3::new(1.0, 0.35, 0.05), strength: 1.2, radius: 600.0 };
+    pub const PORTAL: Emissive = Emissive { color: Vec3::new(0.95, 0.95, 0.6), strength: 2.0, radius: 1500.0 };
+
+    pub fn apply(&self, mesh: &mut Mesh) {
+        for vertex in mesh.vertices.iter_mut() {
+            vertex.color = (self.color * (1.0 + self.strength)).extend(vertex.color.w);
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct PointLight {
+    position: Vec3,
+    emissive: Emissive,
+}
+
+// how the dungeon is lit: the torch, the fog that hides whatever it doesn't reach, and the lights emissive
+// decorations put out
+pub struct Lighting {
+    fog: Fog,
+    // by the name of the mesh giving it off
+    lights: HashMap<String, PointLight>,
+    // the nearest ones, what was handed to the renderer and goes to the fog shader
+    shown: Vec<PointLight>,
+    skies: HashMap<String, Sky>,
+}
+
+impl Lighting {
+    pub fn new(settings: &LightingSettings) -> Self {
+        let color = Vec3::from(settings.fog_color);
+        Self { fog: Fog::around_torch(settings.torch_radius, color), lights: HashMap::new(), shown: Vec::new(), skies: settings.skies.clone() }
+    }
+
+    // `name` is the emissive mesh's, placing it again moves it
+    pub fn place_light(&mut self, name: &str, position: Vec3, emissive: Emissive) {
+        self.lights.insert(name.to_string(), PointLight { position, emissive });
+    }
+
+    pub fn remove_light(&mut self, name: &str) {
+        self.lights.remove(name);
+    }
+
+    // the framework only has room for a few lights and they don't fall off on their own, so the ones closest to
+    // `eye` are handed over, dimmed by how far away they are. the fog shader gets the same ones in `apply`, it does
+    // its own falloff
+    pub fn update(&mut self, r: &mut Renderer, eye: Vec3, resolution: f32) {
+        for i in 0..self.shown.len() {
+            r.lights.remove(&format!("POINT_LIGHT{}", i));
+        }
+
+        let mut near: Vec<(f32, PointLight)> = self.lights.values().copied()
+            .map(|light| (light.position.distance(eye), light))
+            .filter(|(distance, light)| *distance < light.emissive.radius)
+            .collect();
+        near.sort_by(|a, b| a.0.total_cmp(&b.0));
+        near.truncate(MAX_POINT_LIGHTS);
+
+        self.shown = near.iter().map(|(_, light)| *light).collect();
+        for (i, (distance, light)) in near.into_iter().enumerate() {
+            let falloff = 1.0 - distance / light.emissive.radius;
+            r.add_light(&format!("POINT_LIGHT{}", i), Light {
+                position: light.position / resolution,
+                color: light.emissive.color * light.emissive.strength * falloff,
+            });
+        }
+    }
+
+    pub fn fog(&self) -> Fog {
+        self.fog
+    }
+
+    pub fn sky(&self, biome: &str) -> Sky {
+        self.skies.get(biome).copied().unwrap_or_default()
+    }
+
+    // swaps fog into the framework's default mesh shader, in place, so every mesh already using it gets fogged
+    // without being touched. needs the gl context, call it once after the event loop is up. the old shader stays
+    // if anything fails
+    pub fn install(&self) -> Result<(), ShaderError> {
+        unsafe {
+            let vs = compile(gl::VERTEX_SHADER, "vertex", FOG_SHADER_VS)?;
+            let fs = match compile(gl::FRAGMENT_SHADER, "fragment", FOG_SHADER_FS) {
+                Ok(fs) => fs,
+                Err(e) => {
+                    gl::DeleteShader(vs);
+                    return Err(e);
+                }
+            };
+
+            let program = DEFAULT_SHADER.id;
+            let mut old = [0; 2];
+            let mut count = 0;
+            gl::GetAttachedShaders(program, old.len() as i32, &mut count, old.as_mut_ptr());
+            let old = &old[..count as usize];
+
+            for &shader in old {
+                gl::DetachShader(program, shader);
+            }
+            gl::AttachShader(program, vs);
+            gl::AttachShader(program, fs);
+            gl::LinkProgram(program);
+            let linked = link_result(program);
+
+            if linked.is_ok() {
+                for &shader in old {
+                    gl::DeleteShader(shader);
+                }
+            } else {
+                // back to how the framework left it
+                for shader in [vs, fs] {
+                    gl::DetachShader(program, shader);
+                    gl::DeleteShader(shader);
+                }
+                for &shader in old {
+                    gl::AttachShader(program, shader);
+                }
+                gl::LinkProgram(program);
+            }
+            linked
+        }
+    }
+
+    // fog distances and lights go in the camera's units, which are world units over `resolution`. call before drawing
+    pub fn apply(&self, resolution: f32) {
+        unsafe {
+            DEFAULT_SHADER.use_shader();
+            let program = DEFAULT_SHADER.id;
+            gl::Uniform3f(location(program, "fog_color"), self.fog.color.x, self.fog.color.y, self.fog.color.z);
+            gl::Uniform1f(location(program, "fog_start"), self.fog.start / resolution);
+            gl::Uniform1f(location(program, "fog_end"), self.fog.end / resolution);
+
+            gl::Uniform1i(location(program, "point_light_count"), self.shown.len() as i32);
+            for (i, light) in self.shown.iter().enumerate() {
+                let position = light.position / resolution;
+                let color = light.emissive.color * light.emissive.strength;
+                gl::Uniform3f(location(program, &format!("point_light_position[{}]", i)), position.x, position.y, position.z);
+                gl::Uniform3f(location(program, &format!("point_light_color[{}]", i)), color.x, color.y, color.z);
+                gl::Uniform1f(location(program, &format!("point_light_radius[{}]", i)), light.emissive.radius / resolution);
+            }
+            gl::UseProgram(0);
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use std::f32::consts::TAU;
+
+use tiny_game_framework::imgui::{Condition, ProgressBar, Ui};
+
+const BAR_WIDTH: f32 = 300.0;
+const SPINNER_RADIUS: f32 = 16.0;
+const SPINNER_DOTS: usize = 8;
+// turns per second
+const SPINNER_SPEED: f32 = 1.0;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GameState {
+    // nothing is generated until a run is picked
+    MainMenu,
+    // the first quadrant and the assets are still on their way, nothing is simulated and input is ignored
+    Loading,
+    Playing,
+}
+
+pub struct LoadingScreen {
+    elapsed: f32,
+}
+
+impl LoadingScreen {
+    pub fn new() -> Self {
+        Self { elapsed: 0.0 }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.elapsed += dt;
+    }
+
+    // `progress` goes from 0 to 1, `status` is whatever is being waited on
+    pub fn draw(&self, ui: &Ui, progress: f32, status: &str) {
+        let [width, height] = ui.io().display_size;
+        let (cx, cy) = (width / 2.0, height / 2.0);
+
+        ui.window("loading")
+            .position([cx - BAR_WIDTH / 2.0, cy + SPINNER_RADIUS * 2.0], Condition::Always)
+            .no_decoration()
+            .no_inputs()
+            .bg_alpha(0.0)
+            .always_auto_resize(true)
+            .build(|| {
+                ProgressBar::new(progress.clamp(0.0, 1.0))
+                    .overlay_text(format!("{:.0}%", progress * 100.0))
+                    .size([BAR_WIDTH, 0.0])
+                    .build(ui);
+                ui.text(status);
+            });
+
+        // a ring of dots with the bright one going around, the rest trail behind it
+        let draw_list = ui.get_foreground_draw_list();
+        let head = self.elapsed * SPINNER_SPEED * SPINNER_DOTS as f32;
+        for i in 0..SPINNER_DOTS {
+            let angle = i as f32 / SPINNER_DOTS as f32 * TAU;
+            let behind = (head - i as f32).rem_euclid(SPINNER_DOTS as f32);
+            let alpha = 1.0 - behind / SPINNER_DOTS as f32;
+            let center = [cx + angle.cos() * SPINNER_RADIUS, cy - SPINNER_RADIUS + angle.sin() * SPINNER_RADIUS];
+            draw_list.add_circle(center, 3.0, [1.0, 1.0, 1.0, alpha]).filled(true).build();
+        }
+    }
+}
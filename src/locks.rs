@@ -0,0 +1,258 @@
+use std::collections::HashSet;
+
+use rapier3d::prelude::*;
+use tiny_game_framework::{glam::{vec3, Vec2, Vec3, Vec4}, Cuboid, Renderer};
+
+use crate::{
+    billboards::{Impostor, Sprite},
+    character_controller::PlayerInput,
+    coords::{self, TileCoord, WorldPos},
+    entity::{EntityId, EntityKind},
+    error::RendererExt,
+    highlight::Interactable,
+    inventory::{Inventory, Item},
+    patrol::WalkableGrid,
+    rapier_integration::{BodySpec, CollisionLayer, RapierPhysicsWorld},
+    world_text::WorldText,
+};
+
+const KEY_COLORS: [(&str, Vec4); 3] = [
+    ("red", Vec4::new(0.8, 0.15, 0.15, 1.0)),
+    ("blue", Vec4::new(0.2, 0.35, 0.9, 1.0)),
+    ("gold", Vec4::new(0.9, 0.75, 0.2, 1.0)),
+];
+const MAX_LOCKED_DOORS: usize = KEY_COLORS.len();
+// doors this close to the goal (in tiles) would end up in the boss arena's own doorways
+const GOAL_CLEARANCE: i32 = 4;
+// keys go at least this many tiles off the critical path, so finding them means exploring a side room
+const KEY_MIN_DETOUR: usize = 2;
+const KEY_SIZE: f32 = 30.0;
+// how far above the floor keys float
+const KEY_HOVER: f32 = 40.0;
+// letters on the sign hung on the front of each locked door, in world units
+const SIGN_SIZE: f32 = 24.0;
+
+pub fn key_color_name(key: usize) -> &'static str {
+    KEY_COLORS[key % KEY_COLORS.len()].0
+}
+
+fn key_color(key: usize) -> Vec4 {
+    KEY_COLORS[key % KEY_COLORS.len()].1
+}
+
+fn key_position(tile: TileCoord) -> Vec3 {
+    let floor = coords::floor_height() + coords::tile_size() / 2.0;
+    tile.world(floor + KEY_HOVER).into()
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum LockEvent {
+    KeyPickedUp(usize),
+    DoorOpened(usize),
+    // tried to open a door without its key
+    DoorLocked(usize),
+}
+
+pub struct LockedDoor {
+    // the key that opens it, also its index
+    pub key: usize,
+    pub tile: TileCoord,
+    entity: EntityId,
+    body: Option<RigidBodyHandle>,
+    mesh_name: String,
+    sign_name: String,
+}
+
+pub struct KeyPickup {
+    pub key: usize,
+    pub tile: TileCoord,
+    picked_up: bool,
+    mesh_name: String,
+}
+
+// locks doors along the way from the start to the goal and hides their keys in side rooms. every key can be
+// reached using only the keys before it, so the floor can always be finished
+pub struct LockSystem {
+    pub doors: Vec<LockedDoor>,
+    pub keys: Vec<KeyPickup>,
+    // per player, so holding the interact key doesn't keep trying the door
+    interact_held: Vec<bool>,
+}
+
+impl LockSystem {
+    // call once the floor has finished generating, `start` is where the players spawn
+    pub fn plan(rw: &mut RapierPhysicsWorld, r: &mut Renderer, text: &mut WorldText, walkable: &WalkableGrid, start: Vec3, goal: Vec3) -> Self {
+        let mut locks = Self { doors: Vec::new(), keys: Vec::new(), interact_held: Vec::new() };
+        let (start, goal) = (WorldPos(start).tile(), WorldPos(goal).tile());
+        let Some(critical_path) = walkable.path(start, goal, &HashSet::new()) else { return locks };
+
+        // tiles the goal can't be reached without going through, spread evenly along the way
+        let chokes: Vec<TileCoord> = critical_path.iter().copied()
+            .filter(|&tile| tile != start && (tile.x - goal.x).abs().max((tile.z - goal.z).abs()) > GOAL_CLEARANCE)
+            .filter(|&tile| walkable.path(start, goal, &HashSet::from([tile])).is_none())
+            .collect();
+        let count = chokes.len().min(MAX_LOCKED_DOORS);
+        let mut doors: Vec<TileCoord> = (0..count).map(|i| chokes[(i + 1) * chokes.len() / (count + 1)]).collect();
+        doors.dedup();
+
+        // the first key has to be out in the open, after that each one is ideally behind the door before it
+        let mut keys: Vec<TileCoord> = Vec::new();
+        let mut i = 0;
+        while i < doors.len() {
+            let blocked: HashSet<TileCoord> = doors[i..].iter().copied().collect();
+            let earlier: HashSet<TileCoord> = doors[i.saturating_sub(1)..].iter().copied().collect();
+            let reachable = walkable.distances(&[start], &blocked);
+            let reachable_before = walkable.distances(&[start], &earlier);
+            let detours = walkable.distances(&critical_path, &blocked);
+
+            let key = reachable.keys()
+                .filter(|tile| !keys.contains(tile))
+                .filter_map(|&tile| Some((tile, *detours.get(&tile)?)))
+                .filter(|&(_, detour)| detour >= KEY_MIN_DETOUR)
+                .max_by_key(|&(tile, detour)| (i > 0 && !reachable_before.contains_key(&tile), detour))
+                .map(|(tile, _)| tile)
+                .filter(|&tile| walkable.path(start, tile, &blocked).is_some());
+
+            match key {
+                Some(key) => {
+                    keys.push(key);
+                    i += 1;
+                }
+                // no side room to hide it in, so this door stays open
+                None => {
+                    doors.remove(i);
+                }
+            }
+        }
+
+        for (key, (&door, &key_tile)) in doors.iter().zip(keys.iter()).enumerate() {
+            // the sign goes on the side the players walk up to it from
+            let before = critical_path.iter().position(|&tile| tile == door)
+                .and_then(|i| critical_path.get(i.checked_sub(1)?))
+                .copied()
+                .unwrap_or(door);
+            locks.spawn_door(rw, r, text, key, door, before);
+            locks.spawn_key(r, key, key_tile);
+        }
+
+        locks
+    }
+
+    fn spawn_door(&mut self, rw: &mut RapierPhysicsWorld, r: &mut Renderer, text: &mut WorldText, key: usize, tile: TileCoord, facing: TileCoord) {
+        // walls are centered on the tile rather than sitting on the floor
+        let position: Vec3 = tile.world(0.0).into();
+        let size = vec3(coords::tile_size(), coords::wall_height(), coords::tile_size());
+        let body = rw.add_body(position, &BodySpec::cuboid(size / 2.0)
+            .body_type(RigidBodyType::Fixed)
+            .layer(CollisionLayer::StaticWorld));
+        let entity = EntityId::new(EntityKind::Door);
+        rw.tag_body(body, entity);
+
+        let mesh_name = format!("LOCKED_DOOR_MESH{}", key);
+        let mut mesh = Cuboid::new(size, key_color(key) * 0.6 + Vec4::W * 0.4).mesh();
+        mesh.position = position;
+        mesh.setup_mesh();
+        if let Err(e) = r.try_add_mesh(&mesh_name, mesh) {
+            log::warn!("{}", e);
+        }
+
+        // a bit out from the door's face so it isn't drawn into it
+        let normal = Vec3::new((facing.x - tile.x) as f32, 0.0, (facing.z - tile.z) as f32);
+        let sign_name = format!("DOOR_SIGN{}", key);
+        let sign_position = position + normal * (coords::tile_size() / 2.0 + 1.0);
+        text.place_sign(&sign_name, &Item::Key(key).name(), sign_position, normal, SIGN_SIZE, key_color(key));
+
+        self.doors.push(LockedDoor { key, tile, entity, body: Some(body), mesh_name, sign_name });
+    }
+
+    fn spawn_key(&mut self, r: &mut Renderer, key: usize, tile: TileCoord) {
+        let mesh_name = format!("KEY_MESH{}", key);
+        let mut mesh = Cuboid::new(Vec3::splat(KEY_SIZE), key_color(key)).mesh();
+        mesh.position = key_position(tile);
+        mesh.setup_mesh();
+        if let Err(e) = r.try_add_mesh(&mesh_name, mesh) {
+            log::warn!("{}", e);
+        }
+
+        self.keys.push(KeyPickup { key, tile, picked_up: false, mesh_name });
+    }
+
+    // the keys still lying around
+    pub fn impostors(&self) -> impl Iterator<Item = Impostor<'_>> {
+        self.keys.iter().filter(|key| !key.picked_up).map(|key| Impostor {
+            mesh_name: &key.mesh_name,
+            position: key_position(key.tile),
+            sprite: Sprite::Key,
+            size: Vec2::splat(KEY_SIZE),
+            color: key_color(key.key),
+        })
+    }
+
+    // the doors still locked
+    pub fn interactables(&self) -> impl Iterator<Item = Interactable<'_>> {
+        self.doors.iter().filter(|door| door.body.is_some()).map(|door| Interactable { entity: door.entity, mesh_name: &door.mesh_name })
+    }
+
+    // keys of the doors that have been opened so far
+    pub fn opened(&self) -> Vec<usize> {
+        self.doors.iter().filter(|door| door.body.is_none()).map(|door| door.key).collect()
+    }
+
+    // puts a saved run back the way it was, `opened` doors come out and the keys that were `found` aren't lying
+    // around anymore. doesn't emit anything, it all happened before the save
+    pub fn restore(&mut self, rw: &mut RapierPhysicsWorld, r: &mut Renderer, text: &mut WorldText, opened: &[usize], found: &[usize]) {
+        for door in self.doors.iter_mut().filter(|door| opened.contains(&door.key)) {
+            if let Some(body) = door.body.take() {
+                rw.remove_rigidbody(body);
+                r.destroy_mesh(&door.mesh_name).ok();
+                text.remove_sign(&door.sign_name);
+            }
+        }
+        for key in self.keys.iter_mut().filter(|key| !key.picked_up && found.contains(&key.key)) {
+            key.picked_up = true;
+            r.destroy_mesh(&key.mesh_name).ok();
+        }
+    }
+
+    // walking onto a key picks it up, interacting next to a door tries to open it. events come back with the
+    // index of the player in `players` they happened to
+    pub fn update(&mut self, rw: &mut RapierPhysicsWorld, r: &mut Renderer, text: &mut WorldText, players: &[Vec3], inputs: &[PlayerInput], inventories: &mut [Inventory]) -> Vec<(usize, LockEvent)> {
+        let mut events = Vec::new();
+        self.interact_held.resize(players.len(), false);
+
+        for (player, (&position, input)) in players.iter().zip(inputs.iter()).enumerate() {
+            let tile = WorldPos(position).tile();
+
+            for key in self.keys.iter_mut().filter(|key| !key.picked_up && key.tile == tile) {
+                key.picked_up = true;
+                r.destroy_mesh(&key.mesh_name).ok();
+                inventories[player].add(Item::Key(key.key));
+                events.push((player, LockEvent::KeyPickedUp(key.key)));
+            }
+
+            let pressed = input.interact && !self.interact_held[player];
+            self.interact_held[player] = input.interact;
+            if !pressed {
+                continue;
+            }
+
+            // only the doors right next to the player
+            let Some(door) = self.doors.iter_mut()
+                .filter(|door| door.body.is_some())
+                .find(|door| (door.tile.x - tile.x).abs() + (door.tile.z - tile.z).abs() <= 1) else { continue };
+
+            if inventories[player].remove(Item::Key(door.key)) {
+                if let Some(body) = door.body.take() {
+                    rw.remove_rigidbody(body);
+                }
+                r.destroy_mesh(&door.mesh_name).ok();
+                text.remove_sign(&door.sign_name);
+                events.push((player, LockEvent::DoorOpened(door.key)));
+            } else {
+                events.push((player, LockEvent::DoorLocked(door.key)));
+            }
+        }
+
+        events
+    }
+}
@@ -0,0 +1,73 @@
+use crate::{enemies::EnemyKind, equipment::Equipment, rng};
+
+// coins in a broken pot or crate, anywhere from one up to this
+const CONTAINER_MAX_COINS: usize = 6;
+// odds of there being something in it besides coins
+const CONTAINER_ITEM_CHANCE: f32 = 0.08;
+
+pub struct LootEntry {
+    pub item: Equipment,
+    // how likely it is next to the rest of the table, not a percentage
+    pub weight: u32,
+    // doesn't show up on floors above this one
+    pub min_floor: u32,
+}
+
+// what merchants stock, the good stuff only turns up deeper down
+pub const MERCHANT_LOOT: &[LootEntry] = &[
+    LootEntry { item: Equipment::Sword, weight: 10, min_floor: 1 },
+    LootEntry { item: Equipment::Spear, weight: 8, min_floor: 1 },
+    LootEntry { item: Equipment::Crossbow, weight: 6, min_floor: 1 },
+    LootEntry { item: Equipment::LeatherArmor, weight: 10, min_floor: 1 },
+    LootEntry { item: Equipment::SwiftCharm, weight: 5, min_floor: 1 },
+    LootEntry { item: Equipment::PlateArmor, weight: 4, min_floor: 2 },
+    LootEntry { item: Equipment::RageCharm, weight: 3, min_floor: 3 },
+];
+
+// the odd thing left in a pot or crate, nothing too good
+pub const CONTAINER_LOOT: &[LootEntry] = &[
+    LootEntry { item: Equipment::LeatherArmor, weight: 6, min_floor: 1 },
+    LootEntry { item: Equipment::SwiftCharm, weight: 4, min_floor: 1 },
+    LootEntry { item: Equipment::Spear, weight: 3, min_floor: 1 },
+    LootEntry { item: Equipment::RageCharm, weight: 1, min_floor: 2 },
+];
+
+// `count` weighted picks from what can show up on `floor`, the same item can come up more than once
+pub fn roll(table: &[LootEntry], floor: u32, count: usize) -> Vec<Equipment> {
+    let available: Vec<&LootEntry> = table.iter().filter(|entry| entry.min_floor <= floor).collect();
+    let total: u32 = available.iter().map(|entry| entry.weight).sum();
+    if total == 0 {
+        return Vec::new();
+    }
+
+    (0..count).filter_map(|_| {
+        let mut pick = rng::usize(..total as usize) as u32;
+        available.iter().find(|entry| {
+            if pick < entry.weight {
+                return true;
+            }
+            pick -= entry.weight;
+            false
+        }).map(|entry| entry.item)
+    }).collect()
+}
+
+// what breaking a pot or crate on `floor` turns up, always a few coins and sometimes something out of
+// `CONTAINER_LOOT`
+pub fn container_drop(floor: u32) -> (u32, Option<Equipment>) {
+    let coins = rng::usize(1..=CONTAINER_MAX_COINS) as u32;
+    let item = if rng::f32() < CONTAINER_ITEM_CHANCE { roll(CONTAINER_LOOT, floor, 1).pop() } else { None };
+    (coins, item)
+}
+
+// coins dropped for a kill, they go straight to whoever landed it
+pub fn kill_coins(kind: EnemyKind) -> u32 {
+    match kind {
+        EnemyKind::Grunt => 5,
+        EnemyKind::Brute => 15,
+        EnemyKind::Boss => 100,
+        EnemyKind::Archer => 8,
+        EnemyKind::Tank => 25,
+        EnemyKind::Swarmer => 2,
+    }
+}
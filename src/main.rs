@@ -1,101 +1,1201 @@
 use std::{sync::Arc, time::{Duration, Instant}, sync::Mutex as StdMutex};
 
-use character_controller::Player;
-use generation::Canvas;
+use achievements::AchievementTracker;
+use animation::Animator;
+use assets::AssetManager;
+use billboards::Billboards;
+use boss::{BossEncounter, BossState};
+use camera::{CameraShake, PlayerCamera};
+use character_controller::{Player, PlayerInput, Surface};
+use clap::Parser;
+use chat::{draw_name_tags, Chat};
+use chunks::ChunkManager;
+use config::{load_archetypes, Config};
+use coords::{ChunkCoord, WorldPos};
+use cutscene::{CameraSequence, CutscenePlayer};
+use daily::{DailyRun, Leaderboard, DAILY_DIFFICULTY};
+use decals::{DecalKind, DecalSystem};
+use deltas::{Modification, WorldDeltas};
+use destruction::DestructionSystem;
+use director::SpawnDirector;
+use dungeon::DungeonConfig;
+use enemies::{EnemyEvent, EnemySystem};
+use equipment::{AttackKind, Loadout};
+use events::{EventBus, GameEvent};
+use entity::EntityKind;
+use error::RendererExt;
+use feedback::{Feedback, FeedbackEvent};
+use generator::{BuildContext, UploadBudget};
+use grapple::GrapplingHook;
 
-use generator::{gen_maze_async, new_quadrant};
+use net::{NetSession, WorldEvent};
+use headless::GenerateOptions;
+use debug::DebugOverlay;
+use highlight::Highlight;
+use hud::Hud;
+use impacts::Impacts;
+use inventory::{Inventory, Item};
+use lighting::Lighting;
+use loading::{GameState, LoadingScreen};
+use menu::{format_time, MainMenu, MenuAction};
+use merchant::{Merchant, TradeEvent};
+use locks::{LockEvent, LockSystem};
 use once_cell::sync::Lazy;
-use rapier_integration::RapierPhysicsWorld;
+use particles::{ParticlePreset, ParticleSystem};
+use perception::NoiseBus;
+use physics_thread::{Blend, FixedSteps, PhysicsThread};
+use postprocess::PostProcess;
+use platforms::{MovingPlatform, PlatformLoopMode};
+use objectives::{ObjectiveKind, Objectives};
+use profiler::profile_scope;
+use progression::Progression;
+use props::{PropSystem, ScatterContext};
+use rapier_integration::{RapierPhysicsWorld, FIXED_TIMESTEP};
+use save::{Autosave, RunProgress, SaveData};
+use shadows::{BlobShadows, ShadowCaster};
+use shutdown::Shutdown;
+use sky::SkyRenderer;
 use tiny_game_framework::{
-    gl::{Clear, ClearColor, COLOR_BUFFER_BIT, DEPTH_BUFFER_BIT}, glam::{vec2, vec3, vec4, Vec3, Vec3A, Vec4}, glfw::{self, Key}, rand_vec3, Cuboid, EventLoop, Light, Quad, Renderer, Sphere
+    gl::{Clear, ClearColor, COLOR_BUFFER_BIT, DEPTH_BUFFER_BIT}, glam::{vec2, vec3, vec4, Quat, Vec3, Vec3A, Vec4}, glfw::{self, Key}, rand_vec3, Cuboid, EventLoop, Light, Quad, Renderer, Sphere
 };
 use tokio::sync::{mpsc, Mutex};
+use teleporters::TeleporterSystem;
+use throwing::{Throwables, ThrownEvent, TrajectoryPreview};
+use time::TimeScale;
+use traps::{TrapEvent, TrapSystem};
+use world_text::WorldText;
 
-mod generation;
+mod achievements;
+mod ai;
+mod animation;
+mod assets;
+mod billboards;
+mod boss;
+mod dungeon;
 mod generator;
+mod geometry;
+mod grapple;
+mod headless;
+mod highlight;
+mod hud;
+mod impacts;
+mod inventory;
+mod lighting;
+mod loading;
+mod locks;
+mod loot;
+mod menu;
+mod merchant;
+mod model;
+mod navmesh;
+mod net;
+mod objectives;
 mod rapier_integration;
+mod camera;
+mod capture;
 mod character_controller;
+mod chat;
+mod chunks;
+mod config;
+mod console;
+mod coords;
+mod cutscene;
+mod daily;
+mod debug;
+mod decals;
+mod deltas;
+mod destruction;
+mod director;
+mod enemies;
+mod equipment;
+mod entity;
+mod events;
+mod error;
+mod feedback;
+mod particles;
+mod physics_thread;
+mod postprocess;
+mod patrol;
+mod perception;
+mod platforms;
+mod profiler;
+mod progression;
+mod props;
+mod render_layers;
+mod rng;
+mod save;
+mod shader;
+mod shadows;
+mod shutdown;
+mod sky;
+mod teleporters;
+mod throwing;
+mod time;
+mod traps;
+mod versioning;
+mod world_text;
 
-const GRAVITY: f32 = 10.;
+// how far down from a hit to look for floor to put blood on
+const BLOOD_REACH: f32 = 300.0;
+// enemies this close to whatever got hit come to look
+const ATTACK_NOISE_RADIUS: f32 = 1000.0;
+// how far footsteps carry when running at full speed
+const FOOTSTEP_NOISE_RADIUS: f32 = 500.0;
+// something thrown landing is about as loud as a hit, that's what makes it a distraction
+const THROW_NOISE_RADIUS: f32 = 1000.0;
+// how far the hardest crash of something landing carries, softer ones carry less
+const IMPACT_NOISE_RADIUS: f32 = 800.0;
+// thrown things start this far in front of the player so they don't hit the capsule on the way out
+const THROW_OFFSET: f32 = 60.0;
+const BOMB_RADIUS: f32 = 400.0;
+const BOMB_FORCE: f32 = 4000.0;
+const BOMB_DAMAGE: f32 = 60.0;
+// how hard a player right next to a bomb gets thrown, less further out
+const BOMB_KNOCKBACK: f32 = 600.0;
+const BOMB_TRAUMA: f32 = 0.8;
+const EXPLOSION_SPARKS: usize = 60;
+const EXPLOSION_DUST: usize = 40;
+// landing faster than this shakes the camera, scaling up to full trauma at twice the speed
+const HEAVY_LANDING_SPEED: f32 = 8.0;
+const STOMP_TRAUMA: f32 = 0.6;
+// how flat the player mesh gets on a heavy landing
+const LANDING_SQUASH: f32 = 0.3;
+// stomps further away than this aren't felt
+const STOMP_RADIUS: f32 = 2000.0;
+const HIT_SPARKS: usize = 2;
+const WALL_BREAK_DUST: usize = 40;
+const PROP_BREAK_DUST: usize = 15;
+const TELEPORT_SPARKS: usize = 30;
+// how long a lockstep frame waits on late inputs before giving up and trying again next frame
+const LOCKSTEP_WAIT: Duration = Duration::from_millis(50);
+// bullet time after dodging a hit at the last moment, in real seconds
+const DODGE_SLOW_MOTION: f32 = 0.3;
+const DODGE_SLOW_MOTION_DURATION: f32 = 1.5;
+// fastest daily runs listed in the main menu
+const DAILY_LEADERBOARD_SIZE: usize = 5;
+
+#[derive(Parser)]
+struct Args {
+    #[arg(long, help = "generate a dungeon without opening a window, then exit")]
+    generate: bool,
+    #[arg(long, help = "random when not set")]
+    seed: Option<u64>,
+    #[arg(long, help = "width and height of the canvas in tiles, the configured quadrant size when not set")]
+    size: Option<u32>,
+    #[arg(long, help = "canvas width in tiles, overrides --size")]
+    width: Option<u32>,
+    #[arg(long, help = "canvas height in tiles, overrides --size")]
+    height: Option<u32>,
+    #[arg(long, help = "image to learn the patterns from, the built in rooms when not set")]
+    sample: Option<String>,
+    #[arg(long, default_value = "dungeon.png", help = "where the png goes, the stats report is written next to it as json")]
+    output: String,
+}
 
 #[tokio::main]
 async fn main() {
+    let args = Args::parse();
+    let config = Config::load();
+    coords::configure(&config.generation);
+
+    if args.generate {
+        let size = args.size.unwrap_or_else(coords::quadrant_tiles);
+        let options = GenerateOptions {
+            seed: args.seed.unwrap_or_else(|| fastrand::u64(..)),
+            width: args.width.unwrap_or(size),
+            height: args.height.unwrap_or(size),
+            sample: args.sample,
+            output: args.output,
+        };
+        match headless::generate(&options) {
+            Ok(stats) => println!(
+                "seed {} -> {} ({} floor tiles, {:.0}% walkable, {} regions) in {}ms",
+                stats.seed, options.output, stats.floor, stats.floor_ratio * 100.0, stats.regions, stats.elapsed_ms,
+            ),
+            Err(e) => {
+                eprintln!("generation failed: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
     let resolution = vec2(800., 800.);
     let mut el = EventLoop::new(resolution.x as u32, resolution.y as u32);
     let mut renderer = Renderer::new();
     let mut rapier_world = RapierPhysicsWorld::new();
+    rapier_world.configure(&config.physics);
 
-    renderer.add_texture("test".to_string(), "src/images/tex.png".to_string());
+    let mut assets = AssetManager::new();
+    let player_texture = assets.load_texture("src/images/tex.png");
     renderer.add_light("l1", Light { color: Vec3::ONE, position: vec3(1.0, 1.0, 1.0)});
+    let mut lighting = Lighting::new(&config.lighting);
+    if let Err(e) = lighting.install() {
+        log::warn!("no fog: {}", e);
+    }
+    let mut post = PostProcess::new(config.post_processing.clone());
+    let mut sky = SkyRenderer::new();
+    let mut billboards = Billboards::new();
+    let mut world_text = WorldText::new();
+    let mut highlight = Highlight::new();
+    let mut shadows = BlobShadows::new();
     
     el.window.set_cursor_mode(glfw::CursorMode::Disabled);
 
     let mut player_mesh = Cuboid::new(vec3(100.0, 100.0, 100.0), vec4(1., 1., 1., 1.)).mesh();
-    player_mesh.set_texture("test", &renderer);
     player_mesh.set_shader_type(&tiny_game_framework::ShaderType::Full);
     player_mesh.setup_mesh();
-    renderer.add_mesh("player", player_mesh).unwrap();
+    if let Err(e) = renderer.try_add_mesh("player", player_mesh) {
+        log::warn!("{}", e);
+    }
+
+    let mut net = match NetSession::start(&config.net) {
+        Ok(net) => net,
+        Err(e) => {
+            log::warn!("couldn't start {:?} networking, playing offline: {}", config.net.mode, e);
+            None
+        }
+    };
+    // lockstep peers all run the full simulation from the same inputs, otherwise clients mirror the host's
+    // enemies and boss fight instead of simulating their own
+    let lockstep = net.as_ref().map_or(false, |net| net.lockstep);
+    let authoritative = net.as_ref().map_or(true, |net| net.is_host() || net.lockstep);
+    let mut physics_thread = (config.physics.threaded && !lockstep).then(PhysicsThread::spawn);
+    let seed = net.as_ref().map_or_else(|| fastrand::u64(..), |net| net.seed);
+    rng::seed(seed);
 
-    let mut receiver = new_quadrant(); // generate new maze quadrant
+    let mut shutdown = Shutdown::new();
+    let mut chunks = ChunkManager::new(seed, DungeonConfig::quadrant(coords::settings())).with_speculation(!lockstep);
+    // a networked game already agreed on a seed, so it skips the menu and starts right away on the configured
+    // difficulty, which every peer has to share
+    let mut run_start = net.as_ref().map(|_| (seed, config.difficulty.preset));
     
     // defining game state variables ~~~~~
     // ~~~~~
 
-    let mut player = Player::setup(&mut rapier_world, &mut renderer);
+    // everyone this machine simulates, in player id order. just us unless it's a lockstep game
+    let own_id = net.as_ref().map_or(0, |net| net.player_id);
+    let player_ids: Vec<u32> = match net.as_ref().filter(|net| net.lockstep) {
+        Some(net) => (0..net.lockstep_players).collect(),
+        None => vec![own_id],
+    };
+    let me = player_ids.iter().position(|&id| id == own_id).unwrap_or(0);
+    let players: Result<Vec<Player>, _> = player_ids.iter().map(|_| Player::setup(&mut rapier_world, &mut renderer)).collect();
+    let mut players = match players {
+        Ok(players) => players,
+        Err(e) => {
+            log::error!("couldn't set up the players: {}", e);
+            return;
+        }
+    };
+    let mut progressions: Vec<Progression> = player_ids.iter().map(|_| Progression::new(1.0)).collect();
+    let mut inventories: Vec<Inventory> = player_ids.iter().map(|_| Inventory::new()).collect();
+    let mut loadouts: Vec<Loadout> = player_ids.iter().map(|_| Loadout::new()).collect();
+    let mut grapples: Vec<GrapplingHook> = (0..player_ids.len()).map(GrapplingHook::new).collect();
+    for (player, progression) in players.iter_mut().zip(progressions.iter()) {
+        player.apply_stats(progression.stats());
+    }
+    for (i, id) in player_ids.iter().enumerate().filter(|&(i, _)| i != me) {
+        let mut mesh = Cuboid::new(vec3(100.0, 100.0, 100.0), vec4(0.3, 0.6, 1.0, 1.0)).mesh();
+        mesh.position = players[i].position();
+        mesh.setup_mesh();
+        if let Err(e) = renderer.try_add_mesh(&format!("REMOTE_PLAYER_MESH{}", id), mesh) {
+            log::warn!("{}", e);
+        }
+    }
+
+    let mut hud = Hud::new();
+    let mut debug_overlay = DebugOverlay::new();
+    let mut capture = capture::Capture::new();
+    let mut feedback = Feedback::new();
+    let mut chat = Chat::new();
+    let mut player_camera = PlayerCamera::new(config.camera.clone());
+    let mut cutscenes = CutscenePlayer::default();
+    let mut shake = CameraShake::new(&config.camera);
+    let mut player_animator = Animator::new();
+    let mut time_scale = TimeScale::new();
+
+    let mut traps = TrapSystem::new();
+    let mut teleporters = TeleporterSystem::new();
+    let mut destruction = DestructionSystem::new();
+    let mut world_deltas = WorldDeltas::default();
+    let mut props = PropSystem::new();
+    let mut particles = ParticleSystem::new();
+    let mut impacts = Impacts::default();
+    let mut decals = DecalSystem::new();
+    let mut throwables = Throwables::default();
+    let mut trajectory_preview = TrajectoryPreview::default();
+    let mut enemies = EnemySystem::new(config.ai.clone(), load_archetypes());
+    let mut noises = NoiseBus::default();
+    let mut bus = EventBus::default();
+    let mut save = SaveData::load();
+    let mut autosave = Autosave::new();
+    // how far the slot loaded from the menu had got, put back as the floor comes in
+    let mut resume: Option<RunProgress> = None;
+    let mut achievements = AchievementTracker::new(&save);
+    let mut leaderboard = Leaderboard::load();
+    let mut daily_run: Option<DailyRun> = None;
+    let mut objectives = Objectives::new();
+    let mut boss: Option<BossEncounter> = None;
+    let mut locks: Option<LockSystem> = None;
+    let mut merchant: Option<Merchant> = None;
+    let mut director = SpawnDirector::new(config.spawning.clone(), "dungeon");
 
-    while !el.window.should_close() {
+    let mut platforms = vec![
+        MovingPlatform::new(
+            &mut rapier_world,
+            &mut renderer,
+            "elevator",
+            vec![vec3(-300.0, -100.0, -300.0), vec3(-300.0, 300.0, -300.0)],
+            vec3(200.0, 20.0, 200.0),
+            50.0,
+            PlatformLoopMode::PingPong,
+        ),
+        // going round at the height the elevator tops out at
+        MovingPlatform::new(
+            &mut rapier_world,
+            &mut renderer,
+            "ferry",
+            vec![vec3(-300.0, 300.0, 100.0), vec3(100.0, 300.0, 100.0), vec3(100.0, 300.0, -300.0)],
+            vec3(200.0, 20.0, 200.0),
+            80.0,
+            PlatformLoopMode::Loop,
+        ),
+    ];
+    // a torch riding the elevator
+    particles.attach_emitter(ParticlePreset::TorchFlame, platforms[0].handle, vec3(80.0, 40.0, 80.0));
+
+    // nothing moves until the first quadrant is fully built, which also means every lockstep peer starts from
+    // the exact same world
+    let mut state = if net.is_some() { GameState::Loading } else { GameState::MainMenu };
+    let mut menu = MainMenu::new(args.seed, config.difficulty.preset);
+    let mut loading_screen = LoadingScreen::new();
+    let mut tiles_built = 0;
+    let mut steps = FixedSteps::default();
+
+    while !el.window.should_close() && !shutdown.is_requested() {
+        profiler::begin_frame();
         el.update();
+        assets.update();
+
+        // everything the difficulty touches has to be in place before the first quadrant is requested
+        if let Some((seed, difficulty)) = run_start.take() {
+            let scaling = difficulty.scaling(&config.difficulty.custom);
+            coords::set_quadrant_tiles((config.generation.quadrant_size as f32 * scaling.dungeon_size).round() as u32);
+            director.density = scaling.enemy_density;
+            enemies.damage_scale = scaling.enemy_damage;
+            for (player, progression) in players.iter_mut().zip(progressions.iter_mut()) {
+                *progression = Progression::new(scaling.player_health);
+                player.apply_stats(progression.stats());
+            }
+            for (inventory, loadout) in inventories.iter_mut().zip(loadouts.iter_mut()) {
+                *inventory = Inventory::new();
+                *loadout = Loadout::new();
+                for equipment in equipment::STARTING_KIT {
+                    inventory.add(Item::Equipment(equipment));
+                    loadout.equip(equipment);
+                }
+                inventory.add_coins(equipment::STARTING_COINS);
+            }
+            world_deltas = resume.as_ref().map(|progress| progress.world.clone()).unwrap_or_default();
+            // a loaded slot picks up from its last autosave instead of the starting kit
+            if let Some(progress) = resume.as_ref() {
+                progressions[me].level = progress.level.max(1);
+                progressions[me].xp = progress.xp;
+                players[me].apply_stats(progressions[me].stats());
+                inventories[me] = Inventory::new();
+                loadouts[me] = Loadout::new();
+                for &item in &progress.items {
+                    inventories[me].add(item);
+                    if let Item::Equipment(equipment) = item {
+                        loadouts[me].equip(equipment);
+                    }
+                }
+                inventories[me].add_coins(progress.coins);
+                director.floor = progress.floor.max(1);
+            }
+            autosave.reset();
+            let items = inventories[me].items();
+            hud.hotbar.items = std::array::from_fn(|slot| items.get(slot).map(Item::name));
+
+            rng::seed(seed);
+            chunks = ChunkManager::new(seed, DungeonConfig {
+                trap_frequency: scaling.trap_frequency,
+                ..DungeonConfig::quadrant(coords::settings())
+            }).with_speculation(!lockstep);
+            chunks.request(ChunkCoord::default()); // generate new maze quadrant
+            cutscenes.play(CameraSequence::arrival(players[me].position(), renderer.camera.front), renderer.camera.yaw, renderer.camera.pitch);
+        }
         
-        gen_maze_async(&mut receiver, &mut renderer, &mut rapier_world).await;
+        {
+            profile_scope!("generation");
+            let positions: Vec<Vec3> = players.iter().map(|player| player.position()).collect();
+            chunks.update(&positions, players[me].position(), players[me].velocity(&rapier_world), &mut shutdown);
+            let upload_time = (!lockstep).then_some(config.generation.upload_ms_per_frame);
+            let mut budget = UploadBudget::new(config.generation.upload_meshes_per_frame, upload_time);
+            let mut context = BuildContext {
+                renderer: &mut renderer,
+                rw: &mut rapier_world,
+                traps: &mut traps,
+                teleporters: &mut teleporters,
+                destruction: &mut destruction,
+                director: &mut director,
+            };
+            tiles_built += chunks.build(&mut context, &mut budget).await;
+            for chunk in chunks.take_finished() {
+                world_deltas.apply(chunk, &mut rapier_world, &mut renderer, &mut destruction, &mut traps);
+                if let Some(layout) = chunks.layout(chunk) {
+                    let context = ScatterContext { rw: &mut rapier_world, r: &mut renderer, lighting: &mut lighting, director: &mut director, deltas: &world_deltas };
+                    props.scatter(context, chunk, layout);
+                }
+            }
+
+            for (chunk, footprint) in chunks.evict(config.generation.memory_budget_mb << 20, &positions) {
+                footprint.unload(&mut rapier_world, &mut renderer);
+                destruction.unload(chunk);
+                enemies.unload(&mut rapier_world, &mut renderer, chunk);
+                props.unload(&mut rapier_world, &mut renderer, &mut lighting, chunk);
+                director.unload_chunk(chunk);
+            }
+        }
+        let generated = chunks.is_done(ChunkCoord::default());
+        if generated && boss.is_none() {
+            boss = BossEncounter::plan(&mut rapier_world, director.walkable());
+        }
+        if let (Some(boss), None) = (boss.as_ref(), locks.as_ref()) {
+            // everyone starts at the origin
+            let mut planned = LockSystem::plan(&mut rapier_world, &mut renderer, &mut world_text, director.walkable(), Vec3::ZERO, boss.center);
+            objectives.plan(planned.doors.iter().map(|door| door.key), boss.center);
+            if let Some(progress) = resume.take() {
+                // a key that opened its door is gone from the inventory but was still found
+                let found: Vec<usize> = progress.items.iter()
+                    .filter_map(|item| match item {
+                        Item::Key(key) => Some(*key),
+                        Item::Equipment(_) => None,
+                    })
+                    .chain(progress.opened_doors.iter().copied())
+                    .collect();
+                planned.restore(&mut rapier_world, &mut renderer, &mut world_text, &progress.opened_doors, &found);
+                for &key in &found {
+                    objectives.complete(ObjectiveKind::FindKey(key));
+                }
+            }
+            locks = Some(planned);
+        }
+        // trades only ever change the local player's inventory and loadout and never go over the wire, so a
+        // lockstep game has no merchant rather than peers that disagree about what everyone is wearing
+        if let (Some(boss), None, false) = (boss.as_ref(), merchant.as_ref(), lockstep) {
+            let floor = director.floor;
+            merchant = Merchant::plan(&mut rapier_world, &mut renderer, &mut director, &[Vec3::ZERO, boss.center], floor);
+        }
+
+        if state == GameState::MainMenu {
+            el.window.set_cursor_mode(glfw::CursorMode::Normal);
+            let frame = el.ui.frame(&mut el.window);
+            let daily_best = leaderboard.best(daily::today(), DAILY_LEADERBOARD_SIZE);
+            let action = menu.draw(frame, &save.slots, &daily_best, &mut player_camera.settings, &mut post.settings);
+            resume = None;
+            unsafe {
+                Clear(COLOR_BUFFER_BIT | DEPTH_BUFFER_BIT);
+                ClearColor(0.05, 0.05, 0.08, 1.0);
+                el.ui.draw();
+            }
+
+            let (seed, difficulty) = match action {
+                Some(MenuAction::NewRun { seed, difficulty }) => {
+                    let seed = seed.unwrap_or_else(|| fastrand::u64(..));
+                    save.active = Some(save.new_slot(seed, difficulty));
+                    (seed, difficulty)
+                }
+                // loading a daily run would let it be retried with the clock reset, so it doesn't get a slot
+                Some(MenuAction::DailyRun) => {
+                    let run = DailyRun::start();
+                    let seed = run.seed();
+                    daily_run = Some(run);
+                    save.active = None;
+                    (seed, DAILY_DIFFICULTY)
+                }
+                Some(MenuAction::Load(slot)) => match save.slots.get(slot) {
+                    Some(loaded) => {
+                        resume = Some(loaded.run.progress.clone());
+                        save.active = Some(slot);
+                        (loaded.run.seed, loaded.run.difficulty)
+                    }
+                    None => continue,
+                },
+                Some(MenuAction::CopySlot(slot)) => {
+                    save.copy_slot(slot);
+                    if let Err(e) = save.write() {
+                        log::warn!("couldn't write the save: {}", e);
+                    }
+                    continue;
+                }
+                Some(MenuAction::DeleteSlot(slot)) => {
+                    save.delete_slot(slot);
+                    if let Err(e) = save.write() {
+                        log::warn!("couldn't write the save: {}", e);
+                    }
+                    continue;
+                }
+                Some(MenuAction::Quit) => {
+                    el.window.set_should_close(true);
+                    continue;
+                }
+                None => continue,
+            };
+
+            run_start = Some((seed, difficulty));
+            el.window.set_cursor_mode(glfw::CursorMode::Disabled);
+            state = GameState::Loading;
+            continue;
+        }
+
+        if state == GameState::Loading {
+            if generated && assets.progress().is_done() {
+                if let (Some(texture), Some(mesh)) = (assets.get(player_texture), renderer.get_mesh_mut("player")) {
+                    mesh.texture = texture.id;
+                }
+                state = GameState::Playing;
+            } else {
+                // assets and tiles weighted the same, one of each is one step
+                let progress = assets.progress();
+                let quadrant_tiles = (coords::quadrant_tiles() * coords::quadrant_tiles()) as usize;
+                let done = progress.loaded + progress.failed + tiles_built.min(quadrant_tiles);
+                let fraction = done as f32 / (progress.total + quadrant_tiles) as f32;
+                let status = match assets.loading() {
+                    Some(path) => format!("loading {}", path),
+                    None => "generating the dungeon".to_string(),
+                };
+
+                loading_screen.update(el.dt);
+                let frame = el.ui.frame(&mut el.window);
+                loading_screen.draw(frame, fraction, &status);
+                unsafe {
+                    Clear(COLOR_BUFFER_BIT | DEPTH_BUFFER_BIT);
+                    ClearColor(0.05, 0.05, 0.08, 1.0);
+                    el.ui.draw();
+                }
+                continue;
+            }
+        }
         
         renderer.camera.mouse_callback(el.event_handler.mouse_pos.x, el.event_handler.mouse_pos.y, &el.window);
         renderer.camera.input(&el.window, &el.window.glfw);
+
+        // keys typed into the chat shouldn't also walk the player around, and nobody moves during a cutscene
+        let frame_dt = el.dt;
+        chat.update(&mut el, frame_dt);
+        let local_input = if chat.open || cutscenes.is_playing() {
+            PlayerInput { look: renderer.camera.front.to_array(), ..Default::default() }
+        } else {
+            PlayerInput::read(&mut el, renderer.camera.front)
+        };
+        // clicking around the trade window shouldn't swing at anything
+        let trading = merchant.as_ref().is_some_and(|merchant| merchant.open);
+        let local_input = PlayerInput {
+            weapon: hud.hotbar.selected as u8,
+            attack: local_input.attack && !trading,
+            throw: local_input.throw && !trading,
+            ..local_input
+        };
+
+        // in lockstep the simulation only moves once everyone's input for the tick is in
+        let inputs: Vec<PlayerInput> = match net.as_mut().filter(|net| net.lockstep) {
+            Some(net) => {
+                let Some(inputs) = net.wait_for_tick(LOCKSTEP_WAIT) else { continue };
+                net.submit_input(local_input);
+                inputs.into_iter().map(|(_, input)| input).collect()
+            }
+            None => vec![local_input],
+        };
+        // lockstep peers have to take the exact same steps, so time only bends when nobody else is simulating
+        time_scale.update(frame_dt);
+        let dt = if lockstep { FIXED_TIMESTEP } else { time_scale.apply(el.dt) };
         
-        
+        hud.update(&mut el, frame_dt);
+        debug_overlay.update(&mut el);
+        let capture_request = capture.update(&mut el);
+        achievements.handle_input(&mut el);
+        feedback.update(frame_dt);
         let frame = el.ui.frame(&mut el.window);
+        hud.draw(frame, &players[me], &progressions[me], &objectives.objectives, renderer.camera.front);
+        feedback.draw(frame);
+        feedback.label_numbers(&mut world_text);
+        if let Some(boss) = boss.as_ref() {
+            boss.label_name(&mut world_text);
+        }
+        debug_overlay.draw(frame, &rapier_world.stats(), chunks.memory_used(), &profiler::last_frame());
+        achievements.draw(frame);
+        // only the local player trades and it isn't sent anywhere, lockstep peers won't see what was bought
+        if let Some(merchant) = merchant.as_mut() {
+            merchant.update(players[me].position(), &local_input);
+            let trade = merchant.draw(frame, &mut inventories[me], &mut loadouts[me]);
+            match trade {
+                Some(TradeEvent::Bought(equipment)) => chat.receive("merchant", &format!("bought the {}", equipment.name())),
+                Some(TradeEvent::Sold(equipment)) => chat.receive("merchant", &format!("sold the {}", equipment.name())),
+                Some(TradeEvent::TooPoor(equipment)) => chat.receive("merchant", &format!("the {} costs {} coins", equipment.name(), equipment.price())),
+                None => {}
+            }
+            if trade.is_some() {
+                let items = inventories[me].items();
+                hud.hotbar.items = std::array::from_fn(|slot| items.get(slot).map(Item::name));
+            }
+        }
+        let mut said = chat.draw(frame);
+        if let Some(command) = said.as_deref().and_then(|text| text.strip_prefix('/')) {
+            let reply = console::run(command, &mut time_scale, &mut rapier_world, net.is_some(), lockstep);
+            chat.receive("console", &reply);
+            said = None;
+        }
+        if let Some(net) = net.as_mut() {
+            for (name, text) in net.take_chat() {
+                chat.receive(&name, &text);
+            }
+            if let Some(text) = said {
+                net.send_chat(&text);
+                chat.receive(&net.name, &text);
+            }
+
+            // lockstep squadmates are simulated here, everyone else is whatever the network last said
+            let tags: Vec<(String, Vec3)> = if net.lockstep {
+                player_ids.iter().enumerate()
+                    .filter(|&(i, _)| i != me)
+                    .map(|(i, &id)| (net.player_name(id), players[i].position()))
+                    .collect()
+            } else {
+                net.remote_players.values().map(|player| (player.name.clone(), player.position)).collect()
+            };
+            draw_name_tags(frame, &renderer.camera, resolution.x, &tags);
+        }
         
+        for platform in platforms.iter_mut() {
+            platform.update(&mut rapier_world, &mut renderer, dt);
+        }
+
+        // levels, what's worn and what's held all add up to the stats, the held weapon can change any frame
+        for (i, input) in inputs.iter().enumerate() {
+            let held = inventories[i].weapon_in(input.weapon as usize);
+            players[i].apply_stats(loadouts[i].stats(progressions[i].stats(), held));
+        }
+        for ((player, input), grapple) in players.iter_mut().zip(inputs.iter()).zip(grapples.iter_mut()) {
+            grapple.update(&mut rapier_world, &mut renderer, player, input, dt);
+            player.update(&mut rapier_world, input, dt, &platforms);
+            if player.current_surface() != Surface::Air && player.speed_ratio() > 0.1 {
+                noises.emit(player.position(), FOOTSTEP_NOISE_RADIUS * player.speed_ratio());
+            }
+        }
+        let landing_speed = players[me].landing_speed();
+        if landing_speed > 0.0 {
+            player_animator.squash((landing_speed / HEAVY_LANDING_SPEED * LANDING_SQUASH).min(LANDING_SQUASH));
+        }
+        if landing_speed > HEAVY_LANDING_SPEED {
+            shake.add_trauma(landing_speed / HEAVY_LANDING_SPEED - 1.0);
+        }
+
+        let bodies: Vec<_> = players.iter().map(|player| player.body()).collect();
+        for (target, event) in traps.update(&mut rapier_world, &mut renderer, &bodies, dt) {
+            match event {
+                TrapEvent::Damage(amount) => {
+                    players[target].damage(amount);
+                    if target == me {
+                        feedback.emit(FeedbackEvent::PlayerDamaged { amount });
+                    }
+                }
+                TrapEvent::Knockback(impulse) => {
+                    players[target].apply_knockback(impulse);
+                    if target == me {
+                        hud.damage_from(-impulse);
+                    }
+                }
+                TrapEvent::PlateTriggered { plate } => world_deltas.record(WorldPos(traps.traps[plate].position).tile(), Modification::TrapTriggered),
+                _ => {}
+            }
+        }
+
+        for (target, teleport) in teleporters.update(&mut rapier_world, &bodies) {
+            // a rope through the teleporter would yank them straight back
+            grapples[target].detach(&mut rapier_world, &mut renderer);
+            players[target].teleported(&rapier_world);
+            particles.burst(ParticlePreset::Sparks, teleport.from, TELEPORT_SPARKS);
+            particles.burst(ParticlePreset::Sparks, teleport.to, TELEPORT_SPARKS);
+            // the other end may be right at the edge of what's been generated
+            chunks.request_around(WorldPos(teleport.to).chunk());
+            if target == me {
+                player_camera.snap();
+                feedback.emit(FeedbackEvent::Teleported);
+            }
+        }
+
+        if let Some(locks) = locks.as_mut() {
+            let positions: Vec<Vec3> = players.iter().map(|player| player.position()).collect();
+            for (target, event) in locks.update(&mut rapier_world, &mut renderer, &mut world_text, &positions, &inputs, &mut inventories) {
+                match event {
+                    LockEvent::KeyPickedUp(key) => bus.emit(GameEvent::KeyPickedUp { player: target, key }),
+                    LockEvent::DoorOpened(key) => bus.emit(GameEvent::DoorOpened { player: target, key }),
+                    LockEvent::DoorLocked(_) => {}
+                }
+                if target != me {
+                    continue;
+                }
+                match event {
+                    LockEvent::KeyPickedUp(key) => chat.receive("dungeon", &format!("picked up the {}", Item::Key(key).name())),
+                    LockEvent::DoorLocked(key) => chat.receive("dungeon", &format!("this door needs the {}", Item::Key(key).name())),
+                    LockEvent::DoorOpened(_) => {}
+                }
+                let items = inventories[me].items();
+                hud.hotbar.items = std::array::from_fn(|slot| items.get(slot).map(Item::name));
+            }
+        }
+
+        // hold lmb to keep attacking with whatever weapon is in the selected hotbar slot. melee lands right away, bolts
+        // land once they hit something further down
+        let mut strikes = Vec::new();
+        for (i, input) in inputs.iter().enumerate() {
+            let player = &players[i];
+            let held = inventories[i].weapon_in(input.weapon as usize);
+            let weapon = held.and_then(|held| held.weapon()).unwrap_or(equipment::FISTS);
+            if !loadouts[i].attack(input.attack, &weapon, dt) {
+                continue;
+            }
+
+            let look = input.look();
+            let damage = weapon.damage * loadouts[i].stats(progressions[i].stats(), held).damage;
+            match weapon.attack {
+                AttackKind::Melee => {
+                    if let Some(hit) = rapier_world.cast_ray_with_normal(player.position(), look, weapon.range, player.body()) {
+                        strikes.push((i, hit.collider, player.position() + look * hit.distance, hit.normal, damage));
+                    }
+                }
+                AttackKind::Projectile => {
+                    throwables.shoot(&mut rapier_world, &mut renderer, i, player.position() + look * THROW_OFFSET, look, damage, weapon.range);
+                }
+            }
+        }
+
+        // hold rmb to aim, the arc shows until it's let go and the throw happens
+        for (i, input) in inputs.iter().enumerate() {
+            let player = &players[i];
+            let origin = player.position() + input.look() * THROW_OFFSET;
+            let velocity = throwing::throw_velocity(input.look());
+            if throwables.released(i, input.throw) {
+                throwables.throw(&mut rapier_world, &mut renderer, i, origin, velocity);
+            }
+            if i != me {
+                continue;
+            }
+            if input.throw {
+                let arc = throwing::predict(&rapier_world, origin, velocity, rapier_world.gravity_at(player.body()), player.body());
+                trajectory_preview.show(&mut renderer, &arc);
+            } else {
+                trajectory_preview.hide(&mut renderer);
+            }
+        }
+        // meshes following bodies are drawn between the last two steps, same as the players
+        let blend = Blend::new(&steps);
+        for event in throwables.update(&mut rapier_world, &mut renderer, &blend, dt) {
+            let (position, thrower) = match event {
+                ThrownEvent::Landed(position) => {
+                    noises.emit(position, THROW_NOISE_RADIUS);
+                    continue;
+                }
+                ThrownEvent::Exploded { position, player } => (position, player),
+                ThrownEvent::Struck { position, direction, collider, player, damage } => {
+                    strikes.push((player, collider, position, -direction, damage));
+                    continue;
+                }
+            };
+
+            particles.burst(ParticlePreset::Sparks, position, EXPLOSION_SPARKS);
+            particles.burst(ParticlePreset::Dust, position, EXPLOSION_DUST);
+            if let Some(floor) = rapier_world.cast_ray_with_normal(position, -Vec3::Y, BOMB_RADIUS, players[me].body()) {
+                decals.place(&mut renderer, DecalKind::Scorch, position - Vec3::Y * floor.distance, floor.normal);
+            }
+            let falloff = 1.0 - position.distance(players[me].position()) / (BOMB_RADIUS * 2.0);
+            shake.add_trauma(BOMB_TRAUMA * falloff.max(0.0));
+            noises.emit(position, ATTACK_NOISE_RADIUS);
+
+            for hit in rapier_world.explode(position, BOMB_RADIUS, BOMB_FORCE, BOMB_DAMAGE) {
+                let Some(entity) = hit.entity else { continue };
+                match entity.kind {
+                    EntityKind::Wall => {
+                        if let Some(tile) = destruction.damage(&mut rapier_world, &mut renderer, entity, hit.damage) {
+                            world_deltas.record(tile, Modification::WallDestroyed);
+                            decals.clear_near(&mut renderer, position, coords::tile_size());
+                        }
+                    }
+                    EntityKind::Prop => {
+                        if let Some((kind, tile, position)) = props.damage(&mut rapier_world, &mut renderer, &mut destruction, entity, hit.damage) {
+                            world_deltas.record(tile, Modification::PropBroken);
+                            bus.emit(GameEvent::PropBroken { player: thrower, kind, position });
+                        }
+                    }
+                    EntityKind::Enemy if authoritative => {
+                        let kind = enemies.find(entity).map(|enemy| enemy.kind);
+                        let killed = enemies.damage(&mut rapier_world, &mut renderer, entity, hit.damage);
+                        if thrower == me {
+                            feedback.emit(FeedbackEvent::Hit { target: entity, position, amount: hit.damage });
+                        }
+                        if let (true, Some(kind)) = (killed, kind) {
+                            bus.emit(GameEvent::EnemyKilled { player: thrower, kind });
+                            progressions[thrower].award_kill(kind);
+                        }
+                    }
+                    EntityKind::Enemy => {
+                        if let (Some(net), Some(enemy), true) = (net.as_mut(), enemies.find(entity), thrower == me) {
+                            net.send_event(WorldEvent::EnemyHit { enemy: enemy.net_id, amount: hit.damage });
+                        }
+                    }
+                    EntityKind::Player => {
+                        let Some(target) = players.iter().position(|player| player.id == entity) else { continue };
+                        players[target].damage(hit.damage);
+                        players[target].apply_knockback(hit.direction * BOMB_KNOCKBACK * hit.damage / BOMB_DAMAGE);
+                        if target == me {
+                            feedback.emit(FeedbackEvent::PlayerDamaged { amount: hit.damage });
+                            hud.damage_from(-hit.direction);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        // every melee and bolt hit from this frame
+        for (i, collider, hit_point, normal, damage) in strikes {
+            match rapier_world.entity_of(collider) {
+                Some(entity) if entity.kind == EntityKind::Wall => {
+                    particles.burst(ParticlePreset::Sparks, hit_point, HIT_SPARKS);
+                    decals.place(&mut renderer, DecalKind::ImpactMark, hit_point, normal);
+                    if let Some(tile) = destruction.damage(&mut rapier_world, &mut renderer, entity, damage) {
+                        world_deltas.record(tile, Modification::WallDestroyed);
+                        particles.burst(ParticlePreset::Dust, hit_point, WALL_BREAK_DUST);
+                        // about a wall tile, so marks on a broken wall don't float in the air
+                        decals.clear_near(&mut renderer, hit_point, coords::tile_size());
+                    }
+                }
+                Some(entity) if entity.kind == EntityKind::Prop => {
+                    if let Some((kind, tile, position)) = props.damage(&mut rapier_world, &mut renderer, &mut destruction, entity, damage) {
+                        world_deltas.record(tile, Modification::PropBroken);
+                        bus.emit(GameEvent::PropBroken { player: i, kind, position });
+                    }
+                }
+                Some(entity) if entity.kind == EntityKind::Enemy => {
+                    particles.burst(ParticlePreset::Sparks, hit_point, HIT_SPARKS);
+                    // blood goes on the floor under the enemy, not on the enemy itself
+                    let struck = enemies.find(entity).map_or(players[i].body(), |enemy| enemy.body);
+                    if let Some(floor) = rapier_world.cast_ray_with_normal(hit_point, -Vec3::Y, BLOOD_REACH, struck) {
+                        decals.place(&mut renderer, DecalKind::Blood, hit_point - Vec3::Y * floor.distance, floor.normal);
+                    }
+                    if i == me {
+                        feedback.emit(FeedbackEvent::Hit { target: entity, position: hit_point, amount: damage });
+                    }
+                    if authoritative {
+                        let kind = enemies.find(entity).map(|enemy| enemy.kind);
+                        let killed = enemies.damage(&mut rapier_world, &mut renderer, entity, damage);
+                        if let (true, Some(kind)) = (killed, kind) {
+                            bus.emit(GameEvent::EnemyKilled { player: i, kind });
+                            progressions[i].award_kill(kind);
+                        }
+                    } else if let (Some(net), Some(enemy)) = (net.as_mut(), enemies.find(entity)) {
+                        // the host owns the enemy, it applies the damage and tells everyone how it went
+                        net.send_event(WorldEvent::EnemyHit { enemy: enemy.net_id, amount: damage });
+                    }
+                }
+                _ => {
+                    decals.place(&mut renderer, DecalKind::ImpactMark, hit_point, normal);
+                }
+            }
+
+            noises.emit(hit_point, ATTACK_NOISE_RADIUS);
+        }
+        destruction.update(&mut rapier_world, &mut renderer, &blend, dt);
+        let player_positions: Vec<Vec3> = players.iter().map(|player| player.position()).collect();
+        rapier_world.update_debris(&player_positions);
+        props.update(&rapier_world, &mut renderer, &blend);
+        particles.update(&rapier_world, &mut renderer, dt);
+        if authoritative {
+            // the first player drives spawning so every lockstep peer makes the same call
+            profile_scope!("spawning");
+            director.update(&mut rapier_world, &mut renderer, &mut enemies, players[0].position(), inputs[0].look(), dt);
+            if let Some(boss) = boss.as_mut() {
+                let was_locked = boss.is_locked();
+                let was_defeated = boss.state == BossState::Defeated;
+                boss.update(&mut rapier_world, &mut renderer, &mut enemies, &bodies, dt);
+                if boss.state == BossState::Defeated && !was_defeated {
+                    bus.emit(GameEvent::BossDefeated);
+                }
+                if boss.is_locked() && !was_locked {
+                    cutscenes.play(CameraSequence::boss_intro(player_camera.position, renderer.camera.front, boss.center), renderer.camera.yaw, renderer.camera.pitch);
+                }
+                if let (Some(net), true, false) = (net.as_mut(), boss.is_locked() != was_locked, lockstep) {
+                    net.send_event(WorldEvent::BossDoors { locked: boss.is_locked() });
+                }
+            }
+        }
+
+        // the players we simulate, then anyone we only see through the network
+        let mut targets: Vec<(u32, Vec3)> = player_ids.iter().copied().zip(players.iter().map(|player| player.position())).collect();
+        if let Some(net) = net.as_mut().filter(|net| !net.lockstep) {
+            net.update(&mut renderer, &enemies, players[me].position(), frame_dt);
+            targets.extend(net.remote_positions());
+
+            for event in net.take_events() {
+                match event {
+                    WorldEvent::BossDoors { locked } => {
+                        if let Some(boss) = boss.as_mut() {
+                            // the host only opens the doors back up once the boss is dead
+                            if !locked && boss.is_locked() {
+                                bus.emit(GameEvent::BossDefeated);
+                            }
+                            if locked && !boss.is_locked() {
+                                cutscenes.play(CameraSequence::boss_intro(player_camera.position, renderer.camera.front, boss.center), renderer.camera.yaw, renderer.camera.pitch);
+                            }
+                            boss.set_locked(&mut rapier_world, &mut renderer, locked);
+                        }
+                    }
+                    WorldEvent::EnemyHit { enemy, amount } if net.is_host() => {
+                        if let Some(id) = enemies.find_by_net_id(enemy).map(|enemy| enemy.id) {
+                            enemies.damage(&mut rapier_world, &mut renderer, id, amount);
+                        }
+                    }
+                    WorldEvent::PlayerHit { player_id, amount, knockback } if player_id == net.player_id => {
+                        let impulse = Vec3::from_array(knockback);
+                        if amount > 0.0 {
+                            players[me].damage(amount);
+                            feedback.emit(FeedbackEvent::PlayerDamaged { amount });
+                        }
+                        if impulse != Vec3::ZERO {
+                            players[me].apply_knockback(impulse);
+                            hud.damage_from(-impulse);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+
+            if !net.is_host() {
+                for (&net_id, replica) in net.enemies.iter() {
+                    enemies.sync_replica(&mut rapier_world, &mut renderer, net_id, replica.kind, replica.position, replica.health);
+                }
+            }
+        }
+
+        let positions: Vec<Vec3> = targets.iter().map(|&(_, position)| position).collect();
+        let enemy_events = {
+            profile_scope!("ai");
+            enemies.update(&mut rapier_world, &mut renderer, &positions, &noises, &director.navigator(), &blend, dt)
+        };
+        for (target, event) in enemy_events {
+            // somebody the host only knows through the network got hit, they apply it on their end
+            if target >= players.len() {
+                let (player_id, _) = targets[target];
+                let hit = match event {
+                    EnemyEvent::Damage(amount) => Some(WorldEvent::PlayerHit { player_id, amount, knockback: [0.0; 3] }),
+                    EnemyEvent::Knockback(impulse) => Some(WorldEvent::PlayerHit { player_id, amount: 0.0, knockback: impulse.to_array() }),
+                    // everyone nearby feels those, including us
+                    EnemyEvent::Stomp { .. } => None,
+                };
+                if let (Some(net), Some(hit)) = (net.as_mut(), hit) {
+                    net.send_event(hit);
+                    continue;
+                }
+            }
+
+            match event {
+                EnemyEvent::Damage(amount) => {
+                    players[target].damage(amount);
+                    if target == me {
+                        feedback.emit(FeedbackEvent::PlayerDamaged { amount });
+                    }
+                }
+                EnemyEvent::Knockback(impulse) => {
+                    players[target].apply_knockback(impulse);
+                    if target == me {
+                        hud.damage_from(-impulse);
+                    }
+                }
+                EnemyEvent::Stomp { position } => {
+                    let falloff = 1.0 - position.distance(players[me].position()) / STOMP_RADIUS;
+                    shake.add_trauma(STOMP_TRAUMA * falloff.max(0.0));
+                }
+            }
+        }
+        if players[me].take_perfect_dodge() {
+            time_scale.slow_motion(DODGE_SLOW_MOTION, DODGE_SLOW_MOTION_DURATION);
+        }
+        for (i, player) in players.iter_mut().enumerate() {
+            if player.take_death() {
+                bus.emit(GameEvent::PlayerDied { player: i });
+            }
+        }
+        objectives.update(&mut renderer, &mut lighting, &mut bus, &player_positions);
+        if let Some(run) = daily_run.as_mut().filter(|run| !run.finished) {
+            // real time, slowing down doesn't stop the clock
+            run.elapsed += frame_dt;
+            if bus.iter().any(|event| *event == GameEvent::FloorCleared) {
+                run.finished = true;
+                leaderboard.record(run.day, &config.net.name, run.elapsed);
+                if let Err(e) = leaderboard.write() {
+                    log::warn!("couldn't write the leaderboard: {}", e);
+                }
+                chat.receive("daily", &format!("finished in {}", format_time(run.elapsed)));
+            }
+        }
+        for event in bus.iter() {
+            match *event {
+                GameEvent::EnemyKilled { player, kind } => inventories[player].add_coins(loot::kill_coins(kind)),
+                GameEvent::PropBroken { player, position, .. } => {
+                    let (coins, item) = loot::container_drop(director.floor);
+                    inventories[player].add_coins(coins);
+                    if let Some(item) = item {
+                        if player == me {
+                            chat.receive("loot", &format!("found {:?}", item));
+                        }
+                        inventories[player].add(Item::Equipment(item));
+                    }
+                    particles.burst(ParticlePreset::Dust, position, PROP_BREAK_DUST);
+                    noises.emit(position, IMPACT_NOISE_RADIUS);
+                }
+                _ => {}
+            }
+        }
+        let grounded = players[me].current_surface() != Surface::Air;
+        for achievement in achievements.update(&bus, me, players[me].position(), grounded) {
+            chat.receive("achievements", &format!("unlocked {}: {}", achievement.name, achievement.description));
+        }
+        // daily runs don't have a slot so there's nothing to keep for them
+        if save.active.is_some() && autosave.update(frame_dt, WorldPos(players[me].position()).chunk(), director.floor) {
+            save.record(RunProgress {
+                floor: director.floor,
+                level: progressions[me].level,
+                xp: progressions[me].xp,
+                coins: inventories[me].coins(),
+                items: inventories[me].items().to_vec(),
+                opened_doors: locks.as_ref().map_or_else(Vec::new, LockSystem::opened),
+                world: world_deltas.clone(),
+            }, autosave.take_playtime());
+            achievements.store(&mut save);
+            if let Err(e) = save.write() {
+                log::warn!("couldn't autosave: {}", e);
+            }
+        }
+        noises.clear();
+        bus.clear();
+        // the world is away being stepped until `finish`, drawing doesn't need it
+        if let Some(thread) = physics_thread.as_mut() {
+            thread.begin(&mut rapier_world, &mut steps, dt);
+        }
         
-        frame.text("hello, world!");
-        
-        player.update(&mut rapier_world, &mut el, &mut renderer);
-        rapier_world.set_dt(el.dt);
-        
+        let (width, height) = el.window.get_framebuffer_size();
+        let (width, height) = (width.max(1) as u32, height.max(1) as u32);
         unsafe {
+            profile_scope!("rendering");
+            post.begin(width, height);
+            // the sky's horizon is the same fog the far walls fade into
+            let fog = lighting.fog();
+            ClearColor(fog.color.x, fog.color.y, fog.color.z, 1.0);
             Clear(COLOR_BUFFER_BIT | DEPTH_BUFFER_BIT);
-            ClearColor(0.1, 0.2, 0.3, 1.0);
-            renderer.draw(&el);
+            sky.draw(lighting.sky(&director.biome), fog, &renderer.camera, frame_dt);
+            lighting.apply(resolution.x);
+            render_layers::draw(&renderer, &el, resolution.x);
+            shadows.draw(&renderer.camera, fog, resolution.x);
+            highlight.draw(&renderer, &renderer.camera, resolution.x);
+            billboards.draw(&renderer.camera, fog, resolution.x);
+            world_text.draw(&renderer.camera, fog, resolution.x);
+            post.end(&director.biome);
             el.ui.draw();
         }
+
+        // read back before the buffers swap at the top of the next frame
+        capture.record(width, height, frame_dt);
+        if capture_request.screenshot {
+            match capture.screenshot(width, height) {
+                Ok(path) => chat.receive("capture", &format!("saved {}", path.display())),
+                Err(e) => log::warn!("couldn't save a screenshot: {}", e),
+            }
+        }
+        if capture_request.clip {
+            match capture.save_clip() {
+                Ok(path) => chat.receive("capture", &format!("saved {}", path.display())),
+                Err(e) => log::warn!("couldn't save the clip: {}", e),
+            }
+        }
         
-        let mut move_vec = Vec3::ZERO;
-        if el.is_key_down(Key::W){
-            move_vec += renderer.camera.front;
+        match physics_thread.as_mut() {
+            Some(thread) => thread.finish(&mut rapier_world, &mut steps),
+            None => steps.advance(&mut rapier_world, dt),
         }
-        if el.is_key_down(Key::S){
-            move_vec -= renderer.camera.front;
+        for player in players.iter_mut() {
+            player.sync_from_body(&rapier_world);
         }
-        if el.is_key_down(Key::A){
-            move_vec -= renderer.camera.front.cross(vec3(0.0, 1.0, 0.0));
+        if let Some(net) = net.as_mut().filter(|net| net.lockstep) {
+            net.submit_hash(rapier_world.state_hash());
         }
-        if el.is_key_down(Key::D){
-            move_vec += renderer.camera.front.cross(vec3(0.0, 1.0, 0.0));
+        // the bursts and noise show up next frame, same as everything else the step did
+        for impact in impacts.update(&mut rapier_world, &props, dt) {
+            particles.burst(impact.material.particles(), impact.position, impact.particles);
+            noises.emit(impact.position, IMPACT_NOISE_RADIUS * impact.volume);
         }
-        // move_vec.y += GRAVITY;
-        
-        let player_mesh = renderer.get_mesh_mut("player").unwrap();
-        player_mesh.position = player.pos.into();
-        let pos = player_mesh.position;
-        renderer.camera.update((pos + renderer.camera.front * 10.0) / resolution.x);
-        
-        player.pos = (vec3(player.pos.x, player.pos.y, player.pos.z) + move_vec).into();
-        rapier_world.step().await;
+
+        // fixed steps don't line up with frames, so what's drawn is blended between the last two of them
+        let blend = Blend::new(&steps);
+        let drawn: Vec<(Vec3, Quat)> = players.iter().map(|player| {
+            let blended = blend.transform(&rapier_world, player.body());
+            blended.map_or((player.position(), Quat::IDENTITY), |transform| (transform.position, transform.rotation))
+        }).collect();
+
+        for (i, id) in player_ids.iter().enumerate().filter(|&(i, _)| i != me) {
+            if let Some(mesh) = renderer.get_mesh_mut(&format!("REMOTE_PLAYER_MESH{}", id)) {
+                (mesh.position, mesh.rotation) = drawn[i];
+            }
+        }
+
+        let player = &players[me];
+        player_animator.walk(player.velocity(&rapier_world), player.movement.max_speed, 100.0, dt);
+        player_animator.update(dt);
+        if let Some(player_mesh) = renderer.get_mesh_mut("player") {
+            player_animator.apply(player_mesh, drawn[me].0, drawn[me].1);
+        }
+        let eye = player_camera.update(drawn[me].0, player.speed_ratio(), frame_dt) + shake.update(frame_dt);
+        match cutscenes.update(frame_dt) {
+            Some((position, front)) => {
+                renderer.camera.front = front;
+                renderer.camera.update(position / resolution.x);
+            }
+            None => renderer.camera.update((eye + renderer.camera.front * 10.0) / resolution.x),
+        }
+        // the mouse kept turning the camera underneath, put it back to where it was before
+        if let Some((yaw, pitch)) = cutscenes.take_restore() {
+            renderer.camera.yaw = yaw;
+            renderer.camera.pitch = pitch;
+        }
+        let camera_position = renderer.camera.pos * resolution.x;
+        lighting.update(&mut renderer, camera_position, resolution.x);
+        let impostors = enemies.impostors().chain(locks.iter().flat_map(LockSystem::impostors));
+        billboards.update(&mut renderer, camera_position, lighting.fog(), resolution.x, impostors);
+        // every player's cube is 100 across, see where their meshes are made
+        let casters = players.iter().zip(drawn.iter())
+            .map(|(player, &(position, _))| ShadowCaster { body: player.body(), position, size: Vec3::splat(100.0) })
+            .chain(enemies.shadow_casters());
+        shadows.update(&rapier_world, camera_position, resolution.x, casters);
+        let interactables = locks.iter().flat_map(LockSystem::interactables).chain(merchant.as_ref().map(Merchant::interactable));
+        highlight.update(&rapier_world, camera_position, renderer.camera.front, players[me].body(), interactables, frame_dt);
         
-        if el.is_key_down(Key::LeftAlt) {
+        if el.is_key_down(Key::LeftAlt) || merchant.as_ref().is_some_and(|merchant| merchant.open) {
             el.window.set_cursor_mode(glfw::CursorMode::Normal);
         }
         else {
             el.window.set_cursor_mode(glfw::CursorMode::Disabled);
         }
     }
-    
+
+    if let Some(net) = net.as_ref() {
+        net.leave();
+    }
+    achievements.store(&mut save);
+    shutdown.on_exit("save", move || {
+        if let Err(e) = save.write() {
+            log::error!("couldn't write the save: {}", e);
+        }
+    });
+    shutdown.run().await;
 }
@@ -0,0 +1,187 @@
+use tiny_game_framework::imgui::{Condition, Ui};
+
+use crate::{config::{CameraSettings, Difficulty, PostProcessSettings}, daily::{self, LeaderboardEntry, SECONDS_PER_DAY}, save::SaveSlot};
+
+const MENU_WIDTH: f32 = 260.0;
+const BUTTON_HEIGHT: f32 = 32.0;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MenuAction {
+    // `None` picks a random seed
+    NewRun { seed: Option<u64>, difficulty: Difficulty },
+    // today's dungeon, the seed comes from the date
+    DailyRun,
+    // indices into the save's slots
+    Load(usize),
+    CopySlot(usize),
+    DeleteSlot(usize),
+    Quit,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum MenuPage {
+    Main,
+    Slots,
+    Settings,
+}
+
+// shown before anything gets generated, the run only starts once something is picked here
+pub struct MainMenu {
+    page: MenuPage,
+    seed_text: String,
+    difficulty: Difficulty,
+}
+
+impl MainMenu {
+    // `seed` fills in the seed field, e.g. from the command line, and `difficulty` is what's picked to begin with
+    pub fn new(seed: Option<u64>, difficulty: Difficulty) -> Self {
+        Self {
+            page: MenuPage::Main,
+            seed_text: seed.map(|seed| seed.to_string()).unwrap_or_default(),
+            difficulty,
+        }
+    }
+
+    // `slots` are the runs in the save that can be loaded, `daily_best` are today's fastest daily runs
+    pub fn draw(&mut self, ui: &Ui, slots: &[SaveSlot], daily_best: &[&LeaderboardEntry], camera: &mut CameraSettings, post: &mut PostProcessSettings) -> Option<MenuAction> {
+        let [width, height] = ui.io().display_size;
+        let mut action = None;
+
+        ui.window("main menu")
+            .position([width / 2.0, height / 2.0], Condition::Always)
+            .position_pivot([0.5, 0.5])
+            .no_decoration()
+            .always_auto_resize(true)
+            .build(|| {
+                match self.page {
+                    MenuPage::Main => action = self.draw_main(ui, !slots.is_empty(), daily_best),
+                    MenuPage::Slots => action = self.draw_slots(ui, slots),
+                    MenuPage::Settings => self.draw_settings(ui, camera, post),
+                }
+            });
+
+        action
+    }
+
+    fn draw_main(&mut self, ui: &Ui, can_load: bool, daily_best: &[&LeaderboardEntry]) -> Option<MenuAction> {
+        let size = [MENU_WIDTH, BUTTON_HEIGHT];
+        ui.text("dungeon");
+        ui.separator();
+
+        ui.set_next_item_width(MENU_WIDTH);
+        ui.input_text("##seed", &mut self.seed_text).hint("seed (random when empty)").build();
+        for (i, difficulty) in Difficulty::ALL.into_iter().enumerate() {
+            if i > 0 {
+                ui.same_line();
+            }
+            ui.radio_button(difficulty.name(), &mut self.difficulty, difficulty);
+        }
+        if ui.button_with_size("new run", size) {
+            return Some(MenuAction::NewRun { seed: parse_seed(&self.seed_text), difficulty: self.difficulty });
+        }
+
+        // the seed field and the difficulty don't apply, everyone gets the same dungeon
+        if ui.button_with_size(format!("daily run {}", daily::date(daily::today())), size) {
+            return Some(MenuAction::DailyRun);
+        }
+        for (i, entry) in daily_best.iter().enumerate() {
+            ui.text(format!("{}. {} {}", i + 1, entry.name, format_time(entry.seconds)));
+        }
+
+        {
+            let _disabled = ui.begin_disabled(!can_load);
+            if ui.button_with_size("load", size) {
+                self.page = MenuPage::Slots;
+            }
+        }
+
+        if ui.button_with_size("settings", size) {
+            self.page = MenuPage::Settings;
+        }
+        if ui.button_with_size("quit", size) {
+            return Some(MenuAction::Quit);
+        }
+
+        None
+    }
+
+    fn draw_slots(&mut self, ui: &Ui, slots: &[SaveSlot]) -> Option<MenuAction> {
+        ui.text("load");
+        ui.separator();
+
+        let mut action = None;
+        for (i, slot) in slots.iter().enumerate() {
+            let metadata = &slot.metadata;
+            ui.text(&slot.name);
+            ui.text_disabled(format!(
+                "floor {}, level {}, {} played, saved {}",
+                metadata.floor, metadata.level, format_time(metadata.playtime), format_timestamp(metadata.saved_at),
+            ));
+            if ui.button(format!("load##{}", i)) {
+                action = Some(MenuAction::Load(i));
+            }
+            ui.same_line();
+            if ui.button(format!("copy##{}", i)) {
+                action = Some(MenuAction::CopySlot(i));
+            }
+            ui.same_line();
+            if ui.button(format!("delete##{}", i)) {
+                action = Some(MenuAction::DeleteSlot(i));
+            }
+            ui.separator();
+        }
+
+        // nothing left to load after deleting the last one
+        if ui.button_with_size("back", [MENU_WIDTH, BUTTON_HEIGHT]) || slots.is_empty() {
+            self.page = MenuPage::Main;
+        }
+        action
+    }
+
+    // only lasts for this session, config.toml is where they're set for good
+    fn draw_settings(&mut self, ui: &Ui, camera: &mut CameraSettings, post: &mut PostProcessSettings) {
+        ui.text("settings");
+        ui.separator();
+
+        ui.checkbox("head bob", &mut camera.head_bob);
+        ui.set_next_item_width(MENU_WIDTH / 2.0);
+        ui.slider("bob amplitude", 0.0, 20.0, &mut camera.bob_amplitude);
+        ui.checkbox("camera smoothing", &mut camera.smoothing);
+        ui.set_next_item_width(MENU_WIDTH / 2.0);
+        ui.slider("smoothing speed", 1.0, 60.0, &mut camera.smoothing_speed);
+
+        ui.checkbox("post processing", &mut post.enabled);
+        ui.set_next_item_width(MENU_WIDTH / 2.0);
+        ui.slider("vignette", 0.0, 1.0, &mut post.vignette);
+        ui.checkbox("bloom", &mut post.bloom);
+        ui.checkbox("color grading", &mut post.color_grading);
+
+        if ui.button_with_size("back", [MENU_WIDTH, BUTTON_HEIGHT]) {
+            self.page = MenuPage::Main;
+        }
+    }
+}
+
+// minutes:seconds.tenths
+pub fn format_time(seconds: f32) -> String {
+    format!("{}:{:04.1}", (seconds / 60.0) as u32, seconds % 60.0)
+}
+
+// yyyy-mm-dd hh:mm in utc, from unix seconds
+fn format_timestamp(seconds: u64) -> String {
+    let time = seconds % SECONDS_PER_DAY;
+    format!("{} {:02}:{:02}", daily::date(seconds / SECONDS_PER_DAY), time / 3600, time / 60 % 60)
+}
+
+// numbers are used as they are, anything else is hashed so a word works as a seed too
+fn parse_seed(text: &str) -> Option<u64> {
+    let text = text.trim();
+    if text.is_empty() {
+        return None;
+    }
+
+    // fnv-1a, unlike std's hasher it's the same on every build
+    Some(text.parse().unwrap_or_else(|_| {
+        text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100_0000_01b3))
+    }))
+}
@@ -0,0 +1,164 @@
+use rapier3d::prelude::*;
+use tiny_game_framework::{glam::{vec3, Vec3, Vec4}, imgui::{Condition, Ui}, Cuboid, Renderer};
+
+use crate::{
+    character_controller::PlayerInput, coords, director::SpawnDirector, entity::{EntityId, EntityKind}, equipment::{Equipment, Loadout},
+    error::RendererExt, highlight::Interactable, inventory::{Inventory, Item}, loot, rapier_integration::{BodySpec, CollisionLayer, RapierPhysicsWorld},
+};
+
+const MERCHANT_MESH: &str = "MERCHANT_MESH";
+const MERCHANT_SIZE: Vec3 = Vec3::new(70.0, 160.0, 70.0);
+const MERCHANT_COLOR: Vec4 = Vec4::new(0.3, 0.75, 0.6, 1.0);
+// tiles out from the merchant in every direction that enemies don't spawn on
+const SAFE_ROOM_RADIUS: i32 = 2;
+// the safe room stays at least this far from where the run starts and from the boss
+const MIN_DISTANCE: f32 = 1500.0;
+const STOCK_SIZE: usize = 4;
+const INTERACT_DISTANCE: f32 = 250.0;
+// what a merchant pays for something, as a fraction of what it sells it for
+const SELL_RATIO: f32 = 0.5;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TradeEvent {
+    Bought(Equipment),
+    Sold(Equipment),
+    TooPoor(Equipment),
+}
+
+// a friendly npc in a room enemies leave alone, buys and sells equipment for coins
+pub struct Merchant {
+    pub position: Vec3,
+    pub stock: Vec<Equipment>,
+    // the trade window is up
+    pub open: bool,
+    entity: EntityId,
+    interact_held: bool,
+}
+
+impl Merchant {
+    // picks the most open spot far enough from everything in `avoid`, `None` when nothing is. the stock comes from
+    // the loot table for `floor`
+    pub fn plan(rw: &mut RapierPhysicsWorld, r: &mut Renderer, director: &mut SpawnDirector, avoid: &[Vec3], floor: u32) -> Option<Self> {
+        let tile_size = coords::tile_size();
+        let floor_tiles: Vec<Vec3> = director.walkable().positions(coords::floor_height()).collect();
+        let in_room = |center: Vec3, tile: Vec3| {
+            (tile.x - center.x).abs() <= SAFE_ROOM_RADIUS as f32 * tile_size + 1.0
+                && (tile.z - center.z).abs() <= SAFE_ROOM_RADIUS as f32 * tile_size + 1.0
+        };
+
+        let center = floor_tiles.iter().copied()
+            .filter(|&tile| avoid.iter().all(|&other| tile.distance(other) >= MIN_DISTANCE))
+            .max_by_key(|&center| floor_tiles.iter().filter(|&&tile| in_room(center, tile)).count())?;
+
+        director.add_safe_zone(center, SAFE_ROOM_RADIUS as f32 * tile_size);
+        // the merchant stands on it, nothing else should
+        director.block_tile(center);
+
+        let position = center + Vec3::Y * (tile_size + MERCHANT_SIZE.y) / 2.0;
+        let body = rw.add_body(position, &BodySpec::cuboid(MERCHANT_SIZE / 2.0)
+            .body_type(RigidBodyType::Fixed)
+            .layer(CollisionLayer::StaticWorld));
+        let entity = EntityId::new(EntityKind::Npc);
+        rw.tag_body(body, entity);
+
+        let mut mesh = Cuboid::new(MERCHANT_SIZE, MERCHANT_COLOR).mesh();
+        mesh.position = position;
+        mesh.setup_mesh();
+        if let Err(e) = r.try_add_mesh(MERCHANT_MESH, mesh) {
+            log::warn!("{}", e);
+        }
+
+        Some(Self {
+            position,
+            stock: loot::roll(loot::MERCHANT_LOOT, floor, STOCK_SIZE),
+            open: false,
+            entity,
+            interact_held: false,
+        })
+    }
+
+    pub fn interactable(&self) -> Interactable<'static> {
+        Interactable { entity: self.entity, mesh_name: MERCHANT_MESH }
+    }
+
+    // interacting nearby opens the trade window or closes it again, walking away closes it too
+    pub fn update(&mut self, player_pos: Vec3, input: &PlayerInput) {
+        let pressed = input.interact && !self.interact_held;
+        self.interact_held = input.interact;
+
+        let near = vec3(player_pos.x - self.position.x, 0.0, player_pos.z - self.position.z).length() <= INTERACT_DISTANCE;
+        if !near {
+            self.open = false;
+        } else if pressed {
+            self.open = !self.open;
+        }
+    }
+
+    // buying something wearable puts it on straight away, selling what's worn takes it off
+    pub fn draw(&mut self, ui: &Ui, inventory: &mut Inventory, loadout: &mut Loadout) -> Option<TradeEvent> {
+        if !self.open {
+            return None;
+        }
+
+        let [width, height] = ui.io().display_size;
+        let mut event = None;
+        ui.window("merchant")
+            .position([width / 2.0, height / 2.0], Condition::Always)
+            .position_pivot([0.5, 0.5])
+            .no_decoration()
+            .always_auto_resize(true)
+            .build(|| {
+                ui.text(format!("merchant - you have {} coins", inventory.coins()));
+                ui.separator();
+
+                ui.text("buy");
+                let mut bought = None;
+                for (i, equipment) in self.stock.iter().enumerate() {
+                    if ui.button(format!("{} ({})##buy{}", equipment.name(), equipment.price(), i)) {
+                        bought = Some(i);
+                    }
+                }
+                if let Some(i) = bought {
+                    let equipment = self.stock[i];
+                    if inventory.spend(equipment.price()) {
+                        self.stock.remove(i);
+                        inventory.add(Item::Equipment(equipment));
+                        loadout.equip(equipment);
+                        event = Some(TradeEvent::Bought(equipment));
+                    } else {
+                        event = Some(TradeEvent::TooPoor(equipment));
+                    }
+                }
+
+                ui.separator();
+                ui.text("sell");
+                let mut sold = None;
+                for (i, item) in inventory.items().iter().enumerate() {
+                    let Item::Equipment(equipment) = *item else { continue };
+                    if ui.button(format!("{} ({})##sell{}", equipment.name(), sell_price(equipment), i)) {
+                        sold = Some(equipment);
+                    }
+                }
+                if let Some(equipment) = sold {
+                    inventory.remove(Item::Equipment(equipment));
+                    if !inventory.items().contains(&Item::Equipment(equipment)) {
+                        loadout.unequip(equipment);
+                    }
+                    inventory.add_coins(sell_price(equipment));
+                    self.stock.push(equipment);
+                    event = Some(TradeEvent::Sold(equipment));
+                }
+
+                ui.separator();
+                if ui.button("close") {
+                    self.open = false;
+                }
+            });
+
+        event
+    }
+}
+
+fn sell_price(equipment: Equipment) -> u32 {
+    (equipment.price() as f32 * SELL_RATIO) as u32
+}
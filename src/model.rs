@@ -0,0 +1,94 @@
+use rapier3d::prelude::RigidBodyHandle;
+use tiny_game_framework::{glam::{Mat3, Mat4, Vec2, Vec3, Vec4}, Mesh, Vertex};
+
+use crate::rapier_integration::{BodySpec, RapierPhysicsWorld};
+
+// one primitive of a gltf mesh with its node's transform already applied, plain data so it can be read off the
+// main thread
+#[derive(Clone, Debug)]
+pub struct ModelPart {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+}
+
+// every mesh in a .gltf or .glb's default scene, load it once and place it as often as needed
+#[derive(Clone, Debug)]
+pub struct Model {
+    pub parts: Vec<ModelPart>,
+}
+
+impl Model {
+    pub fn load(path: &str) -> Result<Self, gltf::Error> {
+        let (document, buffers, _) = gltf::import(path)?;
+        let mut parts = Vec::new();
+
+        // files without a default scene still usually have exactly one
+        if let Some(scene) = document.default_scene().or_else(|| document.scenes().next()) {
+            for node in scene.nodes() {
+                read_node(&node, Mat4::IDENTITY, &buffers, &mut parts);
+            }
+        }
+
+        Ok(Self { parts })
+    }
+
+    // every part in one fresh mesh, so the same model can be placed any number of times. it's not set up yet, like
+    // `Cuboid::mesh`, so it can be painted and placed first
+    pub fn mesh(&self) -> Mesh {
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+        for part in self.parts.iter() {
+            let first = vertices.len() as u32;
+            indices.extend(part.indices.iter().map(|index| first + index));
+            vertices.extend(part.vertices.iter().copied());
+        }
+        Mesh::new(&vertices, &indices)
+    }
+
+    pub fn points(&self) -> impl Iterator<Item = Vec3> + '_ {
+        self.parts.iter().flat_map(|part| part.vertices.iter().map(|vertex| vertex.position))
+    }
+
+    // a body wrapped in the convex hull of every vertex, scaled by `scale`. `spec` decides everything but the
+    // shape. `None` if the model is flat or empty
+    pub fn add_collider(&self, rw: &mut RapierPhysicsWorld, position: Vec3, scale: Vec3, spec: &BodySpec) -> Option<RigidBodyHandle> {
+        let points: Vec<Vec3> = self.points().map(|point| point * scale).collect();
+        rw.add_convex_hull(position, &points, spec)
+    }
+}
+
+fn read_node(node: &gltf::Node, parent: Mat4, buffers: &[gltf::buffer::Data], parts: &mut Vec<ModelPart>) {
+    let transform = parent * Mat4::from_cols_array_2d(&node.transform().matrix());
+    // normals don't take the translation, and need the inverse transpose in case the scale isn't uniform
+    let normal_transform = Mat3::from_mat4(transform).inverse().transpose();
+
+    if let Some(mesh) = node.mesh() {
+        for primitive in mesh.primitives() {
+            let reader = primitive.reader(|buffer| Some(buffers[buffer.index()].0.as_slice()));
+            let Some(positions) = reader.read_positions() else { continue };
+
+            let positions: Vec<Vec3> = positions.map(|position| transform.transform_point3(Vec3::from(position))).collect();
+            let mut normals = reader.read_normals()
+                .map(|normals| normals.map(|normal| (normal_transform * Vec3::from(normal)).normalize_or_zero()));
+            let mut tex_coords = reader.read_tex_coords(0).map(|tex_coords| tex_coords.into_f32());
+
+            let vertices = positions.into_iter().map(|position| {
+                let normal = normals.as_mut().and_then(|normals| normals.next()).unwrap_or(Vec3::ZERO);
+                let tex_coords = tex_coords.as_mut().and_then(|tex_coords| tex_coords.next()).map_or(Vec2::ZERO, Vec2::from);
+                Vertex::new(position, Vec4::ONE, tex_coords, normal)
+            }).collect::<Vec<_>>();
+
+            // no index buffer means every three vertices are a triangle
+            let indices = match reader.read_indices() {
+                Some(indices) => indices.into_u32().collect(),
+                None => (0..vertices.len() as u32).collect(),
+            };
+
+            parts.push(ModelPart { vertices, indices });
+        }
+    }
+
+    for child in node.children() {
+        read_node(&child, transform, buffers, parts);
+    }
+}
@@ -0,0 +1,191 @@
+use std::{cmp::Reverse, collections::{BinaryHeap, HashMap, HashSet}};
+
+use tiny_game_framework::glam::{vec3, Vec3};
+
+use crate::{coords::{ChunkCoord, TileCoord, WorldPos}, patrol::WalkableGrid};
+
+// anything steeper than this can't be walked up, in degrees from flat
+const MAX_SLOPE: f32 = 40.0;
+// corners closer than this are the same corner, so triangles from separate meshes still link up
+const WELD_DISTANCE: f32 = 1.0;
+// how far above or below a triangle a point can be and still count as standing on it
+const STEP_HEIGHT: f32 = 150.0;
+
+struct NavTriangle {
+    corners: [usize; 3],
+    center: Vec3,
+    // triangles sharing an edge with this one, with the middle of that edge
+    neighbors: Vec<(usize, Vec3)>,
+    // something was put on it, nothing paths through anymore
+    blocked: bool,
+}
+
+// walkable triangles taken from whatever geometry got spawned, for the places the tile grid can't describe like
+// terrain with hills
+#[derive(Default)]
+pub struct NavMesh {
+    corners: Vec<Vec3>,
+    welded: HashMap<(i32, i32, i32), usize>,
+    // edge (lower corner first) to the first triangle that had it
+    edges: HashMap<(usize, usize), usize>,
+    triangles: Vec<NavTriangle>,
+    // corners of every triangle, lowest first, so geometry that's added again doesn't double up
+    known: HashSet<[usize; 3]>,
+}
+
+impl NavMesh {
+    // `vertices` in world space, three indices per triangle. triangles too steep to walk on are left out
+    pub fn add_geometry(&mut self, vertices: &[Vec3], indices: &[u32]) {
+        let min_up = MAX_SLOPE.to_radians().cos();
+
+        for triangle in indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| vertices[triangle[i] as usize]);
+            let normal = (b - a).cross(c - a).normalize_or_zero();
+            if normal.y.abs() < min_up {
+                continue;
+            }
+
+            let corners = [a, b, c].map(|corner| self.weld(corner));
+            let mut key = corners;
+            key.sort_unstable();
+            if !self.known.insert(key) {
+                continue;
+            }
+            let index = self.triangles.len();
+            self.triangles.push(NavTriangle { corners, center: (a + b + c) / 3.0, neighbors: Vec::new(), blocked: false });
+
+            for (from, to) in [(0, 1), (1, 2), (2, 0)] {
+                let edge = (corners[from].min(corners[to]), corners[from].max(corners[to]));
+                match self.edges.get(&edge) {
+                    Some(&other) => {
+                        let middle = (self.corners[edge.0] + self.corners[edge.1]) / 2.0;
+                        self.triangles[other].neighbors.push((index, middle));
+                        self.triangles[index].neighbors.push((other, middle));
+                    }
+                    None => {
+                        self.edges.insert(edge, index);
+                    }
+                }
+            }
+        }
+    }
+
+    fn weld(&mut self, corner: Vec3) -> usize {
+        let key = ((corner.x / WELD_DISTANCE).round() as i32, (corner.y / WELD_DISTANCE).round() as i32, (corner.z / WELD_DISTANCE).round() as i32);
+        let corners = &mut self.corners;
+        *self.welded.entry(key).or_insert_with(|| {
+            corners.push(corner);
+            corners.len() - 1
+        })
+    }
+
+    // takes out every triangle whose middle is on the tile
+    pub fn block_tile(&mut self, tile: TileCoord) {
+        for triangle in self.triangles.iter_mut() {
+            if WorldPos(triangle.center).tile() == tile {
+                triangle.blocked = true;
+            }
+        }
+    }
+
+    // drops every triangle whose middle is in the chunk. indices shift when triangles go, so what's left is welded
+    // and linked up again from scratch, unloading is rare enough for that
+    pub fn remove_chunk(&mut self, chunk: ChunkCoord) {
+        let old = std::mem::take(self);
+        for triangle in old.triangles.iter().filter(|triangle| WorldPos(triangle.center).chunk() != chunk) {
+            let vertices = triangle.corners.map(|corner| old.corners[corner]);
+            self.add_geometry(&vertices, &[0, 1, 2]);
+            if let Some(added) = self.triangles.last_mut() {
+                added.blocked = triangle.blocked;
+            }
+        }
+    }
+
+    // the triangle right above or below `point`, the closest one in height if several are
+    pub fn find(&self, point: Vec3) -> Option<usize> {
+        self.triangles.iter().enumerate()
+            .filter(|(_, triangle)| !triangle.blocked)
+            .filter_map(|(i, triangle)| {
+                let height = self.height_at(triangle, point)?;
+                let gap = (height - point.y).abs();
+                (gap <= STEP_HEIGHT).then_some((i, gap))
+            })
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(i, _)| i)
+    }
+
+    // where the triangle's surface is under `point`, `None` if the point isn't over it
+    fn height_at(&self, triangle: &NavTriangle, point: Vec3) -> Option<f32> {
+        let [a, b, c] = triangle.corners.map(|corner| self.corners[corner]);
+        let cross = |u: Vec3, v: Vec3| u.x * v.z - u.z * v.x;
+        let area = cross(b - a, c - a);
+        if area.abs() <= f32::EPSILON {
+            return None;
+        }
+
+        let u = cross(c - b, point - b) / area;
+        let v = cross(a - c, point - c) / area;
+        let w = 1.0 - u - v;
+        (u >= 0.0 && v >= 0.0 && w >= 0.0).then_some(a.y * u + b.y * v + c.y * w)
+    }
+
+    // a* from triangle to triangle, through the middle of every edge crossed. `None` when either end is off the
+    // mesh or there's no way through
+    pub fn path(&self, from: Vec3, to: Vec3) -> Option<Vec<Vec3>> {
+        let (start, goal) = (self.find(from)?, self.find(to)?);
+
+        // costs in whole world units so they can go in the heap
+        let mut open = BinaryHeap::from([Reverse((0, start))]);
+        let mut came_from: HashMap<usize, (usize, Vec3)> = HashMap::new();
+        let mut cost = HashMap::from([(start, 0)]);
+        while let Some(Reverse((_, current))) = open.pop() {
+            if current == goal {
+                let mut path = vec![to];
+                let mut step = current;
+                while let Some(&(previous, through)) = came_from.get(&step) {
+                    path.push(through);
+                    step = previous;
+                }
+                path.reverse();
+                return Some(path);
+            }
+
+            for &(neighbor, through) in self.triangles[current].neighbors.iter() {
+                if self.triangles[neighbor].blocked {
+                    continue;
+                }
+                let next = cost[&current] + self.triangles[current].center.distance(self.triangles[neighbor].center) as u32;
+                if cost.get(&neighbor).is_none_or(|&known| next < known) {
+                    cost.insert(neighbor, next);
+                    came_from.insert(neighbor, (current, through));
+                    let estimate = self.triangles[neighbor].center.distance(to) as u32;
+                    open.push(Reverse((next + estimate, neighbor)));
+                }
+            }
+        }
+
+        None
+    }
+}
+
+// one place to ask for a way from a to b, the navmesh where there is one and the tile grid everywhere else
+pub struct Navigator<'a> {
+    pub navmesh: &'a NavMesh,
+    pub grid: &'a WalkableGrid,
+}
+
+impl Navigator<'_> {
+    // waypoints up to and including `to`, at the height of `from` wherever the grid had to be used
+    pub fn path(&self, from: Vec3, to: Vec3) -> Option<Vec<Vec3>> {
+        if let Some(path) = self.navmesh.path(from, to) {
+            return Some(path);
+        }
+
+        let tiles = self.grid.find_path(WorldPos(from).tile(), WorldPos(to).tile())?;
+        let mut path: Vec<Vec3> = tiles.into_iter().skip(1).map(|tile| tile.world(from.y).into()).collect();
+        // the last tile's middle isn't where we were going
+        path.pop();
+        path.push(vec3(to.x, from.y, to.z));
+        Some(path)
+    }
+}
@@ -0,0 +1,514 @@
+use std::{collections::{BTreeMap, HashMap, VecDeque}, io, net::{SocketAddr, UdpSocket}, thread, time::{Duration, Instant}};
+
+use serde::{Deserialize, Serialize};
+use tiny_game_framework::{glam::{Vec3, Vec4}, Cuboid, Renderer};
+
+use crate::{error::RendererExt, character_controller::PlayerInput, config::{NetMode, NetSettings}, enemies::{EnemyKind, EnemySystem}};
+
+// biggest thing that fits in a udp datagram
+const MAX_PACKET_SIZE: usize = 65507;
+// transform updates per second
+const SEND_RATE: f32 = 20.0;
+// remote things are drawn this far in the past so there's (almost) always two snapshots to blend between
+const INTERPOLATION_DELAY: f32 = 0.1;
+const MAX_SNAPSHOTS: usize = 16;
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+const HELLO_RESEND: Duration = Duration::from_millis(500);
+const HOST_PLAYER_ID: u32 = 0;
+// lockstep inputs are scheduled this many ticks ahead, so they have time to arrive before they're needed
+const INPUT_DELAY: u64 = 3;
+// every input packet repeats this many of the latest inputs, so a lost packet doesn't stall everyone
+const INPUT_REDUNDANCY: usize = 8;
+// ticks between state hash checks
+const HASH_INTERVAL: u64 = 60;
+// seconds without hearing from a remote player before they count as gone, for when they couldn't say goodbye
+const PEER_TIMEOUT: f32 = 5.0;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum WorldEvent {
+    BossDoors { locked: bool },
+    // clients don't own enemies, so their hits get forwarded to the host
+    EnemyHit { enemy: u64, amount: f32 },
+    // and the host tells clients when its enemies hit them
+    PlayerHit { player_id: u32, amount: f32, knockback: [f32; 3] },
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub struct EnemySnapshot {
+    // the enemy's entity id on the host, local ids don't line up between machines
+    pub net_id: u64,
+    pub kind: EnemyKind,
+    pub position: [f32; 3],
+    pub health: f32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+enum Packet {
+    Hello { name: String },
+    Welcome { player_id: u32, seed: u64 },
+    PlayerState { player_id: u32, name: String, position: [f32; 3] },
+    Enemies(Vec<EnemySnapshot>),
+    Event(WorldEvent),
+    // lockstep only, the latest few inputs of one player keyed by the tick they're for
+    Inputs { player_id: u32, inputs: Vec<(u64, PlayerInput)> },
+    StateHash { player_id: u32, tick: u64, hash: u64 },
+    Chat { player_id: u32, name: String, text: String },
+    Goodbye { player_id: u32 },
+}
+
+// positions as they came in over the network, sampled a little in the past to smooth out jitter and packet loss
+#[derive(Default)]
+pub struct Interpolated {
+    snapshots: VecDeque<(f32, Vec3)>,
+}
+
+impl Interpolated {
+    fn push(&mut self, time: f32, position: Vec3) {
+        self.snapshots.push_back((time, position));
+        if self.snapshots.len() > MAX_SNAPSHOTS {
+            self.snapshots.pop_front();
+        }
+    }
+
+    // holds the oldest or newest position when `time` is outside what we have
+    pub fn sample(&self, time: f32) -> Option<Vec3> {
+        let &(_, newest) = self.snapshots.back()?;
+
+        for ((t0, p0), (t1, p1)) in self.snapshots.iter().zip(self.snapshots.iter().skip(1)) {
+            if time >= *t0 && time <= *t1 {
+                let t = if t1 > t0 { (time - t0) / (t1 - t0) } else { 1.0 };
+                return Some(p0.lerp(*p1, t));
+            }
+        }
+
+        match self.snapshots.front() {
+            Some(&(t0, oldest)) if time < t0 => Some(oldest),
+            _ => Some(newest),
+        }
+    }
+}
+
+pub struct RemotePlayer {
+    pub name: String,
+    pub position: Vec3,
+    pub motion: Interpolated,
+    pub mesh_name: String,
+    // `NetSession::clock` when their last state came in
+    last_heard: f32,
+}
+
+pub struct ReplicatedEnemy {
+    pub kind: EnemyKind,
+    pub health: f32,
+    pub position: Vec3,
+    pub motion: Interpolated,
+}
+
+// listen server: the host simulates everything and clients mirror it. every client generates the dungeon
+// locally from the host's seed, so only what moves goes over the wire
+pub struct NetSession {
+    pub mode: NetMode,
+    pub player_id: u32,
+    pub seed: u64,
+    pub name: String,
+    pub remote_players: HashMap<u32, RemotePlayer>,
+    // clients only, what the host last said about its enemies
+    pub enemies: HashMap<u64, ReplicatedEnemy>,
+
+    socket: UdpSocket,
+    // everyone that said hello for the host, just the host for clients
+    peers: HashMap<SocketAddr, u32>,
+    next_player_id: u32,
+    events: Vec<WorldEvent>,
+    // (sender name, message) received since the last `take_chat`
+    chat: Vec<(String, String)>,
+    // whatever each player last called themselves
+    names: HashMap<u32, String>,
+    // players that said goodbye since the last `update`
+    left: Vec<u32>,
+    clock: f32,
+    send_timer: f32,
+
+    pub lockstep: bool,
+    pub lockstep_players: u32,
+    // the next tick to simulate
+    tick: u64,
+    inputs: BTreeMap<u64, HashMap<u32, PlayerInput>>,
+    sent_inputs: VecDeque<(u64, PlayerInput)>,
+    hashes: HashMap<u64, u64>,
+    remote_hashes: Vec<(u32, u64, u64)>,
+}
+
+impl NetSession {
+    // `None` when playing offline
+    pub fn start(settings: &NetSettings) -> io::Result<Option<Self>> {
+        match settings.mode {
+            NetMode::Offline => Ok(None),
+            NetMode::Host => {
+                let socket = UdpSocket::bind(&settings.address)?;
+                socket.set_nonblocking(true)?;
+                Ok(Some(Self::new(settings, socket, HOST_PLAYER_ID, fastrand::u64(..))))
+            }
+            NetMode::Client => {
+                let host: SocketAddr = settings.address.parse()
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+                let socket = UdpSocket::bind("0.0.0.0:0")?;
+                let (player_id, seed) = Self::handshake(&socket, host, &settings.name)?;
+                socket.set_nonblocking(true)?;
+
+                let mut session = Self::new(settings, socket, player_id, seed);
+                session.peers.insert(host, HOST_PLAYER_ID);
+                Ok(Some(session))
+            }
+        }
+    }
+
+    fn new(settings: &NetSettings, socket: UdpSocket, player_id: u32, seed: u64) -> Self {
+        Self {
+            mode: settings.mode,
+            player_id,
+            seed,
+            name: settings.name.clone(),
+            remote_players: HashMap::new(),
+            enemies: HashMap::new(),
+            socket,
+            peers: HashMap::new(),
+            next_player_id: HOST_PLAYER_ID + 1,
+            events: Vec::new(),
+            chat: Vec::new(),
+            names: HashMap::new(),
+            left: Vec::new(),
+            clock: 0.0,
+            send_timer: 0.0,
+            lockstep: settings.lockstep,
+            lockstep_players: settings.lockstep_players.max(1),
+            tick: 0,
+            inputs: BTreeMap::new(),
+            sent_inputs: VecDeque::new(),
+            hashes: HashMap::new(),
+            remote_hashes: Vec::new(),
+        }
+    }
+
+    // blocks until the host answers, we need the seed before the dungeon can be generated
+    fn handshake(socket: &UdpSocket, host: SocketAddr, name: &str) -> io::Result<(u32, u64)> {
+        socket.set_read_timeout(Some(HELLO_RESEND))?;
+        let hello = serde_json::to_vec(&Packet::Hello { name: name.to_string() })?;
+        let started = Instant::now();
+        let mut buffer = vec![0; MAX_PACKET_SIZE];
+
+        while started.elapsed() < HANDSHAKE_TIMEOUT {
+            socket.send_to(&hello, host)?;
+
+            match socket.recv_from(&mut buffer) {
+                Ok((size, from)) if from == host => {
+                    if let Ok(Packet::Welcome { player_id, seed }) = serde_json::from_slice(&buffer[..size]) {
+                        return Ok((player_id, seed));
+                    }
+                }
+                Ok(_) => {}
+                Err(e) if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        Err(io::Error::new(io::ErrorKind::TimedOut, format!("no answer from {}", host)))
+    }
+
+    pub fn is_host(&self) -> bool {
+        self.mode == NetMode::Host
+    }
+
+    // everything that happened on other machines since the last call
+    pub fn take_events(&mut self) -> Vec<WorldEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    pub fn send_event(&mut self, event: WorldEvent) {
+        self.broadcast(&Packet::Event(event), None);
+    }
+
+    // call when quitting, so everyone else drops our cube straight away instead of after `PEER_TIMEOUT`
+    pub fn leave(&self) {
+        self.broadcast(&Packet::Goodbye { player_id: self.player_id }, None);
+    }
+
+    pub fn send_chat(&mut self, text: &str) {
+        let packet = Packet::Chat { player_id: self.player_id, name: self.name.clone(), text: text.to_string() };
+        self.broadcast(&packet, None);
+    }
+
+    // messages from everyone else, ours aren't echoed back
+    pub fn take_chat(&mut self) -> Vec<(String, String)> {
+        std::mem::take(&mut self.chat)
+    }
+
+    // lockstep peers never send their state, so until someone chats they get a placeholder
+    pub fn player_name(&self, player_id: u32) -> String {
+        match self.names.get(&player_id) {
+            Some(name) => name.clone(),
+            None => format!("player {}", player_id),
+        }
+    }
+
+    // sorted by player id
+    pub fn remote_positions(&self) -> Vec<(u32, Vec3)> {
+        let mut positions: Vec<(u32, Vec3)> = self.remote_players.iter().map(|(&id, player)| (id, player.position)).collect();
+        positions.sort_by_key(|&(id, _)| id);
+        positions
+    }
+
+    pub fn update(&mut self, r: &mut Renderer, enemies: &EnemySystem, position: Vec3, dt: f32) {
+        self.clock += dt;
+        self.poll();
+
+        self.send_timer -= dt;
+        if self.send_timer <= 0.0 {
+            self.send_timer = 1.0 / SEND_RATE;
+
+            let state = Packet::PlayerState { player_id: self.player_id, name: self.name.clone(), position: position.to_array() };
+            self.broadcast(&state, None);
+
+            if self.is_host() {
+                let snapshots = enemies.enemies.iter().map(|enemy| EnemySnapshot {
+                    net_id: enemy.net_id,
+                    kind: enemy.kind,
+                    position: enemy.position.to_array(),
+                    health: enemy.health,
+                }).collect();
+                self.broadcast(&Packet::Enemies(snapshots), None);
+            }
+        }
+
+        // whoever said goodbye or went quiet takes their cube with them
+        let clock = self.clock;
+        self.left.extend(self.remote_players.iter().filter(|(_, player)| clock - player.last_heard > PEER_TIMEOUT).map(|(&id, _)| id));
+        for player_id in std::mem::take(&mut self.left) {
+            if let Some(player) = self.remote_players.remove(&player_id) {
+                log::info!("{} left", player.name);
+                r.destroy_mesh(&player.mesh_name).ok();
+            }
+            self.peers.retain(|_, &mut id| id != player_id);
+        }
+
+        let render_time = self.clock - INTERPOLATION_DELAY;
+        for player in self.remote_players.values_mut() {
+            if let Some(position) = player.motion.sample(render_time) {
+                player.position = position;
+            }
+
+            if r.get_mesh(&player.mesh_name).is_none() {
+                let mut mesh = Cuboid::new(Vec3::splat(100.0), Vec4::new(0.3, 0.6, 1.0, 1.0)).mesh();
+                mesh.setup_mesh();
+                if let Err(e) = r.try_add_mesh(&player.mesh_name, mesh) {
+                    log::warn!("{}", e);
+                }
+            }
+            if let Some(mesh) = r.get_mesh_mut(&player.mesh_name) {
+                mesh.position = player.position;
+            }
+        }
+        for enemy in self.enemies.values_mut() {
+            if let Some(position) = enemy.motion.sample(render_time) {
+                enemy.position = position;
+            }
+        }
+    }
+
+    // lockstep: everyone's input for the next tick in player id order, or `None` if someone's input hasn't
+    // shown up within `timeout`
+    pub fn wait_for_tick(&mut self, timeout: Duration) -> Option<Vec<(u32, PlayerInput)>> {
+        let started = Instant::now();
+        loop {
+            self.poll();
+            if let Some(inputs) = self.take_tick() {
+                return Some(inputs);
+            }
+            if started.elapsed() >= timeout {
+                return None;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    fn take_tick(&mut self) -> Option<Vec<(u32, PlayerInput)>> {
+        // nobody could have sent anything for the first few ticks, so those start out idle
+        let mut inputs = self.inputs.remove(&self.tick).unwrap_or_default();
+        if self.tick < INPUT_DELAY {
+            for player_id in 0..self.lockstep_players {
+                inputs.entry(player_id).or_default();
+            }
+        }
+
+        if (0..self.lockstep_players).any(|player_id| !inputs.contains_key(&player_id)) {
+            self.inputs.insert(self.tick, inputs);
+            return None;
+        }
+
+        self.tick += 1;
+        let mut inputs: Vec<(u32, PlayerInput)> = inputs.into_iter().collect();
+        inputs.sort_by_key(|&(player_id, _)| player_id);
+        Some(inputs)
+    }
+
+    // call once per simulated tick, the input gets used `INPUT_DELAY` ticks from now
+    pub fn submit_input(&mut self, input: PlayerInput) {
+        let tick = self.tick - 1 + INPUT_DELAY;
+        self.inputs.entry(tick).or_default().insert(self.player_id, input);
+
+        self.sent_inputs.push_back((tick, input));
+        if self.sent_inputs.len() > INPUT_REDUNDANCY {
+            self.sent_inputs.pop_front();
+        }
+        let packet = Packet::Inputs { player_id: self.player_id, inputs: self.sent_inputs.iter().copied().collect() };
+        self.broadcast(&packet, None);
+    }
+
+    // call after stepping the tick that `wait_for_tick` just handed out
+    pub fn submit_hash(&mut self, hash: u64) {
+        let tick = self.tick - 1;
+        if !tick.is_multiple_of(HASH_INTERVAL) {
+            return;
+        }
+
+        self.hashes.insert(tick, hash);
+        self.broadcast(&Packet::StateHash { player_id: self.player_id, tick, hash }, None);
+        self.check_hashes();
+    }
+
+    fn check_hashes(&mut self) {
+        let hashes = &self.hashes;
+        self.remote_hashes.retain(|&(player_id, tick, hash)| {
+            let Some(&ours) = hashes.get(&tick) else {
+                return true;
+            };
+            if ours != hash {
+                log::error!("desync with player {} at tick {}", player_id, tick);
+            }
+            false
+        });
+    }
+
+    fn poll(&mut self) {
+        let mut buffer = vec![0; MAX_PACKET_SIZE];
+
+        loop {
+            let (size, from) = match self.socket.recv_from(&mut buffer) {
+                Ok(received) => received,
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) => {
+                    log::warn!("network error: {}", e);
+                    break;
+                }
+            };
+
+            match serde_json::from_slice(&buffer[..size]) {
+                Ok(packet) => self.handle(packet, from),
+                Err(e) => log::warn!("dropping a bad packet from {}: {}", from, e),
+            }
+        }
+    }
+
+    fn handle(&mut self, packet: Packet, from: SocketAddr) {
+        match packet {
+            Packet::Hello { .. } if self.is_host() => {
+                // hellos get resent until a welcome makes it through, so the same client can say it more than once
+                let player_id = *self.peers.entry(from).or_insert_with(|| {
+                    self.next_player_id += 1;
+                    self.next_player_id - 1
+                });
+                self.send_to(&Packet::Welcome { player_id, seed: self.seed }, from);
+            }
+            Packet::PlayerState { player_id, name, position } => {
+                if player_id == self.player_id {
+                    return;
+                }
+                let player = self.remote_players.entry(player_id).or_insert_with(|| RemotePlayer {
+                    name: String::new(),
+                    position: Vec3::from_array(position),
+                    motion: Interpolated::default(),
+                    mesh_name: format!("REMOTE_PLAYER_MESH{}", player_id),
+                    last_heard: self.clock,
+                });
+                player.name = name.clone();
+                player.last_heard = self.clock;
+                self.names.insert(player_id, name.clone());
+                player.motion.push(self.clock, Vec3::from_array(position));
+
+                // clients only talk to the host, so it passes everyone along to everyone else
+                if self.is_host() {
+                    self.broadcast(&Packet::PlayerState { player_id, name, position }, Some(from));
+                }
+            }
+            Packet::Enemies(snapshots) if !self.is_host() => {
+                for snapshot in snapshots {
+                    let enemy = self.enemies.entry(snapshot.net_id).or_insert_with(|| ReplicatedEnemy {
+                        kind: snapshot.kind,
+                        health: snapshot.health,
+                        position: Vec3::from_array(snapshot.position),
+                        motion: Interpolated::default(),
+                    });
+                    enemy.health = snapshot.health;
+                    enemy.motion.push(self.clock, Vec3::from_array(snapshot.position));
+                }
+            }
+            Packet::Inputs { player_id, inputs } => {
+                for (tick, input) in inputs.iter().copied().filter(|&(tick, _)| tick >= self.tick) {
+                    self.inputs.entry(tick).or_default().insert(player_id, input);
+                }
+                if self.is_host() {
+                    self.broadcast(&Packet::Inputs { player_id, inputs }, Some(from));
+                }
+            }
+            Packet::StateHash { player_id, tick, hash } => {
+                if self.is_host() {
+                    self.broadcast(&Packet::StateHash { player_id, tick, hash }, Some(from));
+                }
+                self.remote_hashes.push((player_id, tick, hash));
+                self.check_hashes();
+            }
+            Packet::Chat { player_id, name, text } => {
+                if player_id == self.player_id {
+                    return;
+                }
+                if self.is_host() {
+                    self.broadcast(&Packet::Chat { player_id, name: name.clone(), text: text.clone() }, Some(from));
+                }
+                self.names.insert(player_id, name.clone());
+                self.chat.push((name, text));
+            }
+            Packet::Goodbye { player_id } => {
+                if self.is_host() {
+                    self.broadcast(&Packet::Goodbye { player_id }, Some(from));
+                }
+                self.left.push(player_id);
+            }
+            Packet::Event(event) => {
+                if self.is_host() {
+                    self.broadcast(&Packet::Event(event), Some(from));
+                }
+                self.events.push(event);
+            }
+            _ => {}
+        }
+    }
+
+    fn broadcast(&self, packet: &Packet, except: Option<SocketAddr>) {
+        for &peer in self.peers.keys().filter(|&&peer| Some(peer) != except) {
+            self.send_to(packet, peer);
+        }
+    }
+
+    fn send_to(&self, packet: &Packet, to: SocketAddr) {
+        let bytes = match serde_json::to_vec(packet) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                log::warn!("couldn't encode a packet: {}", e);
+                return;
+            }
+        };
+        if let Err(e) = self.socket.send_to(&bytes, to) {
+            log::warn!("couldn't send to {}: {}", to, e);
+        }
+    }
+}
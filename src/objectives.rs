@@ -0,0 +1,119 @@
+use tiny_game_framework::{glam::{Vec3, Vec4}, Cuboid, Renderer};
+
+use crate::{
+    coords::{self, TileCoord, WorldPos},
+    error::RendererExt,
+    events::{EventBus, GameEvent},
+    inventory::Item,
+    lighting::{Emissive, Lighting},
+};
+
+const EXIT_MESH: &str = "EXIT_MESH";
+const EXIT_HEIGHT: f32 = 8.0;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ObjectiveKind {
+    FindKey(usize),
+    DefeatBoss,
+    ReachExit,
+}
+
+impl ObjectiveKind {
+    pub fn description(&self) -> String {
+        match self {
+            ObjectiveKind::FindKey(key) => format!("find the {}", Item::Key(*key).name()),
+            ObjectiveKind::DefeatBoss => "defeat the boss".to_string(),
+            ObjectiveKind::ReachExit => "reach the exit".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Objective {
+    pub kind: ObjectiveKind,
+    pub done: bool,
+}
+
+// what the players have to do to finish the floor. everything is ticked off from the event bus, the only thing
+// it checks itself is whether someone is standing on the exit
+pub struct Objectives {
+    pub objectives: Vec<Objective>,
+    // the tile the exit opens up on once the boss is dead
+    exit: Option<TileCoord>,
+    exit_open: bool,
+    cleared: bool,
+}
+
+impl Objectives {
+    pub fn new() -> Self {
+        Self {
+            objectives: Vec::new(),
+            exit: None,
+            exit_open: false,
+            cleared: false,
+        }
+    }
+
+    // call once the floor is planned, `keys` are the locked doors' keys and the exit goes where the boss fights
+    pub fn plan(&mut self, keys: impl Iterator<Item = usize>, exit: Vec3) {
+        self.objectives = keys.map(ObjectiveKind::FindKey)
+            .chain([ObjectiveKind::DefeatBoss, ObjectiveKind::ReachExit])
+            .map(|kind| Objective { kind, done: false })
+            .collect();
+        self.exit = Some(WorldPos(exit).tile());
+    }
+
+    pub fn complete(&mut self, kind: ObjectiveKind) {
+        for objective in self.objectives.iter_mut().filter(|objective| objective.kind == kind) {
+            objective.done = true;
+        }
+    }
+
+    fn is_done(&self, kind: ObjectiveKind) -> bool {
+        self.objectives.iter().any(|objective| objective.kind == kind && objective.done)
+    }
+
+    // run after everything else had its chance to emit this frame
+    pub fn update(&mut self, r: &mut Renderer, lighting: &mut Lighting, bus: &mut EventBus, players: &[Vec3]) {
+        let Some(exit) = self.exit else { return };
+
+        if self.exit_open && !self.is_done(ObjectiveKind::ReachExit) {
+            if let Some(player) = players.iter().position(|&position| WorldPos(position).tile() == exit) {
+                bus.emit(GameEvent::ExitReached { player });
+            }
+        }
+
+        let events: Vec<GameEvent> = bus.iter().copied().collect();
+        for event in events {
+            match event {
+                GameEvent::KeyPickedUp { key, .. } => self.complete(ObjectiveKind::FindKey(key)),
+                GameEvent::BossDefeated => self.complete(ObjectiveKind::DefeatBoss),
+                GameEvent::ExitReached { .. } => self.complete(ObjectiveKind::ReachExit),
+                _ => {}
+            }
+        }
+
+        if self.is_done(ObjectiveKind::DefeatBoss) && !self.exit_open {
+            self.open_exit(r, lighting, exit);
+        }
+
+        if !self.cleared && self.objectives.iter().all(|objective| objective.done) {
+            self.cleared = true;
+            bus.emit(GameEvent::FloorCleared);
+        }
+    }
+
+    fn open_exit(&mut self, r: &mut Renderer, lighting: &mut Lighting, exit: TileCoord) {
+        self.exit_open = true;
+
+        let floor = coords::floor_height() + coords::tile_size() / 2.0;
+        let mut mesh = Cuboid::new(Vec3::new(coords::tile_size() * 0.9, EXIT_HEIGHT, coords::tile_size() * 0.9), Vec4::new(0.95, 0.95, 0.6, 1.0)).mesh();
+        mesh.position = exit.world(floor).into();
+        Emissive::PORTAL.apply(&mut mesh);
+        lighting.place_light(EXIT_MESH, mesh.position, Emissive::PORTAL);
+        mesh.setup_mesh();
+        if let Err(e) = r.try_add_mesh(EXIT_MESH, mesh) {
+            log::warn!("{}", e);
+        }
+    }
+}
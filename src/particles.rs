@@ -0,0 +1,233 @@
+use rapier3d::prelude::*;
+use tiny_game_framework::{glam::{vec2, vec3, Vec3, Vec4}, rand_betw, Mesh, Renderer, Vertex};
+
+use crate::{error::RendererExt, rapier_integration::RapierPhysicsWorld, render_layers::RenderLayer};
+
+// upper bound per emitter so a long running torch can't grow forever
+const MAX_PARTICLES: usize = 256;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ParticlePreset {
+    TorchFlame,
+    Dust,
+    Sparks,
+    Splinters,
+}
+
+impl ParticlePreset {
+    // particles per second for continuous emitters, bursts ignore this
+    fn rate(&self) -> f32 {
+        match self {
+            ParticlePreset::TorchFlame => 40.0,
+            ParticlePreset::Dust => 20.0,
+            ParticlePreset::Sparks => 30.0,
+            ParticlePreset::Splinters => 30.0,
+        }
+    }
+
+    fn lifetime(&self) -> f32 {
+        match self {
+            ParticlePreset::TorchFlame => rand_betw(0.4, 0.8),
+            ParticlePreset::Dust => rand_betw(1.0, 2.0),
+            ParticlePreset::Sparks => rand_betw(0.2, 0.4),
+            ParticlePreset::Splinters => rand_betw(0.4, 0.7),
+        }
+    }
+
+    fn size(&self) -> f32 {
+        match self {
+            ParticlePreset::TorchFlame => 12.0,
+            ParticlePreset::Dust => 20.0,
+            ParticlePreset::Sparks => 5.0,
+            ParticlePreset::Splinters => 7.0,
+        }
+    }
+
+    fn gravity(&self) -> f32 {
+        match self {
+            // flames rise
+            ParticlePreset::TorchFlame => -60.0,
+            ParticlePreset::Dust => 20.0,
+            ParticlePreset::Sparks => 600.0,
+            ParticlePreset::Splinters => 800.0,
+        }
+    }
+
+    fn initial_velocity(&self) -> Vec3 {
+        let spread = vec3(rand_betw(-1.0, 1.0), rand_betw(-1.0, 1.0), rand_betw(-1.0, 1.0));
+        match self {
+            ParticlePreset::TorchFlame => vec3(spread.x * 10.0, rand_betw(20.0, 40.0), spread.z * 10.0),
+            ParticlePreset::Dust => spread * 60.0,
+            ParticlePreset::Sparks => spread.normalize_or_zero() * rand_betw(150.0, 300.0),
+            // kicked up off the floor, not out in every direction
+            ParticlePreset::Splinters => vec3(spread.x * 120.0, rand_betw(100.0, 250.0), spread.z * 120.0),
+        }
+    }
+
+    // color at birth and at death, faded between over the lifetime
+    fn colors(&self) -> (Vec4, Vec4) {
+        match self {
+            ParticlePreset::TorchFlame => (Vec4::new(1.0, 0.8, 0.2, 1.0), Vec4::new(0.8, 0.1, 0.0, 0.0)),
+            ParticlePreset::Dust => (Vec4::new(0.55, 0.5, 0.45, 0.8), Vec4::new(0.45, 0.4, 0.35, 0.0)),
+            ParticlePreset::Sparks => (Vec4::new(1.0, 1.0, 0.7, 1.0), Vec4::new(1.0, 0.4, 0.0, 0.0)),
+            ParticlePreset::Splinters => (Vec4::new(0.6, 0.42, 0.25, 1.0), Vec4::new(0.45, 0.3, 0.18, 0.0)),
+        }
+    }
+}
+
+pub struct Particle {
+    pub position: Vec3,
+    pub velocity: Vec3,
+    pub age: f32,
+    pub lifetime: f32,
+}
+
+pub struct ParticleEmitter {
+    pub preset: ParticlePreset,
+    pub position: Vec3,
+    // follows this body around when set, e.g. a torch carried on a platform
+    pub attached: Option<RigidBodyHandle>,
+    pub offset: Vec3,
+    // false for one-off bursts, which get cleaned up once their particles die
+    pub continuous: bool,
+    pub particles: Vec<Particle>,
+    pub mesh_name: String,
+    spawn_accumulator: f32,
+}
+
+impl ParticleEmitter {
+    fn emit(&mut self, count: usize) {
+        let count = count.min(MAX_PARTICLES - self.particles.len());
+        for _ in 0..count {
+            self.particles.push(Particle {
+                position: self.position,
+                velocity: self.preset.initial_velocity(),
+                age: 0.0,
+                lifetime: self.preset.lifetime(),
+            });
+        }
+    }
+
+    fn simulate(&mut self, dt: f32) {
+        let gravity = self.preset.gravity();
+        for particle in self.particles.iter_mut() {
+            particle.age += dt;
+            particle.velocity.y -= gravity * dt;
+            particle.position += particle.velocity * dt;
+        }
+        self.particles.retain(|particle| particle.age < particle.lifetime);
+    }
+
+    // one camera facing quad per particle, all in the same mesh so an emitter is a single draw call
+    fn build_mesh(&self, right: Vec3, up: Vec3) -> Mesh {
+        let mut vertices = Vec::with_capacity(self.particles.len() * 4);
+        let mut indices = Vec::with_capacity(self.particles.len() * 6);
+        let (start, end) = self.preset.colors();
+        let half = self.preset.size() / 2.0;
+        let normal = right.cross(up);
+
+        for particle in self.particles.iter() {
+            let color = start.lerp(end, particle.age / particle.lifetime);
+            let (r, u) = (right * half, up * half);
+            let base = vertices.len() as u32;
+
+            vertices.push(Vertex::new(particle.position - r - u, color, vec2(0.0, 0.0), normal));
+            vertices.push(Vertex::new(particle.position + r - u, color, vec2(1.0, 0.0), normal));
+            vertices.push(Vertex::new(particle.position + r + u, color, vec2(1.0, 1.0), normal));
+            vertices.push(Vertex::new(particle.position - r + u, color, vec2(0.0, 1.0), normal));
+            indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+        }
+
+        let mut mesh = Mesh::new(&vertices, &indices);
+        mesh.setup_mesh();
+        mesh
+    }
+}
+
+pub struct ParticleSystem {
+    pub emitters: Vec<ParticleEmitter>,
+    emitter_counter: usize,
+}
+
+impl ParticleSystem {
+    pub fn new() -> Self {
+        Self {
+            emitters: Vec::new(),
+            emitter_counter: 0,
+        }
+    }
+
+    // keeps emitting for as long as the game runs, following `body` around
+    pub fn attach_emitter(&mut self, preset: ParticlePreset, body: RigidBodyHandle, offset: Vec3) -> usize {
+        let index = self.add(preset, Vec3::ZERO, Some(body), true);
+        self.emitters[index].offset = offset;
+        index
+    }
+
+    // fires `count` particles at once, the emitter goes away when they're all dead
+    pub fn burst(&mut self, preset: ParticlePreset, position: Vec3, count: usize) {
+        let index = self.add(preset, position, None, false);
+        self.emitters[index].emit(count);
+    }
+
+    fn add(&mut self, preset: ParticlePreset, position: Vec3, attached: Option<RigidBodyHandle>, continuous: bool) -> usize {
+        self.emitters.push(ParticleEmitter {
+            preset,
+            position,
+            attached,
+            offset: Vec3::ZERO,
+            continuous,
+            particles: Vec::new(),
+            mesh_name: format!("PARTICLES_MESH{}", self.emitter_counter),
+            spawn_accumulator: 0.0,
+        });
+        self.emitter_counter += 1;
+
+        self.emitters.len() - 1
+    }
+
+    pub fn update(&mut self, rw: &RapierPhysicsWorld, r: &mut Renderer, dt: f32) {
+        let (right, up) = (r.camera.right, r.camera.up);
+
+        let mut i = 0;
+        while i < self.emitters.len() {
+            let emitter = &mut self.emitters[i];
+
+            if let Some(body) = emitter.attached {
+                match rw.rigid_body_set.get(body) {
+                    Some(body) => {
+                        let t = body.translation();
+                        emitter.position = vec3(t.x, t.y, t.z) + emitter.offset;
+                    }
+                    // whatever it was attached to is gone, so let the last particles burn out
+                    None => {
+                        emitter.attached = None;
+                        emitter.continuous = false;
+                    }
+                }
+            }
+
+            if emitter.continuous {
+                emitter.spawn_accumulator += emitter.preset.rate() * dt;
+                let count = emitter.spawn_accumulator as usize;
+                emitter.spawn_accumulator -= count as f32;
+                emitter.emit(count);
+            }
+
+            emitter.simulate(dt);
+
+            // the particle count changes every frame so the mesh is rebuilt instead of patched
+            r.destroy_mesh(&emitter.mesh_name).ok();
+            if !emitter.continuous && emitter.particles.is_empty() {
+                self.emitters.swap_remove(i);
+                continue;
+            }
+            if !emitter.particles.is_empty() {
+                if let Err(e) = r.try_add_mesh_on(&emitter.mesh_name, emitter.build_mesh(right, up), RenderLayer::Particles) {
+                    log::warn!("{}", e);
+                }
+            }
+            i += 1;
+        }
+    }
+}
@@ -0,0 +1,195 @@
+use std::{cmp::Reverse, collections::{BTreeMap, BTreeSet, BinaryHeap, HashSet, VecDeque}};
+
+use tiny_game_framework::glam::Vec3;
+
+use crate::{coords::{TileCoord, WorldPos}, rng};
+
+// loops shorter than this (a 2x2 patch of floor) just look like spinning in place
+const MIN_LOOP_LENGTH: usize = 6;
+const MAX_ROUTE_LENGTH: usize = 16;
+// keeps the loop search from blowing up in big open rooms
+const MAX_SEARCH_STEPS: usize = 5000;
+
+// which floor tiles can be walked on, built up as the generator streams tiles in
+// ordered rather than hashed so iterating it goes the same way on every machine
+#[derive(Default)]
+pub struct WalkableGrid {
+    tiles: BTreeSet<TileCoord>,
+}
+
+impl WalkableGrid {
+    pub fn add(&mut self, position: Vec3) {
+        self.tiles.insert(Self::tile_of(position));
+    }
+
+    pub fn remove(&mut self, position: Vec3) {
+        self.tiles.remove(&Self::tile_of(position));
+    }
+
+    pub fn is_walkable(&self, position: Vec3) -> bool {
+        self.tiles.contains(&Self::tile_of(position))
+    }
+
+    // tile centers at floor level
+    pub fn positions(&self, height: f32) -> impl Iterator<Item = Vec3> + '_ {
+        self.tiles.iter().map(move |tile| tile.world(height).into())
+    }
+
+    fn tile_of(position: Vec3) -> TileCoord {
+        WorldPos(position).tile()
+    }
+
+    fn neighbors(&self, tile: TileCoord) -> impl Iterator<Item = TileCoord> + '_ {
+        tile.neighbors().filter(|neighbor| self.tiles.contains(neighbor))
+    }
+
+    // steps from the nearest of `sources` to every tile that can be reached without going through `blocked`
+    pub fn distances(&self, sources: &[TileCoord], blocked: &HashSet<TileCoord>) -> BTreeMap<TileCoord, usize> {
+        let mut distances = BTreeMap::new();
+        let mut queue = VecDeque::new();
+        for &source in sources.iter().filter(|source| self.tiles.contains(source) && !blocked.contains(source)) {
+            distances.insert(source, 0);
+            queue.push_back(source);
+        }
+
+        while let Some(tile) = queue.pop_front() {
+            let distance = distances[&tile];
+            for neighbor in self.neighbors(tile) {
+                if !blocked.contains(&neighbor) && !distances.contains_key(&neighbor) {
+                    distances.insert(neighbor, distance + 1);
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+
+        distances
+    }
+
+    // the shortest walk from `from` to `to` around `blocked`, both ends included
+    pub fn path(&self, from: TileCoord, to: TileCoord, blocked: &HashSet<TileCoord>) -> Option<Vec<TileCoord>> {
+        // walking back from the goal along ever smaller distances ends up at the start
+        let distances = self.distances(&[to], blocked);
+        let mut current = from;
+        let mut path = vec![current];
+        let mut distance = *distances.get(&from)?;
+
+        while distance > 0 {
+            current = self.neighbors(current).find(|neighbor| distances.get(neighbor) == Some(&(distance - 1)))?;
+            distance -= 1;
+            path.push(current);
+        }
+
+        Some(path)
+    }
+
+    // a* with straight line distance to `to`, both ends included. unlike `path` it only looks at tiles that
+    // could be on the way, so it's fine for the odd query at runtime
+    pub fn find_path(&self, from: TileCoord, to: TileCoord) -> Option<Vec<TileCoord>> {
+        if !self.tiles.contains(&from) || !self.tiles.contains(&to) {
+            return None;
+        }
+        let estimate = |tile: TileCoord| ((tile.x - to.x).abs() + (tile.z - to.z).abs()) as usize;
+
+        let mut open = BinaryHeap::from([Reverse((estimate(from), from))]);
+        let mut came_from = BTreeMap::new();
+        let mut steps = BTreeMap::from([(from, 0)]);
+        while let Some(Reverse((_, current))) = open.pop() {
+            if current == to {
+                let mut path = vec![current];
+                while let Some(&previous) = came_from.get(path.last().unwrap()) {
+                    path.push(previous);
+                }
+                path.reverse();
+                return Some(path);
+            }
+
+            let next = steps[&current] + 1;
+            for neighbor in self.neighbors(current) {
+                if steps.get(&neighbor).is_none_or(|&known| next < known) {
+                    steps.insert(neighbor, next);
+                    came_from.insert(neighbor, current);
+                    open.push(Reverse((next + estimate(neighbor), neighbor)));
+                }
+            }
+        }
+
+        None
+    }
+
+    // a loop of connected floor tiles through `start` if there is one close by, otherwise a there-and-back walk
+    // down the corridor. waypoints are at `height`
+    pub fn patrol_route(&self, start: Vec3, height: f32) -> Vec<Vec3> {
+        let start = Self::tile_of(start);
+        if !self.tiles.contains(&start) {
+            return Vec::new();
+        }
+
+        let route = self.find_loop(start).unwrap_or_else(|| self.out_and_back(start));
+
+        route.into_iter()
+            .map(|tile| tile.world(height).into())
+            .collect()
+    }
+
+    fn find_loop(&self, start: TileCoord) -> Option<Vec<TileCoord>> {
+        let mut path = vec![start];
+        let mut visited = HashSet::from([start]);
+        let mut steps = 0;
+
+        self.extend_loop(start, &mut path, &mut visited, &mut steps).then_some(path)
+    }
+
+    fn extend_loop(&self, start: TileCoord, path: &mut Vec<TileCoord>, visited: &mut HashSet<TileCoord>, steps: &mut usize) -> bool {
+        *steps += 1;
+        if *steps > MAX_SEARCH_STEPS || path.len() > MAX_ROUTE_LENGTH {
+            return false;
+        }
+
+        let current = *path.last().unwrap();
+
+        // shuffled so enemies spawned on the same tile don't all walk the same loop
+        let mut neighbors: Vec<TileCoord> = self.neighbors(current).collect();
+        rng::shuffle(&mut neighbors);
+
+        for neighbor in neighbors {
+            if neighbor == start && path.len() >= MIN_LOOP_LENGTH {
+                return true;
+            }
+            if visited.contains(&neighbor) {
+                continue;
+            }
+
+            visited.insert(neighbor);
+            path.push(neighbor);
+            if self.extend_loop(start, path, visited, steps) {
+                return true;
+            }
+            path.pop();
+            visited.remove(&neighbor);
+        }
+
+        false
+    }
+
+    // wanders away without doubling back, then the route walks the same tiles in reverse
+    fn out_and_back(&self, start: TileCoord) -> Vec<TileCoord> {
+        let mut path = vec![start];
+        let mut visited = HashSet::from([start]);
+
+        while path.len() < MAX_ROUTE_LENGTH / 2 {
+            let current = *path.last().unwrap();
+            let options: Vec<TileCoord> = self.neighbors(current).filter(|tile| !visited.contains(tile)).collect();
+            if options.is_empty() {
+                break;
+            }
+
+            let next = options[rng::usize(..options.len())];
+            visited.insert(next);
+            path.push(next);
+        }
+
+        let back: Vec<TileCoord> = path.iter().rev().skip(1).take(path.len().saturating_sub(2)).copied().collect();
+        path.extend(back);
+        path
+    }
+}
@@ -0,0 +1,94 @@
+use rapier3d::prelude::*;
+use tiny_game_framework::glam::Vec3;
+
+use crate::{entity::EntityKind, rapier_integration::RapierPhysicsWorld, rng};
+
+// full cone angle in degrees
+const SIGHT_FOV: f32 = 120.0;
+const SIGHT_DISTANCE: f32 = 1500.0;
+// sight raycasts aren't cheap, so they only happen this often (seconds)
+const SIGHT_INTERVAL: f32 = 0.2;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Noise {
+    pub position: Vec3,
+    // anyone within this distance hears it
+    pub radius: f32,
+}
+
+// anything can make a noise during the frame, AI reads them all and the bus gets cleared at the end of the frame
+#[derive(Default)]
+pub struct NoiseBus {
+    noises: Vec<Noise>,
+}
+
+impl NoiseBus {
+    pub fn emit(&mut self, position: Vec3, radius: f32) {
+        self.noises.push(Noise { position, radius });
+    }
+
+    // the closest noise `listener` can hear, if any
+    pub fn loudest_heard(&self, listener: Vec3) -> Option<Vec3> {
+        self.noises.iter()
+            .filter(|noise| noise.position.distance(listener) <= noise.radius)
+            .min_by(|a, b| a.position.distance(listener).total_cmp(&b.position.distance(listener)))
+            .map(|noise| noise.position)
+    }
+
+    pub fn clear(&mut self) {
+        self.noises.clear();
+    }
+}
+
+pub struct Perception {
+    pub can_see_player: bool,
+    // where the player was the last time they were in sight
+    pub last_seen: Option<Vec3>,
+    timer: f32,
+}
+
+impl Perception {
+    pub fn new() -> Self {
+        Self {
+            can_see_player: false,
+            last_seen: None,
+            // spread out so a group spawned together doesn't raycast on the same frame
+            timer: rng::f32() * SIGHT_INTERVAL,
+        }
+    }
+
+    // returns true on the frame the player goes out of sight
+    pub fn update(&mut self, rw: &RapierPhysicsWorld, body: RigidBodyHandle, eye: Vec3, facing: Vec3, player_pos: Vec3, dt: f32) -> bool {
+        self.timer -= dt;
+        if self.timer > 0.0 {
+            return false;
+        }
+        self.timer = SIGHT_INTERVAL;
+
+        let saw_player = self.can_see_player;
+        self.can_see_player = Self::sees(rw, body, eye, facing, player_pos);
+        if self.can_see_player {
+            self.last_seen = Some(player_pos);
+        }
+
+        saw_player && !self.can_see_player
+    }
+
+    fn sees(rw: &RapierPhysicsWorld, body: RigidBodyHandle, eye: Vec3, facing: Vec3, player_pos: Vec3) -> bool {
+        let to_player = player_pos - eye;
+        let distance = to_player.length();
+        if distance > SIGHT_DISTANCE || distance <= f32::EPSILON {
+            return false;
+        }
+
+        let direction = to_player / distance;
+        if direction.dot(facing.normalize_or_zero()) < (SIGHT_FOV / 2.0).to_radians().cos() {
+            return false;
+        }
+
+        // the first thing the ray hits has to be the player, otherwise a wall is in the way
+        rw.cast_ray(eye, direction, distance + 1.0, body)
+            .and_then(|(collider, _)| rw.entity_of(collider))
+            .is_some_and(|entity| entity.kind == EntityKind::Player)
+    }
+}
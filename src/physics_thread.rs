@@ -0,0 +1,164 @@
+use std::{
+    collections::HashMap,
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+};
+
+use rapier3d::prelude::*;
+use tiny_game_framework::glam::{Quat, Vec3};
+
+use crate::{profiler, rapier_integration::{RapierPhysicsWorld, FIXED_TIMESTEP}};
+
+// seconds of steps one frame catches up on at most, anything past that after a stall is dropped instead of stepping
+// through it all at once
+const MAX_CATCH_UP: f32 = 0.25;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct BodyTransform {
+    pub position: Vec3,
+    pub rotation: Quat,
+}
+
+impl BodyTransform {
+    fn of(body: &RigidBody) -> Self {
+        let (t, r) = (body.translation(), body.rotation());
+        Self { position: Vec3::new(t.x, t.y, t.z), rotation: Quat::from_xyzw(r.i, r.j, r.k, r.w) }
+    }
+
+    // `alpha` 0 is `self`, 1 is `to`
+    pub fn lerp(self, to: Self, alpha: f32) -> Self {
+        Self { position: self.position.lerp(to.position, alpha), rotation: self.rotation.slerp(to.rotation, alpha) }
+    }
+}
+
+// where every dynamic and kinematic body was after the newest fixed step, and after the one before it
+#[derive(Default, Debug)]
+pub struct Snapshot {
+    current: HashMap<RigidBodyHandle, BodyTransform>,
+    previous: HashMap<RigidBodyHandle, BodyTransform>,
+}
+
+impl Snapshot {
+    // somewhere between the last two steps. bodies that only showed up in the newest step are just where they are
+    pub fn blended(&self, body: RigidBodyHandle, alpha: f32) -> Option<BodyTransform> {
+        let current = *self.current.get(&body)?;
+        Some(self.previous.get(&body).map_or(current, |previous| previous.lerp(current, alpha)))
+    }
+
+    // the newest step becomes the one before, both maps keep their room from step to step
+    fn record(&mut self, world: &RapierPhysicsWorld) {
+        std::mem::swap(&mut self.previous, &mut self.current);
+        self.current.clear();
+        self.current.extend(world.rigid_body_set.iter()
+            .filter(|(_, body)| !body.is_fixed())
+            .map(|(handle, body)| (handle, BodyTransform::of(body))));
+    }
+}
+
+// physics goes in fixed steps however long frames take, the time left over carries into the next frame. goes
+// wherever the world goes, onto the physics thread and back, so there's only ever one snapshot and nothing is copied
+#[derive(Default, Debug)]
+pub struct FixedSteps {
+    accumulator: f32,
+    snapshot: Snapshot,
+    // how far the time left over is into the next step, from 0 to 1
+    alpha: f32,
+}
+
+impl FixedSteps {
+    // as many steps as fit in `dt` and what was left over. a lockstep frame is exactly one step long, so every peer
+    // steps exactly once
+    pub fn advance(&mut self, world: &mut RapierPhysicsWorld, dt: f32) {
+        world.set_dt(FIXED_TIMESTEP);
+        self.accumulator = (self.accumulator + dt).min(MAX_CATCH_UP);
+        while self.accumulator >= FIXED_TIMESTEP {
+            self.accumulator -= FIXED_TIMESTEP;
+            world.step();
+            self.snapshot.record(world);
+        }
+        self.alpha = self.accumulator / FIXED_TIMESTEP;
+    }
+}
+
+// where a mesh following a body is drawn, between the last two fixed steps
+#[derive(Clone, Copy)]
+pub struct Blend<'a> {
+    snapshot: &'a Snapshot,
+    alpha: f32,
+}
+
+impl<'a> Blend<'a> {
+    pub fn new(steps: &'a FixedSteps) -> Self {
+        Self { snapshot: &steps.snapshot, alpha: steps.alpha }
+    }
+
+    // `None` if there's no such body anymore. one that hasn't been through a step yet is just where it is
+    pub fn transform(&self, rw: &RapierPhysicsWorld, body: RigidBodyHandle) -> Option<BodyTransform> {
+        let current = BodyTransform::of(rw.rigid_body_set.get(body)?);
+        Some(self.snapshot.blended(body, self.alpha).unwrap_or(current))
+    }
+}
+
+// a per frame handoff: the world is sent off to be stepped on a thread of its own while the main thread draws the
+// frame, and `finish` waits for it to come back.
+// this only hides the step behind drawing, a step that takes longer than drawing still holds the frame up. stepping
+// at a rate of its own and handing snapshots over through a triple buffer, so drawing never waits, is deliberately
+// not done: players, enemies, props and the console all read and change the world every frame, and there's no
+// world for them to do that to while the thread has it
+pub struct PhysicsThread {
+    jobs: Sender<(RapierPhysicsWorld, FixedSteps, f32)>,
+    done: Receiver<(RapierPhysicsWorld, FixedSteps)>,
+    // swapped in for the real world while it's away, it's empty and never stepped
+    stand_in: Option<RapierPhysicsWorld>,
+}
+
+impl PhysicsThread {
+    pub fn spawn() -> Self {
+        let (jobs, job_receiver) = mpsc::channel::<(RapierPhysicsWorld, FixedSteps, f32)>();
+        let (done_sender, done) = mpsc::channel();
+
+        thread::Builder::new().name("physics".to_string()).spawn(move || {
+            // ends once the main thread drops its end
+            while let Ok((mut world, mut steps, dt)) = job_receiver.recv() {
+                // nothing reads this thread's profile, this just keeps it from piling up
+                profiler::begin_frame();
+                steps.advance(&mut world, dt);
+                if done_sender.send((world, steps)).is_err() {
+                    break;
+                }
+            }
+        }).expect("couldn't start the physics thread");
+
+        Self { jobs, done, stand_in: Some(RapierPhysicsWorld::new()) }
+    }
+
+    // sends `world` off to be stepped by `dt`, it's an empty stand in until `finish`. so are `steps`, nothing can be
+    // blended until then
+    pub fn begin(&mut self, world: &mut RapierPhysicsWorld, steps: &mut FixedSteps, dt: f32) {
+        let Some(stand_in) = self.stand_in.take() else {
+            log::warn!("the physics thread is already stepping");
+            return;
+        };
+        let stepping = std::mem::replace(world, stand_in);
+        if let Err(mpsc::SendError((stepping, taken, _))) = self.jobs.send((stepping, std::mem::take(steps), dt)) {
+            log::error!("the physics thread is gone, stepping on this one instead");
+            self.stand_in = Some(std::mem::replace(world, stepping));
+            *steps = taken;
+            steps.advance(world, dt);
+        }
+    }
+
+    // waits for the step to be done and puts the world and `steps` back
+    pub fn finish(&mut self, world: &mut RapierPhysicsWorld, steps: &mut FixedSteps) {
+        if self.stand_in.is_some() {
+            return;
+        }
+        match self.done.recv() {
+            Ok((stepped, stepped_steps)) => {
+                self.stand_in = Some(std::mem::replace(world, stepped));
+                *steps = stepped_steps;
+            }
+            Err(_) => log::error!("the physics thread stopped with the world"),
+        }
+    }
+}
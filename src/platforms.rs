@@ -0,0 +1,117 @@
+use rapier3d::prelude::*;
+use tiny_game_framework::{glam::{vec3, Vec3, Vec4}, Cuboid, Renderer};
+
+use crate::{error::RendererExt, entity::{EntityId, EntityKind}, rapier_integration::{BodySpec, CollisionLayer, RapierPhysicsWorld}};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PlatformLoopMode {
+    // goes back to the first waypoint after reaching the last one
+    Loop,
+    // walks the waypoints backwards once it reaches either end (elevators)
+    PingPong,
+}
+
+pub struct MovingPlatform {
+    pub handle: RigidBodyHandle,
+    pub waypoints: Vec<Vec3>,
+    pub speed: f32,
+    pub mode: PlatformLoopMode,
+    pub mesh_name: String,
+
+    // velocity the platform moved with during the last update, the player inherits this
+    pub velocity: Vec3,
+
+    target: usize,
+    direction: i32,
+}
+
+impl MovingPlatform {
+    pub fn new(
+        rw: &mut RapierPhysicsWorld,
+        r: &mut Renderer,
+        name: &str,
+        waypoints: Vec<Vec3>,
+        size: Vec3,
+        speed: f32,
+        mode: PlatformLoopMode,
+    ) -> Self {
+        assert!(!waypoints.is_empty(), "a moving platform needs at least one waypoint");
+
+        let start = waypoints[0];
+        let handle = rw.add_body(start, &BodySpec::cuboid(size / 2.0)
+            .body_type(RigidBodyType::KinematicPositionBased)
+            .friction(1.0)
+            .layer(CollisionLayer::StaticWorld));
+        rw.tag_body(handle, EntityId::new(EntityKind::Platform));
+
+        let mesh_name = format!("PLATFORM_MESH{}", name);
+        let mut mesh = Cuboid::new(size, Vec4::new(0.6, 0.5, 0.4, 1.0)).mesh();
+        mesh.position = start;
+        mesh.setup_mesh();
+        if let Err(e) = r.try_add_mesh(&mesh_name, mesh) {
+            log::warn!("{}", e);
+        }
+
+        Self {
+            target: if waypoints.len() > 1 { 1 } else { 0 },
+            handle,
+            waypoints,
+            speed,
+            mode,
+            mesh_name,
+            velocity: Vec3::ZERO,
+            direction: 1,
+        }
+    }
+
+    pub fn update(&mut self, rw: &mut RapierPhysicsWorld, r: &mut Renderer, dt: f32) {
+        if dt <= 0.0 {
+            return;
+        }
+
+        let body = &mut rw.rigid_body_set[self.handle];
+        let t = body.translation();
+        let pos = vec3(t.x, t.y, t.z);
+
+        let target = self.waypoints[self.target.min(self.waypoints.len() - 1)];
+        let to_target = target - pos;
+        let distance = to_target.length();
+        let step = self.speed * dt;
+
+        let next = if distance <= step {
+            self.advance();
+            target
+        } else {
+            pos + to_target / distance * step
+        };
+
+        self.velocity = (next - pos) / dt;
+        body.set_next_kinematic_translation(vector![next.x, next.y, next.z]);
+
+        if let Some(mesh) = r.get_mesh_mut(&self.mesh_name) {
+            mesh.position = next;
+        }
+    }
+
+    fn advance(&mut self) {
+        let len = self.waypoints.len();
+        if len < 2 {
+            return;
+        }
+
+        match self.mode {
+            PlatformLoopMode::Loop => {
+                self.target = (self.target + 1) % len;
+            }
+            PlatformLoopMode::PingPong => {
+                if self.target == len - 1 {
+                    self.direction = -1;
+                } else if self.target == 0 {
+                    self.direction = 1;
+                }
+                self.target = (self.target as i32 + self.direction) as usize;
+            }
+        }
+    }
+}
+
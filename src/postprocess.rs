@@ -0,0 +1,323 @@
+use std::collections::HashMap;
+
+use tiny_game_framework::gl::{self, types::{GLint, GLuint}};
+
+use crate::{config::{ColorGrade, PostProcessSettings}, error::ShaderError, shader::{location, program, FULLSCREEN_VS}};
+
+// texels along each side of a biome's color lut
+const LUT_SIZE: usize = 16;
+// blur passes over the bloom, each one horizontal then vertical
+const BLOOM_PASSES: usize = 3;
+
+const BRIGHT_FS: &str = r#"
+#version 330 core
+in vec2 uv;
+out vec4 FragColor;
+
+uniform sampler2D scene;
+uniform float threshold;
+
+void main() {
+    vec3 color = texture(scene, uv).rgb;
+    float brightness = max(color.r, max(color.g, color.b));
+    // only however much is over the threshold
+    FragColor = vec4(color * max(brightness - threshold, 0.0) / max(brightness, 0.0001), 1.0);
+}
+"#;
+
+const BLUR_FS: &str = r#"
+#version 330 core
+in vec2 uv;
+out vec4 FragColor;
+
+uniform sampler2D image;
+uniform vec2 direction;
+
+const float weights[5] = float[](0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+
+void main() {
+    vec2 texel = direction / vec2(textureSize(image, 0));
+    vec3 color = texture(image, uv).rgb * weights[0];
+    for (int i = 1; i < 5; ++i) {
+        color += texture(image, uv + texel * i).rgb * weights[i];
+        color += texture(image, uv - texel * i).rgb * weights[i];
+    }
+    FragColor = vec4(color, 1.0);
+}
+"#;
+
+const COMPOSITE_FS: &str = r#"
+#version 330 core
+in vec2 uv;
+out vec4 FragColor;
+
+uniform sampler2D scene;
+uniform sampler2D bloom;
+uniform sampler3D lut;
+uniform float bloom_intensity;
+uniform int graded;
+uniform float vignette;
+uniform float lut_size;
+
+void main() {
+    vec3 color = texture(scene, uv).rgb + texture(bloom, uv).rgb * bloom_intensity;
+    color = clamp(color, 0.0, 1.0);
+    if (graded == 1) {
+        // through the middle of the edge texels, so 0 and 1 land on them exactly
+        color = texture(lut, color * (lut_size - 1.0) / lut_size + 0.5 / lut_size).rgb;
+    }
+    float edge = length(uv - 0.5) * 1.414;
+    color *= 1.0 - vignette * smoothstep(0.4, 1.0, edge);
+    FragColor = vec4(color, 1.0);
+}
+"#;
+
+// something to draw into instead of the window. floating point, so emissive colors past white get through to the
+// bloom
+struct Target {
+    framebuffer: GLuint,
+    texture: GLuint,
+    // only the scene needs one, the passes after it are flat
+    depth: Option<GLuint>,
+    width: i32,
+    height: i32,
+}
+
+impl Target {
+    fn new(width: i32, height: i32, depth: bool) -> Self {
+        unsafe {
+            let mut framebuffer = 0;
+            gl::GenFramebuffers(1, &mut framebuffer);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, framebuffer);
+
+            let mut texture = 0;
+            gl::GenTextures(1, &mut texture);
+            gl::BindTexture(gl::TEXTURE_2D, texture);
+            gl::TexImage2D(gl::TEXTURE_2D, 0, gl::RGBA16F as GLint, width, height, 0, gl::RGBA, gl::FLOAT, std::ptr::null());
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as GLint);
+            gl::FramebufferTexture2D(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, gl::TEXTURE_2D, texture, 0);
+
+            let depth = depth.then(|| {
+                let mut renderbuffer = 0;
+                gl::GenRenderbuffers(1, &mut renderbuffer);
+                gl::BindRenderbuffer(gl::RENDERBUFFER, renderbuffer);
+                gl::RenderbufferStorage(gl::RENDERBUFFER, gl::DEPTH24_STENCIL8, width, height);
+                gl::FramebufferRenderbuffer(gl::FRAMEBUFFER, gl::DEPTH_STENCIL_ATTACHMENT, gl::RENDERBUFFER, renderbuffer);
+                renderbuffer
+            });
+
+            if gl::CheckFramebufferStatus(gl::FRAMEBUFFER) != gl::FRAMEBUFFER_COMPLETE {
+                log::warn!("the {}x{} post processing target is incomplete", width, height);
+            }
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            Self { framebuffer, texture, depth, width, height }
+        }
+    }
+
+    fn bind(&self) {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.framebuffer);
+            gl::Viewport(0, 0, self.width, self.height);
+        }
+    }
+
+    fn delete(&self) {
+        unsafe {
+            gl::DeleteFramebuffers(1, &self.framebuffer);
+            gl::DeleteTextures(1, &self.texture);
+            if let Some(depth) = self.depth {
+                gl::DeleteRenderbuffers(1, &depth);
+            }
+        }
+    }
+}
+
+struct Programs {
+    bright: GLuint,
+    blur: GLuint,
+    composite: GLuint,
+}
+
+// the scene is drawn into a texture, then vignette, bloom and the biome's color grade are put on top on the way to
+// the window. wrap `renderer.draw` in `begin` and `end`, the ui goes after so it isn't touched
+pub struct PostProcess {
+    pub settings: PostProcessSettings,
+    // `None` when the shaders didn't build, everything's drawn straight to the window then
+    programs: Option<Programs>,
+    // the empty vertex array the fullscreen triangle needs bound
+    vertex_array: GLuint,
+    scene: Option<Target>,
+    // half size, blurred back and forth between the two
+    bloom: Option<[Target; 2]>,
+    // baked on first use, and again when the grades change in settings
+    luts: HashMap<String, (ColorGrade, GLuint)>,
+    active: bool,
+}
+
+impl PostProcess {
+    // needs the gl context
+    pub fn new(settings: PostProcessSettings) -> Self {
+        let programs = (|| -> Result<Programs, ShaderError> {
+            Ok(Programs {
+                bright: program(FULLSCREEN_VS, BRIGHT_FS)?,
+                blur: program(FULLSCREEN_VS, BLUR_FS)?,
+                composite: program(FULLSCREEN_VS, COMPOSITE_FS)?,
+            })
+        })();
+        let programs = programs.inspect_err(|e| log::warn!("no post processing: {}", e)).ok();
+
+        let mut vertex_array = 0;
+        unsafe { gl::GenVertexArrays(1, &mut vertex_array) };
+        Self { settings, programs, vertex_array, scene: None, bloom: None, luts: HashMap::new(), active: false }
+    }
+
+    // everything drawn until `end` goes into the scene texture instead of the window
+    pub fn begin(&mut self, width: u32, height: u32) {
+        self.active = self.settings.enabled && self.programs.is_some();
+        if !self.active {
+            return;
+        }
+
+        let (width, height) = (width.max(1) as i32, height.max(1) as i32);
+        if self.scene.as_ref().is_none_or(|scene| (scene.width, scene.height) != (width, height)) {
+            self.resize(width, height);
+        }
+        if let Some(scene) = &self.scene {
+            scene.bind();
+        }
+    }
+
+    // puts the processed scene on the window, `biome` picks the color grade
+    pub fn end(&mut self, biome: &str) {
+        if !self.active {
+            return;
+        }
+        self.active = false;
+        let lut = self.settings.color_grading.then(|| self.lut(biome)).flatten();
+        let (Some(programs), Some(scene), Some(bloom)) = (&self.programs, &self.scene, &self.bloom) else { return };
+
+        unsafe {
+            let depth_test = gl::IsEnabled(gl::DEPTH_TEST) == gl::TRUE;
+            let blend = gl::IsEnabled(gl::BLEND) == gl::TRUE;
+            gl::Disable(gl::DEPTH_TEST);
+            gl::Disable(gl::BLEND);
+            gl::BindVertexArray(self.vertex_array);
+            gl::ActiveTexture(gl::TEXTURE0);
+
+            if self.settings.bloom {
+                bloom[0].bind();
+                gl::UseProgram(programs.bright);
+                gl::Uniform1i(location(programs.bright, "scene"), 0);
+                gl::Uniform1f(location(programs.bright, "threshold"), self.settings.bloom_threshold);
+                gl::BindTexture(gl::TEXTURE_2D, scene.texture);
+                gl::DrawArrays(gl::TRIANGLES, 0, 3);
+
+                gl::UseProgram(programs.blur);
+                gl::Uniform1i(location(programs.blur, "image"), 0);
+                for _ in 0..BLOOM_PASSES {
+                    for (from, to, direction) in [(0, 1, (1.0, 0.0)), (1, 0, (0.0, 1.0))] {
+                        bloom[to].bind();
+                        gl::Uniform2f(location(programs.blur, "direction"), direction.0, direction.1);
+                        gl::BindTexture(gl::TEXTURE_2D, bloom[from].texture);
+                        gl::DrawArrays(gl::TRIANGLES, 0, 3);
+                    }
+                }
+            }
+
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            gl::Viewport(0, 0, scene.width, scene.height);
+            gl::UseProgram(programs.composite);
+            gl::Uniform1i(location(programs.composite, "scene"), 0);
+            gl::Uniform1i(location(programs.composite, "bloom"), 1);
+            gl::Uniform1i(location(programs.composite, "lut"), 2);
+            gl::Uniform1f(location(programs.composite, "bloom_intensity"), if self.settings.bloom { self.settings.bloom_intensity } else { 0.0 });
+            gl::Uniform1i(location(programs.composite, "graded"), lut.is_some() as i32);
+            gl::Uniform1f(location(programs.composite, "vignette"), self.settings.vignette);
+            gl::Uniform1f(location(programs.composite, "lut_size"), LUT_SIZE as f32);
+            gl::BindTexture(gl::TEXTURE_2D, scene.texture);
+            gl::ActiveTexture(gl::TEXTURE1);
+            gl::BindTexture(gl::TEXTURE_2D, bloom[0].texture);
+            gl::ActiveTexture(gl::TEXTURE2);
+            gl::BindTexture(gl::TEXTURE_3D, lut.unwrap_or(0));
+            gl::DrawArrays(gl::TRIANGLES, 0, 3);
+
+            gl::BindTexture(gl::TEXTURE_3D, 0);
+            gl::ActiveTexture(gl::TEXTURE1);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+            gl::BindVertexArray(0);
+            gl::UseProgram(0);
+            if depth_test {
+                gl::Enable(gl::DEPTH_TEST);
+            }
+            if blend {
+                gl::Enable(gl::BLEND);
+            }
+        }
+    }
+
+    fn resize(&mut self, width: i32, height: i32) {
+        for target in self.scene.iter().chain(self.bloom.iter().flatten()) {
+            target.delete();
+        }
+        self.scene = Some(Target::new(width, height, true));
+        let (half_width, half_height) = ((width / 2).max(1), (height / 2).max(1));
+        self.bloom = Some([Target::new(half_width, half_height, false), Target::new(half_width, half_height, false)]);
+    }
+
+    // `None` for biomes without a grade
+    fn lut(&mut self, biome: &str) -> Option<GLuint> {
+        let grade = *self.settings.grades.get(biome)?;
+        if let Some(&(baked, texture)) = self.luts.get(biome) {
+            if baked == grade {
+                return Some(texture);
+            }
+            unsafe { gl::DeleteTextures(1, &texture) };
+        }
+
+        let texture = bake_lut(&grade);
+        self.luts.insert(biome.to_string(), (grade, texture));
+        Some(texture)
+    }
+}
+
+// every color the lut's texels sit on, run through the grade
+fn bake_lut(grade: &ColorGrade) -> GLuint {
+    let step = 1.0 / (LUT_SIZE - 1) as f32;
+    let mut texels = Vec::with_capacity(LUT_SIZE * LUT_SIZE * LUT_SIZE * 3);
+    // red changes fastest, then green, then blue, the way gl reads a 3d texture
+    for b in 0..LUT_SIZE {
+        for g in 0..LUT_SIZE {
+            for r in 0..LUT_SIZE {
+                let color = [r, g, b].map(|channel| channel as f32 * step * grade.exposure);
+                let color = color.map(|channel| (channel - 0.5) * grade.contrast + 0.5);
+                let luma = color[0] * 0.2126 + color[1] * 0.7152 + color[2] * 0.0722;
+                let color = color.map(|channel| luma + (channel - luma) * grade.saturation);
+                for (channel, tint) in color.into_iter().zip(grade.tint) {
+                    texels.push(((channel * tint).clamp(0.0, 1.0) * 255.0).round() as u8);
+                }
+            }
+        }
+    }
+
+    unsafe {
+        let mut texture = 0;
+        gl::GenTextures(1, &mut texture);
+        gl::BindTexture(gl::TEXTURE_3D, texture);
+        gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+        let size = LUT_SIZE as i32;
+        gl::TexImage3D(gl::TEXTURE_3D, 0, gl::RGB8 as GLint, size, size, size, 0, gl::RGB, gl::UNSIGNED_BYTE, texels.as_ptr().cast());
+        gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+        for parameter in [gl::TEXTURE_WRAP_S, gl::TEXTURE_WRAP_T, gl::TEXTURE_WRAP_R] {
+            gl::TexParameteri(gl::TEXTURE_3D, parameter, gl::CLAMP_TO_EDGE as GLint);
+        }
+        gl::TexParameteri(gl::TEXTURE_3D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as GLint);
+        gl::TexParameteri(gl::TEXTURE_3D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as GLint);
+        gl::BindTexture(gl::TEXTURE_3D, 0);
+        texture
+    }
+}
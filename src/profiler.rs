@@ -0,0 +1,144 @@
+use std::{cell::RefCell, fs, time::{Duration, Instant}};
+
+use serde::Serialize;
+
+use crate::error::SaveError;
+
+pub const TRACE_PATH: &str = "trace.json";
+
+// times everything until the end of the enclosing block under `name`, scopes inside it show up nested below it
+macro_rules! profile_scope {
+    ($name:expr) => {
+        let _profile_scope = $crate::profiler::ScopeGuard::new($name);
+    };
+}
+pub(crate) use profile_scope;
+
+#[derive(Clone, Debug)]
+pub struct ScopeTiming {
+    pub name: &'static str,
+    // how many scopes it's nested in
+    pub depth: usize,
+    // since the frame started
+    pub start: Duration,
+    pub duration: Duration,
+}
+
+// everything timed during one whole frame
+#[derive(Clone, Default, Debug)]
+pub struct FrameProfile {
+    pub scopes: Vec<ScopeTiming>,
+    pub total: Duration,
+}
+
+// one complete event in chrome's trace format, times in microseconds
+#[derive(Serialize)]
+struct TraceEvent {
+    name: &'static str,
+    ph: &'static str,
+    ts: f64,
+    dur: f64,
+    pid: u32,
+    tid: u32,
+}
+
+#[derive(Serialize)]
+struct Trace {
+    #[serde(rename = "traceEvents")]
+    trace_events: Vec<TraceEvent>,
+}
+
+struct Profiler {
+    frame_start: Instant,
+    depth: usize,
+    current: Vec<ScopeTiming>,
+    last_frame: FrameProfile,
+    // when a trace started being recorded and everything in it so far
+    trace: Option<(Instant, Vec<TraceEvent>)>,
+}
+
+// per thread, only the main thread's frame is ever looked at. scopes in background tasks go nowhere
+thread_local! {
+    static PROFILER: RefCell<Profiler> = RefCell::new(Profiler {
+        frame_start: Instant::now(),
+        depth: 0,
+        current: Vec::new(),
+        last_frame: FrameProfile::default(),
+        trace: None,
+    });
+}
+
+pub struct ScopeGuard {
+    name: &'static str,
+    start: Instant,
+}
+
+impl ScopeGuard {
+    pub fn new(name: &'static str) -> Self {
+        PROFILER.with(|profiler| profiler.borrow_mut().depth += 1);
+        Self { name, start: Instant::now() }
+    }
+}
+
+impl Drop for ScopeGuard {
+    fn drop(&mut self) {
+        let duration = self.start.elapsed();
+        PROFILER.with(|profiler| {
+            let mut profiler = profiler.borrow_mut();
+            profiler.depth -= 1;
+            let timing = ScopeTiming {
+                name: self.name,
+                depth: profiler.depth,
+                start: self.start.saturating_duration_since(profiler.frame_start),
+                duration,
+            };
+            if let Some((started, events)) = profiler.trace.as_mut() {
+                events.push(TraceEvent {
+                    name: self.name,
+                    ph: "X",
+                    ts: self.start.saturating_duration_since(*started).as_secs_f64() * 1e6,
+                    dur: duration.as_secs_f64() * 1e6,
+                    pid: 0,
+                    tid: 0,
+                });
+            }
+            profiler.current.push(timing);
+        });
+    }
+}
+
+// call once at the top of every frame, what was timed since the last call becomes `last_frame`
+pub fn begin_frame() {
+    PROFILER.with(|profiler| {
+        let mut profiler = profiler.borrow_mut();
+        let mut scopes = std::mem::take(&mut profiler.current);
+        // inner scopes finish first, sorting by start puts every parent before its children
+        scopes.sort_by_key(|scope| scope.start);
+        profiler.last_frame = FrameProfile { scopes, total: profiler.frame_start.elapsed() };
+        profiler.frame_start = Instant::now();
+    });
+}
+
+pub fn last_frame() -> FrameProfile {
+    PROFILER.with(|profiler| profiler.borrow().last_frame.clone())
+}
+
+// starts recording a chrome trace, or stops and writes it out for chrome://tracing. returns how many scopes were
+// written, `None` when it just started
+pub fn toggle_trace() -> Result<Option<usize>, SaveError> {
+    let recorded = PROFILER.with(|profiler| {
+        let mut profiler = profiler.borrow_mut();
+        match profiler.trace.take() {
+            Some((_, events)) => Some(events),
+            None => {
+                profiler.trace = Some((Instant::now(), Vec::new()));
+                None
+            }
+        }
+    });
+
+    let Some(trace_events) = recorded else { return Ok(None) };
+    let count = trace_events.len();
+    fs::write(TRACE_PATH, serde_json::to_string(&Trace { trace_events })?)?;
+    Ok(Some(count))
+}
@@ -0,0 +1,79 @@
+use crate::enemies::EnemyKind;
+
+const BASE_MAX_HEALTH: f32 = 100.0;
+const HEALTH_PER_LEVEL: f32 = 15.0;
+// fractions added on top of the base for every level past the first
+const SPEED_PER_LEVEL: f32 = 0.03;
+const DAMAGE_PER_LEVEL: f32 = 0.1;
+// xp needed to go from level n to n + 1 is XP_PER_LEVEL * n
+const XP_PER_LEVEL: u32 = 100;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Stats {
+    pub max_health: f32,
+    // multiply the base values with these
+    pub speed: f32,
+    pub damage: f32,
+}
+
+pub struct Progression {
+    pub level: u32,
+    // towards the next level, resets on level up
+    pub xp: u32,
+    // from the difficulty, multiplies max health at every level
+    health_scale: f32,
+}
+
+impl Progression {
+    pub fn new(health_scale: f32) -> Self {
+        Self { level: 1, xp: 0, health_scale }
+    }
+
+    pub fn xp_to_next_level(&self) -> u32 {
+        XP_PER_LEVEL * self.level
+    }
+
+    // returns how many levels were gained, can be more than one for big rewards
+    pub fn award(&mut self, xp: u32) -> u32 {
+        self.xp += xp;
+
+        let mut gained = 0;
+        while self.xp >= self.xp_to_next_level() {
+            self.xp -= self.xp_to_next_level();
+            self.level += 1;
+            gained += 1;
+        }
+
+        gained
+    }
+
+    pub fn award_kill(&mut self, kind: EnemyKind) -> u32 {
+        self.award(kill_reward(kind))
+    }
+
+    pub fn stats(&self) -> Stats {
+        let bonus = (self.level - 1) as f32;
+
+        Stats {
+            max_health: (BASE_MAX_HEALTH + HEALTH_PER_LEVEL * bonus) * self.health_scale,
+            speed: 1.0 + SPEED_PER_LEVEL * bonus,
+            damage: 1.0 + DAMAGE_PER_LEVEL * bonus,
+        }
+    }
+
+    // how full the xp bar is, 0 to 1
+    pub fn progress(&self) -> f32 {
+        self.xp as f32 / self.xp_to_next_level() as f32
+    }
+}
+
+fn kill_reward(kind: EnemyKind) -> u32 {
+    match kind {
+        EnemyKind::Grunt => 20,
+        EnemyKind::Brute => 50,
+        EnemyKind::Boss => 500,
+        EnemyKind::Archer => 30,
+        EnemyKind::Tank => 80,
+        EnemyKind::Swarmer => 8,
+    }
+}
@@ -0,0 +1,363 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use rapier3d::prelude::*;
+use tiny_game_framework::{glam::{vec3, Quat, Vec3, Vec4}, Cuboid, Renderer};
+
+use crate::{
+    coords::{self, ChunkCoord, TileCoord},
+    deltas::{Modification, WorldDeltas},
+    destruction::DestructionSystem,
+    director::SpawnDirector,
+    dungeon::{DungeonLayout, SpawnAnchor, Tile},
+    entity::{EntityId, EntityKind},
+    error::RendererExt,
+    impacts::ImpactMaterial,
+    lighting::{Emissive, Lighting},
+    model::Model,
+    physics_thread::Blend,
+    rapier_integration::{BodySpec, CollisionLayer, RapierPhysicsWorld},
+};
+
+// chance for any floor tile to get something on it, if the rules allow anything there
+const PROP_CHANCE: f32 = 0.15;
+// tiles around where the players start that are always left empty
+const SPAWN_CLEARANCE: i32 = 2;
+// mixed into the chunk's seed so props don't line up with the walls' own randomness
+const PROP_SEED: u64 = 0x5E_ED0F_9A05;
+// one unit across and centered, stretched to the prop's size
+const POT_MODEL: &str = "src/models/pot.gltf";
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum PropKind {
+    // pushed around by anything that walks into it
+    Barrel,
+    // low and loose, doesn't get in the way
+    Rubble,
+    Pillar,
+    // stands against a wall and lights it up
+    Torch,
+    // grows out of corners, glows a little
+    Crystal,
+    // these two break when hit and have something in them
+    Pot,
+    Crate,
+}
+
+impl PropKind {
+    const ALL: [PropKind; 7] = [PropKind::Barrel, PropKind::Rubble, PropKind::Pillar, PropKind::Torch, PropKind::Crystal, PropKind::Pot, PropKind::Crate];
+
+    // as a fraction of a tile
+    fn size(&self) -> Vec3 {
+        match self {
+            PropKind::Barrel => vec3(0.3, 0.4, 0.3),
+            PropKind::Rubble => vec3(0.5, 0.1, 0.4),
+            PropKind::Pillar => vec3(0.35, 1.0, 0.35),
+            PropKind::Torch => vec3(0.08, 0.5, 0.08),
+            PropKind::Crystal => vec3(0.15, 0.3, 0.15),
+            PropKind::Pot => vec3(0.15, 0.2, 0.15),
+            PropKind::Crate => vec3(0.3, 0.3, 0.3),
+        }
+    }
+
+    fn color(&self) -> Vec4 {
+        match self {
+            PropKind::Barrel => Vec4::new(0.5, 0.32, 0.18, 1.0),
+            PropKind::Rubble => Vec4::new(0.4, 0.4, 0.4, 1.0),
+            PropKind::Pillar => Vec4::new(0.6, 0.58, 0.55, 1.0),
+            PropKind::Torch => Vec4::new(1.0, 0.6, 0.2, 1.0),
+            PropKind::Crystal => Vec4::new(0.35, 0.6, 1.0, 1.0),
+            PropKind::Pot => Vec4::new(0.7, 0.4, 0.25, 1.0),
+            PropKind::Crate => Vec4::new(0.6, 0.45, 0.25, 1.0),
+        }
+    }
+
+    // how much it takes to break, `None` for things that don't
+    fn health(&self) -> Option<f32> {
+        match self {
+            PropKind::Pot => Some(10.0),
+            PropKind::Crate => Some(30.0),
+            _ => None,
+        }
+    }
+
+    // what landing on something looks like, see `Impacts`
+    fn material(&self) -> ImpactMaterial {
+        match self {
+            PropKind::Barrel | PropKind::Crate => ImpactMaterial::Wood,
+            PropKind::Torch => ImpactMaterial::Metal,
+            PropKind::Rubble | PropKind::Pillar | PropKind::Crystal | PropKind::Pot => ImpactMaterial::Stone,
+        }
+    }
+
+    // drawn and collided with as a box when there's none, or it couldn't be loaded
+    fn model(&self) -> Option<&'static str> {
+        match self {
+            PropKind::Pot => Some(POT_MODEL),
+            _ => None,
+        }
+    }
+
+    fn emissive(&self) -> Option<Emissive> {
+        match self {
+            PropKind::Torch => Some(Emissive::TORCH),
+            PropKind::Crystal => Some(Emissive::CRYSTAL),
+            _ => None,
+        }
+    }
+
+    fn is_dynamic(&self) -> bool {
+        matches!(self, PropKind::Barrel | PropKind::Pot | PropKind::Crate)
+    }
+
+    // whether the tile stops counting as walkable. nothing that breaks does, the tile couldn't be given back
+    fn blocks(&self) -> bool {
+        matches!(self, PropKind::Barrel | PropKind::Pillar)
+    }
+
+    // pillars hold up corners, barrels get stacked against walls, rubble is wherever
+    fn fits(&self, walls: &[bool; 4]) -> bool {
+        let [east, west, south, north] = *walls;
+        match self {
+            PropKind::Pillar | PropKind::Crystal => (east || west) && (south || north),
+            PropKind::Barrel | PropKind::Torch | PropKind::Crate => walls.iter().any(|&wall| wall),
+            PropKind::Rubble | PropKind::Pot => true,
+        }
+    }
+}
+
+// everything scattering a chunk touches besides the props themselves
+pub struct ScatterContext<'a> {
+    pub rw: &'a mut RapierPhysicsWorld,
+    pub r: &'a mut Renderer,
+    pub lighting: &'a mut Lighting,
+    pub director: &'a mut SpawnDirector,
+    // pots and crates in here were broken already and stay that way
+    pub deltas: &'a WorldDeltas,
+}
+
+// where one prop goes and which way it's turned
+#[derive(Clone, Copy, Debug)]
+struct Placement {
+    kind: PropKind,
+    chunk: ChunkCoord,
+    tile: TileCoord,
+    // 0 to 1, a whole turn around
+    turn: f32,
+}
+
+pub struct Prop {
+    pub id: EntityId,
+    pub kind: PropKind,
+    // the one it was scattered over, barrels can roll out of it
+    pub chunk: ChunkCoord,
+    // where it was scattered, it's remembered as broken by this even if it got pushed somewhere else first
+    pub tile: TileCoord,
+    pub body: RigidBodyHandle,
+    pub mesh_name: String,
+    health: Option<f32>,
+}
+
+// decorates chunks once they're built. placement only depends on the layout's seed, so every client puts the
+// same props in the same places
+pub struct PropSystem {
+    pub props: Vec<Prop>,
+    prop_counter: usize,
+    // read once up front rather than through `AssetManager`, every client has to build the same colliders from the
+    // very first chunk
+    models: HashMap<PropKind, Model>,
+}
+
+impl PropSystem {
+    pub fn new() -> Self {
+        let mut models = HashMap::new();
+        for kind in PropKind::ALL {
+            let Some(path) = kind.model() else { continue };
+            match Model::load(path) {
+                Ok(model) => {
+                    models.insert(kind, model);
+                }
+                Err(e) => log::warn!("couldn't load {}, using a box: {}", path, e),
+            }
+        }
+
+        Self {
+            props: Vec::new(),
+            prop_counter: 0,
+            models,
+        }
+    }
+
+    pub fn scatter(&mut self, context: ScatterContext, chunk: ChunkCoord, layout: &DungeonLayout) {
+        let ScatterContext { rw, r, lighting, director, deltas } = context;
+        let mut rng = fastrand::Rng::with_seed(layout.seed ^ PROP_SEED);
+        let mut blocked = HashSet::new();
+        let regions = count_regions(layout, &blocked);
+
+        for (tile, meta) in layout.metadata() {
+            // anchored tiles are rolled for like the rest, so the tiles after them come out the same either way
+            if meta.kind != Tile::Floor || (rng.f32() >= PROP_CHANCE && meta.anchor != Some(SpawnAnchor::Prop)) {
+                continue;
+            }
+
+            let world_tile = chunk.origin() + tile;
+            if world_tile.x.abs() <= SPAWN_CLEARANCE && world_tile.z.abs() <= SPAWN_CLEARANCE {
+                continue;
+            }
+
+            let walls = TileCoord::NEIGHBORS.map(|offset| layout.tile(tile + offset) == Some(Tile::Wall));
+            let candidates: Vec<PropKind> = [PropKind::Pillar, PropKind::Barrel, PropKind::Rubble, PropKind::Torch, PropKind::Crystal, PropKind::Pot, PropKind::Crate]
+                .into_iter()
+                .filter(|prop| prop.fits(&walls))
+                .collect();
+            let prop = candidates[rng.usize(..candidates.len())];
+
+            // a blocking prop can't cut one part of the chunk off from another, so whatever path there was from
+            // where we came in to the way out is still there
+            if prop.blocks() {
+                blocked.insert(tile);
+                if count_regions(layout, &blocked) > regions {
+                    blocked.remove(&tile);
+                    continue;
+                }
+                director.block_tile(world_tile.world(coords::floor_height()).into());
+            }
+
+            // rolled for a broken one all the same, so the props after it come out where they always did
+            let turn = rng.f32();
+            if deltas.has(world_tile, Modification::PropBroken) {
+                continue;
+            }
+            self.spawn(rw, r, lighting, Placement { kind: prop, chunk, tile: world_tile, turn });
+        }
+    }
+
+    fn spawn(&mut self, rw: &mut RapierPhysicsWorld, r: &mut Renderer, lighting: &mut Lighting, placement: Placement) {
+        let Placement { kind, chunk, tile, turn } = placement;
+        let size = kind.size() * vec3(coords::tile_size(), coords::wall_height(), coords::tile_size());
+        // the floor tile's top face is half a tile above its center
+        let floor = coords::floor_height() + coords::tile_size() / 2.0;
+        let position: Vec3 = tile.world(floor + size.y / 2.0).into();
+        let rotation = Quat::from_rotation_y(turn * std::f32::consts::TAU);
+
+        let spec = if kind.is_dynamic() {
+            BodySpec::cuboid(size / 2.0).friction(0.8).layer(CollisionLayer::Debris).impacts(true)
+        } else {
+            BodySpec::cuboid(size / 2.0).body_type(RigidBodyType::Fixed).layer(CollisionLayer::StaticWorld)
+        };
+        let model = self.models.get(&kind);
+        let body = match model.and_then(|model| model.add_collider(rw, position, size, &spec)) {
+            Some(body) => body,
+            None => rw.add_body(position, &spec),
+        };
+        let (axis, angle) = rotation.to_axis_angle();
+        let axis_angle = vector![axis.x, axis.y, axis.z] * angle;
+        rw.rigid_body_set[body].set_rotation(Rotation::new(axis_angle), false);
+        let id = EntityId::new(EntityKind::Prop);
+        rw.tag_body(body, id);
+
+        let mesh_name = format!("PROP_MESH{}", self.prop_counter);
+        self.prop_counter += 1;
+
+        let mut mesh = match model {
+            Some(model) => {
+                let mut mesh = model.mesh();
+                for vertex in mesh.vertices.iter_mut() {
+                    vertex.color = kind.color();
+                }
+                mesh.scale = size;
+                mesh
+            }
+            None => Cuboid::new(size, kind.color()).mesh(),
+        };
+        mesh.position = position;
+        mesh.rotation = rotation;
+        if let Some(emissive) = kind.emissive() {
+            emissive.apply(&mut mesh);
+            // from the top, where a flame would be
+            lighting.place_light(&mesh_name, position + Vec3::Y * size.y / 2.0, emissive);
+        }
+        mesh.setup_mesh();
+        if let Err(e) = r.try_add_mesh(&mesh_name, mesh) {
+            log::warn!("{}", e);
+        }
+
+        self.props.push(Prop { id, kind, chunk, tile, body, mesh_name, health: kind.health() });
+    }
+
+    // takes out everything scattered over `chunk`, it's scattered the same way again when the chunk is rebuilt
+    pub fn unload(&mut self, rw: &mut RapierPhysicsWorld, r: &mut Renderer, lighting: &mut Lighting, chunk: ChunkCoord) {
+        self.props.retain(|prop| {
+            if prop.chunk != chunk {
+                return true;
+            }
+            rw.remove_rigidbody(prop.body);
+            r.destroy_mesh(&prop.mesh_name).ok();
+            lighting.remove_light(&prop.mesh_name);
+            false
+        });
+    }
+
+    // returns what it was, the tile it was scattered on and where it ended up if this hit broke it. it goes to
+    // pieces right away, what was in it is up to whoever broke it
+    pub fn damage(&mut self, rw: &mut RapierPhysicsWorld, r: &mut Renderer, destruction: &mut DestructionSystem, id: EntityId, amount: f32) -> Option<(PropKind, TileCoord, Vec3)> {
+        let index = self.props.iter().position(|prop| prop.id == id)?;
+        let health = self.props[index].health.as_mut()?;
+        *health -= amount;
+        if *health > 0.0 {
+            return None;
+        }
+
+        let prop = self.props.swap_remove(index);
+        let t = *rw.rigid_body_set[prop.body].translation();
+        let position = vec3(t.x, t.y, t.z);
+        rw.remove_rigidbody(prop.body);
+        r.destroy_mesh(&prop.mesh_name).ok();
+        let size = prop.kind.size() * vec3(coords::tile_size(), coords::wall_height(), coords::tile_size());
+        destruction.shatter(rw, r, position, size, prop.kind.color());
+
+        Some((prop.kind, prop.tile, position))
+    }
+
+    // `None` if it's not one of these props
+    pub fn material(&self, id: EntityId) -> Option<ImpactMaterial> {
+        self.props.iter().find(|prop| prop.id == id).map(|prop| prop.kind.material())
+    }
+
+    // only the ones that can move need their mesh kept up with the body
+    pub fn update(&mut self, rw: &RapierPhysicsWorld, r: &mut Renderer, blend: &Blend) {
+        for prop in self.props.iter().filter(|prop| prop.kind.is_dynamic()) {
+            let Some(body) = rw.rigid_body_set.get(prop.body) else { continue };
+            if body.is_sleeping() {
+                continue;
+            }
+
+            let (Some(transform), Some(mesh)) = (blend.transform(rw, prop.body), r.get_mesh_mut(&prop.mesh_name)) else { continue };
+            (mesh.position, mesh.rotation) = (transform.position, transform.rotation);
+        }
+    }
+}
+
+// 4-connected patches of floor that isn't in `blocked`
+fn count_regions(layout: &DungeonLayout, blocked: &HashSet<TileCoord>) -> usize {
+    let open = |tile: TileCoord| layout.tile(tile).is_some_and(|kind| kind.is_walkable()) && !blocked.contains(&tile);
+    let mut visited = HashSet::new();
+    let mut regions = 0;
+
+    for (start, _) in layout.tiles() {
+        if !open(start) || !visited.insert(start) {
+            continue;
+        }
+
+        regions += 1;
+        let mut queue = VecDeque::from([start]);
+        while let Some(tile) = queue.pop_front() {
+            for neighbor in tile.neighbors() {
+                if open(neighbor) && visited.insert(neighbor) {
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+    }
+
+    regions
+}
@@ -1,6 +1,375 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap, HashSet, VecDeque},
+    hash::{Hash, Hasher},
+    sync::{Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
 use nalgebra::{Point, Point3, Vector};
 use rapier3d::prelude::*;
-use tiny_game_framework::{glam::Vec3, rand_betw, Vertex};
+use tiny_game_framework::{glam::{vec3, Vec3}, rand_betw};
+
+use crate::{config::PhysicsSettings, entity::{EntityId, EntityKind}, profiler::profile_scope};
+
+// how hard water pushes up compared to gravity, above 1 means things float
+const WATER_BUOYANCY: f32 = 1.2;
+// fraction of velocity lost per second while submerged
+const WATER_DRAG: f32 = 2.0;
+// lockstep steps by exactly this much every tick whatever the frame rate is
+pub const FIXED_TIMESTEP: f32 = 1.0 / 60.0;
+// longer frames (or sped up time) get split into steps no bigger than this so fast things don't tunnel
+const MAX_SUBSTEP: f32 = 1.0 / 30.0;
+// frozen bodies wake back up a bit closer than where they froze, so one standing right at the edge doesn't flip
+// back and forth every frame
+const UNFREEZE_RATIO: f32 = 0.8;
+// contacts that slow a body down by less than this in a step are it resting or sliding, not hitting something
+const MIN_IMPACT_SPEED: f32 = 120.0;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum CollisionLayer {
+    Player,
+    Enemy,
+    Projectile,
+    Trigger,
+    StaticWorld,
+    Debris,
+    Water,
+    // sensors that change gravity for whatever is inside
+    GravityField,
+}
+
+impl CollisionLayer {
+    pub fn group(self) -> Group {
+        match self {
+            CollisionLayer::Player => Group::GROUP_1,
+            CollisionLayer::Enemy => Group::GROUP_2,
+            CollisionLayer::Projectile => Group::GROUP_3,
+            CollisionLayer::Trigger => Group::GROUP_4,
+            CollisionLayer::StaticWorld => Group::GROUP_5,
+            CollisionLayer::Debris => Group::GROUP_6,
+            CollisionLayer::Water => Group::GROUP_7,
+            CollisionLayer::GravityField => Group::GROUP_8,
+        }
+    }
+
+    // what each layer interacts with unless the caller asks for something else
+    pub fn default_mask(self) -> Group {
+        use CollisionLayer::*;
+
+        match self {
+            Player => mask(&[Enemy, Projectile, Trigger, StaticWorld, Debris, Water, GravityField]),
+            Enemy => mask(&[Player, Enemy, Projectile, StaticWorld, Debris, Water, GravityField]),
+            Projectile => mask(&[Player, Enemy, StaticWorld, GravityField]),
+            // triggers only care about the player walking into them
+            Trigger => mask(&[Player]),
+            StaticWorld => mask(&[Player, Enemy, Projectile, Debris]),
+            Debris => mask(&[Player, Enemy, StaticWorld, Debris, Water, GravityField]),
+            // anything that should float or swim
+            Water => mask(&[Player, Enemy, Debris]),
+            GravityField => mask(&[Player, Enemy, Projectile, Debris]),
+        }
+    }
+
+    pub fn interaction_groups(self, mask: Group) -> InteractionGroups {
+        InteractionGroups::new(self.group(), mask)
+    }
+}
+
+pub fn mask(layers: &[CollisionLayer]) -> Group {
+    layers.iter().fold(Group::NONE, |mask, layer| mask | layer.group())
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum BodyShape {
+    // `size` holds the half extents
+    Cuboid,
+    // `size.x` is the radius
+    Ball,
+    // `size.x` is the radius and `size.y` the half height of the segment, standing along y
+    Capsule,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct BodySpec {
+    pub shape: BodyShape,
+    pub size: Vec3,
+    pub body_type: RigidBodyType,
+    pub restitution: f32,
+    pub friction: f32,
+    pub density: f32,
+    pub linear_damping: f32,
+    pub ccd: bool,
+    pub layer: CollisionLayer,
+    pub mask: Group,
+    // reports hitting the world, see `RapierPhysicsWorld::take_impacts`
+    pub impacts: bool,
+}
+
+impl BodySpec {
+    fn new(shape: BodyShape, size: Vec3) -> Self {
+        Self {
+            shape,
+            size,
+            body_type: RigidBodyType::Dynamic,
+            restitution: 0.0,
+            friction: 0.5,
+            density: 1.0,
+            linear_damping: 0.0,
+            ccd: false,
+            layer: CollisionLayer::Debris,
+            mask: CollisionLayer::Debris.default_mask(),
+            impacts: false,
+        }
+    }
+
+    pub fn cuboid(half_extents: Vec3) -> Self {
+        Self::new(BodyShape::Cuboid, half_extents)
+    }
+
+    pub fn ball(radius: f32) -> Self {
+        Self::new(BodyShape::Ball, Vec3::splat(radius))
+    }
+
+    pub fn capsule(radius: f32, half_height: f32) -> Self {
+        Self::new(BodyShape::Capsule, vec3(radius, half_height, radius))
+    }
+
+    pub fn body_type(mut self, body_type: RigidBodyType) -> Self {
+        self.body_type = body_type;
+        self
+    }
+
+    pub fn restitution(mut self, restitution: f32) -> Self {
+        self.restitution = restitution;
+        self
+    }
+
+    pub fn friction(mut self, friction: f32) -> Self {
+        self.friction = friction;
+        self
+    }
+
+    pub fn density(mut self, density: f32) -> Self {
+        self.density = density;
+        self
+    }
+
+    pub fn linear_damping(mut self, linear_damping: f32) -> Self {
+        self.linear_damping = linear_damping;
+        self
+    }
+
+    pub fn ccd(mut self, ccd: bool) -> Self {
+        self.ccd = ccd;
+        self
+    }
+
+    // also resets the mask to the layer's default, call `mask` afterwards to override it
+    pub fn layer(mut self, layer: CollisionLayer) -> Self {
+        self.layer = layer;
+        self.mask = layer.default_mask();
+        self
+    }
+
+    pub fn mask(mut self, mask: Group) -> Self {
+        self.mask = mask;
+        self
+    }
+
+    pub fn impacts(mut self, impacts: bool) -> Self {
+        self.impacts = impacts;
+        self
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum RagdollJoint {
+    // free rotation (shoulders, hips, neck)
+    Spherical,
+    // hinge around the given axis (elbows, knees)
+    Revolute(Vec3),
+}
+
+#[derive(Clone, Debug)]
+pub struct RagdollBone {
+    pub name: &'static str,
+    pub parent: Option<usize>,
+    // capsule center, relative to the skeleton root
+    pub center: Vec3,
+    // where this bone hangs off its parent, relative to the skeleton root
+    pub joint_anchor: Vec3,
+    pub joint: RagdollJoint,
+    pub half_height: f32,
+    pub radius: f32,
+}
+
+// bones are expected to be ordered so that parents always come before their children
+#[derive(Clone, Debug)]
+pub struct Skeleton {
+    pub bones: Vec<RagdollBone>,
+}
+
+impl Skeleton {
+    // a ~1.8 * scale tall humanoid with the pelvis at the origin
+    pub fn humanoid(scale: f32) -> Self {
+        let bone = |name, parent, center: Vec3, joint_anchor: Vec3, joint, half_height: f32, radius: f32| RagdollBone {
+            name,
+            parent,
+            center: center * scale,
+            joint_anchor: joint_anchor * scale,
+            joint,
+            half_height: half_height * scale,
+            radius: radius * scale,
+        };
+        let knee = RagdollJoint::Revolute(Vec3::X);
+
+        Self {
+            bones: vec![
+                bone("pelvis", None, vec3(0.0, 0.0, 0.0), Vec3::ZERO, RagdollJoint::Spherical, 0.05, 0.15),
+                bone("torso", Some(0), vec3(0.0, 0.35, 0.0), vec3(0.0, 0.15, 0.0), RagdollJoint::Spherical, 0.12, 0.18),
+                bone("head", Some(1), vec3(0.0, 0.75, 0.0), vec3(0.0, 0.62, 0.0), RagdollJoint::Spherical, 0.01, 0.12),
+                bone("upper_arm_l", Some(1), vec3(-0.3, 0.35, 0.0), vec3(-0.22, 0.5, 0.0), RagdollJoint::Spherical, 0.1, 0.06),
+                bone("lower_arm_l", Some(3), vec3(-0.3, 0.05, 0.0), vec3(-0.3, 0.2, 0.0), knee, 0.1, 0.05),
+                bone("upper_arm_r", Some(1), vec3(0.3, 0.35, 0.0), vec3(0.22, 0.5, 0.0), RagdollJoint::Spherical, 0.1, 0.06),
+                bone("lower_arm_r", Some(5), vec3(0.3, 0.05, 0.0), vec3(0.3, 0.2, 0.0), knee, 0.1, 0.05),
+                bone("thigh_l", Some(0), vec3(-0.1, -0.35, 0.0), vec3(-0.1, -0.12, 0.0), RagdollJoint::Spherical, 0.15, 0.08),
+                bone("shin_l", Some(7), vec3(-0.1, -0.8, 0.0), vec3(-0.1, -0.58, 0.0), knee, 0.15, 0.07),
+                bone("thigh_r", Some(0), vec3(0.1, -0.35, 0.0), vec3(0.1, -0.12, 0.0), RagdollJoint::Spherical, 0.15, 0.08),
+                bone("shin_r", Some(9), vec3(0.1, -0.8, 0.0), vec3(0.1, -0.58, 0.0), knee, 0.15, 0.07),
+            ],
+        }
+    }
+}
+
+pub struct ShapeHit {
+    pub collider: ColliderHandle,
+    // how far along the cast direction the shape got before touching something
+    pub distance: f32,
+    // outward normal of whatever got hit, in world space
+    pub normal: Vec3,
+}
+
+// something caught in an explosion it had a clear line to
+pub struct ExplosionHit {
+    pub entity: Option<EntityId>,
+    // `damage` scaled down by how far from the center it was
+    pub damage: f32,
+    // from the center towards it, flat when it was right on top
+    pub direction: Vec3,
+}
+
+pub struct Ragdoll {
+    // one body per skeleton bone, in the same order
+    pub bodies: Vec<RigidBodyHandle>,
+    pub joints: Vec<MultibodyJointHandle>,
+}
+
+// keeps loose dynamic bodies from piling up as walls get broken and enemies die. debris and ragdoll bones past the
+// cap are removed oldest first, and sleeping debris, bones or props far from every player are switched to kinematic
+// until someone comes back. whoever
+// spawned a removed body finds out from it being gone from `rigid_body_set`
+pub struct DebrisBudget {
+    pub max_debris: usize,
+    pub freeze_distance: f32,
+    // oldest first
+    debris: VecDeque<RigidBodyHandle>,
+    frozen: HashSet<RigidBodyHandle>,
+}
+
+impl DebrisBudget {
+    pub fn new(settings: &PhysicsSettings) -> Self {
+        Self {
+            max_debris: settings.max_debris,
+            freeze_distance: settings.freeze_distance,
+            debris: VecDeque::new(),
+            frozen: HashSet::new(),
+        }
+    }
+
+    pub fn debris_count(&self) -> usize {
+        self.debris.len()
+    }
+
+    pub fn frozen_count(&self) -> usize {
+        self.frozen.len()
+    }
+}
+
+// a snapshot of what the simulation is doing, for the debug overlay
+#[derive(Clone, Copy, Default, Debug)]
+pub struct PhysicsStats {
+    // dynamic bodies only, fixed and frozen ones cost next to nothing
+    pub active_bodies: usize,
+    pub sleeping_bodies: usize,
+    pub colliders: usize,
+    // groups of awake dynamic bodies that touch or are jointed to each other, the solver handles each on its own
+    pub islands: usize,
+    pub debris: usize,
+    pub frozen: usize,
+    // every substep of the last `step` together
+    pub last_step: Duration,
+}
+
+// a box where gravity is something else, low gravity rooms and shafts that pull upwards
+#[derive(Clone, Copy, Debug)]
+pub struct GravityVolume {
+    pub sensor: ColliderHandle,
+    // replaces the world's gravity inside, bodies' gravity scale still applies
+    pub gravity: Vec3,
+}
+
+// a dynamic body slamming into something that doesn't move
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Impact {
+    pub body: RigidBodyHandle,
+    pub position: Vec3,
+    // what the contact pushed back with over the step
+    pub impulse: f32,
+    // that divided by the body's mass, how much it slowed it down
+    pub speed: f32,
+}
+
+// rapier only hands events over through a shared reference, and from the physics thread too, so they wait behind a
+// mutex until `take_impacts`
+#[derive(Default)]
+pub struct ImpactCollector {
+    impacts: Mutex<Vec<Impact>>,
+}
+
+impl EventHandler for ImpactCollector {
+    fn handle_collision_event(&self, _: &RigidBodySet, _: &ColliderSet, _: CollisionEvent, _: Option<&ContactPair>) {}
+
+    fn handle_contact_force_event(&self, dt: Real, bodies: &RigidBodySet, colliders: &ColliderSet, contact_pair: &ContactPair, total_force_magnitude: Real) {
+        let parent = |collider: ColliderHandle| colliders.get(collider)
+            .and_then(|collider| collider.parent())
+            .and_then(|handle| Some((handle, bodies.get(handle)?)));
+        // no parent is a collider stuck straight into the world, like the ground
+        let is_world = |collider: ColliderHandle| parent(collider).is_none_or(|(_, body)| body.is_fixed());
+        // bodies bumping into each other don't count
+        let (handle, body) = match (parent(contact_pair.collider1), parent(contact_pair.collider2)) {
+            (Some((handle, body)), _) if body.is_dynamic() && is_world(contact_pair.collider2) => (handle, body),
+            (_, Some((handle, body))) if body.is_dynamic() && is_world(contact_pair.collider1) => (handle, body),
+            _ => return,
+        };
+
+        let impulse = total_force_magnitude * dt;
+        let speed = impulse / body.mass().max(f32::EPSILON);
+        if speed < MIN_IMPACT_SPEED {
+            return;
+        }
+
+        let position = contact_pair.manifolds.iter()
+            .flat_map(|manifold| manifold.data.solver_contacts.iter())
+            .next()
+            .map_or(*body.translation(), |contact| contact.point.coords);
+        self.impacts.lock().unwrap_or_else(PoisonError::into_inner).push(Impact {
+            body: handle,
+            position: vec3(position.x, position.y, position.z),
+            impulse,
+            speed,
+        });
+    }
+}
 
 pub struct RapierPhysicsWorld {
     pub rigid_body_set: RigidBodySet,
@@ -15,11 +384,22 @@ pub struct RapierPhysicsWorld {
     pub ccd_solver: CCDSolver,
     pub query_pipeline: QueryPipeline,
     pub physics_hooks: (),
-    pub event_handler: (),
+    pub event_handler: ImpactCollector,
 
     pub received_delta_time: Option<f32>,
 
     pub handles: Vec<RigidBodyHandle>,
+    // sensors that apply buoyancy and drag to whatever is inside them
+    pub water_volumes: Vec<ColliderHandle>,
+    pub lava_volumes: Vec<ColliderHandle>,
+    // sensors around climbable walls
+    pub ladders: Vec<ColliderHandle>,
+    pub gravity_volumes: Vec<GravityVolume>,
+    pub debris: DebrisBudget,
+    // when false, `BodySpec::ccd` is ignored and nothing gets continuous collision
+    pub ccd_enabled: bool,
+    gravity: Vec3,
+    last_step: Duration,
 }
 
 impl RapierPhysicsWorld {
@@ -28,12 +408,13 @@ impl RapierPhysicsWorld {
         let mut collider_set = ColliderSet::new();
 
         /* Create the ground. */
-        let ground_collider = ColliderBuilder::cuboid(25.0, 0.1, 25.0).build();
+        let ground_collider = ColliderBuilder::cuboid(25.0, 0.1, 25.0)
+            .collision_groups(CollisionLayer::StaticWorld.interaction_groups(CollisionLayer::StaticWorld.default_mask()))
+            .build();
         collider_set.insert(ground_collider);
 
         let mut handles = vec![];
 
-        let gravity = vector![0.0, -9.8, 0.0];
         let integration_parameters = IntegrationParameters::default();
         let physics_pipeline = PhysicsPipeline::new();
         let island_manager = IslandManager::new();
@@ -44,7 +425,7 @@ impl RapierPhysicsWorld {
         let ccd_solver = CCDSolver::new();
         let query_pipeline = QueryPipeline::new();
         let physics_hooks = ();
-        let event_handler = ();
+        let event_handler = ImpactCollector::default();
 
         Self {
             rigid_body_set,
@@ -61,91 +442,740 @@ impl RapierPhysicsWorld {
             physics_hooks,
             event_handler,
             handles,
+            water_volumes: Vec::new(),
+            lava_volumes: Vec::new(),
+            ladders: Vec::new(),
+            gravity_volumes: Vec::new(),
+            debris: DebrisBudget::new(&PhysicsSettings::default()),
+            ccd_enabled: true,
+            gravity: Vec3::NEG_Y * PhysicsSettings::default().gravity,
+            last_step: Duration::ZERO,
 
             received_delta_time: Some(0.032),
         }
     }
 
-    pub async fn step(&mut self) {
-        self.integration_parameters.dt = self.received_delta_time.unwrap();
-
-        self.physics_pipeline.step(
-            &vector![0.0, -9.81, 0.0], // gravity
-            &self.integration_parameters,
-            &mut self.island_manager,
-            &mut self.broad_phase,
-            &mut self.narrow_phase,
-            &mut self.rigid_body_set,
-            &mut self.collider_set,
-            &mut self.impulse_joint_set,
-            &mut self.multibody_joint_set,
-            &mut self.ccd_solver,
-            Some(&mut self.query_pipeline),
-            &self.physics_hooks,
-            &self.event_handler,
-        );
+    // by whatever `set_dt` was given last, the game always steps through `physics_thread::FixedSteps`
+    pub fn step(&mut self) {
+        profile_scope!("physics_step");
+        let dt = self.received_delta_time.unwrap();
+        // always the same number of equal steps for the same dt, lockstep's fixed step is a single one
+        let substeps = (dt / MAX_SUBSTEP).ceil().max(1.0);
+        self.integration_parameters.dt = dt / substeps;
+        let start = Instant::now();
+
+        for _ in 0..substeps as u32 {
+            self.apply_water_forces(self.integration_parameters.dt);
+            self.apply_gravity_volumes(self.integration_parameters.dt);
+
+            self.physics_pipeline.step(
+                &vector![self.gravity.x, self.gravity.y, self.gravity.z],
+                &self.integration_parameters,
+                &mut self.island_manager,
+                &mut self.broad_phase,
+                &mut self.narrow_phase,
+                &mut self.rigid_body_set,
+                &mut self.collider_set,
+                &mut self.impulse_joint_set,
+                &mut self.multibody_joint_set,
+                &mut self.ccd_solver,
+                Some(&mut self.query_pipeline),
+                &self.physics_hooks,
+                &self.event_handler,
+            );
+        }
+
+        self.last_step = start.elapsed();
+    }
+
+    // uses the overlaps from the previous step, which is close enough
+    fn apply_water_forces(&mut self, dt: f32) {
+        for &volume in self.water_volumes.iter() {
+            for (a, b, intersecting) in self.narrow_phase.intersection_pairs_with(volume) {
+                if !intersecting {
+                    continue;
+                }
+
+                let other = if a == volume { b } else { a };
+                let Some(parent) = self.collider_set.get(other).and_then(|collider| collider.parent()) else { continue };
+                let body = &mut self.rigid_body_set[parent];
+                if !body.is_dynamic() {
+                    continue;
+                }
+
+                let lift = self.gravity.length() * WATER_BUOYANCY * body.mass() * dt;
+                body.apply_impulse(vector![0.0, lift, 0.0], true);
+
+                let velocity = *body.linvel() * (1.0 - WATER_DRAG * dt).max(0.0);
+                body.set_linvel(velocity, true);
+            }
+        }
+    }
+
+    // the pipeline already pulls everything with the world's gravity, so inside a volume only the difference is
+    // added on top. a body in two overlapping volumes gets both
+    fn apply_gravity_volumes(&mut self, dt: f32) {
+        for volume in self.gravity_volumes.iter() {
+            let extra = volume.gravity - self.gravity;
+            for (a, b, intersecting) in self.narrow_phase.intersection_pairs_with(volume.sensor) {
+                if !intersecting {
+                    continue;
+                }
+
+                let other = if a == volume.sensor { b } else { a };
+                let Some(parent) = self.collider_set.get(other).and_then(|collider| collider.parent()) else { continue };
+                let body = &mut self.rigid_body_set[parent];
+                if !body.is_dynamic() {
+                    continue;
+                }
+
+                let impulse = extra * body.gravity_scale() * body.mass() * dt;
+                body.apply_impulse(vector![impulse.x, impulse.y, impulse.z], true);
+            }
+        }
+    }
+
+    // a fingerprint of where every body is and how it's moving, lockstep peers compare these to catch desyncs.
+    // only comparable between the same build on the same kind of cpu, rapier isn't bit for bit cross platform
+    pub fn state_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        for (_, body) in self.rigid_body_set.iter() {
+            let (t, v) = (body.translation(), body.linvel());
+            for value in [t.x, t.y, t.z, v.x, v.y, v.z] {
+                value.to_bits().hash(&mut hasher);
+            }
+        }
+
+        hasher.finish()
+    }
+
+    pub fn configure(&mut self, settings: &PhysicsSettings) {
+        self.debris.max_debris = settings.max_debris;
+        self.debris.freeze_distance = settings.freeze_distance;
+        self.set_gravity(Vec3::NEG_Y * settings.gravity);
+
+        self.integration_parameters.max_ccd_substeps = settings.max_ccd_substeps.max(1);
+        self.integration_parameters.min_ccd_dt = settings.min_ccd_dt;
+        if self.ccd_enabled != settings.ccd {
+            self.ccd_enabled = settings.ccd;
+            // bodies spawned before this keep whatever they were built with otherwise
+            if !settings.ccd {
+                for (_, body) in self.rigid_body_set.iter_mut() {
+                    body.enable_ccd(false);
+                }
+            }
+        }
+    }
+
+    pub fn gravity(&self) -> Vec3 {
+        self.gravity
+    }
+
+    // sleeping bodies wouldn't notice otherwise
+    pub fn set_gravity(&mut self, gravity: Vec3) {
+        if gravity == self.gravity {
+            return;
+        }
+
+        self.gravity = gravity;
+        for (_, body) in self.rigid_body_set.iter_mut().filter(|(_, body)| body.is_dynamic()) {
+            body.wake_up(true);
+        }
+    }
+
+    // for bodies that only sometimes move fast enough to tunnel, like something that just got launched
+    pub fn set_ccd(&mut self, body: RigidBodyHandle, enabled: bool) {
+        let enabled = enabled && self.ccd_enabled;
+        if let Some(body) = self.rigid_body_set.get_mut(body) {
+            body.enable_ccd(enabled);
+        }
+    }
+
+    // counts against the debris cap, the oldest tracked body is the first to go
+    pub fn track_debris(&mut self, body: RigidBodyHandle) {
+        self.debris.debris.push_back(body);
+    }
+
+    pub fn dynamic_body_count(&self) -> usize {
+        self.rigid_body_set.iter().filter(|(_, body)| body.is_dynamic()).count()
+    }
+
+    // `players` are everyone being simulated, bodies only freeze when they're far from all of them
+    pub fn update_debris(&mut self, players: &[Vec3]) {
+        let bodies = &self.rigid_body_set;
+        self.debris.debris.retain(|&body| bodies.contains(body));
+        self.debris.frozen.retain(|&body| bodies.contains(body));
+
+        while self.debris.debris.len() > self.debris.max_debris {
+            let Some(oldest) = self.debris.debris.pop_front() else { break };
+            self.debris.frozen.remove(&oldest);
+            self.remove_rigidbody(oldest);
+        }
+
+        let freeze_distance = self.debris.freeze_distance;
+        // ragdoll bones are tagged with the enemy they were, being tracked is what tells them apart
+        let tracked: HashSet<RigidBodyHandle> = self.debris.debris.iter().copied().collect();
+        let loose: Vec<RigidBodyHandle> = self.rigid_body_set.iter()
+            .filter(|(handle, body)| tracked.contains(handle) || matches!(EntityId::from_user_data(body.user_data), Some(entity) if matches!(entity.kind, EntityKind::Debris | EntityKind::Prop)))
+            .map(|(handle, _)| handle)
+            .collect();
+
+        for handle in loose {
+            let body = &mut self.rigid_body_set[handle];
+            let t = body.translation();
+            let distance = players.iter().map(|player| player.distance(vec3(t.x, t.y, t.z))).fold(f32::INFINITY, f32::min);
+
+            if self.debris.frozen.contains(&handle) {
+                if distance < freeze_distance * UNFREEZE_RATIO {
+                    body.set_body_type(RigidBodyType::Dynamic, true);
+                    self.debris.frozen.remove(&handle);
+                }
+            } else if body.is_dynamic() && body.is_sleeping() && distance > freeze_distance {
+                body.set_body_type(RigidBodyType::KinematicPositionBased, false);
+                self.debris.frozen.insert(handle);
+            }
+        }
+    }
+
+    pub fn stats(&self) -> PhysicsStats {
+        let (mut active_bodies, mut sleeping_bodies) = (0, 0);
+        for (_, body) in self.rigid_body_set.iter().filter(|(_, body)| body.is_dynamic()) {
+            if body.is_sleeping() {
+                sleeping_bodies += 1;
+            } else {
+                active_bodies += 1;
+            }
+        }
+
+        PhysicsStats {
+            active_bodies,
+            sleeping_bodies,
+            colliders: self.collider_set.len(),
+            islands: self.count_islands(),
+            debris: self.debris.debris_count(),
+            frozen: self.debris.frozen_count(),
+            last_step: self.last_step,
+        }
+    }
+
+    // rapier keeps its islands to itself, so this redoes the grouping from the contact and joint graphs
+    fn count_islands(&self) -> usize {
+        let awake = |body: RigidBodyHandle| self.rigid_body_set.get(body).is_some_and(|body| body.is_dynamic() && !body.is_sleeping());
+        let mut parents: HashMap<RigidBodyHandle, RigidBodyHandle> = self.island_manager.active_dynamic_bodies()
+            .iter()
+            .filter(|&&body| awake(body))
+            .map(|&body| (body, body))
+            .collect();
+
+        fn root(parents: &mut HashMap<RigidBodyHandle, RigidBodyHandle>, mut body: RigidBodyHandle) -> RigidBodyHandle {
+            while parents[&body] != body {
+                let grandparent = parents[&parents[&body]];
+                parents.insert(body, grandparent);
+                body = grandparent;
+            }
+            body
+        }
+
+        let contacts = self.narrow_phase.contact_pairs()
+            .filter(|pair| pair.has_any_active_contact)
+            .filter_map(|pair| Some((self.collider_set.get(pair.collider1)?.parent()?, self.collider_set.get(pair.collider2)?.parent()?)));
+        let joints = self.impulse_joint_set.iter().map(|(_, joint)| (joint.body1, joint.body2));
+
+        for (a, b) in contacts.chain(joints).collect::<Vec<_>>() {
+            // fixed bodies don't join islands together
+            if !parents.contains_key(&a) || !parents.contains_key(&b) {
+                continue;
+            }
+            let (a, b) = (root(&mut parents, a), root(&mut parents, b));
+            parents.insert(a, b);
+        }
+
+        let bodies: Vec<RigidBodyHandle> = parents.keys().copied().collect();
+        bodies.into_iter().filter(|&body| root(&mut parents, body) == body).count()
     }
 
     pub fn set_dt(&mut self, dt: f32) {
         self.received_delta_time = Some(dt);
     }    
 
-    pub fn add_capsule_rigidbody(&mut self, x: f32, y: f32, z: f32) -> RigidBodyHandle {
-        let capsule_rigid_body = RigidBodyBuilder::dynamic()
-            .translation(vector![x, y, z])
+    pub fn add_body(&mut self, position: Vec3, spec: &BodySpec) -> RigidBodyHandle {
+        let shape = match spec.shape {
+            BodyShape::Cuboid => ColliderBuilder::cuboid(spec.size.x, spec.size.y, spec.size.z),
+            BodyShape::Ball => ColliderBuilder::ball(spec.size.x),
+            BodyShape::Capsule => ColliderBuilder::capsule_y(spec.size.y, spec.size.x),
+        };
+        self.insert_body(position, spec, shape)
+    }
+
+    // like `add_body` but shaped like the smallest convex shape around `points`, which are relative to `position`.
+    // `spec.shape` and `spec.size` are ignored. `None` if the points don't enclose any volume
+    pub fn add_convex_hull(&mut self, position: Vec3, points: &[Vec3], spec: &BodySpec) -> Option<RigidBodyHandle> {
+        let points: Vec<Point3<Real>> = points.iter().map(|&point| to_point(point)).collect();
+        let shape = ColliderBuilder::convex_hull(&points)?;
+        Some(self.insert_body(position, spec, shape))
+    }
+
+    fn insert_body(&mut self, position: Vec3, spec: &BodySpec, shape: ColliderBuilder) -> RigidBodyHandle {
+        let rigid_body = RigidBodyBuilder::new(spec.body_type)
+            .translation(vector![position.x, position.y, position.z])
+            .linear_damping(spec.linear_damping)
+            .ccd_enabled(spec.ccd && self.ccd_enabled)
+            .build();
+
+        let collider = shape
+            .restitution(spec.restitution)
+            .friction(spec.friction)
+            .density(spec.density)
+            .collision_groups(spec.layer.interaction_groups(spec.mask))
+            .active_events(if spec.impacts { ActiveEvents::CONTACT_FORCE_EVENTS } else { ActiveEvents::empty() })
             .build();
-        let capsule_collider = ColliderBuilder::capsule_y(0.5, 0.5).restitution(0.5).friction(1.0).build();
-        let capsule_body_handle = self.rigid_body_set.insert(capsule_rigid_body.clone());
 
-        self.handles.push(capsule_body_handle.clone());
-        self.collider_set.insert_with_parent(capsule_collider.clone(), capsule_body_handle, &mut self.rigid_body_set);
+        let body_handle = self.rigid_body_set.insert(rigid_body);
+
+        self.handles.push(body_handle);
+        self.collider_set.insert_with_parent(collider, body_handle, &mut self.rigid_body_set);
 
-        return capsule_body_handle;
+        body_handle
     }
 
-    pub fn add_cube_rigidbody(&mut self, x: f32, y: f32, z: f32) -> RigidBodyHandle {
-        // i ain't bothering renaming stuff now
+    // casts a ray straight down from `origin` and returns the body it lands on, ignoring `exclude`
+    pub fn body_below(&self, origin: Vec3, max_distance: f32, exclude: RigidBodyHandle) -> Option<RigidBodyHandle> {
+        let ray = Ray::new(point![origin.x, origin.y, origin.z], vector![0.0, -1.0, 0.0]);
+        let filter = QueryFilter::default().exclude_rigid_body(exclude);
 
-        let capsule_rigid_body = RigidBodyBuilder::dynamic()
-            .translation(vector![x, y, z])
-            .build();
-        let capsule_collider = ColliderBuilder::cuboid(0.3, 0.3, 0.3).restitution(0.7).friction(0.5).build();
-        let capsule_body_handle = self.rigid_body_set.insert(capsule_rigid_body.clone());
+        let (collider_handle, _) = self.query_pipeline.cast_ray(
+            &self.rigid_body_set,
+            &self.collider_set,
+            &ray,
+            max_distance,
+            true,
+            filter,
+        )?;
+
+        self.collider_set[collider_handle].parent()
+    }
+
+    // returns the collider hit by the ray and the distance to it, use `entity_of` to find out what was hit
+    pub fn cast_ray(&self, origin: Vec3, direction: Vec3, max_distance: f32, exclude: RigidBodyHandle) -> Option<(ColliderHandle, f32)> {
+        let ray = Ray::new(point![origin.x, origin.y, origin.z], vector![direction.x, direction.y, direction.z]);
+        let filter = QueryFilter::default().exclude_rigid_body(exclude).exclude_sensors();
+
+        self.query_pipeline.cast_ray(
+            &self.rigid_body_set,
+            &self.collider_set,
+            &ray,
+            max_distance,
+            true,
+            filter,
+        )
+    }
 
-        self.handles.push(capsule_body_handle.clone());
-        self.collider_set.insert_with_parent(capsule_collider.clone(), capsule_body_handle, &mut self.rigid_body_set);
+    // same as `cast_ray` but also reports the surface normal at the hit point
+    pub fn cast_ray_with_normal(&self, origin: Vec3, direction: Vec3, max_distance: f32, exclude: RigidBodyHandle) -> Option<ShapeHit> {
+        let ray = Ray::new(point![origin.x, origin.y, origin.z], vector![direction.x, direction.y, direction.z]);
+        let filter = QueryFilter::default().exclude_rigid_body(exclude).exclude_sensors();
 
-        return capsule_body_handle;
+        let (collider, intersection) = self.query_pipeline.cast_ray_and_get_normal(
+            &self.rigid_body_set,
+            &self.collider_set,
+            &ray,
+            max_distance,
+            true,
+            filter,
+        )?;
+
+        Some(ShapeHit {
+            collider,
+            distance: intersection.toi,
+            normal: vec3(intersection.normal.x, intersection.normal.y, intersection.normal.z),
+        })
     }
 
-    pub fn add_sphere_rigidbody(&mut self, x: f32, y: f32, z: f32) -> RigidBodyHandle {
-        // i ain't bothering renaming stuff now
+    // sweeps `shape` from `origin` along `direction` (normalized) and reports the first thing it touches
+    pub fn cast_shape(&self, origin: Vec3, direction: Vec3, max_distance: f32, shape: &dyn Shape, exclude: RigidBodyHandle) -> Option<ShapeHit> {
+        let shape_pos = Isometry::translation(origin.x, origin.y, origin.z);
+        let shape_vel = vector![direction.x, direction.y, direction.z];
+        let filter = QueryFilter::default().exclude_rigid_body(exclude).exclude_sensors();
+
+        let (collider, toi) = self.query_pipeline.cast_shape(
+            &self.rigid_body_set,
+            &self.collider_set,
+            &shape_pos,
+            &shape_vel,
+            shape,
+            max_distance,
+            false,
+            filter,
+        )?;
+
+        Some(ShapeHit {
+            collider,
+            distance: toi.toi,
+            normal: vec3(toi.normal1.x, toi.normal1.y, toi.normal1.z),
+        })
+    }
+
+    // every body with a collider whose bounding box comes within `radius` of `center`, straight from the broad phase
+    // so it's cheap but rough. each body shows up once, `exclude` never does
+    pub fn bodies_near(&self, center: Vec3, radius: f32, exclude: RigidBodyHandle) -> Vec<RigidBodyHandle> {
+        let aabb = Aabb::new(point![center.x - radius, center.y - radius, center.z - radius], point![center.x + radius, center.y + radius, center.z + radius]);
+        let mut bodies = Vec::new();
+        self.query_pipeline.colliders_with_aabb_intersecting_aabb(&aabb, |&collider| {
+            if let Some(body) = self.collider_set.get(collider).and_then(|collider| collider.parent()) {
+                if body != exclude && !bodies.contains(&body) {
+                    bodies.push(body);
+                }
+            }
+            true
+        });
+        bodies
+    }
 
-        let capsule_rigid_body = RigidBodyBuilder::dynamic()
+    // every tagged entity with a collider whose bounding box overlaps the box from `min` to `max`. broad phase only,
+    // so something just outside a corner can still show up. each entity once, sensors included
+    pub fn query_aabb(&self, min: Vec3, max: Vec3) -> Vec<EntityId> {
+        let aabb = Aabb::new(to_point(min), to_point(max));
+        let mut entities = Vec::new();
+        self.query_pipeline.colliders_with_aabb_intersecting_aabb(&aabb, |&collider| {
+            if let Some(entity) = self.entity_of(collider).filter(|entity| !entities.contains(entity)) {
+                entities.push(entity);
+            }
+            true
+        });
+        entities
+    }
+
+    // every tagged entity with a solid collider actually inside the sphere, each once
+    pub fn query_sphere(&self, center: Vec3, radius: f32) -> Vec<EntityId> {
+        let mut entities = Vec::new();
+        for collider in self.colliders_in_sphere(center, radius) {
+            if let Some(entity) = self.entity_of(collider).filter(|entity| !entities.contains(entity)) {
+                entities.push(entity);
+            }
+        }
+        entities
+    }
+
+    // the broad phase narrows it down first, only the colliders it lets through get the exact test
+    fn colliders_in_sphere(&self, center: Vec3, radius: f32) -> Vec<ColliderHandle> {
+        let mut caught = Vec::new();
+        self.query_pipeline.intersections_with_shape(
+            &self.rigid_body_set,
+            &self.collider_set,
+            &Isometry::translation(center.x, center.y, center.z),
+            &Ball::new(radius),
+            QueryFilter::default().exclude_sensors(),
+            |collider| {
+                caught.push(collider);
+                true
+            },
+        );
+        caught
+    }
+
+    // pushes every dynamic body within `radius` away from `center` and reports everything that could see the center.
+    // both fall off to nothing at the edge, walls in the way block the damage but not the push
+    pub fn explode(&mut self, center: Vec3, radius: f32, force: f32, damage: f32) -> Vec<ExplosionHit> {
+        let caught = self.colliders_in_sphere(center, radius);
+
+        let mut hits = Vec::new();
+        let mut pushed = Vec::new();
+        for collider_handle in caught {
+            let collider = &self.collider_set[collider_handle];
+            let closest = collider.shape().project_point(collider.position(), &to_point(center), true).point;
+            let offset = vec3(closest.x, closest.y, closest.z) - center;
+            let distance = offset.length();
+            let falloff = (1.0 - distance / radius).clamp(0.0, 1.0);
+            let direction = if distance > f32::EPSILON { offset / distance } else { Vec3::Y };
+
+            if let Some(body) = collider.parent().filter(|body| !pushed.contains(body)) {
+                pushed.push(body);
+                let rigid_body = &mut self.rigid_body_set[body];
+                if rigid_body.is_dynamic() {
+                    let impulse = direction * force * falloff;
+                    rigid_body.apply_impulse(vector![impulse.x, impulse.y, impulse.z], true);
+                }
+            }
+
+            // whatever the ray to the closest point hits first is what's shielding it
+            let ray = Ray::new(to_point(center), vector![direction.x, direction.y, direction.z]);
+            let blocker = self.query_pipeline.cast_ray(&self.rigid_body_set, &self.collider_set, &ray, distance, true, QueryFilter::default().exclude_sensors());
+            if distance > f32::EPSILON && blocker.is_some_and(|(blocker, _)| blocker != collider_handle) {
+                continue;
+            }
+
+            hits.push(ExplosionHit {
+                entity: self.entity_of(collider_handle),
+                damage: damage * falloff,
+                direction,
+            });
+        }
+
+        hits
+    }
+
+    // marks the body and every collider attached to it as belonging to `entity`
+    pub fn tag_body(&mut self, body: RigidBodyHandle, entity: EntityId) {
+        let user_data = entity.to_user_data();
+        let rigid_body = &mut self.rigid_body_set[body];
+        rigid_body.user_data = user_data;
+
+        for collider in rigid_body.colliders().to_vec() {
+            self.collider_set[collider].user_data = user_data;
+        }
+    }
+
+    pub fn tag_collider(&mut self, collider: ColliderHandle, entity: EntityId) {
+        self.collider_set[collider].user_data = entity.to_user_data();
+    }
+
+    pub fn entity_of(&self, collider: ColliderHandle) -> Option<EntityId> {
+        EntityId::from_user_data(self.collider_set.get(collider)?.user_data)
+    }
+
+    pub fn entity_of_body(&self, body: RigidBodyHandle) -> Option<EntityId> {
+        EntityId::from_user_data(self.rigid_body_set.get(body)?.user_data)
+    }
+
+    // a parentless sensor collider, used for trigger volumes like traps
+    pub fn add_sensor(&mut self, x: f32, y: f32, z: f32, half_extents: Vec3, layer: CollisionLayer, mask: Group) -> ColliderHandle {
+        let sensor_collider = ColliderBuilder::cuboid(half_extents.x, half_extents.y, half_extents.z)
             .translation(vector![x, y, z])
+            .sensor(true)
+            .collision_groups(layer.interaction_groups(mask))
             .build();
-        let capsule_collider = ColliderBuilder::ball(0.5).restitution(0.7).friction(0.5).build();
-        let capsule_body_handle = self.rigid_body_set.insert(capsule_rigid_body.clone());
 
-        self.handles.push(capsule_body_handle.clone());
-        self.collider_set.insert_with_parent(capsule_collider.clone(), capsule_body_handle, &mut self.rigid_body_set);
+        self.collider_set.insert(sensor_collider)
+    }
 
-        return capsule_body_handle;
+    pub fn is_body_in_sensor(&self, sensor: ColliderHandle, body: RigidBodyHandle) -> bool {
+        let Some(body) = self.rigid_body_set.get(body) else { return false };
+
+        body.colliders().iter().any(|&collider| {
+            self.narrow_phase.intersection_pair(sensor, collider) == Some(true)
+        })
     }
 
-    pub fn add_static_cube_rigidbody(&mut self, x: f32, y: f32, z: f32) -> RigidBodyHandle {
-        // i ain't bothering renaming stuff now
-        
-        let capsule_rigid_body = RigidBodyBuilder::fixed()
-            .translation(vector![x, y, z])
+    // fixed ground shaped by `heights`, row by row along z with `columns` samples each. `size` is the whole
+    // field's extent, it's centered on `center` like the other colliders
+    pub fn add_heightfield(&mut self, center: Vec3, columns: usize, rows: usize, heights: &[f32], size: Vec3) -> ColliderHandle {
+        let heights = DMatrix::from_fn(rows, columns, |row, column| heights[row * columns + column]);
+        let collider = ColliderBuilder::heightfield(heights, vector![size.x, size.y, size.z])
+            .translation(vector![center.x, center.y, center.z])
+            .collision_groups(CollisionLayer::StaticWorld.interaction_groups(CollisionLayer::StaticWorld.default_mask()))
             .build();
-        let capsule_collider = ColliderBuilder::cuboid(0.5, 0.5, 0.5).restitution(0.7).friction(3.0).build();
-        let capsule_body_handle = self.rigid_body_set.insert(capsule_rigid_body.clone());
+        self.collider_set.insert(collider)
+    }
+
+    pub fn add_water_volume(&mut self, position: Vec3, half_extents: Vec3) -> ColliderHandle {
+        let volume = self.add_sensor(position.x, position.y, position.z, half_extents, CollisionLayer::Water, CollisionLayer::Water.default_mask());
+        self.water_volumes.push(volume);
 
-        self.handles.push(capsule_body_handle.clone());
-        self.collider_set.insert_with_parent(capsule_collider.clone(), capsule_body_handle, &mut self.rigid_body_set);
+        volume
+    }
+
+    // pushes nothing around, whoever's in it just gets hurt, see `TrapSystem::update`
+    pub fn add_lava_volume(&mut self, position: Vec3, half_extents: Vec3) -> ColliderHandle {
+        let volume = self.add_sensor(position.x, position.y, position.z, half_extents, CollisionLayer::Trigger, CollisionLayer::Trigger.default_mask());
+        self.lava_volumes.push(volume);
 
-        return capsule_body_handle;
+        volume
+    }
+
+    pub fn add_gravity_volume(&mut self, position: Vec3, half_extents: Vec3, gravity: Vec3) -> ColliderHandle {
+        let sensor = self.add_sensor(position.x, position.y, position.z, half_extents, CollisionLayer::GravityField, CollisionLayer::GravityField.default_mask());
+        self.gravity_volumes.push(GravityVolume { sensor, gravity });
+
+        sensor
+    }
+
+    pub fn remove_gravity_volume(&mut self, sensor: ColliderHandle) {
+        self.gravity_volumes.retain(|volume| volume.sensor != sensor);
+        self.collider_set.remove(sensor, &mut self.island_manager, &mut self.rigid_body_set, true);
+    }
+
+    // what's pulling on `body` right now, counting every volume it's in
+    pub fn gravity_at(&self, body: RigidBodyHandle) -> Vec3 {
+        self.gravity_volumes.iter()
+            .filter(|volume| self.is_body_in_sensor(volume.sensor, body))
+            .fold(self.gravity, |gravity, volume| gravity + volume.gravity - self.gravity)
+    }
+
+    pub fn is_body_in_water(&self, body: RigidBodyHandle) -> bool {
+        self.water_volumes.iter().any(|&volume| self.is_body_in_sensor(volume, body))
+    }
+
+    pub fn is_body_in_lava(&self, body: RigidBodyHandle) -> bool {
+        self.lava_volumes.iter().any(|&volume| self.is_body_in_sensor(volume, body))
+    }
+
+    pub fn add_ladder(&mut self, position: Vec3, half_extents: Vec3) -> ColliderHandle {
+        let ladder = self.add_sensor(position.x, position.y, position.z, half_extents, CollisionLayer::Trigger, CollisionLayer::Trigger.default_mask());
+        self.ladders.push(ladder);
+
+        ladder
+    }
+
+    pub fn is_body_on_ladder(&self, body: RigidBodyHandle) -> bool {
+        self.ladders.iter().any(|&ladder| self.is_body_in_sensor(ladder, body))
+    }
+
+    pub fn are_bodies_touching(&self, a: RigidBodyHandle, b: RigidBodyHandle) -> bool {
+        let (Some(a), Some(b)) = (self.rigid_body_set.get(a), self.rigid_body_set.get(b)) else { return false };
+
+        a.colliders().iter().any(|&collider_a| {
+            b.colliders().iter().any(|&collider_b| {
+                self.narrow_phase.contact_pair(collider_a, collider_b).is_some_and(|pair| pair.has_any_active_contact)
+            })
+        })
+    }
+
+    // some other collider the body is touching, sensors don't count since they never make contacts
+    pub fn touching(&self, body: RigidBodyHandle) -> Option<ColliderHandle> {
+        let body = self.rigid_body_set.get(body)?;
+
+        body.colliders().iter().find_map(|&collider| {
+            let pair = self.narrow_phase.contact_pairs_with(collider).find(|pair| pair.has_any_active_contact)?;
+            Some(if pair.collider1 == collider { pair.collider2 } else { pair.collider1 })
+        })
+    }
+
+    // every impact since the last call, a hit that lasts a few steps shows up once per step
+    pub fn take_impacts(&mut self) -> Vec<Impact> {
+        std::mem::take(self.event_handler.impacts.get_mut().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    // anchors are in each body's local space, so `Vec3::ZERO` means "at the body's center"
+    pub fn add_fixed_joint(&mut self, body1: RigidBodyHandle, body2: RigidBodyHandle, anchor1: Vec3, anchor2: Vec3) -> ImpulseJointHandle {
+        let joint = FixedJointBuilder::new()
+            .local_anchor1(to_point(anchor1))
+            .local_anchor2(to_point(anchor2));
+
+        self.impulse_joint_set.insert(body1, body2, joint, true)
+    }
+
+    // hinges, e.g. doors: `axis` is the hinge axis and `limits` the allowed angle range in radians
+    pub fn add_revolute_joint(&mut self, body1: RigidBodyHandle, body2: RigidBodyHandle, anchor1: Vec3, anchor2: Vec3, axis: Vec3, limits: Option<[f32; 2]>) -> ImpulseJointHandle {
+        let mut joint = RevoluteJointBuilder::new(UnitVector::new_normalize(vector![axis.x, axis.y, axis.z]))
+            .local_anchor1(to_point(anchor1))
+            .local_anchor2(to_point(anchor2));
+        if let Some(limits) = limits {
+            joint = joint.limits(limits);
+        }
+
+        self.impulse_joint_set.insert(body1, body2, joint, true)
+    }
+
+    pub fn add_spherical_joint(&mut self, body1: RigidBodyHandle, body2: RigidBodyHandle, anchor1: Vec3, anchor2: Vec3) -> ImpulseJointHandle {
+        let joint = SphericalJointBuilder::new()
+            .local_anchor1(to_point(anchor1))
+            .local_anchor2(to_point(anchor2));
+
+        self.impulse_joint_set.insert(body1, body2, joint, true)
+    }
+
+    // keeps the anchors at most `max_distance` apart but lets them get as close as they like (chains, grapples)
+    pub fn add_rope_joint(&mut self, body1: RigidBodyHandle, body2: RigidBodyHandle, anchor1: Vec3, anchor2: Vec3, max_distance: f32) -> ImpulseJointHandle {
+        let joint = RopeJointBuilder::new(max_distance)
+            .local_anchor1(to_point(anchor1))
+            .local_anchor2(to_point(anchor2));
+
+        self.impulse_joint_set.insert(body1, body2, joint, true)
+    }
+
+    // rope joints only, lets the rope be reeled in or out after it's made
+    pub fn set_rope_length(&mut self, handle: ImpulseJointHandle, max_distance: f32) {
+        if let Some(joint) = self.impulse_joint_set.get_mut(handle) {
+            joint.data.set_limits(JointAxis::X, [0.0, max_distance]);
+        }
+    }
+
+    // a fixed body with no collider, for hanging joints off a point in the world that isn't a body itself
+    pub fn add_anchor(&mut self, position: Vec3) -> RigidBodyHandle {
+        self.rigid_body_set.insert(RigidBodyBuilder::fixed().translation(vector![position.x, position.y, position.z]).build())
+    }
+
+    pub fn remove_joint(&mut self, handle: ImpulseJointHandle) {
+        self.impulse_joint_set.remove(handle, true);
+    }
+
+    pub fn remove_joints_attached_to(&mut self, body: RigidBodyHandle) {
+        let attached: Vec<ImpulseJointHandle> = self.impulse_joint_set
+            .attached_joints(body)
+            .map(|(_, _, handle, _)| handle)
+            .collect();
+
+        for handle in attached {
+            self.impulse_joint_set.remove(handle, true);
+        }
+    }
+
+    // every bone gets tagged with `entity`, so hitting a corpse still resolves to whoever it was
+    pub fn build_ragdoll(&mut self, skeleton: &Skeleton, root: Vec3, initial_velocity: Vec3, entity: EntityId) -> Ragdoll {
+        let mut bodies = Vec::with_capacity(skeleton.bones.len());
+        let mut joints = Vec::new();
+
+        for bone in skeleton.bones.iter() {
+            let center = root + bone.center;
+            let bone_rigid_body = RigidBodyBuilder::dynamic()
+                .translation(vector![center.x, center.y, center.z])
+                .linvel(vector![initial_velocity.x, initial_velocity.y, initial_velocity.z])
+                .user_data(entity.to_user_data())
+                .build();
+            let bone_collider = ColliderBuilder::capsule_y(bone.half_height, bone.radius)
+                .friction(0.8)
+                .collision_groups(CollisionLayer::Debris.interaction_groups(CollisionLayer::Debris.default_mask()))
+                .user_data(entity.to_user_data())
+                .build();
+            let bone_body_handle = self.rigid_body_set.insert(bone_rigid_body);
+
+            self.handles.push(bone_body_handle);
+            self.collider_set.insert_with_parent(bone_collider, bone_body_handle, &mut self.rigid_body_set);
+
+            if let Some(parent) = bone.parent {
+                let parent_bone = &skeleton.bones[parent];
+                let anchor1 = bone.joint_anchor - parent_bone.center;
+                let anchor2 = bone.joint_anchor - bone.center;
+
+                let joint: GenericJoint = match bone.joint {
+                    RagdollJoint::Spherical => SphericalJointBuilder::new()
+                        .local_anchor1(to_point(anchor1))
+                        .local_anchor2(to_point(anchor2))
+                        .contacts_enabled(false)
+                        .into(),
+                    RagdollJoint::Revolute(axis) => RevoluteJointBuilder::new(UnitVector::new_normalize(vector![axis.x, axis.y, axis.z]))
+                        .local_anchor1(to_point(anchor1))
+                        .local_anchor2(to_point(anchor2))
+                        .limits([-2.5, 0.0])
+                        .contacts_enabled(false)
+                        .into(),
+                };
+
+                if let Some(joint_handle) = self.multibody_joint_set.insert(bodies[parent], bone_body_handle, joint, true) {
+                    joints.push(joint_handle);
+                }
+            }
+
+            bodies.push(bone_body_handle);
+        }
+
+        Ragdoll { bodies, joints }
+    }
+
+    pub fn remove_ragdoll(&mut self, ragdoll: Ragdoll) {
+        // removing the bodies also takes their multibody links with them
+        for body in ragdoll.bodies.into_iter().rev() {
+            self.remove_rigidbody(body);
+        }
+    }
+
+    // for colliders that were added without a body, like sensors and terrain, or ones attached to a static body
+    pub fn remove_collider(&mut self, collider: ColliderHandle) {
+        self.water_volumes.retain(|&volume| volume != collider);
+        self.lava_volumes.retain(|&volume| volume != collider);
+        self.ladders.retain(|&ladder| ladder != collider);
+        self.gravity_volumes.retain(|volume| volume.sensor != collider);
+        self.collider_set.remove(collider, &mut self.island_manager, &mut self.rigid_body_set, true);
     }
 
     pub fn remove_rigidbody(&mut self, handle: RigidBodyHandle) {
@@ -159,22 +1189,30 @@ impl RapierPhysicsWorld {
         );
     }
 
-    pub fn build_collider_from_mesh(&mut self, vertices: Vec<Vertex>, indices: Vec<u32>, x: f32, y: f32, z: f32) -> RigidBodyHandle {
-        let trimesh = SharedShape::trimesh(
-            vertices.iter().map(|v| Point3::new(v.position.x, v.position.y, v.position.z)).collect(),
-            indices.chunks(3).map(|c| [c[0] as u32, c[1] as u32, c[2] as u32]).collect()
-        );
-        
-        let mesh_rigid_body = RigidBodyBuilder::kinematic_position_based()
-            .translation(vector![x, y, z])
+    // a fixed body at the origin for static world geometry to hang off of, see `attach_static_shape`. removing it
+    // takes everything attached with it
+    pub fn add_static_body(&mut self) -> RigidBodyHandle {
+        self.rigid_body_set.insert(RigidBodyBuilder::fixed().build())
+    }
+
+    // `shape` placed at `position` in the world, on a body from `add_static_body`
+    pub fn attach_static_shape(&mut self, body: RigidBodyHandle, shape: SharedShape, position: Vec3) -> ColliderHandle {
+        let collider = ColliderBuilder::new(shape)
+            .translation(vector![position.x, position.y, position.z])
+            .collision_groups(CollisionLayer::StaticWorld.interaction_groups(CollisionLayer::StaticWorld.default_mask()))
             .build();
-        let mesh_collider = ColliderBuilder::new(trimesh).build();
-        let mesh_body_handle = self.rigid_body_set.insert(mesh_rigid_body.clone());
-    
-        self.handles.push(mesh_body_handle.clone());
-        self.collider_set.insert_with_parent(mesh_collider.clone(), mesh_body_handle, &mut self.rigid_body_set);
-    
-        mesh_body_handle
-    }
-    
+        self.collider_set.insert_with_parent(collider, body, &mut self.rigid_body_set)
+    }
+}
+
+// boxes as one shape, each one `(center, half extents)` from wherever the shape ends up. only data, so it can be built
+// on another thread and attached later
+pub fn compound_of_boxes(boxes: &[(Vec3, Vec3)]) -> SharedShape {
+    SharedShape::compound(boxes.iter().map(|&(center, half_extents)| {
+        (Isometry::translation(center.x, center.y, center.z), SharedShape::cuboid(half_extents.x, half_extents.y, half_extents.z))
+    }).collect())
+}
+
+fn to_point(v: Vec3) -> Point3<Real> {
+    point![v.x, v.y, v.z]
 }
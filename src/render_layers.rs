@@ -0,0 +1,118 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use once_cell::sync::Lazy;
+use tiny_game_framework::{gl, EventLoop, Mesh, Renderer, DEFAULT_SHADER, FULL_SHADER, INSTANCE_SHADER, LIGHT_SHADER};
+
+// meshes put on a layer other than `Opaque`, by name. the framework's renderer has no room for anything of ours
+static LAYERS: Lazy<Mutex<HashMap<String, RenderLayer>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+// what a mesh is drawn with and when. layers go in the order they're declared
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum RenderLayer {
+    // the maze and everything standing in it, the only layer that writes depth
+    Opaque,
+    // flat on a surface, after it so they're never hidden under it
+    Decals,
+    // water and anything else you can see through, furthest first so nearer ones blend over them
+    Transparent,
+    Particles,
+    // aiming guides and debug geometry, drawn over everything
+    Overlay,
+}
+
+impl RenderLayer {
+    pub const ALL: [RenderLayer; 5] = [RenderLayer::Opaque, RenderLayer::Decals, RenderLayer::Transparent, RenderLayer::Particles, RenderLayer::Overlay];
+
+    fn depth_write(&self) -> bool {
+        *self == RenderLayer::Opaque
+    }
+
+    fn depth_test(&self) -> bool {
+        *self != RenderLayer::Overlay
+    }
+
+    fn sorted(&self) -> bool {
+        matches!(self, RenderLayer::Transparent | RenderLayer::Particles)
+    }
+}
+
+// call right after adding the mesh, see `RendererExt::try_add_mesh_on`
+pub fn assign(name: &str, layer: RenderLayer) {
+    let mut layers = LAYERS.lock().unwrap_or_else(|e| e.into_inner());
+    if layer == RenderLayer::Opaque {
+        layers.remove(name);
+    } else {
+        layers.insert(name.to_string(), layer);
+    }
+}
+
+// the framework's `Renderer::draw` but a layer at a time, each with its own depth and blend state. a mesh that's
+// taken out of the renderer loses its layer, it has to be assigned again when it's put back
+pub unsafe fn draw(r: &Renderer, el: &EventLoop, resolution: f32) {
+    for shader in [&INSTANCE_SHADER, &DEFAULT_SHADER] {
+        shader.use_shader();
+        r.camera.send_uniforms(shader);
+    }
+    for shader in [&LIGHT_SHADER, &FULL_SHADER] {
+        shader.use_shader();
+        r.camera.send_uniforms(shader);
+        r.send_light_uniforms(shader);
+    }
+    gl::UseProgram(0);
+
+    let mut by_layer: HashMap<RenderLayer, Vec<&Mesh>> = HashMap::new();
+    {
+        let mut layers = LAYERS.lock().unwrap_or_else(|e| e.into_inner());
+        layers.retain(|name, _| r.meshes.contains_key(name));
+        for (name, mesh) in r.meshes.iter() {
+            by_layer.entry(layers.get(name).copied().unwrap_or(RenderLayer::Opaque)).or_default().push(mesh);
+        }
+    }
+
+    let depth_test = gl::IsEnabled(gl::DEPTH_TEST) == gl::TRUE;
+    let blend = gl::IsEnabled(gl::BLEND) == gl::TRUE;
+    let eye = r.camera.pos * resolution;
+    for layer in RenderLayer::ALL {
+        if layer.depth_test() {
+            gl::Enable(gl::DEPTH_TEST);
+            gl::DepthFunc(gl::LEQUAL);
+        } else {
+            gl::Disable(gl::DEPTH_TEST);
+        }
+        gl::DepthMask(if layer.depth_write() { gl::TRUE } else { gl::FALSE });
+        if layer == RenderLayer::Opaque {
+            gl::Disable(gl::BLEND);
+        } else {
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+        }
+
+        if layer == RenderLayer::Opaque {
+            for mesh in r.instance_meshes.values() {
+                mesh.draw(el);
+            }
+            for model in r.models.values() {
+                model.draw(el);
+            }
+        }
+        let Some(meshes) = by_layer.get_mut(&layer) else { continue };
+        if layer.sorted() {
+            meshes.sort_by(|a, b| b.position.distance_squared(eye).total_cmp(&a.position.distance_squared(eye)));
+        }
+        for mesh in meshes.iter() {
+            mesh.draw(el);
+        }
+    }
+
+    gl::DepthMask(gl::TRUE);
+    if depth_test {
+        gl::Enable(gl::DEPTH_TEST);
+    } else {
+        gl::Disable(gl::DEPTH_TEST);
+    }
+    if blend {
+        gl::Enable(gl::BLEND);
+    } else {
+        gl::Disable(gl::BLEND);
+    }
+}
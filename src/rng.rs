@@ -0,0 +1,27 @@
+use std::{ops::RangeBounds, sync::Mutex};
+
+use once_cell::sync::Lazy;
+
+// randomness that changes how the game plays out (spawns, patrols, rubble), seeded from the dungeon seed so
+// lockstep peers all roll the same numbers. purely cosmetic stuff like particles can keep using `rand_betw`
+static RNG: Lazy<Mutex<fastrand::Rng>> = Lazy::new(|| Mutex::new(fastrand::Rng::new()));
+
+pub fn seed(seed: u64) {
+    RNG.lock().unwrap().seed(seed);
+}
+
+pub fn f32() -> f32 {
+    RNG.lock().unwrap().f32()
+}
+
+pub fn range(min: f32, max: f32) -> f32 {
+    min + f32() * (max - min)
+}
+
+pub fn usize(range: impl RangeBounds<usize>) -> usize {
+    RNG.lock().unwrap().usize(range)
+}
+
+pub fn shuffle<T>(slice: &mut [T]) {
+    RNG.lock().unwrap().shuffle(slice);
+}
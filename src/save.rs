@@ -0,0 +1,231 @@
+use std::{fs::{self, File}, io::Write, time::{SystemTime, UNIX_EPOCH}};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{achievements::Statistics, config::Difficulty, coords::ChunkCoord, deltas::WorldDeltas, error::SaveError, inventory::Item, versioning::SAVE_FORMAT};
+
+const SAVE_PATH: &str = "save.json";
+// written first and renamed over the real one, so a crash halfway through never leaves a broken save behind
+const SAVE_TMP_PATH: &str = "save.json.tmp";
+// a save this build couldn't read is moved here before anything can be written over it
+const SAVE_BACKUP_PATH: &str = "save.json.bak";
+// seconds between autosaves when nothing else triggered one
+const AUTOSAVE_INTERVAL: f32 = 120.0;
+
+// everything that carries over between runs
+#[derive(Serialize, Deserialize, Default, Debug)]
+#[serde(default)]
+pub struct SaveData {
+    pub statistics: Statistics,
+    // ids of the unlocked achievements
+    pub achievements: Vec<String>,
+    // runs that can be loaded from the main menu, in the order they were started
+    pub slots: Vec<SaveSlot>,
+    // the slot the run being played saves into, none for daily runs
+    #[serde(skip)]
+    pub active: Option<usize>,
+    // set when a save that couldn't be read couldn't be moved out of the way either, nothing is written over it then
+    #[serde(skip)]
+    protected: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SaveSlot {
+    pub name: String,
+    pub run: RunSave,
+    #[serde(default)]
+    pub metadata: SlotMetadata,
+}
+
+// what the load screen shows about a slot without having to start it
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+#[serde(default)]
+pub struct SlotMetadata {
+    pub floor: u32,
+    pub level: u32,
+    // seconds played in the run, over every session
+    pub playtime: f32,
+    // unix seconds of the last save, 0 if it was never saved
+    pub saved_at: u64,
+}
+
+impl Default for SlotMetadata {
+    fn default() -> Self {
+        Self { floor: 1, level: 1, playtime: 0.0, saved_at: 0 }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RunSave {
+    pub seed: u64,
+    // saves from before difficulties existed were all normal
+    #[serde(default)]
+    pub difficulty: Difficulty,
+    // how far the local player got, kept up to date by the autosave
+    #[serde(default)]
+    pub progress: RunProgress,
+}
+
+// the same seed gives the same dungeon back, this is what happened in it. a fresh run if it's all defaults
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct RunProgress {
+    pub floor: u32,
+    pub level: u32,
+    pub xp: u32,
+    pub coins: u32,
+    pub items: Vec<Item>,
+    // keys of the locked doors that were opened
+    pub opened_doors: Vec<usize>,
+    // walls knocked down and anything else about the dungeon that's no longer how it was generated
+    pub world: WorldDeltas,
+}
+
+impl Default for RunProgress {
+    fn default() -> Self {
+        Self { floor: 1, level: 1, xp: 0, coins: 0, items: Vec::new(), opened_doors: Vec::new(), world: WorldDeltas::default() }
+    }
+}
+
+impl SaveData {
+    // a fresh save if there isn't one yet. one that's broken or from a newer build is reported and moved to
+    // `SAVE_BACKUP_PATH` so the fresh one doesn't overwrite it, and if it can't be moved the fresh one is never written
+    pub fn load() -> Self {
+        match Self::read() {
+            Ok(save) => save,
+            Err(SaveError::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => Self::default(),
+            Err(e) => {
+                let protected = match fs::rename(SAVE_PATH, SAVE_BACKUP_PATH) {
+                    Ok(()) => {
+                        log::warn!("starting from a fresh save, {}. the old one was kept as {}", e, SAVE_BACKUP_PATH);
+                        false
+                    }
+                    Err(backup) => {
+                        log::warn!("starting from a fresh save that won't be written, {} and it couldn't be moved: {}", e, backup);
+                        true
+                    }
+                };
+                Self { protected, ..Self::default() }
+            }
+        }
+    }
+
+    fn read() -> Result<Self, SaveError> {
+        let source = fs::read_to_string(SAVE_PATH)?;
+        let value = SAVE_FORMAT.upgrade(serde_json::from_str(&source)?)?;
+        Ok(serde_json::from_value(value)?)
+    }
+
+    // a fresh run in a slot of its own, comes back with its index
+    pub fn new_slot(&mut self, seed: u64, difficulty: Difficulty) -> usize {
+        let name = (1..).map(|n| format!("slot {}", n)).find(|name| !self.is_taken(name)).unwrap_or_default();
+        self.slots.push(SaveSlot {
+            name,
+            run: RunSave { seed, difficulty, progress: RunProgress::default() },
+            metadata: SlotMetadata { saved_at: now(), ..Default::default() },
+        });
+        self.slots.len() - 1
+    }
+
+    // `None` if there's no slot `index`
+    pub fn copy_slot(&mut self, index: usize) -> Option<usize> {
+        let mut copy = self.slots.get(index)?.clone();
+        copy.name = self.free_name(&format!("{} copy", copy.name));
+        self.slots.push(copy);
+        Some(self.slots.len() - 1)
+    }
+
+    // the slot being played keeps pointing at the same run, or at nothing if that's the one that went
+    pub fn delete_slot(&mut self, index: usize) {
+        if index >= self.slots.len() {
+            return;
+        }
+        self.slots.remove(index);
+        self.active = match self.active {
+            Some(active) if active == index => None,
+            Some(active) if active > index => Some(active - 1),
+            active => active,
+        };
+    }
+
+    // call right before writing, `playtime` is how long was played since the last save
+    pub fn record(&mut self, progress: RunProgress, playtime: f32) {
+        let Some(slot) = self.active.and_then(|active| self.slots.get_mut(active)) else { return };
+        slot.metadata = SlotMetadata {
+            floor: progress.floor,
+            level: progress.level,
+            playtime: slot.metadata.playtime + playtime,
+            saved_at: now(),
+        };
+        slot.run.progress = progress;
+    }
+
+    fn is_taken(&self, name: &str) -> bool {
+        self.slots.iter().any(|slot| slot.name == name)
+    }
+
+    // `base` if no slot has that name yet, otherwise the first of `base 2`, `base 3`... that's free
+    fn free_name(&self, base: &str) -> String {
+        if !self.is_taken(base) {
+            return base.to_string();
+        }
+        (2..).map(|n| format!("{} {}", base, n)).find(|name| !self.is_taken(name)).unwrap_or_default()
+    }
+
+    // does nothing when `load` couldn't get the unreadable save out of the way
+    pub fn write(&self) -> Result<(), SaveError> {
+        if self.protected {
+            return Ok(());
+        }
+        let mut value = serde_json::to_value(self)?;
+        SAVE_FORMAT.stamp(&mut value);
+        let mut file = File::create(SAVE_TMP_PATH)?;
+        file.write_all(serde_json::to_string_pretty(&value)?.as_bytes())?;
+        file.sync_all()?;
+        fs::rename(SAVE_TMP_PATH, SAVE_PATH)?;
+        Ok(())
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |since| since.as_secs())
+}
+
+// says when to save during a run, every so often and right after the player crosses into another chunk or floor
+pub struct Autosave {
+    timer: f32,
+    // played since the last save
+    playtime: f32,
+    chunk: Option<ChunkCoord>,
+    floor: u32,
+}
+
+impl Autosave {
+    pub fn new() -> Self {
+        Self { timer: AUTOSAVE_INTERVAL, playtime: 0.0, chunk: None, floor: 0 }
+    }
+
+    // true when it's time to save, the first call after a reset only takes note of where the player is
+    pub fn update(&mut self, dt: f32, chunk: ChunkCoord, floor: u32) -> bool {
+        self.timer -= dt;
+        self.playtime += dt;
+        let crossed = self.chunk.is_some_and(|last| last != chunk || self.floor != floor);
+        self.chunk = Some(chunk);
+        self.floor = floor;
+        if self.timer > 0.0 && !crossed {
+            return false;
+        }
+        self.timer = AUTOSAVE_INTERVAL;
+        true
+    }
+
+    // how long was played since this was last called, for `SaveData::record`
+    pub fn take_playtime(&mut self) -> f32 {
+        std::mem::take(&mut self.playtime)
+    }
+
+    // when a new run starts
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
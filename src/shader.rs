@@ -0,0 +1,86 @@
+use std::ffi::CString;
+
+use tiny_game_framework::gl::{self, types::{GLchar, GLenum, GLint, GLuint}};
+
+use crate::error::ShaderError;
+
+// raw gl programs for what the framework's shaders can't do, it only checks its own by printing
+
+// one big triangle over the whole screen, made up from the vertex id so there's nothing to upload. needs
+// an empty vertex array bound and 3 vertices drawn
+pub const FULLSCREEN_VS: &str = r#"
+#version 330 core
+out vec2 uv;
+
+void main() {
+    uv = vec2((gl_VertexID << 1) & 2, gl_VertexID & 2);
+    gl_Position = vec4(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+"#;
+
+pub fn location(program: GLuint, name: &str) -> GLint {
+    let name = CString::new(name).expect("uniform names have no nul bytes");
+    unsafe { gl::GetUniformLocation(program, name.as_ptr()) }
+}
+
+// a vertex and fragment shader linked together, nothing is left behind if either fails
+pub fn program(vertex: &str, fragment: &str) -> Result<GLuint, ShaderError> {
+    unsafe {
+        let vs = compile(gl::VERTEX_SHADER, "vertex", vertex)?;
+        let fs = match compile(gl::FRAGMENT_SHADER, "fragment", fragment) {
+            Ok(fs) => fs,
+            Err(e) => {
+                gl::DeleteShader(vs);
+                return Err(e);
+            }
+        };
+
+        let program = gl::CreateProgram();
+        gl::AttachShader(program, vs);
+        gl::AttachShader(program, fs);
+        gl::LinkProgram(program);
+        // the program keeps them alive for as long as it's around
+        gl::DeleteShader(vs);
+        gl::DeleteShader(fs);
+
+        match link_result(program) {
+            Ok(()) => Ok(program),
+            Err(e) => {
+                gl::DeleteProgram(program);
+                Err(e)
+            }
+        }
+    }
+}
+
+pub unsafe fn compile(kind: GLenum, stage: &'static str, source: &str) -> Result<GLuint, ShaderError> {
+    let shader = gl::CreateShader(kind);
+    let source = CString::new(source).expect("shader sources have no nul bytes");
+    gl::ShaderSource(shader, 1, &source.as_ptr(), std::ptr::null());
+    gl::CompileShader(shader);
+
+    let mut success = 0;
+    gl::GetShaderiv(shader, gl::COMPILE_STATUS, &mut success);
+    if success == gl::TRUE as GLint {
+        return Ok(shader);
+    }
+    let mut log = vec![0u8; 1024];
+    let mut length = 0;
+    gl::GetShaderInfoLog(shader, log.len() as i32, &mut length, log.as_mut_ptr() as *mut GLchar);
+    gl::DeleteShader(shader);
+    log.truncate(length.max(0) as usize);
+    Err(ShaderError::Compile { stage, log: String::from_utf8_lossy(&log).into_owned() })
+}
+
+pub unsafe fn link_result(program: GLuint) -> Result<(), ShaderError> {
+    let mut success = 0;
+    gl::GetProgramiv(program, gl::LINK_STATUS, &mut success);
+    if success == gl::TRUE as GLint {
+        return Ok(());
+    }
+    let mut log = vec![0u8; 1024];
+    let mut length = 0;
+    gl::GetProgramInfoLog(program, log.len() as i32, &mut length, log.as_mut_ptr() as *mut GLchar);
+    log.truncate(length.max(0) as usize);
+    Err(ShaderError::Link(String::from_utf8_lossy(&log).into_owned()))
+}
@@ -0,0 +1,107 @@
+use rapier3d::prelude::*;
+use tiny_game_framework::{gl::{self, types::GLuint}, glam::{vec2, Vec3, Vec4}, Camera};
+
+use crate::{billboards::{upload_atlas, QuadBatch, BILLBOARD_FS, BILLBOARD_VS}, lighting::Fog, rapier_integration::RapierPhysicsWorld, shader::program};
+
+// texels across the blob's texture
+const BLOB_SIZE: usize = 64;
+// characters further up than this off whatever is below them don't cast anything
+const MAX_DROP: f32 = 800.0;
+// further from the camera than this nobody gets a shadow, that's about where enemies turn into sprites
+const MAX_DISTANCE: f32 = 2500.0;
+// how dark the middle of a shadow is when standing right on the floor
+const OPACITY: f32 = 0.55;
+// how much smaller a shadow gets at `MAX_DROP`
+const SHRINK: f32 = 0.5;
+// pushed off the floor a little so it doesn't z-fight with it
+const SURFACE_OFFSET: f32 = 1.0;
+
+// something that gets a shadow under it
+pub struct ShadowCaster {
+    // left out of the ray, so it doesn't land on itself
+    pub body: RigidBodyHandle,
+    // the middle of its mesh
+    pub position: Vec3,
+    pub size: Vec3,
+}
+
+// a soft dark blob on the floor under every character, there's no shadow mapping so this is what keeps them from
+// looking like they float. each one is a ray straight down and a quad laid flat on what it hits
+pub struct BlobShadows {
+    // `None` when the shader didn't build, nobody has a shadow then
+    program: Option<GLuint>,
+    quads: QuadBatch,
+    atlas: GLuint,
+}
+
+impl BlobShadows {
+    // needs the gl context
+    pub fn new() -> Self {
+        let program = program(BILLBOARD_VS, BILLBOARD_FS).inspect_err(|e| log::warn!("no blob shadows: {}", e)).ok();
+        Self { program, quads: QuadBatch::new(), atlas: bake_blob() }
+    }
+
+    // finds the floor under each caster near `eye` and lays its shadow there, the higher up it is the smaller and
+    // fainter the shadow
+    pub fn update(&mut self, rw: &RapierPhysicsWorld, eye: Vec3, resolution: f32, casters: impl Iterator<Item = ShadowCaster>) {
+        self.quads.clear();
+        if self.program.is_none() {
+            return;
+        }
+
+        for caster in casters.filter(|caster| caster.position.distance(eye) < MAX_DISTANCE) {
+            let Some(hit) = rw.cast_ray_with_normal(caster.position, -Vec3::Y, MAX_DROP + caster.size.y / 2.0, caster.body) else { continue };
+            let height = (hit.distance - caster.size.y / 2.0).max(0.0);
+            let t = height / MAX_DROP;
+            let radius = caster.size.x.max(caster.size.z) / 2.0 * (1.0 - SHRINK * t);
+
+            // any two directions along the surface
+            let normal = hit.normal.normalize_or(Vec3::Y);
+            let across = normal.any_orthonormal_vector() * radius;
+            let along = normal.cross(across);
+            let center = caster.position - Vec3::Y * hit.distance + normal * SURFACE_OFFSET;
+
+            let corners = [center - across - along, center + across - along, center + across + along, center - across + along].map(|corner| corner / resolution);
+            self.quads.push(corners, [vec2(0.0, 0.0), vec2(1.0, 1.0)], Vec4::new(0.0, 0.0, 0.0, OPACITY * (1.0 - t)));
+        }
+    }
+
+    // call right after the scene, so the characters' own billboards and text go over their shadows
+    pub fn draw(&self, camera: &Camera, fog: Fog, resolution: f32) {
+        let Some(program) = self.program else { return };
+
+        let depth_test = unsafe {
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+            // hidden behind whatever's in front of the floor, but they don't hide each other
+            let depth_test = gl::IsEnabled(gl::DEPTH_TEST) == gl::TRUE;
+            gl::Enable(gl::DEPTH_TEST);
+            gl::DepthFunc(gl::LEQUAL);
+            gl::DepthMask(gl::FALSE);
+            depth_test
+        };
+        self.quads.draw(program, self.atlas, camera, fog, resolution);
+        unsafe {
+            gl::DepthMask(gl::TRUE);
+            if !depth_test {
+                gl::Disable(gl::DEPTH_TEST);
+            }
+        }
+    }
+}
+
+// white, fading out from the middle to nothing at the edge
+fn bake_blob() -> GLuint {
+    let mut texels = vec![0u8; BLOB_SIZE * BLOB_SIZE * 4];
+    for y in 0..BLOB_SIZE {
+        for x in 0..BLOB_SIZE {
+            let offset = vec2(x as f32 + 0.5, y as f32 + 0.5) / BLOB_SIZE as f32 * 2.0 - 1.0;
+            let alpha = (1.0 - offset.length()).clamp(0.0, 1.0);
+            let index = (y * BLOB_SIZE + x) * 4;
+            // smoothed so there's no hard ring where it starts
+            texels[index..index + 4].copy_from_slice(&[255, 255, 255, (alpha * alpha * (3.0 - 2.0 * alpha) * 255.0) as u8]);
+        }
+    }
+
+    upload_atlas(BLOB_SIZE, BLOB_SIZE, &texels)
+}
@@ -0,0 +1,72 @@
+use std::time::Duration;
+
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+// how long quitting waits on background tasks before giving up on them
+const TASK_TIMEOUT: Duration = Duration::from_secs(2);
+
+// everything that has to happen on the way out. background tasks get a clone of the token and stop when it's
+// cancelled, then the exit hooks run once nothing is writing anymore
+pub struct Shutdown {
+    token: CancellationToken,
+    tasks: Vec<JoinHandle<()>>,
+    hooks: Vec<(String, Box<dyn FnOnce()>)>,
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        let token = CancellationToken::new();
+
+        // ctrl+c in the terminal quits the same way closing the window does
+        let on_interrupt = token.clone();
+        tokio::spawn(async move {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                on_interrupt.cancel();
+            }
+        });
+
+        Self {
+            token,
+            tasks: Vec::new(),
+            hooks: Vec::new(),
+        }
+    }
+
+    // hand one to every background task
+    pub fn token(&self) -> CancellationToken {
+        self.token.clone()
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    // waited on when quitting, finished tasks are dropped as they're added
+    pub fn track(&mut self, task: JoinHandle<()>) {
+        self.tasks.retain(|task| !task.is_finished());
+        self.tasks.push(task);
+    }
+
+    // runs after every task has stopped, in the order they were added
+    pub fn on_exit(&mut self, name: &str, hook: impl FnOnce() + 'static) {
+        self.hooks.push((name.to_string(), Box::new(hook)));
+    }
+
+    pub async fn run(self) {
+        self.token.cancel();
+
+        for task in self.tasks {
+            match tokio::time::timeout(TASK_TIMEOUT, task).await {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => log::warn!("a background task failed while shutting down: {}", e),
+                Err(_) => log::warn!("a background task didn't stop within {:?}, leaving it", TASK_TIMEOUT),
+            }
+        }
+
+        for (name, hook) in self.hooks {
+            log::info!("running exit hook: {}", name);
+            hook();
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use tiny_game_framework::{gl::{self, types::GLuint}, glam::{Mat3, Mat4, Vec3}, Camera};
+
+use crate::{config::Sky, error::ShaderError, lighting::Fog, shader::{location, program, FULLSCREEN_VS}};
+
+// how high above the horizon an open sky is fully `zenith`, as the sine of the angle
+const ZENITH_HEIGHT: f32 = 0.6;
+
+const SKY_FS: &str = r#"
+#version 330 core
+in vec2 uv;
+out vec4 FragColor;
+
+// from the screen to a direction, the camera's position left out
+uniform mat4 unproject;
+uniform vec3 horizon;
+uniform vec3 top;
+uniform int open;
+uniform float zenith_height;
+uniform float time;
+uniform float drift;
+
+float hash(vec3 p) {
+    return fract(sin(dot(p, vec3(127.1, 311.7, 74.7))) * 43758.5453);
+}
+
+float noise(vec3 p) {
+    vec3 i = floor(p);
+    vec3 f = fract(p);
+    f = f * f * (3.0 - 2.0 * f);
+    return mix(
+        mix(mix(hash(i), hash(i + vec3(1, 0, 0)), f.x), mix(hash(i + vec3(0, 1, 0)), hash(i + vec3(1, 1, 0)), f.x), f.y),
+        mix(mix(hash(i + vec3(0, 0, 1)), hash(i + vec3(1, 0, 1)), f.x), mix(hash(i + vec3(0, 1, 1)), hash(i + vec3(1, 1, 1)), f.x), f.y),
+        f.z);
+}
+
+void main() {
+    vec4 far = unproject * vec4(uv * 2.0 - 1.0, 1.0, 1.0);
+    vec3 direction = normalize(far.xyz / far.w);
+
+    vec3 color;
+    if (open == 1) {
+        color = mix(horizon, top, smoothstep(0.0, zenith_height, direction.y));
+    } else {
+        // two layers of mist sliding past each other, thickest around the horizon
+        vec3 flow = vec3(time * drift, 0.0, time * drift * 0.6);
+        float mist = noise(direction * 3.0 + flow) * 0.6 + noise(direction * 7.0 - flow * 1.7) * 0.4;
+        mist *= 1.0 - abs(direction.y);
+        color = mix(horizon, top, mist);
+    }
+    FragColor = vec4(color, 1.0);
+}
+"#;
+
+// draws the biome's sky over the whole screen before anything else, the scene covers it wherever there's geometry
+pub struct SkyRenderer {
+    // `None` when the shader didn't build, the clear color shows through then
+    program: Option<GLuint>,
+    vertex_array: GLuint,
+    time: f32,
+}
+
+impl SkyRenderer {
+    // needs the gl context
+    pub fn new() -> Self {
+        let program = program(FULLSCREEN_VS, SKY_FS).inspect_err(|e: &ShaderError| log::warn!("no sky: {}", e)).ok();
+        let mut vertex_array = 0;
+        unsafe { gl::GenVertexArrays(1, &mut vertex_array) };
+        Self { program, vertex_array, time: 0.0 }
+    }
+
+    // call right after clearing, before the scene
+    pub fn draw(&mut self, sky: Sky, fog: Fog, camera: &Camera, dt: f32) {
+        self.time += dt;
+        let Some(program) = self.program else { return };
+
+        let rotation = Mat4::from_mat3(Mat3::from_mat4(camera.view));
+        let unproject = (camera.proj * rotation).inverse();
+        let (open, top, drift) = match sky {
+            Sky::Void { mist, drift } => (false, Vec3::from(mist), drift),
+            Sky::Open { zenith } => (true, Vec3::from(zenith), 0.0),
+        };
+
+        unsafe {
+            let depth_test = gl::IsEnabled(gl::DEPTH_TEST) == gl::TRUE;
+            gl::Disable(gl::DEPTH_TEST);
+            // nothing drawn after it should be hidden behind it
+            gl::DepthMask(gl::FALSE);
+            gl::BindVertexArray(self.vertex_array);
+            gl::UseProgram(program);
+            gl::UniformMatrix4fv(location(program, "unproject"), 1, gl::FALSE, unproject.to_cols_array().as_ptr());
+            gl::Uniform3f(location(program, "horizon"), fog.color.x, fog.color.y, fog.color.z);
+            gl::Uniform3f(location(program, "top"), top.x, top.y, top.z);
+            gl::Uniform1i(location(program, "open"), open as i32);
+            gl::Uniform1f(location(program, "zenith_height"), ZENITH_HEIGHT);
+            gl::Uniform1f(location(program, "time"), self.time);
+            gl::Uniform1f(location(program, "drift"), drift);
+            gl::DrawArrays(gl::TRIANGLES, 0, 3);
+
+            gl::UseProgram(0);
+            gl::BindVertexArray(0);
+            gl::DepthMask(gl::TRUE);
+            if depth_test {
+                gl::Enable(gl::DEPTH_TEST);
+            }
+        }
+    }
+}
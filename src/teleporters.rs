@@ -0,0 +1,97 @@
+use rapier3d::prelude::*;
+use tiny_game_framework::{glam::{vec3, Vec3, Vec4}, Cuboid, Renderer};
+
+use crate::{
+    entity::{EntityId, EntityKind},
+    error::RendererExt,
+    rapier_integration::{CollisionLayer, RapierPhysicsWorld},
+};
+
+// how high above the destination pad the player is put down, so they don't end up inside it
+const ARRIVAL_HEIGHT: f32 = 60.0;
+const PAD_HEIGHT: f32 = 6.0;
+const PAD_COLOR: Vec4 = Vec4::new(0.7, 0.2, 0.9, 1.0);
+
+pub struct Teleporter {
+    // the top of the floor tile it's on
+    pub position: Vec3,
+    pub sensor: ColliderHandle,
+    // index into `TeleporterSystem::teleporters`, `None` until another one turns up to pair with
+    pub link: Option<usize>,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Teleport {
+    pub from: Vec3,
+    pub to: Vec3,
+}
+
+pub struct TeleporterSystem {
+    pub teleporters: Vec<Teleporter>,
+    // (player body, teleporter they arrived on), they have to step off it before it can send them back
+    arrivals: Vec<(RigidBodyHandle, usize)>,
+}
+
+impl TeleporterSystem {
+    pub fn new() -> Self {
+        Self {
+            teleporters: Vec::new(),
+            arrivals: Vec::new(),
+        }
+    }
+
+    // `position` is the center of the tile at floor level. pairs up with the oldest teleporter still waiting for
+    // one, so pads from chunks built far apart end up linked
+    pub fn spawn(&mut self, rw: &mut RapierPhysicsWorld, r: &mut Renderer, position: Vec3, tile_size: f32) {
+        let half = tile_size / 2.0;
+        let position = position + Vec3::Y * half;
+        // still here from before its chunk was unloaded and built again
+        if self.teleporters.iter().any(|teleporter| teleporter.position == position) {
+            return;
+        }
+        let sensor = rw.add_sensor(position.x, position.y + half / 2.0, position.z, vec3(half * 0.8, half / 2.0, half * 0.8), CollisionLayer::Trigger, CollisionLayer::Trigger.default_mask());
+        rw.tag_collider(sensor, EntityId::new(EntityKind::Teleporter));
+
+        let mesh_name = format!("TELEPORTER_MESH{:?}{:?}{:?}", position.x, position.z, self.teleporters.len());
+        let mut mesh = Cuboid::new(vec3(tile_size * 0.8, PAD_HEIGHT, tile_size * 0.8), PAD_COLOR).mesh();
+        mesh.position = position;
+        mesh.setup_mesh();
+        if let Err(e) = r.try_add_mesh(&mesh_name, mesh) {
+            log::warn!("{}", e);
+        }
+
+        let index = self.teleporters.len();
+        let waiting = self.teleporters.iter().position(|teleporter| teleporter.link.is_none());
+        if let Some(other) = waiting {
+            self.teleporters[other].link = Some(index);
+        }
+        self.teleporters.push(Teleporter { position, sensor, link: waiting });
+    }
+
+    // moves anyone standing on a linked pad over to the other one. returns the index in `players` of whoever went
+    // through, the caller takes care of the camera and anything else that should follow them
+    pub fn update(&mut self, rw: &mut RapierPhysicsWorld, players: &[RigidBodyHandle]) -> Vec<(usize, Teleport)> {
+        self.arrivals.retain(|&(body, pad)| rw.is_body_in_sensor(self.teleporters[pad].sensor, body));
+
+        let mut teleports = Vec::new();
+        for (player, &body) in players.iter().enumerate() {
+            if self.arrivals.iter().any(|&(arrived, _)| arrived == body) {
+                continue;
+            }
+
+            let Some(teleporter) = self.teleporters.iter().find(|teleporter| rw.is_body_in_sensor(teleporter.sensor, body)) else { continue };
+            let Some(link) = teleporter.link else { continue };
+
+            let from = teleporter.position;
+            let to = self.teleporters[link].position;
+            let Some(rigid_body) = rw.rigid_body_set.get_mut(body) else { continue };
+            rigid_body.set_translation(vector![to.x, to.y + ARRIVAL_HEIGHT, to.z], true);
+            rigid_body.set_linvel(vector![0.0, 0.0, 0.0], true);
+
+            self.arrivals.push((body, link));
+            teleports.push((player, Teleport { from, to }));
+        }
+
+        teleports
+    }
+}
@@ -0,0 +1,229 @@
+use rapier3d::prelude::*;
+use tiny_game_framework::{glam::{vec3, Vec3, Vec4}, Cuboid, Mesh, Renderer, Sphere};
+
+use crate::{error::RendererExt, entity::{EntityId, EntityKind}, physics_thread::Blend, rapier_integration::{BodySpec, CollisionLayer, RapierPhysicsWorld}, render_layers::RenderLayer};
+
+const PREVIEW_MESH: &str = "TRAJECTORY_PREVIEW";
+const THROW_SPEED: f32 = 700.0;
+// thrown a bit above where the camera looks, so looking straight ahead still gives an arc
+const THROW_LIFT: f32 = 0.3;
+const THROWN_RADIUS: f32 = 8.0;
+// seconds from leaving the hand to going off
+const BOMB_FUSE: f32 = 2.5;
+const BOLT_SPEED: f32 = 2000.0;
+const BOLT_RADIUS: f32 = 4.0;
+// the preview steps through the arc this many seconds at a time, for at most this many steps
+const PREVIEW_STEP: f32 = 0.05;
+const PREVIEW_STEPS: usize = 60;
+// every other step gets a dot, which is what makes the line dotted
+const DOT_SPACING: usize = 2;
+const DOT_SIZE: f32 = 5.0;
+const DOT_COLOR: Vec4 = Vec4::new(1.0, 0.9, 0.5, 1.0);
+
+// where something thrown along `look` starts moving
+pub fn throw_velocity(look: Vec3) -> Vec3 {
+    (look + Vec3::Y * THROW_LIFT).normalize_or_zero() * THROW_SPEED
+}
+
+// the arc something thrown from `origin` follows, stepped with shapecasts so it ends where it'd first hit something.
+// `exclude` is whoever is throwing
+pub fn predict(rw: &RapierPhysicsWorld, origin: Vec3, velocity: Vec3, gravity: Vec3, exclude: RigidBodyHandle) -> Vec<Vec3> {
+    let shape = Ball::new(THROWN_RADIUS);
+    let mut points = vec![origin];
+    let (mut position, mut velocity) = (origin, velocity);
+
+    for _ in 0..PREVIEW_STEPS {
+        let next = position + velocity * PREVIEW_STEP + gravity * (0.5 * PREVIEW_STEP * PREVIEW_STEP);
+        let step = next - position;
+        let length = step.length();
+        if length <= f32::EPSILON {
+            break;
+        }
+
+        if let Some(hit) = rw.cast_shape(position, step / length, length, &shape, exclude) {
+            points.push(position + step / length * hit.distance);
+            break;
+        }
+        points.push(next);
+        position = next;
+        velocity += gravity * PREVIEW_STEP;
+    }
+
+    points
+}
+
+// the dotted line shown while aiming, rebuilt every frame from whatever `predict` said
+#[derive(Default)]
+pub struct TrajectoryPreview {
+    shown: bool,
+}
+
+impl TrajectoryPreview {
+    pub fn show(&mut self, r: &mut Renderer, points: &[Vec3]) {
+        self.hide(r);
+
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+        for &point in points.iter().step_by(DOT_SPACING) {
+            let dot = Cuboid::new(Vec3::splat(DOT_SIZE), DOT_COLOR).mesh();
+            let offset = vertices.len() as u32;
+            vertices.extend(dot.vertices.iter().map(|vertex| {
+                let mut vertex = *vertex;
+                vertex.position += point;
+                vertex
+            }));
+            indices.extend(dot.indices.iter().map(|index| index + offset));
+        }
+
+        let mut mesh = Mesh::new(&vertices, &indices);
+        mesh.setup_mesh();
+        if let Err(e) = r.try_add_mesh_on(PREVIEW_MESH, mesh, RenderLayer::Overlay) {
+            log::warn!("{}", e);
+            return;
+        }
+        self.shown = true;
+    }
+
+    pub fn hide(&mut self, r: &mut Renderer) {
+        if self.shown {
+            r.destroy_mesh(PREVIEW_MESH).ok();
+            self.shown = false;
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ThrownEvent {
+    // hit something for the first time, loud enough to draw enemies
+    Landed(Vec3),
+    // the fuse ran out, it's gone now. `player` is who threw it
+    Exploded { position: Vec3, player: usize },
+    // a bolt hit `collider` and is gone, `direction` is the way it was flying
+    Struck { position: Vec3, direction: Vec3, collider: ColliderHandle, player: usize, damage: f32 },
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum ThrownKind {
+    Bomb,
+    // from a projectile weapon, flies straight and is done at the first thing it touches
+    Bolt { damage: f32 },
+}
+
+struct Thrown {
+    kind: ThrownKind,
+    player: usize,
+    body: RigidBodyHandle,
+    mesh_name: String,
+    // until a bomb goes off, or a bolt flew as far as it goes
+    fuse: f32,
+    landed: bool,
+}
+
+// bombs and bolts players let loose that are still flying around
+#[derive(Default)]
+pub struct Throwables {
+    thrown: Vec<Thrown>,
+    // whether each player was holding throw last frame, letting go is what throws
+    aiming: Vec<bool>,
+    counter: usize,
+}
+
+impl Throwables {
+    // true on the frame `player` lets go of throw
+    pub fn released(&mut self, player: usize, held: bool) -> bool {
+        if self.aiming.len() <= player {
+            self.aiming.resize(player + 1, false);
+        }
+        let released = self.aiming[player] && !held;
+        self.aiming[player] = held;
+        released
+    }
+
+    pub fn throw(&mut self, rw: &mut RapierPhysicsWorld, r: &mut Renderer, player: usize, origin: Vec3, velocity: Vec3) {
+        let body = rw.add_body(origin, &BodySpec::ball(THROWN_RADIUS)
+            .restitution(0.4)
+            .ccd(true)
+            .layer(CollisionLayer::Projectile)
+            .impacts(true));
+        rw.rigid_body_set[body].set_linvel(vector![velocity.x, velocity.y, velocity.z], true);
+        rw.tag_body(body, EntityId::new(EntityKind::Projectile));
+
+        let mesh_name = format!("THROWN_MESH{}", self.counter);
+        self.counter += 1;
+        let mut mesh = Sphere::new(8, THROWN_RADIUS, vec3(0.5, 0.45, 0.4).extend(1.0)).mesh();
+        mesh.position = origin;
+        mesh.setup_mesh();
+        if let Err(e) = r.try_add_mesh(&mesh_name, mesh) {
+            log::warn!("{}", e);
+        }
+
+        self.thrown.push(Thrown { kind: ThrownKind::Bomb, player, body, mesh_name, fuse: BOMB_FUSE, landed: false });
+    }
+
+    // `range` is how far it flies before it's gone without hitting anything
+    pub fn shoot(&mut self, rw: &mut RapierPhysicsWorld, r: &mut Renderer, player: usize, origin: Vec3, direction: Vec3, damage: f32, range: f32) {
+        let body = rw.add_body(origin, &BodySpec::ball(BOLT_RADIUS)
+            .ccd(true)
+            .layer(CollisionLayer::Projectile));
+        let velocity = direction * BOLT_SPEED;
+        let bolt = &mut rw.rigid_body_set[body];
+        bolt.set_gravity_scale(0.0, true);
+        bolt.set_linvel(vector![velocity.x, velocity.y, velocity.z], true);
+        rw.tag_body(body, EntityId::new(EntityKind::Projectile));
+
+        let mesh_name = format!("THROWN_MESH{}", self.counter);
+        self.counter += 1;
+        let mut mesh = Sphere::new(6, BOLT_RADIUS, vec3(0.8, 0.8, 0.8).extend(1.0)).mesh();
+        mesh.position = origin;
+        mesh.setup_mesh();
+        if let Err(e) = r.try_add_mesh(&mesh_name, mesh) {
+            log::warn!("{}", e);
+        }
+
+        self.thrown.push(Thrown { kind: ThrownKind::Bolt { damage }, player, body, mesh_name, fuse: range / BOLT_SPEED, landed: false });
+    }
+
+    // the bomb is already out of the world by the time `Exploded` comes back, so it's not caught in its own blast
+    pub fn update(&mut self, rw: &mut RapierPhysicsWorld, r: &mut Renderer, blend: &Blend, dt: f32) -> Vec<ThrownEvent> {
+        let mut events = Vec::new();
+
+        let mut i = 0;
+        while i < self.thrown.len() {
+            let thrown = &mut self.thrown[i];
+            thrown.fuse -= dt;
+            let t = rw.rigid_body_set[thrown.body].translation();
+            let position = vec3(t.x, t.y, t.z);
+
+            let touching = rw.touching(thrown.body);
+            let struck = match thrown.kind {
+                ThrownKind::Bolt { damage } => touching.map(|collider| {
+                    let v = rw.rigid_body_set[thrown.body].linvel();
+                    let direction = vec3(v.x, v.y, v.z).normalize_or_zero();
+                    ThrownEvent::Struck { position, direction, collider, player: thrown.player, damage }
+                }),
+                ThrownKind::Bomb => None,
+            };
+            let exploded = (thrown.kind == ThrownKind::Bomb && thrown.fuse <= 0.0).then_some(ThrownEvent::Exploded { position, player: thrown.player });
+
+            if thrown.fuse <= 0.0 || struck.is_some() {
+                let thrown = self.thrown.swap_remove(i);
+                rw.remove_rigidbody(thrown.body);
+                r.destroy_mesh(&thrown.mesh_name).ok();
+                events.extend(struck.or(exploded));
+                continue;
+            }
+
+            if thrown.kind == ThrownKind::Bomb && !thrown.landed && touching.is_some() {
+                thrown.landed = true;
+                events.push(ThrownEvent::Landed(position));
+            }
+            let drawn = blend.transform(rw, thrown.body).map_or(position, |transform| transform.position);
+            if let Some(mesh) = r.get_mesh_mut(&thrown.mesh_name) {
+                mesh.position = drawn;
+            }
+            i += 1;
+        }
+
+        events
+    }
+}
@@ -0,0 +1,69 @@
+// rapier gets unstable with tiny steps and nothing would visibly move anyway
+const MIN_SCALE: f32 = 0.05;
+// past this the physics needs so many substeps that the frame rate tanks
+const MAX_SCALE: f32 = 4.0;
+// slow motion spends this much of its duration easing back to normal speed
+const EASE_OUT: f32 = 0.25;
+
+struct SlowMotion {
+    scale: f32,
+    duration: f32,
+    remaining: f32,
+}
+
+// how fast the game runs compared to real time. everything simulated takes its dt through `apply`, the camera,
+// ui and networking keep running in real time
+pub struct TimeScale {
+    base: f32,
+    slow_motion: Option<SlowMotion>,
+}
+
+impl TimeScale {
+    pub fn new() -> Self {
+        Self { base: 1.0, slow_motion: None }
+    }
+
+    // the speed the game settles back to, set from the console. anything that isn't a number is ignored, it'd make
+    // every dt nan
+    pub fn set(&mut self, scale: f32) {
+        if !scale.is_finite() {
+            return;
+        }
+        self.base = scale.clamp(MIN_SCALE, MAX_SCALE);
+    }
+
+    pub fn base(&self) -> f32 {
+        self.base
+    }
+
+    // a slowdown on top of the base scale for `duration` real seconds, replaces one that's still running
+    pub fn slow_motion(&mut self, scale: f32, duration: f32) {
+        self.slow_motion = Some(SlowMotion { scale, duration, remaining: duration });
+    }
+
+    pub fn scale(&self) -> f32 {
+        let slow = match &self.slow_motion {
+            Some(slow) => {
+                let ease_time = slow.duration * EASE_OUT;
+                let t = if ease_time > 0.0 { (slow.remaining / ease_time).min(1.0) } else { 1.0 };
+                1.0 + (slow.scale - 1.0) * t
+            }
+            None => 1.0,
+        };
+        (self.base * slow).clamp(MIN_SCALE, MAX_SCALE)
+    }
+
+    // `real_dt` is the unscaled frame time, slow motion runs out in real seconds rather than game ones
+    pub fn update(&mut self, real_dt: f32) {
+        if let Some(slow) = self.slow_motion.as_mut() {
+            slow.remaining -= real_dt;
+            if slow.remaining <= 0.0 {
+                self.slow_motion = None;
+            }
+        }
+    }
+
+    pub fn apply(&self, dt: f32) -> f32 {
+        dt * self.scale()
+    }
+}
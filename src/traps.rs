@@ -0,0 +1,277 @@
+use rapier3d::prelude::*;
+use tiny_game_framework::{glam::{vec3, Vec3, Vec4}, Cuboid, Renderer, Sphere};
+
+use crate::{coords::{TileCoord, WorldPos}, error::RendererExt, entity::{EntityId, EntityKind}, rapier_integration::{BodySpec, CollisionLayer, RapierPhysicsWorld}};
+
+// colors in the sample image that get turned into traps instead of plain floor
+pub const SPIKES_COLOR: [u8; 4] = [255, 0, 0, 255];
+pub const PRESSURE_PLATE_COLOR: [u8; 4] = [0, 255, 0, 255];
+pub const ARROW_SHOOTER_COLOR: [u8; 4] = [0, 0, 255, 255];
+
+const SPIKE_DAMAGE: f32 = 10.0;
+const SPIKE_INTERVAL: f32 = 0.5;
+const ARROW_DAMAGE: f32 = 25.0;
+const ARROW_SPEED: f32 = 600.0;
+const ARROW_LIFETIME: f32 = 3.0;
+const ARROW_KNOCKBACK: f32 = 300.0;
+const SHOOTER_COOLDOWN: f32 = 1.5;
+const LAVA_DAMAGE: f32 = 15.0;
+const LAVA_INTERVAL: f32 = 0.4;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TrapKind {
+    Spikes,
+    PressurePlate,
+    ArrowShooter,
+}
+
+impl TrapKind {
+    pub fn from_pixel(pixel: [u8; 4]) -> Option<Self> {
+        match pixel {
+            SPIKES_COLOR => Some(TrapKind::Spikes),
+            PRESSURE_PLATE_COLOR => Some(TrapKind::PressurePlate),
+            ARROW_SHOOTER_COLOR => Some(TrapKind::ArrowShooter),
+            _ => None,
+        }
+    }
+
+    pub fn pixel(&self) -> [u8; 4] {
+        match self {
+            TrapKind::Spikes => SPIKES_COLOR,
+            TrapKind::PressurePlate => PRESSURE_PLATE_COLOR,
+            TrapKind::ArrowShooter => ARROW_SHOOTER_COLOR,
+        }
+    }
+
+    fn color(&self) -> Vec4 {
+        match self {
+            TrapKind::Spikes => vec3(0.7, 0.1, 0.1).extend(1.0),
+            TrapKind::PressurePlate => vec3(0.2, 0.6, 0.2).extend(1.0),
+            TrapKind::ArrowShooter => vec3(0.2, 0.2, 0.7).extend(1.0),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TrapEvent {
+    Damage(f32),
+    Knockback(Vec3),
+    // doors and whatever else listens for plates can pick this up. a plate only ever springs once
+    PlateTriggered { plate: usize },
+    ArrowFired { shooter: usize },
+}
+
+pub struct Trap {
+    pub kind: TrapKind,
+    pub position: Vec3,
+    pub sensor: ColliderHandle,
+
+    // shooters fired by this plate, filled in by `TrapSystem::link`
+    pub linked: Vec<usize>,
+
+    occupied: bool,
+    // a plate that has been stepped on stays down
+    spent: bool,
+    cooldown: f32,
+}
+
+pub struct Arrow {
+    pub handle: RigidBodyHandle,
+    pub mesh_name: String,
+    lifetime: f32,
+}
+
+pub struct TrapSystem {
+    pub traps: Vec<Trap>,
+    pub arrows: Vec<Arrow>,
+    arrow_counter: usize,
+    // until each player in lava gets burned again, by their index in `players`
+    burning: Vec<f32>,
+}
+
+impl TrapSystem {
+    pub fn new() -> Self {
+        Self {
+            traps: Vec::new(),
+            arrows: Vec::new(),
+            arrow_counter: 0,
+            burning: Vec::new(),
+        }
+    }
+
+    // `position` is the center of the tile at floor level
+    pub fn spawn(&mut self, rw: &mut RapierPhysicsWorld, r: &mut Renderer, kind: TrapKind, position: Vec3, tile_size: f32) {
+        // still here from before its chunk was unloaded and built again
+        if self.traps.iter().any(|trap| trap.position == position) {
+            return;
+        }
+        let half = tile_size / 2.0;
+        let sensor = rw.add_sensor(position.x, position.y + half / 2.0, position.z, vec3(half, half / 2.0, half), CollisionLayer::Trigger, CollisionLayer::Trigger.default_mask());
+        rw.tag_collider(sensor, EntityId::new(EntityKind::Trap));
+
+        let mesh_name = format!("TRAP_MESH{:?}{:?}{:?}", position.x, position.z, self.traps.len());
+        let mut mesh = Cuboid::new(vec3(tile_size * 0.9, 10.0, tile_size * 0.9), kind.color()).mesh();
+        mesh.position = position;
+        mesh.setup_mesh();
+        if let Err(e) = r.try_add_mesh(&mesh_name, mesh) {
+            log::warn!("{}", e);
+        }
+
+        self.traps.push(Trap {
+            kind,
+            position,
+            sensor,
+            linked: Vec::new(),
+            occupied: false,
+            spent: false,
+            cooldown: 0.0,
+        });
+
+        self.link();
+    }
+
+    // the plate on `tile` won't spring again, for plates stepped on before their chunk was built again
+    pub fn disarm(&mut self, tile: TileCoord) {
+        for trap in self.traps.iter_mut().filter(|trap| WorldPos(trap.position).tile() == tile) {
+            trap.spent = true;
+        }
+    }
+
+    // every plate fires the closest shooter that shares a row or column with it
+    fn link(&mut self) {
+        let shooters: Vec<(usize, Vec3)> = self.traps.iter().enumerate()
+            .filter(|(_, trap)| trap.kind == TrapKind::ArrowShooter)
+            .map(|(i, trap)| (i, trap.position))
+            .collect();
+
+        for trap in self.traps.iter_mut().filter(|trap| trap.kind == TrapKind::PressurePlate) {
+            trap.linked = shooters.iter()
+                .filter(|(_, pos)| (pos.x - trap.position.x).abs() < 1.0 || (pos.z - trap.position.z).abs() < 1.0)
+                .min_by(|(_, a), (_, b)| a.distance(trap.position).total_cmp(&b.distance(trap.position)))
+                .map(|(i, _)| vec![*i])
+                .unwrap_or_default();
+        }
+    }
+
+    // events come back with the index of the player in `players` they happened to
+    pub fn update(&mut self, rw: &mut RapierPhysicsWorld, r: &mut Renderer, players: &[RigidBodyHandle], dt: f32) -> Vec<(usize, TrapEvent)> {
+        let mut events = Vec::new();
+        let mut to_fire = Vec::new();
+
+        for (i, trap) in self.traps.iter_mut().enumerate() {
+            trap.cooldown = (trap.cooldown - dt).max(0.0);
+            let inside: Vec<usize> = players.iter().enumerate()
+                .filter(|(_, &player)| rw.is_body_in_sensor(trap.sensor, player))
+                .map(|(index, _)| index)
+                .collect();
+
+            match trap.kind {
+                TrapKind::Spikes => {
+                    if !inside.is_empty() && trap.cooldown <= 0.0 {
+                        events.extend(inside.iter().map(|&player| (player, TrapEvent::Damage(SPIKE_DAMAGE))));
+                        trap.cooldown = SPIKE_INTERVAL;
+                    }
+                }
+                TrapKind::PressurePlate => {
+                    // only fires when stepped on, not every frame someone stands there
+                    if let (Some(&player), false, false) = (inside.first(), trap.occupied, trap.spent) {
+                        trap.spent = true;
+                        events.push((player, TrapEvent::PlateTriggered { plate: i }));
+                        to_fire.extend(trap.linked.iter().copied().map(|shooter| (player, shooter, trap.position)));
+                    }
+                }
+                TrapKind::ArrowShooter => {}
+            }
+
+            trap.occupied = !inside.is_empty();
+        }
+
+        for (player, shooter, target) in to_fire {
+            if self.traps[shooter].cooldown > 0.0 {
+                continue;
+            }
+            self.traps[shooter].cooldown = SHOOTER_COOLDOWN;
+            self.fire_arrow(rw, r, self.traps[shooter].position, target);
+            events.push((player, TrapEvent::ArrowFired { shooter }));
+        }
+
+        self.update_arrows(rw, r, players, dt, &mut events);
+        self.update_lava(rw, players, dt, &mut events);
+
+        events
+    }
+
+    // burns as soon as someone steps in and every `LAVA_INTERVAL` they stay, getting out and back in doesn't reset it
+    fn update_lava(&mut self, rw: &RapierPhysicsWorld, players: &[RigidBodyHandle], dt: f32, events: &mut Vec<(usize, TrapEvent)>) {
+        self.burning.resize(players.len(), 0.0);
+        for (player, cooldown) in self.burning.iter_mut().enumerate() {
+            *cooldown = (*cooldown - dt).max(0.0);
+            if *cooldown <= 0.0 && rw.is_body_in_lava(players[player]) {
+                events.push((player, TrapEvent::Damage(LAVA_DAMAGE)));
+                *cooldown = LAVA_INTERVAL;
+            }
+        }
+    }
+
+    fn fire_arrow(&mut self, rw: &mut RapierPhysicsWorld, r: &mut Renderer, from: Vec3, to: Vec3) {
+        // shoot down the corridor, so snap the direction to whichever axis the plate is on
+        let delta = to - from;
+        let direction = if delta.x.abs() > delta.z.abs() {
+            vec3(delta.x.signum(), 0.0, 0.0)
+        } else {
+            vec3(0.0, 0.0, delta.z.signum())
+        };
+
+        let origin = from + vec3(0.0, 50.0, 0.0);
+        let handle = rw.add_body(origin, &BodySpec::ball(0.5)
+            .restitution(0.7)
+            .ccd(true)
+            .layer(CollisionLayer::Projectile));
+        let body = &mut rw.rigid_body_set[handle];
+        body.set_gravity_scale(0.0, true);
+        let velocity = direction * ARROW_SPEED;
+        body.set_linvel(vector![velocity.x, velocity.y, velocity.z], true);
+        rw.tag_body(handle, EntityId::new(EntityKind::Projectile));
+
+        let mesh_name = format!("ARROW_MESH{}", self.arrow_counter);
+        self.arrow_counter += 1;
+        let mut mesh = Sphere::new(8, 10.0, Vec4::new(0.8, 0.8, 0.8, 1.0)).mesh();
+        mesh.position = origin;
+        mesh.setup_mesh();
+        if let Err(e) = r.try_add_mesh(&mesh_name, mesh) {
+            log::warn!("{}", e);
+        }
+
+        self.arrows.push(Arrow { handle, mesh_name, lifetime: ARROW_LIFETIME });
+    }
+
+    fn update_arrows(&mut self, rw: &mut RapierPhysicsWorld, r: &mut Renderer, players: &[RigidBodyHandle], dt: f32, events: &mut Vec<(usize, TrapEvent)>) {
+        let mut i = 0;
+        while i < self.arrows.len() {
+            let arrow = &mut self.arrows[i];
+            arrow.lifetime -= dt;
+
+            let hit_player = players.iter().position(|&player| rw.are_bodies_touching(arrow.handle, player));
+            if let Some(player) = hit_player {
+                events.push((player, TrapEvent::Damage(ARROW_DAMAGE)));
+
+                let v = rw.rigid_body_set[arrow.handle].linvel();
+                let direction = vec3(v.x, 0.0, v.z).normalize_or_zero();
+                events.push((player, TrapEvent::Knockback(direction * ARROW_KNOCKBACK)));
+            }
+
+            if hit_player.is_some() || arrow.lifetime <= 0.0 {
+                let arrow = self.arrows.swap_remove(i);
+                rw.remove_rigidbody(arrow.handle);
+                r.destroy_mesh(&arrow.mesh_name).ok();
+                continue;
+            }
+
+            let t = rw.rigid_body_set[arrow.handle].translation();
+            if let Some(mesh) = r.get_mesh_mut(&arrow.mesh_name) {
+                mesh.position = vec3(t.x, t.y, t.z);
+            }
+            i += 1;
+        }
+    }
+}
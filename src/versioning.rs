@@ -0,0 +1,110 @@
+use serde_json::{json, Value};
+
+use crate::error::SaveError;
+
+// where the version goes in a file, files from before versions existed don't have it and count as version 0
+const VERSION_KEY: &str = "version";
+
+// something written to disk whose layout changes over time. `migrations[n]` takes a file from version n to n + 1,
+// so the current version is how many there are. changing what gets written means adding one at the end, never
+// editing the old ones, since players have files at every version lying around
+pub struct Format {
+    pub name: &'static str,
+    pub migrations: &'static [fn(&mut Value)],
+}
+
+pub const SAVE_FORMAT: Format = Format { name: "save", migrations: &[save_slots] };
+pub const LEADERBOARD_FORMAT: Format = Format { name: "leaderboard", migrations: &[] };
+pub const CONFIG_FORMAT: Format = Format { name: "config", migrations: &[] };
+
+impl Format {
+    pub fn version(&self) -> u32 {
+        self.migrations.len() as u32
+    }
+
+    // runs whatever migrations `value` hasn't been through yet, one from a newer build is left alone and refused
+    pub fn upgrade(&self, mut value: Value) -> Result<Value, SaveError> {
+        let found = value.get(VERSION_KEY).and_then(Value::as_u64).unwrap_or(0) as u32;
+        if found > self.version() {
+            return Err(SaveError::TooNew { format: self.name, found, supported: self.version() });
+        }
+
+        for (version, migrate) in self.migrations.iter().enumerate().skip(found as usize) {
+            log::info!("upgrading the {} from version {} to {}", self.name, version, version + 1);
+            migrate(&mut value);
+        }
+        self.stamp(&mut value);
+        Ok(value)
+    }
+
+    // marks `value` as the current version, right before it's written
+    pub fn stamp(&self, value: &mut Value) {
+        if let Some(object) = value.as_object_mut() {
+            object.insert(VERSION_KEY.to_string(), json!(self.version()));
+        }
+    }
+}
+
+// 0 -> 1, the save used to have room for a single run and now has slots
+fn save_slots(value: &mut Value) {
+    let Some(object) = value.as_object_mut() else { return };
+    let Some(run) = object.remove("run").filter(|run| !run.is_null()) else { return };
+    object.insert("slots".to_string(), json!([{ "name": "slot 1", "run": run }]));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single_run_becomes_the_first_slot() {
+        let old = json!({ "achievements": ["first blood"], "run": { "seed": 42, "progress": { "floor": 3 } } });
+        let upgraded = SAVE_FORMAT.upgrade(old).unwrap();
+        assert_eq!(upgraded["slots"], json!([{ "name": "slot 1", "run": { "seed": 42, "progress": { "floor": 3 } } }]));
+        assert!(upgraded.get("run").is_none());
+        assert_eq!(upgraded["achievements"], json!(["first blood"]));
+        assert_eq!(upgraded[VERSION_KEY], json!(1));
+    }
+
+    #[test]
+    fn no_run_means_no_slots() {
+        let upgraded = SAVE_FORMAT.upgrade(json!({ "run": null })).unwrap();
+        assert!(upgraded.get("slots").is_none());
+        assert_eq!(upgraded[VERSION_KEY], json!(SAVE_FORMAT.version()));
+    }
+
+    #[test]
+    fn current_version_is_left_as_is() {
+        let current = json!({ "slots": [], VERSION_KEY: SAVE_FORMAT.version() });
+        assert_eq!(SAVE_FORMAT.upgrade(current.clone()).unwrap(), current);
+    }
+
+    #[test]
+    fn newer_version_is_refused() {
+        let newer = json!({ "slots": [], VERSION_KEY: SAVE_FORMAT.version() + 1 });
+        match SAVE_FORMAT.upgrade(newer) {
+            Err(SaveError::TooNew { format, found, supported }) => {
+                assert_eq!(format, "save");
+                assert_eq!(found, SAVE_FORMAT.version() + 1);
+                assert_eq!(supported, SAVE_FORMAT.version());
+            }
+            other => panic!("expected the save to be refused, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn stamp_writes_the_current_version() {
+        let mut value = json!({ VERSION_KEY: 0, "slots": [] });
+        SAVE_FORMAT.stamp(&mut value);
+        assert_eq!(value[VERSION_KEY], json!(SAVE_FORMAT.version()));
+
+        let mut value = json!({});
+        LEADERBOARD_FORMAT.stamp(&mut value);
+        assert_eq!(value[VERSION_KEY], json!(0));
+
+        // only objects have anywhere to put it
+        let mut value = json!([1, 2]);
+        SAVE_FORMAT.stamp(&mut value);
+        assert_eq!(value, json!([1, 2]));
+    }
+}
@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+
+use tiny_game_framework::{gl::{self, types::GLuint}, glam::{vec2, Vec3, Vec4}, Camera};
+
+use crate::{billboards::{upload_atlas, QuadBatch, BILLBOARD_FS, BILLBOARD_VS}, lighting::Fog, shader::program};
+
+// every glyph is 5 texels across and 7 up, with a texel of space to its right and above it in the atlas
+const GLYPH_WIDTH: usize = 5;
+const GLYPH_HEIGHT: usize = 7;
+const CELL_WIDTH: usize = GLYPH_WIDTH + 1;
+const CELL_HEIGHT: usize = GLYPH_HEIGHT + 1;
+// what's drawn for characters the font doesn't have
+const MISSING: char = '?';
+
+// a small pixel font, uppercase only. each row is a glyph's line from the top down, the highest bit on the left
+const FONT: [(char, [u8; GLYPH_HEIGHT]); 43] = [
+    ('A', [0b01110, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001]),
+    ('B', [0b11110, 0b10001, 0b10001, 0b11110, 0b10001, 0b10001, 0b11110]),
+    ('C', [0b01110, 0b10001, 0b10000, 0b10000, 0b10000, 0b10001, 0b01110]),
+    ('D', [0b11110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b11110]),
+    ('E', [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b11111]),
+    ('F', [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b10000]),
+    ('G', [0b01110, 0b10001, 0b10000, 0b10111, 0b10001, 0b10001, 0b01111]),
+    ('H', [0b10001, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001]),
+    ('I', [0b01110, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110]),
+    ('J', [0b00111, 0b00010, 0b00010, 0b00010, 0b00010, 0b10010, 0b01100]),
+    ('K', [0b10001, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010, 0b10001]),
+    ('L', [0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b11111]),
+    ('M', [0b10001, 0b11011, 0b10101, 0b10101, 0b10001, 0b10001, 0b10001]),
+    ('N', [0b10001, 0b10001, 0b11001, 0b10101, 0b10011, 0b10001, 0b10001]),
+    ('O', [0b01110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110]),
+    ('P', [0b11110, 0b10001, 0b10001, 0b11110, 0b10000, 0b10000, 0b10000]),
+    ('Q', [0b01110, 0b10001, 0b10001, 0b10001, 0b10101, 0b10010, 0b01101]),
+    ('R', [0b11110, 0b10001, 0b10001, 0b11110, 0b10100, 0b10010, 0b10001]),
+    ('S', [0b01111, 0b10000, 0b10000, 0b01110, 0b00001, 0b00001, 0b11110]),
+    ('T', [0b11111, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100]),
+    ('U', [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110]),
+    ('V', [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100]),
+    ('W', [0b10001, 0b10001, 0b10001, 0b10101, 0b10101, 0b10101, 0b01010]),
+    ('X', [0b10001, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001, 0b10001]),
+    ('Y', [0b10001, 0b10001, 0b01010, 0b00100, 0b00100, 0b00100, 0b00100]),
+    ('Z', [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b11111]),
+    ('0', [0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110]),
+    ('1', [0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110]),
+    ('2', [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111]),
+    ('3', [0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110]),
+    ('4', [0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010]),
+    ('5', [0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110]),
+    ('6', [0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110]),
+    ('7', [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000]),
+    ('8', [0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110]),
+    ('9', [0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100]),
+    ('.', [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b01100]),
+    ('!', [0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00000, 0b00100]),
+    ('?', [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b00000, 0b00100]),
+    ('-', [0b00000, 0b00000, 0b00000, 0b11111, 0b00000, 0b00000, 0b00000]),
+    (':', [0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b01100, 0b00000]),
+    ('\'', [0b00100, 0b00100, 0b01000, 0b00000, 0b00000, 0b00000, 0b00000]),
+    ('+', [0b00000, 0b00100, 0b00100, 0b11111, 0b00100, 0b00100, 0b00000]),
+];
+
+// one line of text, `position` is its middle and `size` how tall a letter is
+struct Label {
+    text: String,
+    position: Vec3,
+    size: f32,
+    color: Vec4,
+}
+
+// text on a wall, it faces one way and can be read from the front
+struct Sign {
+    label: Label,
+    normal: Vec3,
+}
+
+// text placed in the world out of bitmap font quads, drawn with the billboards' shader so it's fogged like
+// everything else and sits behind what's in front of it. signs stay until they're removed, labels have to be
+// asked for every frame
+pub struct WorldText {
+    // `None` when the shader didn't build, there's no text then
+    program: Option<GLuint>,
+    quads: QuadBatch,
+    atlas: GLuint,
+    signs: HashMap<String, Sign>,
+    labels: Vec<Label>,
+}
+
+impl WorldText {
+    // needs the gl context
+    pub fn new() -> Self {
+        let program = program(BILLBOARD_VS, BILLBOARD_FS).inspect_err(|e| log::warn!("no world text: {}", e)).ok();
+        Self { program, quads: QuadBatch::new(), atlas: bake_font(), signs: HashMap::new(), labels: Vec::new() }
+    }
+
+    // `position` is the middle of the text and `normal` the way it's read from, both in world units like `size`,
+    // which is how tall a letter is. placing the same name again replaces it
+    pub fn place_sign(&mut self, name: &str, text: &str, position: Vec3, normal: Vec3, size: f32, color: Vec4) {
+        let label = Label { text: text.to_string(), position, size, color };
+        self.signs.insert(name.to_string(), Sign { label, normal: normal.normalize_or_zero() });
+    }
+
+    pub fn remove_sign(&mut self, name: &str) {
+        self.signs.remove(name);
+    }
+
+    // shows `text` facing the camera for the next `draw`, like `place_sign` otherwise
+    pub fn label(&mut self, text: &str, position: Vec3, size: f32, color: Vec4) {
+        self.labels.push(Label { text: text.to_string(), position, size, color });
+    }
+
+    // call after the scene, with the camera the frame was drawn with. the labels are used up
+    pub fn draw(&mut self, camera: &Camera, fog: Fog, resolution: f32) {
+        let Some(program) = self.program else {
+            self.labels.clear();
+            return;
+        };
+
+        self.quads.clear();
+        for sign in self.signs.values() {
+            let right = (-sign.normal).cross(Vec3::Y).normalize_or_zero();
+            push_text(&mut self.quads, &sign.label, right, Vec3::Y, resolution);
+        }
+        // the view's rows are the camera's axes
+        let (right, up) = (camera.view.row(0).truncate(), camera.view.row(1).truncate());
+        for label in self.labels.drain(..) {
+            push_text(&mut self.quads, &label, right, up, resolution);
+        }
+
+        unsafe {
+            // labels fade out through their alpha
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+        }
+        self.quads.draw(program, self.atlas, camera, fog, resolution);
+    }
+}
+
+// laid out along `right` and `up`, lowercase is drawn as uppercase
+fn push_text(quads: &mut QuadBatch, label: &Label, right: Vec3, up: Vec3, resolution: f32) {
+    let Label { text, position, size, color } = label;
+    let (size, color) = (*size, *color);
+    let (width, advance) = (size * GLYPH_WIDTH as f32 / GLYPH_HEIGHT as f32, size * CELL_WIDTH as f32 / GLYPH_HEIGHT as f32);
+    let length = text.chars().count() as f32 * advance - (advance - width);
+    let start = *position - right * length / 2.0 - up * size / 2.0;
+    let atlas_width = (FONT.len() * CELL_WIDTH) as f32;
+
+    for (i, c) in text.chars().enumerate() {
+        if c.is_whitespace() {
+            continue;
+        }
+        let c = c.to_ascii_uppercase();
+        let cell = FONT.iter().position(|&(glyph, _)| glyph == c)
+            .or_else(|| FONT.iter().position(|&(glyph, _)| glyph == MISSING))
+            .unwrap_or(0);
+
+        let corner = start + right * advance * i as f32;
+        let corners = [corner, corner + right * width, corner + right * width + up * size, corner + up * size].map(|corner| corner / resolution);
+        let u = (cell * CELL_WIDTH) as f32 / atlas_width;
+        let uv = [vec2(u, 0.0), vec2(u + GLYPH_WIDTH as f32 / atlas_width, GLYPH_HEIGHT as f32 / CELL_HEIGHT as f32)];
+        quads.push(corners, uv, color);
+    }
+}
+
+// every glyph side by side in one row, white so the text's color tints it
+fn bake_font() -> GLuint {
+    let width = FONT.len() * CELL_WIDTH;
+    let mut texels = vec![0u8; width * CELL_HEIGHT * 4];
+    for (cell, (_, rows)) in FONT.iter().enumerate() {
+        for (row, bits) in rows.iter().enumerate() {
+            // the atlas goes up from the bottom, the font down from the top
+            let y = GLYPH_HEIGHT - 1 - row;
+            for x in (0..GLYPH_WIDTH).filter(|x| bits & (1 << (GLYPH_WIDTH - 1 - x)) != 0) {
+                let index = (y * width + cell * CELL_WIDTH + x) * 4;
+                texels[index..index + 4].copy_from_slice(&[255; 4]);
+            }
+        }
+    }
+
+    upload_atlas(width, CELL_HEIGHT, &texels)
+}